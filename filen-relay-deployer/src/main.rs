@@ -4,13 +4,35 @@ use filen_cli::serialize_auth_config;
 use filen_sdk_rs::{auth::Client, ErrorKind};
 use filen_types::error::ResponseError;
 
+mod object_storage;
 mod scaleway_api;
 
+/// The Filen Relay container image (without the tag).
+const IMAGE: &str = "ghcr.io/jupiterpi/filen-relay";
+/// Environment variable of the container recording which image channel was deployed ("release", "main" or "custom"),
+/// so that updates can keep it.
+const IMAGE_CHANNEL_VARIABLE: &str = "FILEN_RELAY_IMAGE_CHANNEL";
+
+/// Prices of Scaleway Serverless Containers in EUR (see https://www.scaleway.com/en/pricing/serverless/),
+/// without the monthly free tier.
+const PRICE_PER_VCPU_SECOND: f64 = 0.000_010;
+const PRICE_PER_GB_SECOND: f64 = 0.000_001;
+const SECONDS_PER_MONTH: f64 = 730.0 * 3600.0;
+
+/// The Scaleway regions offering Serverless Containers, with their names.
+const REGIONS: &[(&str, &str)] = &[
+    ("fr-par", "Paris (fr-par)"),
+    ("nl-ams", "Amsterdam (nl-ams)"),
+    ("pl-waw", "Warsaw (pl-waw)"),
+];
+
 #[derive(Parser, Clone)]
 #[command()]
 struct Args {
     #[arg(long, help = "Ignore update check")]
     ignore_updates: bool,
+    #[arg(long, help = "Don't ask for confirmation (e.g. of the cost estimate)")]
+    yes: bool,
     #[arg(
         long,
         env = "FILEN_RELAY_ADMIN_EMAIL",
@@ -35,6 +57,12 @@ struct Args {
         help = "Scaleway API Secret Key"
     )]
     scaleway_api_key_secret: Option<String>,
+    #[arg(
+        long,
+        env = "FILEN_RELAY_SCALEWAY_API_KEY_ACCESS",
+        help = "Scaleway API Access Key (only needed to create a bucket for the database)"
+    )]
+    scaleway_api_key_access: Option<String>,
     #[arg(
         long,
         env = "FILEN_RELAY_SCALEWAY_ORGANIZATION_ID",
@@ -59,6 +87,45 @@ struct Args {
         help = "Scaleway Containers Namespace ID to deploy to, or 'create_new' to create a new namespace"
     )]
     scaleway_namespace_id: Option<String>,
+    #[arg(
+        long,
+        env = "FILEN_RELAY_SCALEWAY_CONTAINER_ID",
+        help = "Scaleway Container ID of an existing Filen Relay container to update, or 'create_new' to create a new container"
+    )]
+    scaleway_container_id: Option<String>,
+    #[arg(
+        long,
+        env = "FILEN_RELAY_SCALEWAY_DB_BUCKET",
+        help = "Scaleway Object Storage bucket to store the database in (created if missing), or 'none' to store it in the admin's Filen drive"
+    )]
+    scaleway_db_bucket: Option<String>,
+    #[arg(
+        long,
+        env = "FILEN_RELAY_SCALEWAY_CPU_LIMIT",
+        default_value_t = 250,
+        help = "CPU limit of a new container in mvCPU (1000 = 1 vCPU)"
+    )]
+    scaleway_cpu_limit: u32,
+    #[arg(
+        long,
+        env = "FILEN_RELAY_SCALEWAY_MEMORY_LIMIT",
+        default_value_t = 256,
+        help = "Memory limit of a new container in MB"
+    )]
+    scaleway_memory_limit: u32,
+    #[arg(
+        long,
+        env = "FILEN_RELAY_SCALEWAY_MIN_SCALE",
+        default_value_t = 0,
+        help = "Minimum number of instances of a new container (0 to scale to zero when not in use)"
+    )]
+    scaleway_min_scale: u32,
+    #[arg(
+        long,
+        env = "FILEN_RELAY_IMAGE",
+        help = "Image to deploy: 'release' (the release of this deployer), 'main' (the latest development build) or a custom image reference"
+    )]
+    image: Option<String>,
 }
 
 #[tokio::main]
@@ -147,7 +214,10 @@ async fn main_() -> Result<()> {
         .interact()?
     {
         "scaleway" => {
-            deploy_to_scaleway(filen_relay_version, client, args).await?;
+            if !deploy_to_scaleway(filen_relay_version, client, args).await? {
+                cliclack::outro_cancel("Deployment cancelled.")?;
+                return Ok(());
+            }
         }
         _ => unimplemented!(),
     }
@@ -158,7 +228,8 @@ async fn main_() -> Result<()> {
     // todo: keep open
 }
 
-async fn deploy_to_scaleway(filen_relay_version: &str, client: Client, args: Args) -> Result<()> {
+/// Deploys to Scaleway. Returns false if the user cancelled the deployment.
+async fn deploy_to_scaleway(filen_relay_version: &str, client: Client, args: Args) -> Result<bool> {
     // enter api key, organization id, region
     let api_key: String = match args.scaleway_api_key_secret {
         Some(ref api_key) => api_key.clone(),
@@ -170,11 +241,47 @@ async fn deploy_to_scaleway(filen_relay_version: &str, client: Client, args: Arg
     };
     let region = match args.scaleway_region {
         Some(ref region) => region,
-        None => cliclack::select("Enter the region to deploy to")
-            .item("fr-par", "Paris (fr-par)", "")
-            .item("nl-ams", "Amsterdam (nl-ams)", "")
-            .item("pl-waw", "Warsaw (pl-waw)", "")
-            .interact()?,
+        None => {
+            // WebDAV clients are sensitive to latency, so the closest region is usually the best
+            let latencies = if cliclack::confirm(
+                "Measure the latency to the Scaleway regions to find the closest one?",
+            )
+            .interact()?
+            {
+                let latency_spinner = cliclack::spinner();
+                latency_spinner.start("Measuring latencies...");
+                let latencies = probe_region_latencies().await;
+                latency_spinner.stop("Measured latencies!");
+                latencies
+            } else {
+                vec![None; REGIONS.len()]
+            };
+            let closest = REGIONS
+                .iter()
+                .zip(&latencies)
+                .filter_map(|((region, _), latency)| latency.map(|latency| (*region, latency)))
+                .min_by_key(|(_, latency)| *latency)
+                .map(|(region, _)| region);
+            let hints = latencies
+                .iter()
+                .zip(REGIONS)
+                .map(|(latency, (region, _))| match latency {
+                    Some(latency) if closest == Some(*region) => {
+                        format!("{} ms, recommended", latency.as_millis())
+                    }
+                    Some(latency) => format!("{} ms", latency.as_millis()),
+                    None => String::new(),
+                })
+                .collect::<Vec<_>>();
+            let mut select = cliclack::select("Enter the region to deploy to");
+            for ((region, name), hint) in REGIONS.iter().zip(&hints) {
+                select = select.item(*region, *name, hint);
+            }
+            if let Some(closest) = closest {
+                select = select.initial_value(closest);
+            }
+            select.interact()?
+        }
     };
     let scaleway = scaleway_api::ScalewayApi::new(&api_key, &organization_id, region);
 
@@ -239,36 +346,210 @@ async fn deploy_to_scaleway(filen_relay_version: &str, client: Client, args: Arg
     }
     namespace_ready_spinner.stop("Namespace is ready!");
 
-    // create container and deploy it
-    let container_name = format!(
-        "filen-relay-{}",
-        &uuid::Uuid::new_v4().as_simple().to_string()[..8]
-    );
-    let container = scaleway
-        .create_container(&serde_json::json!({
-            "namespace_id": namespace.id,
-            "name": container_name,
-            "registry_image": format!("ghcr.io/jupiterpi/filen-relay:{}", filen_relay_version),
-            "min_scale": 0,
-            "max_scale": 1,
-            "port": 80,
-            "cpu_limit": 250,
-            "memory_limit": 256,
-            "secret_environment_variables": [
-                {
-                    "key": "FILEN_RELAY_ADMIN_AUTH_CONFIG",
-                    "value": serialize_auth_config(&client)?,
-                },
-            ],
-            "health_check": {
-                "http": {
-                    "path": "/api/ready",
+    // update an existing Filen Relay container in the namespace, or create a new one
+    let existing_containers = scaleway
+        .list_containers(&namespace.id)
+        .await?
+        .into_iter()
+        .filter(|c| {
+            c.registry_image.starts_with(IMAGE)
+                || c.environment_variables.contains_key(IMAGE_CHANNEL_VARIABLE)
+        })
+        .collect::<Vec<_>>();
+    let container_id = match args.scaleway_container_id {
+        Some(ref container_id) => container_id.as_str(),
+        None if existing_containers.is_empty() => "create_new",
+        None => cliclack::select(
+            "Found existing Filen Relay containers in this namespace. Update one of them in place?",
+        )
+        .items(
+            existing_containers
+                .iter()
+                .map(|c| (c.id.as_str(), c.name.as_str(), c.registry_image.as_str()))
+                .collect::<Vec<_>>()
+                .as_slice(),
+        )
+        .item("create_new", "Create a new container", "")
+        .interact()?,
+    };
+
+    // store the database in an Object Storage bucket, so that it doesn't depend on the admin's Filen drive at boot
+    let db_bucket = match args.scaleway_db_bucket {
+        Some(ref bucket) if bucket == "none" => None,
+        Some(ref bucket) => Some(bucket.clone()),
+        None => {
+            if cliclack::confirm(
+                "Store the database in a Scaleway Object Storage bucket instead of the admin's Filen drive?",
+            )
+            .initial_value(false)
+            .interact()?
+            {
+                let default_name = format!(
+                    "filen-relay-db-{}",
+                    &uuid::Uuid::new_v4().as_simple().to_string()[..8]
+                );
+                Some(
+                    cliclack::input("Bucket name (created if it doesn't exist):")
+                        .default_input(&default_name)
+                        .interact()?,
+                )
+            } else {
+                None
+            }
+        }
+    };
+    let mut environment_variables = serde_json::Map::new();
+    let mut secret_environment_variables = vec![serde_json::json!({
+        "key": "FILEN_RELAY_ADMIN_AUTH_CONFIG",
+        "value": serialize_auth_config(&client)?,
+    })];
+    if let Some(bucket) = db_bucket {
+        let access_key: String = match args.scaleway_api_key_access {
+            Some(ref access_key) => access_key.clone(),
+            None => cliclack::input("Enter your Scaleway API Access Key:").interact()?,
+        };
+        let bucket_spinner = cliclack::spinner();
+        bucket_spinner.start("Creating bucket...");
+        object_storage::create_bucket_if_missing(region, &access_key, &api_key, &bucket).await?;
+        bucket_spinner.stop(format!("Bucket {} is ready!", bucket));
+        // the relay gets its own key, restricted to Object Storage, instead of the deployer's API key
+        let key_spinner = cliclack::spinner();
+        key_spinner.start("Creating an API key for the bucket...");
+        let bucket_key = scaleway
+            .create_object_storage_key(project_id, &format!("filen-relay-{}", bucket))
+            .await?;
+        key_spinner.stop("API key for the bucket is ready!");
+        for (key, value) in [
+            ("FILEN_RELAY_ADMIN_EMAIL", client.email().to_string()),
+            ("FILEN_RELAY_S3_BUCKET", bucket),
+            ("FILEN_RELAY_S3_ENDPOINT", object_storage::endpoint(region)),
+            ("FILEN_RELAY_S3_REGION", region.to_string()),
+        ] {
+            environment_variables.insert(key.to_string(), value.into());
+        }
+        secret_environment_variables.extend([
+            serde_json::json!({ "key": "FILEN_RELAY_S3_ACCESS_KEY", "value": bucket_key.access_key }),
+            serde_json::json!({ "key": "FILEN_RELAY_S3_SECRET_KEY", "value": bucket_key.secret_key }),
+        ]);
+    }
+
+    // choose the image, keeping the channel of an updated container by default
+    let existing_container = existing_containers.iter().find(|c| c.id == container_id);
+    let previous_channel = existing_container
+        .and_then(|c| c.environment_variables.get(IMAGE_CHANNEL_VARIABLE))
+        .map(|channel| channel.as_str())
+        .unwrap_or("release");
+    let release_image = format!("{}:{}", IMAGE, filen_relay_version);
+    let main_image = format!("{}:main", IMAGE);
+    let (channel, registry_image) = match args.image.as_deref() {
+        Some("release") => ("release", release_image),
+        Some("main") => ("main", main_image),
+        Some(image) => ("custom", image.to_string()),
+        None => match cliclack::select("Choose the image to deploy:")
+            .item(
+                "release",
+                format!("Release v{}", filen_relay_version),
+                &release_image,
+            )
+            .item("main", "Latest development build", &main_image)
+            .item("custom", "Custom image", "")
+            .initial_value(previous_channel)
+            .interact()?
+        {
+            "main" => ("main", main_image),
+            "custom" => {
+                let mut input = cliclack::input("Image reference:");
+                if let Some(c) = existing_container.filter(|_| previous_channel == "custom") {
+                    input = input.default_input(&c.registry_image);
+                }
+                ("custom", input.interact::<String>()?)
+            }
+            _ => ("release", release_image),
+        },
+    };
+    environment_variables.insert(IMAGE_CHANNEL_VARIABLE.to_string(), channel.into());
+    let container = if container_id == "create_new" {
+        let max_scale = args.scaleway_min_scale.max(1);
+        let (min_cost, max_cost) = (
+            estimate_monthly_cost(
+                args.scaleway_cpu_limit,
+                args.scaleway_memory_limit,
+                args.scaleway_min_scale,
+            ),
+            estimate_monthly_cost(
+                args.scaleway_cpu_limit,
+                args.scaleway_memory_limit,
+                max_scale,
+            ),
+        );
+        cliclack::log::info(format!(
+            "Estimated cost of the container ({} mvCPU, {} MB, {} to {} instances): \
+            €{:.2} to €{:.2} per month, depending on how long it runs.\n\
+            The free tier of Scaleway Serverless Containers may cover part of it.",
+            args.scaleway_cpu_limit,
+            args.scaleway_memory_limit,
+            args.scaleway_min_scale,
+            max_scale,
+            min_cost,
+            max_cost
+        ))?;
+        if !args.yes && !cliclack::confirm("Create the container?").interact()? {
+            return Ok(false);
+        }
+        let container_name = format!(
+            "filen-relay-{}",
+            &uuid::Uuid::new_v4().as_simple().to_string()[..8]
+        );
+        scaleway
+            .create_container(&serde_json::json!({
+                "namespace_id": namespace.id,
+                "name": container_name,
+                "registry_image": registry_image,
+                "min_scale": args.scaleway_min_scale,
+                "max_scale": max_scale,
+                "port": 80,
+                "cpu_limit": args.scaleway_cpu_limit,
+                "memory_limit": args.scaleway_memory_limit,
+                "environment_variables": environment_variables,
+                "secret_environment_variables": secret_environment_variables,
+                "health_check": {
+                    "http": {
+                        "path": "/api/ready",
+                    },
+                    "failure_threshold": 24,
+                    "interval": "5s"
                 },
-                "failure_threshold": 24,
-                "interval": "5s"
-            },
-        }))
-        .await?;
+            }))
+            .await?
+    } else {
+        let Some(existing_container) = existing_container else {
+            return Err(anyhow!(
+                "Container {} is not a Filen Relay container in namespace {}",
+                container_id,
+                namespace.name
+            ));
+        };
+        // keep variables that were added in the console, since the API replaces the whole map
+        // (secrets are merged by key by the API, so only the ones set here are sent)
+        let mut merged_environment_variables = existing_container
+            .environment_variables
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone().into()))
+            .collect::<serde_json::Map<_, _>>();
+        merged_environment_variables.extend(environment_variables);
+        let environment_variables = merged_environment_variables;
+        // keep the container's scaling and resources, which may have been adjusted in the console
+        scaleway
+            .update_container(
+                container_id,
+                &serde_json::json!({
+                    "registry_image": registry_image,
+                    "environment_variables": environment_variables,
+                    "secret_environment_variables": secret_environment_variables,
+                }),
+            )
+            .await?
+    };
     scaleway.deploy_container(&container.id).await?;
     let console_url = format!(
         "https://console.scaleway.com/containers/namespaces/{}/{}/containers/{}",
@@ -280,5 +561,43 @@ async fn deploy_to_scaleway(filen_relay_version: &str, client: Client, args: Arg
         container.domain_name
     ))?;
 
-    Ok(())
+    Ok(true)
+}
+
+/// Measures the round-trip time to each of the `REGIONS` (the best of a few requests to its Object Storage endpoint),
+/// or `None` if it couldn't be reached.
+async fn probe_region_latencies() -> Vec<Option<std::time::Duration>> {
+    const ATTEMPTS: usize = 3;
+    let client = reqwest::Client::builder()
+        .user_agent("filen-relay-deployer")
+        .timeout(std::time::Duration::from_secs(5))
+        .build()
+        .unwrap();
+    let mut latencies = Vec::new();
+    for (region, _) in REGIONS {
+        let url = object_storage::endpoint(region);
+        let mut best = None::<std::time::Duration>;
+        // the first request includes the TLS handshake, which the best of the following ones doesn't
+        for _ in 0..ATTEMPTS {
+            let start = std::time::Instant::now();
+            match client.head(&url).send().await {
+                Ok(_) => {
+                    let elapsed = start.elapsed();
+                    best = Some(best.map_or(elapsed, |best| best.min(elapsed)));
+                }
+                Err(e) => log::debug!("Failed to reach {}: {}", url, e),
+            }
+        }
+        latencies.push(best);
+    }
+    latencies
+}
+
+/// Estimates the monthly cost (in EUR) of a container running `instances` instances around the clock.
+fn estimate_monthly_cost(cpu_limit: u32, memory_limit: u32, instances: u32) -> f64 {
+    let vcpus = cpu_limit as f64 / 1000.0;
+    let gbs = memory_limit as f64 / 1024.0;
+    instances as f64
+        * SECONDS_PER_MONTH
+        * (vcpus * PRICE_PER_VCPU_SECOND + gbs * PRICE_PER_GB_SECOND)
 }
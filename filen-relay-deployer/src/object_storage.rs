@@ -0,0 +1,37 @@
+use anyhow::{Context, Result};
+
+/// The S3 endpoint of Scaleway Object Storage in a region.
+pub(crate) fn endpoint(region: &str) -> String {
+    format!("https://s3.{}.scw.cloud", region)
+}
+
+/// Creates a private bucket in Scaleway Object Storage, unless it exists already (e.g. when redeploying).
+/// Scaleway API keys double as S3 credentials.
+pub(crate) async fn create_bucket_if_missing(
+    region: &str,
+    access_key: &str,
+    secret_key: &str,
+    name: &str,
+) -> Result<()> {
+    let credentials =
+        s3::creds::Credentials::new(Some(access_key), Some(secret_key), None, None, None)
+            .context("Invalid Scaleway API key")?;
+    let region = s3::Region::Custom {
+        region: region.to_string(),
+        endpoint: endpoint(region),
+    };
+    let bucket = s3::Bucket::new(name, region.clone(), credentials.clone())?.with_path_style();
+    if bucket.exists().await? {
+        log::debug!("Bucket {} exists already", name);
+        return Ok(());
+    }
+    s3::Bucket::create_with_path_style(
+        name,
+        region,
+        credentials,
+        s3::BucketConfiguration::private(),
+    )
+    .await
+    .with_context(|| format!("Failed to create bucket {}", name))?;
+    Ok(())
+}
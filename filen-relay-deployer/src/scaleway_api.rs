@@ -30,10 +30,35 @@ pub struct ContainersNamespacesListResponseItem {
     pub status: String,
 }
 
+#[derive(Deserialize)]
+pub struct ContainersListResponse {
+    pub containers: Vec<ContainersListResponseItem>,
+}
+
 #[derive(Deserialize)]
 pub struct ContainersListResponseItem {
     pub id: String,
+    pub name: String,
     pub domain_name: String,
+    pub registry_image: String,
+    #[serde(default)]
+    pub environment_variables: std::collections::HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+pub struct IamApplicationsListResponse {
+    pub applications: Vec<IamApplication>,
+}
+
+#[derive(Deserialize)]
+pub struct IamApplication {
+    pub id: String,
+}
+
+#[derive(Deserialize)]
+pub struct IamApiKey {
+    pub access_key: String,
+    pub secret_key: String,
 }
 
 impl ScalewayApi {
@@ -88,6 +113,24 @@ impl ScalewayApi {
         Ok(result)
     }
 
+    async fn patch<T: for<'a> Deserialize<'a>, B: Serialize>(
+        &self,
+        endpoint: &str,
+        body: &B,
+    ) -> Result<T> {
+        let url = format!(
+            "https://api.scaleway.com/{}",
+            endpoint.trim_start_matches('/')
+        );
+        log::debug!("Scaleway API: PATCH {}", url);
+        log::debug!("Request body: {}", serde_json::to_string(body)?);
+        let response = self.client.patch(url).json(body).send().await?;
+        let text = response.text().await?;
+        log::debug!("Response from {}: {}", endpoint, text);
+        let result = serde_json::from_str::<T>(&text)?;
+        Ok(result)
+    }
+
     pub async fn list_projects(&self) -> Result<Vec<ListProjectsResponseItem>> {
         let response = self
             .get::<ListProjectsResponse>(&format!(
@@ -140,6 +183,19 @@ impl ScalewayApi {
         Ok(response)
     }
 
+    pub async fn list_containers(
+        &self,
+        namespace_id: &str,
+    ) -> Result<Vec<ContainersListResponseItem>> {
+        let response = self
+            .get::<ContainersListResponse>(&format!(
+                "containers/v1beta1/regions/{}/containers?namespace_id={}",
+                self.region, namespace_id
+            ))
+            .await?;
+        Ok(response.containers)
+    }
+
     pub async fn create_container(
         &self,
         body: &serde_json::Value,
@@ -153,6 +209,81 @@ impl ScalewayApi {
         Ok(response)
     }
 
+    pub async fn update_container(
+        &self,
+        container_id: &str,
+        body: &serde_json::Value,
+    ) -> Result<ContainersListResponseItem> {
+        let response = self
+            .patch::<ContainersListResponseItem, _>(
+                &format!(
+                    "containers/v1beta1/regions/{}/containers/{}",
+                    self.region, container_id
+                ),
+                body,
+            )
+            .await?;
+        Ok(response)
+    }
+
+    /// Creates an API key that may only use Object Storage in the project, for an IAM application named `name`
+    /// (created with its policy, unless it exists already). Unlike the deployer's API key, it can't manage the project.
+    pub async fn create_object_storage_key(
+        &self,
+        project_id: &str,
+        name: &str,
+    ) -> Result<IamApiKey> {
+        let existing = self
+            .get::<IamApplicationsListResponse>(&format!(
+                "iam/v1alpha1/applications?organization_id={}&name={}",
+                self.organization_id, name
+            ))
+            .await?
+            .applications
+            .into_iter()
+            .next();
+        let application = match existing {
+            Some(application) => application,
+            None => {
+                let application = self
+                    .post::<IamApplication, _>(
+                        "iam/v1alpha1/applications",
+                        &serde_json::json!({
+                            "name": name,
+                            "organization_id": self.organization_id,
+                            "description": "Filen Relay database storage",
+                        }),
+                    )
+                    .await?;
+                self.post::<serde_json::Value, _>(
+                    "iam/v1alpha1/policies",
+                    &serde_json::json!({
+                        "name": name,
+                        "organization_id": self.organization_id,
+                        "application_id": application.id,
+                        "rules": [{
+                            "permission_set_names": ["ObjectStorageFullAccess"],
+                            "project_ids": [project_id],
+                        }],
+                    }),
+                )
+                .await?;
+                application
+            }
+        };
+        let key = self
+            .post::<IamApiKey, _>(
+                "iam/v1alpha1/api-keys",
+                &serde_json::json!({
+                    "application_id": application.id,
+                    "default_project_id": project_id,
+                    "description": "Filen Relay database storage",
+                }),
+            )
+            .await?;
+        Ok(key)
+    }
+
     pub async fn deploy_container(&self, container_id: &str) -> Result<()> {
         self.post::<serde_json::Value, _>(
             &format!(
@@ -1,10 +1,27 @@
-use crate::common::{LogLine, ServerId, ServerState, ServerType};
+use crate::common::{
+    ActivityEvent, AddedServer, AllowedUser, AllowedUsersImportResult, ApiError, AuditEntry,
+    AuditFilter, CacheUsage, DrainStatus, EgressCap, GroupAction, GroupActionResult, LogLevel,
+    LogLine, MaintenanceTaskStatus, NotificationChannel, NotificationChannelKind, ServerDefaults,
+    ServerId, ServerOptions, ServerSpecImportResult, ServerSpecPublic, ServerState,
+    ServerStatsSeries, ServerTemplate, ServerTestResult, ServerType, Settings, StatsRange,
+    StatusPage, StatusPageContent, SystemMetrics, Team, Transfer, UpdateInfo, UserSession,
+};
 use dioxus::fullstack::{response::Response, JsonEncoding, Streaming};
 use dioxus::prelude::*;
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "server")]
-use crate::backend::{auth, db::DB, server_manager, server_manager::SERVER_MANAGER};
+use crate::{
+    backend::{
+        self, activity, audit, auth, dashboard, db::DB, drain, drive, email, ephemeral, forwarded,
+        metrics, mock, notifications, scheduler, server_manager, server_manager::SERVER_MANAGER,
+        teams, undo, usage,
+    },
+    common::{
+        validate_server_spec, ApiErrorCode, ApiErrorDetails, AuditAction, ServerErrorReason,
+        ServerSpec, ServerSpecExport, ServerSpecValidationError, ServerStatus, StatusPageServer,
+    },
+};
 
 #[derive(Serialize, Deserialize)]
 pub(crate) struct User {
@@ -12,48 +29,166 @@ pub(crate) struct User {
     pub is_admin: bool,
 }
 
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub(crate) struct VersionInfo {
+    pub version: String,
+    pub git_commit: Option<String>,
+    pub build_date: Option<String>,
+    pub rclone_version: Option<String>,
+}
+
+#[get("/api/version")]
+pub(crate) async fn get_version() -> Result<VersionInfo, ApiError> {
+    Ok(VersionInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_commit: option_env!("FILEN_RELAY_GIT_COMMIT").map(|s| s.to_string()),
+        build_date: option_env!("FILEN_RELAY_BUILD_DATE").map(|s| s.to_string()),
+        rclone_version: server_manager::rclone_version().await,
+    })
+}
+
+/// Returns the newest release if it is newer than this instance (checked periodically).
+#[get("/api/update", session: auth::Session)]
+pub(crate) async fn get_update() -> Result<Option<UpdateInfo>, ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    Ok(backend::updates::available_update())
+}
+
 #[post("/api/user", session: auth::Session)]
-pub(crate) async fn get_user() -> Result<User> {
+pub(crate) async fn get_user() -> Result<User, ApiError> {
     Ok(User {
         email: session.filen_email,
         is_admin: session.is_admin,
     })
 }
 
-#[post("/api/login")]
+/// Returns the URL under which users reach this relay, if configured or reported by a trusted reverse proxy
+/// (otherwise, the browser's origin applies).
+#[get("/api/publicUrl", client: forwarded::ClientInfo)]
+pub(crate) async fn get_public_url() -> Result<Option<String>, ApiError> {
+    Ok(backend::public_url().or_else(|| client.forwarded_origin()))
+}
+
+/// The `Set-Cookie` header value for the session cookie, only sent via HTTPS if the relay is reached via HTTPS.
+#[cfg(feature = "server")]
+fn session_cookie(token: &str, client: &forwarded::ClientInfo) -> String {
+    let secure =
+        client.https || backend::public_url().is_some_and(|url| url.starts_with("https://"));
+    format!(
+        "Session={}; HttpOnly; Path=/{}",
+        token,
+        if secure { "; Secure" } else { "" }
+    )
+}
+
+#[post("/api/login", client: forwarded::ClientInfo)]
 pub(crate) async fn login(
     email: String,
     password: String,
     two_factor_code: Option<String>,
-) -> Result<Response, anyhow::Error> {
-    let token = auth::login_and_get_session_token(email, password, two_factor_code).await?;
+) -> Result<Response, ApiError> {
+    drain::check_not_draining()?;
+    let token =
+        auth::login_and_get_session_token(email, password, two_factor_code, &client).await?;
+    use dioxus::fullstack::{body::Body, response::Response};
+    Ok(Response::builder()
+        .header("Set-Cookie", session_cookie(&token.to_string(), &client))
+        .body(Body::empty())
+        .unwrap())
+}
+
+/// Like `login`, but with an auth config exported via the Filen CLI (`filen export-auth-config`).
+#[post("/api/login/authConfig", client: forwarded::ClientInfo)]
+pub(crate) async fn login_with_auth_config(auth_config: String) -> Result<Response, ApiError> {
+    drain::check_not_draining()?;
+    let token = auth::login_with_auth_config_and_get_session_token(&auth_config, &client).await?;
     use dioxus::fullstack::{body::Body, response::Response};
     Ok(Response::builder()
-        .header("Set-Cookie", format!("Session={}; HttpOnly; Path=/", token))
+        .header("Set-Cookie", session_cookie(&token.to_string(), &client))
         .body(Body::empty())
         .unwrap())
 }
 
-#[post("/api/logout")]
-pub(crate) async fn logout() -> Result<Response> {
+/// Completes a login that requires entering the code sent to the user's email (see `ApiErrorDetails::EmailVerification`).
+#[post("/api/login/verifyEmail", client: forwarded::ClientInfo)]
+pub(crate) async fn verify_login_email(
+    verification_id: String,
+    code: String,
+) -> Result<Response, ApiError> {
+    drain::check_not_draining()?;
+    let token = auth::verify_email_and_get_session_token(&verification_id, &code, &client).await?;
     use dioxus::fullstack::{body::Body, response::Response};
     Ok(Response::builder()
-        .header("Set-Cookie", "Session=; HttpOnly; Path=/")
+        .header("Set-Cookie", session_cookie(&token.to_string(), &client))
         .body(Body::empty())
         .unwrap())
 }
 
+#[post("/api/logout", client: forwarded::ClientInfo)]
+pub(crate) async fn logout() -> Result<Response, ApiError> {
+    use dioxus::fullstack::{body::Body, response::Response};
+    Ok(Response::builder()
+        .header("Set-Cookie", session_cookie("", &client))
+        .body(Body::empty())
+        .unwrap())
+}
+
+/// Logs out all sessions of the user (e.g. after a lost device), including the current one. The admin may instead
+/// log out all sessions of another user by passing their email.
+#[post("/api/logout_all", session: auth::Session, client: forwarded::ClientInfo)]
+pub(crate) async fn logout_all(email: Option<String>) -> Result<Response, ApiError> {
+    let email = email.unwrap_or_else(|| session.filen_email.clone());
+    let is_own = email == session.filen_email;
+    if !is_own && !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    let revoked = ephemeral::delete_user_sessions(&email)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to revoke sessions: {}", e)))?;
+    if !is_own {
+        audit::record(
+            &session,
+            AuditAction::SessionsRevoked,
+            format!("{} sessions of {}", revoked, email),
+        )
+        .await;
+    }
+    use dioxus::fullstack::{body::Body, response::Response};
+    let mut response = Response::builder();
+    if is_own {
+        response = response.header("Set-Cookie", session_cookie("", &client));
+    }
+    Ok(response.body(Body::empty()).unwrap())
+}
+
+/// Returns the sessions of the user, i.e. the devices they are logged in on.
+#[get("/api/sessions", session: auth::Session)]
+pub(crate) async fn get_sessions() -> Result<Vec<UserSession>, ApiError> {
+    ephemeral::get_user_sessions(&session.filen_email, &session.token)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get sessions: {}", e)))
+}
+
+/// Logs out one of the user's sessions, e.g. on a lost device.
+#[post("/api/sessions/remove", session: auth::Session)]
+pub(crate) async fn remove_session(id: String) -> Result<(), ApiError> {
+    let removed = ephemeral::delete_user_session(&session.filen_email, &id)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to remove session: {}", e)))?;
+    if !removed {
+        return Err(ApiError::not_found("Session not found"));
+    }
+    Ok(())
+}
+
 #[get("/api/servers", session: auth::Session)]
-pub(crate) async fn get_servers() -> Result<Streaming<Vec<ServerState>, JsonEncoding>> {
+pub(crate) async fn get_servers() -> Result<Streaming<Vec<ServerState>, JsonEncoding>, ApiError> {
     Ok(Streaming::spawn(move |tx| async move {
         let send_server_states = || {
-            let server_states = SERVER_MANAGER
-                .get_server_states()
-                .borrow()
-                .iter()
-                .filter(|s| session.is_admin || s.spec.filen_email == session.filen_email)
-                .cloned()
-                .collect::<Vec<ServerState>>();
+            let server_states =
+                session.visible_server_states(&SERVER_MANAGER.get_server_states().borrow());
             if let Err(e) = tx.unbounded_send(server_states) {
                 dioxus::logger::tracing::error!("Failed to send server states: {}", e);
                 false
@@ -79,22 +214,25 @@ pub(crate) async fn get_servers() -> Result<Streaming<Vec<ServerState>, JsonEnco
     }))
 }
 
-#[get("/api/logs/{logs_id}", session: auth::Session)]
-pub(crate) async fn get_logs(logs_id: String) -> Result<Streaming<LogLine, JsonEncoding>> {
-    let Some(logs) = SERVER_MANAGER.get_logs(&logs_id) else {
-        return Err(anyhow::anyhow!("Logs not found"))?;
+/// Streams the logs of a server: the history, then new lines as they come in.
+/// `tail` limits the history to its last lines. To resume after a reconnect, pass the `index` of the last received
+/// line as `cursor`.
+#[post("/api/logs", session: auth::Session)]
+pub(crate) async fn get_logs(
+    server_id: String,
+    tail: Option<usize>,
+    cursor: Option<u64>,
+) -> Result<Streaming<LogLine, JsonEncoding>, ApiError> {
+    let Some(logs) = SERVER_MANAGER.get_logs(&server_id) else {
+        return Err(ApiError::not_found("Logs not found"));
     };
-    if !session.is_admin && logs.server_spec.filen_email != session.filen_email {
-        return Err(anyhow::anyhow!("Unauthorized to access logs"))?;
-    }
-    Ok(Streaming::spawn(|tx| async move {
-        let (history, mut rx) = {
-            let logs = logs.logs.lock().unwrap();
-            let (history, rx) = logs.get();
-            (history.clone(), rx.resubscribe())
-        };
+    if !session.can_access(&logs.server_spec) {
+        return Err(ApiError::forbidden());
+    }
+    Ok(Streaming::spawn(move |tx| async move {
+        let (history, mut rx) = logs.subscribe(tail, cursor);
         for line in history {
-            if tx.unbounded_send(line.clone()).is_err() {
+            if tx.unbounded_send(line).is_err() {
                 return;
             }
         }
@@ -106,81 +244,1352 @@ pub(crate) async fn get_logs(logs_id: String) -> Result<Streaming<LogLine, JsonE
     }))
 }
 
-#[post("/api/servers/add", session: auth::Session)]
+/// Returns the files currently being transferred by a server.
+#[post("/api/servers/transfers", session: auth::Session)]
+pub(crate) async fn get_transfers(server_id: String) -> Result<Vec<Transfer>, ApiError> {
+    let id = SERVER_MANAGER
+        .get_server_states()
+        .borrow()
+        .iter()
+        .find(|s| s.spec.id.to_string() == server_id && session.can_access(&s.spec))
+        .map(|s| s.spec.id.clone())
+        .ok_or_else(|| ApiError::not_found("Server not found or not owned by user"))?;
+    Ok(SERVER_MANAGER.get_transfers(&id).await?)
+}
+
+/// Returns the requests and transferred bytes per hour of the servers the user can access, for the dashboard.
+/// With `all`, the admin gets those of all servers instead.
+#[post("/api/dashboard", session: auth::Session)]
+pub(crate) async fn get_dashboard(
+    range: StatsRange,
+    all: bool,
+) -> Result<Vec<ServerStatsSeries>, ApiError> {
+    if all && !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    let servers = SERVER_MANAGER
+        .get_server_states()
+        .borrow()
+        .iter()
+        .filter(|s| all || session.can_access(&s.spec))
+        .map(|s| s.spec.clone())
+        .collect::<Vec<_>>();
+    Ok(dashboard::get_series(&servers, range).await?)
+}
+
+/// How long `add_server` waits for a new server to start, if asked to.
+#[cfg(feature = "server")]
+const ADD_SERVER_WAIT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Sets the options of a new server that weren't set to the server defaults, and resets the ones that don't apply
+/// to its type.
+#[cfg(feature = "server")]
+async fn prepare_new_server_options(
+    server_type: &ServerType,
+    options: &mut ServerOptions,
+) -> Result<(), ApiError> {
+    DB.get_settings()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get settings: {}", e)))?
+        .server_defaults
+        .apply(options);
+    options.clear_unsupported(server_type);
+    Ok(())
+}
+
+/// Validation errors are returned with `ApiErrorDetails::Validation`, so that they can be displayed next to the offending fields.
+/// If `create_root` is set, missing root directories (including additional roots) are created instead of being rejected.
+/// If the server is created from a template, its admin-only options are taken from the template.
+/// If `wait` is set, the request returns once the server is running, or fails with `ApiErrorDetails::ServerStartFailed`
+/// if it doesn't start (within `ADD_SERVER_WAIT_TIMEOUT`). Otherwise, the server starts in the background.
+#[post("/api/servers/add", session: auth::Session, client: forwarded::ClientInfo)]
 pub(crate) async fn add_server(
     name: String,
     server_type: ServerType,
     root: String,
     read_only: bool,
     password: Option<String>,
-) -> Result<(), anyhow::Error> {
-    SERVER_MANAGER
-        .update_server_spec(server_manager::ServerSpecUpdate::Add(
-            crate::common::ServerSpec {
-                id: ServerId::new(),
-                name,
-                server_type,
-                root,
-                read_only,
-                password,
-                filen_email: session.filen_email,
-                filen_password: session.filen_password,
-                filen_2fa_code: session.filen_2fa_code,
+    mut options: ServerOptions,
+    create_root: bool,
+    template_id: Option<String>,
+    team_id: Option<String>,
+    wait: bool,
+) -> Result<AddedServer, ApiError> {
+    match template_id {
+        Some(template_id) => {
+            let template = DB
+                .get_server_templates()
+                .await
+                .map_err(|e| ApiError::internal(format!("Failed to get server templates: {}", e)))?
+                .into_iter()
+                .find(|t| t.id == template_id)
+                .ok_or_else(|| ApiError::not_found("Server template not found"))?;
+            options.cache_dir = template.options.cache_dir;
+            options.extra_flags = template.options.extra_flags;
+        }
+        None if options.has_admin_only_options() && !session.is_admin => {
+            return Err(ApiError::forbidden());
+        }
+        None => {}
+    }
+    prepare_new_server_options(&server_type, &mut options).await?;
+    let password = password.filter(|p| !p.is_empty());
+    let servers = DB
+        .get_servers()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get servers: {}", e)))?;
+    if let Some(team_id) = &team_id {
+        let team = teams::find(team_id)
+            .filter(|t| session.is_admin || t.is_member(&session.filen_email))
+            .ok_or_else(|| ApiError::not_found("Team not found"))?;
+        let team_servers = servers
+            .iter()
+            .filter(|s| s.team_id.as_ref() == Some(team_id))
+            .count();
+        if team
+            .max_servers
+            .is_some_and(|max| team_servers as u64 >= max)
+        {
+            let message = format!("Team {} can't own more servers", team.name);
+            notifications::notify(notifications::Notification::QuotaExceeded {
+                email: session.filen_email.clone(),
+                message: message.clone(),
+            });
+            return Err(ApiError::new(ApiErrorCode::QuotaExceeded, message));
+        }
+    }
+    let existing_names = servers
+        .into_iter()
+        .filter(|s| s.filen_email == session.filen_email)
+        .map(|s| s.name)
+        .collect::<Vec<_>>();
+    let errors = validate_server_spec(
+        &name,
+        &server_type,
+        &root,
+        read_only,
+        password.as_deref(),
+        &options,
+        &existing_names,
+    );
+    if !errors.is_empty() {
+        return Err(ApiError::validation(errors));
+    }
+    // test accounts have no drive to check in mock mode
+    if !mock::is_enabled() {
+        let client = session.filen_client()?;
+        for root in std::iter::once(&root).chain(&options.additional_roots) {
+            if !drive::directory_exists(&client, root).await? {
+                if create_root {
+                    drive::create_directory(&client, root).await?;
+                } else {
+                    return Err(ApiError::validation(vec![
+                        ServerSpecValidationError::RootNotFound,
+                    ]));
+                }
+            }
+        }
+    }
+    SERVER_MANAGER.check_capacity(&session.filen_email).await?;
+    let id = backend::generate_server_id().await?;
+    let spec = ServerSpec {
+        id: id.clone(),
+        name: name.trim().to_string(),
+        server_type,
+        root,
+        read_only,
+        password,
+        filen_email: session.filen_email.clone(),
+        filen_auth_config: session.filen_auth_config.clone(),
+        options,
+        collaborators: Vec::new(),
+        team_id,
+    };
+    let short_id = id.prefix(backend::short_id_length());
+    let url = match spec.server_type {
+        ServerType::Ftp | ServerType::Sftp => None,
+        _ => Some(format!(
+            "{}/s/{}/",
+            backend::public_url()
+                .or_else(|| client.forwarded_origin())
+                .unwrap_or_default(),
+            short_id
+        )),
+    };
+    let add =
+        SERVER_MANAGER.update_server_spec(server_manager::ServerSpecUpdate::Add(spec.clone()));
+    if !wait {
+        // the progress is reported by the server states stream
+        tokio::spawn(async move {
+            let _ = add.await;
+        });
+        return Ok(AddedServer {
+            state: ServerState {
+                spec: (&spec).into(),
+                short_id,
+                status: ServerStatus::Starting,
+                process: None,
             },
-        ))
+            url,
+        });
+    }
+    let result = tokio::time::timeout(ADD_SERVER_WAIT_TIMEOUT, add).await;
+    let state = SERVER_MANAGER
+        .get_server_states()
+        .borrow()
+        .iter()
+        .find(|s| s.spec.id == id)
+        .cloned();
+    let failed = |message: String, reason: Option<ServerErrorReason>| {
+        ApiError::internal(message).with_details(ApiErrorDetails::ServerStartFailed {
+            server_id: id.clone(),
+            reason,
+        })
+    };
+    match (result, state) {
+        (Ok(Ok(())), Some(state)) => Ok(AddedServer {
+            state: session.visible_server_states(&[state]).remove(0),
+            url,
+        }),
+        (Ok(Ok(())), None) => Err(ApiError::internal("The server was removed after starting")),
+        // the server exists, but failed to start
+        (Ok(Err(e)), Some(state)) => {
+            let reason = match state.status {
+                ServerStatus::Error { reason, .. } => Some(reason),
+                _ => None,
+            };
+            Err(failed(format!("{:#}", e), reason))
+        }
+        (Ok(Err(e)), None) => Err(e.into()),
+        (Err(_), _) => Err(failed(
+            format!(
+                "The server didn't start within {} seconds",
+                ADD_SERVER_WAIT_TIMEOUT.as_secs()
+            ),
+            None,
+        )),
+    }
+}
+
+/// Tests a newly created server once it has started, see `ServerManagerApi::test_server`.
+#[post("/api/servers/test", session: auth::Session)]
+pub(crate) async fn test_server(id: ServerId) -> Result<ServerTestResult, ApiError> {
+    let accessible = SERVER_MANAGER
+        .get_server_states()
+        .borrow()
+        .iter()
+        .any(|s| s.spec.id == id && session.can_access(&s.spec));
+    // the server may not have been added to the states yet, right after creating it
+    let owned = accessible
+        || DB
+            .get_servers()
+            .await
+            .map_err(|e| ApiError::internal(format!("Failed to get servers: {}", e)))?
+            .iter()
+            .any(|s| s.id == id && session.can_access(s));
+    if !owned {
+        return Err(ApiError::not_found("Server not found or not owned by user"));
+    }
+    Ok(SERVER_MANAGER.test_server(&id).await?)
+}
+
+/// Imports server specs previously exported via `/api/servers/export` (as JSON), owned by the current user.
+/// Specs that are invalid or whose roots don't exist in the user's drive are skipped, along with the reasons.
+#[post("/api/servers/import", session: auth::Session)]
+pub(crate) async fn import_servers(json: String) -> Result<ServerSpecImportResult, ApiError> {
+    let specs = serde_json::from_str::<Vec<ServerSpecExport>>(&json).map_err(|e| {
+        ApiError::new(
+            ApiErrorCode::InvalidInput,
+            format!("Invalid server specs: {}", e),
+        )
+    })?;
+    if specs.iter().any(|s| s.options.has_admin_only_options()) && !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    let mut existing_names = DB
+        .get_servers()
         .await
+        .map_err(|e| ApiError::internal(format!("Failed to get servers: {}", e)))?
+        .into_iter()
+        .filter(|s| s.filen_email == session.filen_email)
+        .map(|s| s.name)
+        .collect::<Vec<_>>();
+    // servers beyond the cap are imported, but fail to start with `ServerErrorReason::CapacityReached`
+    SERVER_MANAGER.check_capacity(&session.filen_email).await?;
+    let mut result = ServerSpecImportResult {
+        imported: Vec::new(),
+        skipped: Vec::new(),
+    };
+    // test accounts have no drive to check in mock mode
+    let client = if mock::is_enabled() {
+        None
+    } else {
+        Some(session.filen_client()?)
+    };
+    for mut spec in specs {
+        prepare_new_server_options(&spec.server_type, &mut spec.options).await?;
+        let password = spec.password.filter(|p| !p.is_empty());
+        let errors = validate_server_spec(
+            &spec.name,
+            &spec.server_type,
+            &spec.root,
+            spec.read_only,
+            password.as_deref(),
+            &spec.options,
+            &existing_names,
+        );
+        if !errors.is_empty() {
+            result.skipped.push((spec.name, errors));
+            continue;
+        }
+        if let Some(client) = &client {
+            let mut root_missing = false;
+            for root in std::iter::once(&spec.root).chain(&spec.options.additional_roots) {
+                if !drive::directory_exists(client, root).await? {
+                    root_missing = true;
+                    break;
+                }
+            }
+            if root_missing {
+                result
+                    .skipped
+                    .push((spec.name, vec![ServerSpecValidationError::RootNotFound]));
+                continue;
+            }
+        }
+        let name = spec.name.trim().to_string();
+        SERVER_MANAGER
+            .update_server_spec(server_manager::ServerSpecUpdate::Add(ServerSpec {
+                id: backend::generate_server_id().await?,
+                name: name.clone(),
+                server_type: spec.server_type,
+                root: spec.root,
+                read_only: spec.read_only,
+                password,
+                filen_email: session.filen_email.clone(),
+                filen_auth_config: session.filen_auth_config.clone(),
+                options: spec.options,
+                collaborators: Vec::new(),
+                team_id: None,
+            }))
+            .await?;
+        existing_names.push(name.clone());
+        result.imported.push(name);
+    }
+    Ok(result)
+}
+
+/// Lists the directories at `path` in the current user's Filen drive.
+#[post("/api/drive/directories", session: auth::Session)]
+pub(crate) async fn list_drive_directories(path: String) -> Result<Vec<String>, ApiError> {
+    if mock::is_enabled() {
+        return Ok(mock::list_directories(&path));
+    }
+    let client = session.filen_client()?;
+    Ok(drive::list_directories(&client, &path).await?)
 }
 
+/// Removes a server. Only the owner (or admin) may do this, collaborators can't.
 #[post("/api/servers/remove", session: auth::Session)]
-pub(crate) async fn remove_server(id: ServerId) -> Result<(), anyhow::Error> {
+pub(crate) async fn remove_server(id: ServerId) -> Result<(), ApiError> {
+    let can_share = SERVER_MANAGER
+        .get_server_states()
+        .borrow()
+        .iter()
+        .find(|s| s.spec.id == id && session.can_access(&s.spec))
+        .map(|s| session.can_share(&s.spec))
+        .ok_or_else(|| ApiError::not_found("Server not found or not owned by user"))?;
+    if !can_share {
+        return Err(ApiError::forbidden());
+    }
+    SERVER_MANAGER
+        .update_server_spec(server_manager::ServerSpecUpdate::Remove(id.clone()))
+        .await?;
+    undo::record_removal(id, &session.filen_email);
+    Ok(())
+}
+
+/// Restores a server the user removed within the last `REMOVE_UNDO_WINDOW`.
+#[post("/api/servers/undoRemove", session: auth::Session)]
+pub(crate) async fn undo_remove_server(id: ServerId) -> Result<(), ApiError> {
+    if !undo::has_removal(&id, &session.filen_email) {
+        return Err(ApiError::not_found(
+            "Server not removed by user recently enough to undo",
+        ));
+    }
+    SERVER_MANAGER.check_capacity(&session.filen_email).await?;
+    if !undo::take_removal(&id, &session.filen_email) {
+        return Err(ApiError::not_found(
+            "Server not removed by user recently enough to undo",
+        ));
+    }
+    SERVER_MANAGER
+        .update_server_spec(server_manager::ServerSpecUpdate::Restore(id))
+        .await?;
+    Ok(())
+}
+
+/// Starts a server that was paused due to inactivity.
+#[post("/api/servers/resume", session: auth::Session)]
+pub(crate) async fn resume_server(id: ServerId) -> Result<(), ApiError> {
     SERVER_MANAGER
         .get_server_states()
         .borrow()
         .iter()
         .find(|s| {
-            s.spec.id == id && (session.is_admin || s.spec.filen_email == session.filen_email)
+            s.spec.id == id
+                && session.can_access(&s.spec)
+                && matches!(s.status, ServerStatus::Paused)
         })
-        .ok_or_else(|| anyhow::anyhow!("Server not found or not owned by user"))?;
+        .ok_or_else(|| ApiError::not_found("Paused server not found or not owned by user"))?;
+    SERVER_MANAGER.check_capacity(&session.filen_email).await?;
     SERVER_MANAGER
-        .update_server_spec(server_manager::ServerSpecUpdate::Remove(id))
+        .update_server_spec(server_manager::ServerSpecUpdate::Resume(id))
+        .await?;
+    Ok(())
+}
+
+/// Restarts a server, e.g. to recover from an error.
+#[post("/api/servers/restart", session: auth::Session)]
+pub(crate) async fn restart_server(id: ServerId) -> Result<(), ApiError> {
+    SERVER_MANAGER
+        .get_server_states()
+        .borrow()
+        .iter()
+        .find(|s| {
+            s.spec.id == id
+                && session.can_access(&s.spec)
+                && !matches!(s.status, ServerStatus::Paused)
+        })
+        .ok_or_else(|| ApiError::not_found("Server not found or not owned by user"))?;
+    SERVER_MANAGER
+        .update_server_spec(server_manager::ServerSpecUpdate::Restart(id))
+        .await?;
+    Ok(())
+}
+
+/// Changes the log level of a server (`None` for the default), without restarting it.
+#[post("/api/servers/logLevel", session: auth::Session)]
+pub(crate) async fn set_server_log_level(
+    id: ServerId,
+    level: Option<LogLevel>,
+) -> Result<(), ApiError> {
+    SERVER_MANAGER
+        .get_server_states()
+        .borrow()
+        .iter()
+        .find(|s| s.spec.id == id && session.can_access(&s.spec))
+        .ok_or_else(|| ApiError::not_found("Server not found or not owned by user"))?;
+    SERVER_MANAGER
+        .update_server_spec(server_manager::ServerSpecUpdate::SetLogLevel(id, level))
+        .await?;
+    Ok(())
+}
+
+/// Starts or stops (pauses) every server with the given tag the user can access, streaming the outcome per server.
+#[post("/api/servers/group", session: auth::Session)]
+pub(crate) async fn run_group_action(
+    tag: String,
+    action: GroupAction,
+) -> Result<Streaming<GroupActionResult, JsonEncoding>, ApiError> {
+    let servers = SERVER_MANAGER
+        .get_server_states()
+        .borrow()
+        .iter()
+        .filter(|s| session.can_access(&s.spec) && s.spec.options.tags.contains(&tag))
+        .cloned()
+        .collect::<Vec<_>>();
+    if servers.is_empty() {
+        return Err(ApiError::not_found("No servers with this tag"));
+    }
+    Ok(Streaming::spawn(move |tx| async move {
+        for server in servers {
+            let error = apply_group_action(&session, &server, action)
+                .await
+                .err()
+                .map(|e| e.message);
+            let result = GroupActionResult {
+                server_id: server.spec.id,
+                name: server.spec.name,
+                error,
+            };
+            if tx.unbounded_send(result).is_err() {
+                return;
+            }
+        }
+    }))
+}
+
+#[cfg(feature = "server")]
+async fn apply_group_action(
+    session: &auth::Session,
+    server: &ServerState<ServerSpec>,
+    action: GroupAction,
+) -> Result<(), ApiError> {
+    let id = server.spec.id.clone();
+    let update = match (action, &server.status) {
+        (GroupAction::Start, ServerStatus::Starting | ServerStatus::Running)
+        | (GroupAction::Stop, ServerStatus::Paused) => return Ok(()),
+        (GroupAction::Start, ServerStatus::Paused) => {
+            SERVER_MANAGER.check_capacity(&session.filen_email).await?;
+            server_manager::ServerSpecUpdate::Resume(id)
+        }
+        (GroupAction::Start, ServerStatus::Error { .. }) => {
+            server_manager::ServerSpecUpdate::Restart(id)
+        }
+        (GroupAction::Stop, _) => server_manager::ServerSpecUpdate::Pause(id),
+    };
+    // returns once the server has started or stopped
+    SERVER_MANAGER.update_server_spec(update).await?;
+    Ok(())
+}
+
+/// Shares a server with other allowed users, replacing the previous collaborators. Only the owner (or admin) may do this.
+#[post("/api/servers/collaborators", session: auth::Session)]
+pub(crate) async fn set_server_collaborators(
+    id: ServerId,
+    collaborators: Vec<String>,
+) -> Result<(), ApiError> {
+    let (owner_email, can_share) = SERVER_MANAGER
+        .get_server_states()
+        .borrow()
+        .iter()
+        .find(|s| s.spec.id == id && session.can_access(&s.spec))
+        .map(|s| (s.spec.filen_email.clone(), session.can_share(&s.spec)))
+        .ok_or_else(|| ApiError::not_found("Server not found or not owned by user"))?;
+    if !can_share {
+        return Err(ApiError::forbidden());
+    }
+    let emails = validate_user_emails(collaborators, &owner_email).await?;
+    SERVER_MANAGER
+        .update_server_spec(server_manager::ServerSpecUpdate::SetCollaborators(
+            id, emails,
+        ))
+        .await?;
+    Ok(())
+}
+
+/// Trims and deduplicates the emails (dropping `owner`), rejecting users who aren't allowed to log in.
+#[cfg(feature = "server")]
+async fn validate_user_emails(emails: Vec<String>, owner: &str) -> Result<Vec<String>, ApiError> {
+    let mut valid_emails = Vec::<String>::new();
+    for email in emails {
+        let email = email.trim().to_string();
+        if email.is_empty() || email == owner || valid_emails.contains(&email) {
+            continue;
+        }
+        if !auth::is_user_allowed(&email).await? {
+            return Err(ApiError::new(
+                ApiErrorCode::InvalidInput,
+                format!("{} is not an allowed user", email),
+            ));
+        }
+        valid_emails.push(email);
+    }
+    Ok(valid_emails)
+}
+
+#[get("/api/servers/archived", session: auth::Session)]
+pub(crate) async fn get_archived_servers() -> Result<Vec<ServerSpecPublic>, ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    Ok(DB
+        .get_archived_servers()
         .await
+        .map_err(|e| ApiError::internal(format!("Failed to get archived servers: {}", e)))?
+        .iter()
+        .map(ServerSpecPublic::from)
+        .collect())
+}
+
+#[post("/api/servers/restore", session: auth::Session)]
+pub(crate) async fn restore_server(id: ServerId) -> Result<(), ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    SERVER_MANAGER.check_capacity(&session.filen_email).await?;
+    SERVER_MANAGER
+        .update_server_spec(server_manager::ServerSpecUpdate::Restore(id.clone()))
+        .await?;
+    audit::record(&session, AuditAction::ServerRestored, id.to_string()).await;
+    Ok(())
+}
+
+#[post("/api/servers/purge", session: auth::Session)]
+pub(crate) async fn purge_server(id: ServerId) -> Result<(), ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    SERVER_MANAGER
+        .update_server_spec(server_manager::ServerSpecUpdate::Purge(id.clone()))
+        .await?;
+    audit::record(&session, AuditAction::ServerPurged, id.to_string()).await;
+    Ok(())
 }
 
+/// Returns the allowed users with their activity (last login, servers and active sessions).
 #[get("/api/allowedUsers", session: auth::Session)]
-pub(crate) async fn get_allowed_users() -> Result<Vec<String>, anyhow::Error> {
+pub(crate) async fn get_allowed_users() -> Result<Vec<AllowedUser>, ApiError> {
     if !session.is_admin {
-        return Err(anyhow::anyhow!("Unauthorized"));
+        return Err(ApiError::forbidden());
     }
-    DB.get_allowed_users()
-        .map_err(|e| anyhow::anyhow!("Failed to get allowed users: {}", e))
+    ephemeral::get_allowed_users_activity()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get allowed users: {}", e)))
 }
 
 #[post("/api/allowedUsers/add", session: auth::Session)]
-pub(crate) async fn add_allowed_user(email: String) -> Result<(), anyhow::Error> {
+pub(crate) async fn add_allowed_user(email: String) -> Result<(), ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    let email = email.trim();
+    if !is_valid_email(email) {
+        return Err(ApiError::new(
+            ApiErrorCode::InvalidInput,
+            format!("{} is not a valid email address", email),
+        ));
+    }
+    DB.add_allowed_user(email)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to add allowed user: {}", e)))?;
+    audit::record(
+        &session,
+        AuditAction::AllowedUsersChanged,
+        format!("Added {}", email),
+    )
+    .await;
+    Ok(())
+}
+
+/// Adds the emails in `list` (one per line or comma-separated, e.g. a CSV column) to the allowed users,
+/// skipping duplicates and invalid entries. With `dry_run`, only returns what would be added.
+#[post("/api/allowedUsers/import", session: auth::Session)]
+pub(crate) async fn import_allowed_users(
+    list: String,
+    dry_run: bool,
+) -> Result<AllowedUsersImportResult, ApiError> {
     if !session.is_admin {
-        return Err(anyhow::anyhow!("Unauthorized"));
+        return Err(ApiError::forbidden());
     }
-    DB.add_allowed_user(&email)
+    let mut known = DB
+        .get_allowed_users()
         .await
-        .map_err(|e| anyhow::anyhow!("Failed to add allowed user: {}", e))
+        .map_err(|e| ApiError::internal(format!("Failed to get allowed users: {}", e)))?;
+    let mut result = AllowedUsersImportResult {
+        added: Vec::new(),
+        duplicates: Vec::new(),
+        invalid: Vec::new(),
+    };
+    for entry in list.split(['\n', '\r', ',', ';']) {
+        let email = entry.trim().trim_matches('"').trim();
+        if email.is_empty() || email.eq_ignore_ascii_case("email") {
+            continue; // blank lines or a CSV header
+        }
+        if !is_valid_email(email) {
+            result.invalid.push(email.to_string());
+        } else if known.iter().any(|known| known.eq_ignore_ascii_case(email)) {
+            result.duplicates.push(email.to_string());
+        } else {
+            known.push(email.to_string());
+            result.added.push(email.to_string());
+        }
+    }
+    if !dry_run {
+        DB.add_allowed_users(&result.added)
+            .await
+            .map_err(|e| ApiError::internal(format!("Failed to add allowed users: {}", e)))?;
+        if !result.added.is_empty() {
+            audit::record(
+                &session,
+                AuditAction::AllowedUsersChanged,
+                format!("Imported {}", result.added.join(", ")),
+            )
+            .await;
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(feature = "server")]
+fn is_valid_email(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && domain.contains('.')
+                && !domain.contains('@')
+                && !email.contains(char::is_whitespace)
+        }
+        None => false,
+    }
 }
 
 #[post("/api/allowedUsers/remove", session: auth::Session)]
-pub(crate) async fn remove_allowed_user(email: String) -> Result<(), anyhow::Error> {
+pub(crate) async fn remove_allowed_user(email: String) -> Result<(), ApiError> {
     if !session.is_admin {
-        return Err(anyhow::anyhow!("Unauthorized"));
+        return Err(ApiError::forbidden());
     }
     DB.remove_allowed_user(&email)
         .await
-        .map_err(|e| anyhow::anyhow!("Failed to remove allowed user: {}", e))
+        .map_err(|e| ApiError::internal(format!("Failed to remove allowed user: {}", e)))?;
+    audit::record(
+        &session,
+        AuditAction::AllowedUsersChanged,
+        format!("Removed {}", email),
+    )
+    .await;
+    Ok(())
 }
 
 #[post("/api/allowedUsers/clear", session: auth::Session)]
-pub(crate) async fn clear_allowed_users() -> Result<(), anyhow::Error> {
+pub(crate) async fn clear_allowed_users() -> Result<(), ApiError> {
     if !session.is_admin {
-        return Err(anyhow::anyhow!("Unauthorized"));
+        return Err(ApiError::forbidden());
     }
     DB.clear_allowed_users()
         .await
-        .map_err(|e| anyhow::anyhow!("Failed to clear allowed users: {}", e))
+        .map_err(|e| ApiError::internal(format!("Failed to clear allowed users: {}", e)))?;
+    audit::record(&session, AuditAction::AllowedUsersChanged, "Cleared all").await;
+    Ok(())
+}
+
+/// Lists the database backups (in the admin's Filen drive or the S3 bucket), newest first.
+#[get("/api/backups", session: auth::Session)]
+pub(crate) async fn get_backups() -> Result<Vec<String>, ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    Ok(DB.list_backups().await?)
+}
+
+/// Returns the name of a database backup written by someone else (e.g. another relay instance), if one was detected.
+#[get("/api/backups/conflict", session: auth::Session)]
+pub(crate) async fn get_db_conflict() -> Result<Option<String>, ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    Ok(DB.get_conflict())
+}
+
+/// Resolves a database conflict by overwriting the other backup with this instance's database.
+#[post("/api/backups/conflict/overwrite", session: auth::Session)]
+pub(crate) async fn overwrite_db_conflict() -> Result<(), ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    DB.overwrite_conflict().await?;
+    audit::record(&session, AuditAction::DbConflictOverwritten, "").await;
+    Ok(())
+}
+
+/// Replaces the admin credentials used to sync the database with a new auth config (exported via the Filen CLI).
+#[post("/api/admin/authConfig", session: auth::Session)]
+pub(crate) async fn rotate_admin_auth_config(auth_config: String) -> Result<(), ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    DB.rotate_admin_auth_config(auth_config.trim())
+        .await
+        .map_err(|e| ApiError::new(ApiErrorCode::InvalidInput, format!("{:#}", e)))?;
+    audit::record(&session, AuditAction::AdminAuthConfigRotated, "").await;
+    Ok(())
+}
+
+#[get("/api/settings", session: auth::Session)]
+pub(crate) async fn get_settings() -> Result<Settings, ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    DB.get_settings()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get settings: {}", e)))
+}
+
+/// Returns the admin's defaults for new servers, which the form for creating one starts with.
+#[get("/api/settings/serverDefaults", _session: auth::Session)]
+pub(crate) async fn get_server_defaults() -> Result<ServerDefaults, ApiError> {
+    Ok(DB
+        .get_settings()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get settings: {}", e)))?
+        .server_defaults)
+}
+
+#[post("/api/settings/update", session: auth::Session)]
+pub(crate) async fn update_settings(settings: Settings) -> Result<(), ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    let default_options = ServerOptions {
+        bwlimit: settings.server_defaults.bwlimit.clone(),
+        ..Default::default()
+    };
+    if !default_options.is_valid_bwlimit() {
+        return Err(ApiError::new(
+            ApiErrorCode::InvalidInput,
+            ServerSpecValidationError::BwlimitInvalid.to_string(),
+        ));
+    }
+    if settings.verify_email_on_first_login && !email::is_configured() {
+        return Err(ApiError::new(
+            ApiErrorCode::InvalidInput,
+            "Verifying emails requires an SMTP server (see --smtp-url)",
+        ));
+    }
+    DB.update_settings(&settings)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to update settings: {}", e)))?;
+    audit::record(&session, AuditAction::SettingsChanged, "").await;
+    Ok(())
+}
+
+#[get("/api/drain", session: auth::Session)]
+pub(crate) async fn get_drain_status() -> Result<DrainStatus, ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    Ok(drain::status())
+}
+
+/// Puts the relay into drain mode before a redeploy: logins and new servers are refused, existing servers keep running
+/// and the database is backed up. Once `safe_to_stop` is set, the relay can be stopped. Can be called again to retry the backup.
+#[post("/api/drain/start", session: auth::Session)]
+pub(crate) async fn start_drain() -> Result<DrainStatus, ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    audit::record(&session, AuditAction::DrainStarted, "").await;
+    Ok(drain::start().await)
+}
+
+/// Streams relay-wide events (servers starting, stopping and failing, logins, exceeded quotas) for the admin's activity feed.
+/// To resume after a reconnect, pass the `index` of the last received event as `cursor`.
+#[post("/api/activity", session: auth::Session)]
+pub(crate) async fn get_activity(
+    cursor: Option<u64>,
+) -> Result<Streaming<ActivityEvent, JsonEncoding>, ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    Ok(Streaming::spawn(move |tx| async move {
+        let (history, mut rx) = activity::subscribe(cursor);
+        for event in history {
+            if tx.unbounded_send(event).is_err() {
+                return;
+            }
+        }
+        while let Ok(event) = rx.recv().await {
+            if tx.unbounded_send(event).is_err() {
+                return;
+            }
+        }
+    }))
+}
+
+/// Maximum number of audit log entries returned at once; the CSV export contains all of them.
+#[cfg(feature = "server")]
+const AUDIT_LOG_LIMIT: usize = 1000;
+
+/// Returns the newest entries of the audit log matching `filter`, newest first.
+#[post("/api/audit", session: auth::Session)]
+pub(crate) async fn get_audit_log(filter: AuditFilter) -> Result<Vec<AuditEntry>, ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    DB.get_audit_log(&filter, Some(AUDIT_LOG_LIMIT))
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get audit log: {}", e)))
+}
+
+/// Re-reads the config file and the cached parts of the database (like SIGHUP), without restarting servers.
+#[post("/api/config/reload", session: auth::Session)]
+pub(crate) async fn reload_config() -> Result<(), ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    backend::config::reload()
+        .await
+        .map_err(|e| ApiError::new(ApiErrorCode::InvalidInput, format!("{:#}", e)))?;
+    audit::record(&session, AuditAction::ConfigReloaded, "").await;
+    Ok(())
+}
+
+#[get("/api/templates", _session: auth::Session)]
+pub(crate) async fn get_server_templates() -> Result<Vec<ServerTemplate>, ApiError> {
+    DB.get_server_templates()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get server templates: {}", e)))
+}
+
+#[post("/api/templates/add", session: auth::Session)]
+pub(crate) async fn add_server_template(
+    name: String,
+    server_type: ServerType,
+    read_only: bool,
+    options: ServerOptions,
+) -> Result<(), ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    let existing_names = DB
+        .get_server_templates()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get server templates: {}", e)))?
+        .into_iter()
+        .map(|t| t.name)
+        .collect::<Vec<_>>();
+    // templates have no root, so any valid one will do
+    let errors = validate_server_spec(
+        &name,
+        &server_type,
+        "/",
+        read_only,
+        None,
+        &options,
+        &existing_names,
+    );
+    if !errors.is_empty() {
+        return Err(ApiError::validation(errors));
+    }
+    DB.create_server_template(&ServerTemplate {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.trim().to_string(),
+        server_type,
+        read_only,
+        options,
+    })
+    .await
+    .map_err(|e| ApiError::internal(format!("Failed to create server template: {}", e)))?;
+    audit::record(
+        &session,
+        AuditAction::TemplatesChanged,
+        format!("Added \"{}\"", name.trim()),
+    )
+    .await;
+    Ok(())
+}
+
+#[post("/api/templates/remove", session: auth::Session)]
+pub(crate) async fn remove_server_template(id: String) -> Result<(), ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    DB.delete_server_template(&id)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to remove server template: {}", e)))?;
+    audit::record(
+        &session,
+        AuditAction::TemplatesChanged,
+        format!("Removed {}", id),
+    )
+    .await;
+    Ok(())
+}
+
+/// Returns the user's notification channels, and also the admin's channels to the admin.
+#[get("/api/notifications/channels", session: auth::Session)]
+pub(crate) async fn get_notification_channels() -> Result<Vec<NotificationChannel>, ApiError> {
+    Ok(DB
+        .get_notification_channels()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get notification channels: {}", e)))?
+        .into_iter()
+        .filter(|c| match &c.owner {
+            Some(owner) => *owner == session.filen_email,
+            None => session.is_admin,
+        })
+        .collect())
+}
+
+/// Adds a notification channel. Channels added by the admin receive all notifications,
+/// those added by other users only the ones concerning their own servers and quotas.
+#[post("/api/notifications/channels/add", session: auth::Session)]
+pub(crate) async fn add_notification_channel(
+    kind: NotificationChannelKind,
+    webhook_url: String,
+) -> Result<(), ApiError> {
+    let webhook_url = webhook_url.trim().to_string();
+    if !kind
+        .webhook_url_prefixes()
+        .iter()
+        .any(|prefix| webhook_url.starts_with(prefix))
+    {
+        return Err(ApiError::new(
+            ApiErrorCode::InvalidInput,
+            format!("Not a {} webhook URL", kind),
+        ));
+    }
+    DB.create_notification_channel(&NotificationChannel {
+        id: uuid::Uuid::new_v4().to_string(),
+        kind,
+        webhook_url,
+        owner: (!session.is_admin).then(|| session.filen_email.clone()),
+    })
+    .await
+    .map_err(|e| ApiError::internal(format!("Failed to add notification channel: {}", e)))
+}
+
+#[post("/api/notifications/channels/remove", session: auth::Session)]
+pub(crate) async fn remove_notification_channel(id: String) -> Result<(), ApiError> {
+    let owns_channel = DB
+        .get_notification_channels()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get notification channels: {}", e)))?
+        .into_iter()
+        .any(|c| {
+            c.id == id
+                && match &c.owner {
+                    Some(owner) => *owner == session.filen_email,
+                    None => session.is_admin,
+                }
+        });
+    if !owns_channel {
+        return Err(ApiError::not_found("Notification channel not found"));
+    }
+    DB.delete_notification_channel(&id)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to remove notification channel: {}", e)))
+}
+
+/// Returns the user's status pages (all of them to the admin).
+#[get("/api/statusPages", session: auth::Session)]
+pub(crate) async fn get_status_pages() -> Result<Vec<StatusPage>, ApiError> {
+    Ok(DB
+        .get_status_pages()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get status pages: {}", e)))?
+        .into_iter()
+        .filter(|p| session.is_admin || p.owner == session.filen_email)
+        .collect())
+}
+
+/// Adds a status page showing the given servers, which the user must have access to.
+#[post("/api/statusPages/add", session: auth::Session)]
+pub(crate) async fn add_status_page(
+    name: String,
+    server_ids: Vec<ServerId>,
+) -> Result<StatusPage, ApiError> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(ApiError::new(
+            ApiErrorCode::InvalidInput,
+            "Name cannot be empty",
+        ));
+    }
+    let accessible = server_ids.iter().all(|id| {
+        SERVER_MANAGER
+            .get_server_states()
+            .borrow()
+            .iter()
+            .any(|s| s.spec.id == *id && session.can_access(&s.spec))
+    });
+    if server_ids.is_empty() || !accessible {
+        return Err(ApiError::not_found("Server not found or not owned by user"));
+    }
+    let page = StatusPage {
+        token: uuid::Uuid::new_v4().simple().to_string(),
+        name,
+        owner: session.filen_email.clone(),
+        server_ids,
+    };
+    DB.create_status_page(&page)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to add status page: {}", e)))?;
+    Ok(page)
+}
+
+#[post("/api/statusPages/remove", session: auth::Session)]
+pub(crate) async fn remove_status_page(token: String) -> Result<(), ApiError> {
+    let owns_page = DB
+        .get_status_pages()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get status pages: {}", e)))?
+        .into_iter()
+        .any(|p| p.token == token && (session.is_admin || p.owner == session.filen_email));
+    if !owns_page {
+        return Err(ApiError::not_found("Status page not found"));
+    }
+    DB.delete_status_page(&token)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to remove status page: {}", e)))
+}
+
+/// Returns what a status page shows, to anyone knowing its token (no login needed). Removed servers, and those its
+/// creator can no longer access, are left out.
+#[post("/api/status")]
+pub(crate) async fn get_status_page_content(token: String) -> Result<StatusPageContent, ApiError> {
+    let page = DB
+        .get_status_page(&token)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get status page: {}", e)))?
+        .ok_or_else(|| ApiError::not_found("Status page not found"))?;
+    let server_states = SERVER_MANAGER.get_server_states().borrow().clone();
+    Ok(StatusPageContent {
+        name: page.name,
+        servers: page
+            .server_ids
+            .iter()
+            .filter_map(|id| server_states.iter().find(|s| s.spec.id == *id))
+            // e.g. the creator may have been removed as a collaborator since
+            .filter(|s| auth::user_can_access(&page.owner, &s.spec))
+            .map(|s| StatusPageServer {
+                name: s.spec.name.clone(),
+                server_type: s.spec.server_type.clone(),
+                online: matches!(s.status, ServerStatus::Running),
+            })
+            .collect(),
+    })
+}
+
+/// A team along with the number of servers it owns.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub(crate) struct TeamOverview {
+    pub team: Team,
+    pub servers: usize,
+}
+
+/// Returns all teams to the admin, and the teams the user is a member of to everyone else.
+#[get("/api/teams", session: auth::Session)]
+pub(crate) async fn get_teams() -> Result<Vec<TeamOverview>, ApiError> {
+    let servers = DB
+        .get_servers()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get servers: {}", e)))?;
+    Ok(teams::get_teams()
+        .into_iter()
+        .filter(|t| session.is_admin || t.is_member(&session.filen_email))
+        .map(|team| TeamOverview {
+            servers: servers
+                .iter()
+                .filter(|s| s.team_id.as_ref() == Some(&team.id))
+                .count(),
+            team,
+        })
+        .collect())
+}
+
+#[post("/api/teams/add", session: auth::Session)]
+pub(crate) async fn add_team(name: String, owner: String) -> Result<(), ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    let name = name.trim();
+    let owner = owner.trim();
+    if name.is_empty() || owner.is_empty() {
+        return Err(ApiError::new(
+            ApiErrorCode::InvalidInput,
+            "Team name and owner are required",
+        ));
+    }
+    if !auth::is_user_allowed(owner).await? {
+        return Err(ApiError::new(
+            ApiErrorCode::InvalidInput,
+            format!("{} is not an allowed user", owner),
+        ));
+    }
+    DB.create_team(&Team {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        owner: owner.to_string(),
+        members: Vec::new(),
+        max_servers: None,
+    })
+    .await
+    .map_err(|e| ApiError::internal(format!("Failed to create team: {}", e)))?;
+    audit::record(
+        &session,
+        AuditAction::TeamsChanged,
+        format!("Added \"{}\" owned by {}", name, owner),
+    )
+    .await;
+    Ok(teams::reload().await?)
+}
+
+/// Removes a team. Fails while the team still owns servers.
+#[post("/api/teams/remove", session: auth::Session)]
+pub(crate) async fn remove_team(id: String) -> Result<(), ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    let name = teams::find(&id).map_or_else(|| id.clone(), |t| t.name);
+    DB.delete_team(&id)
+        .await
+        .map_err(|e| ApiError::new(ApiErrorCode::InvalidInput, e.to_string()))?;
+    audit::record(
+        &session,
+        AuditAction::TeamsChanged,
+        format!("Removed \"{}\"", name),
+    )
+    .await;
+    Ok(teams::reload().await?)
+}
+
+/// Replaces the members of a team. Only the team's owner (or admin) may do this.
+#[post("/api/teams/members", session: auth::Session)]
+pub(crate) async fn set_team_members(id: String, members: Vec<String>) -> Result<(), ApiError> {
+    let team = teams::find(&id)
+        .filter(|t| session.is_admin || t.is_member(&session.filen_email))
+        .ok_or_else(|| ApiError::not_found("Team not found"))?;
+    if !session.is_admin && team.owner != session.filen_email {
+        return Err(ApiError::forbidden());
+    }
+    let members = validate_user_emails(members, &team.owner).await?;
+    let list = |emails: Vec<&String>| {
+        if emails.is_empty() {
+            "none".to_string()
+        } else {
+            emails.into_iter().cloned().collect::<Vec<_>>().join(", ")
+        }
+    };
+    let details = format!(
+        "Changed the members of \"{}\" ({}): added {}, removed {}",
+        team.name,
+        team.id,
+        list(
+            members
+                .iter()
+                .filter(|m| !team.members.contains(m))
+                .collect()
+        ),
+        list(
+            team.members
+                .iter()
+                .filter(|m| !members.contains(m))
+                .collect()
+        ),
+    );
+    DB.update_team(&Team { members, ..team })
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to update team: {}", e)))?;
+    audit::record(&session, AuditAction::TeamsChanged, details).await;
+    Ok(teams::reload().await?)
+}
+
+/// Sets the maximum number of servers a team may own, or removes the limit.
+#[post("/api/teams/quota", session: auth::Session)]
+pub(crate) async fn set_team_max_servers(
+    id: String,
+    max_servers: Option<u64>,
+) -> Result<(), ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    let team = teams::find(&id).ok_or_else(|| ApiError::not_found("Team not found"))?;
+    let details = match max_servers {
+        Some(max_servers) => format!("Limited \"{}\" to {} servers", team.name, max_servers),
+        None => format!("Removed the server limit of \"{}\"", team.name),
+    };
+    DB.update_team(&Team {
+        max_servers,
+        ..team
+    })
+    .await
+    .map_err(|e| ApiError::internal(format!("Failed to update team: {}", e)))?;
+    audit::record(&session, AuditAction::TeamsChanged, details).await;
+    Ok(teams::reload().await?)
+}
+
+/// Returns the announcement set by the admin (e.g. "maintenance tonight"), if there is one.
+#[get("/api/announcement", _session: auth::Session)]
+pub(crate) async fn get_announcement() -> Result<Option<String>, ApiError> {
+    DB.get_announcement()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get announcement: {}", e)))
+}
+
+/// Sets the announcement shown to all logged-in users. An empty message removes it.
+#[post("/api/announcement/set", session: auth::Session)]
+pub(crate) async fn set_announcement(message: String) -> Result<(), ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    let message = message.trim();
+    DB.set_announcement((!message.is_empty()).then_some(message))
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to set announcement: {}", e)))?;
+    audit::record(&session, AuditAction::AnnouncementChanged, message).await;
+    Ok(())
+}
+
+/// Returns the monthly egress caps of the users, with their usage in the current month.
+#[get("/api/egressCaps", session: auth::Session)]
+pub(crate) async fn get_egress_caps() -> Result<Vec<EgressCap>, ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    usage::get_egress_caps()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get egress caps: {}", e)))
+}
+
+/// Sets the monthly egress cap of a user, or removes it if `monthly_gb` is unset.
+/// Once the cap is reached, the user's servers are paused until next month.
+#[post("/api/egressCaps/set", session: auth::Session)]
+pub(crate) async fn set_egress_cap(
+    filen_email: String,
+    monthly_gb: Option<u64>,
+) -> Result<(), ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    let filen_email = filen_email.trim();
+    if filen_email.is_empty() {
+        return Err(ApiError::new(
+            ApiErrorCode::InvalidInput,
+            "Email is required",
+        ));
+    }
+    DB.set_egress_cap(filen_email, monthly_gb)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to set egress cap: {}", e)))?;
+    let details = match monthly_gb {
+        Some(monthly_gb) => format!("Set {} GB for {}", monthly_gb, filen_email),
+        None => format!("Removed for {}", filen_email),
+    };
+    audit::record(&session, AuditAction::EgressCapChanged, details).await;
+    Ok(())
+}
+
+#[get("/api/cache", session: auth::Session)]
+pub(crate) async fn get_cache_usage() -> Result<CacheUsage, ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    Ok(server_manager::cache_usage().await?)
+}
+
+/// Returns the latest sample of the system metrics, or `None` right after startup.
+#[get("/api/metrics", session: auth::Session)]
+pub(crate) async fn get_system_metrics() -> Result<Option<SystemMetrics>, ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    Ok(metrics::latest())
+}
+
+/// Returns the maintenance tasks scheduled on this instance with their latest results.
+#[get("/api/maintenance", session: auth::Session)]
+pub(crate) async fn get_maintenance_tasks() -> Result<Vec<MaintenanceTaskStatus>, ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    let settings = DB
+        .get_settings()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get settings: {}", e)))?;
+    Ok(scheduler::statuses(&settings))
+}
+
+/// Restarts all servers with a VFS cache, clearing their caches in between.
+#[post("/api/cache/purge", session: auth::Session)]
+pub(crate) async fn purge_caches() -> Result<(), ApiError> {
+    if !session.is_admin {
+        return Err(ApiError::forbidden());
+    }
+    SERVER_MANAGER
+        .update_server_spec(server_manager::ServerSpecUpdate::PurgeCaches)
+        .await?;
+    audit::record(&session, AuditAction::CachesPurged, "").await;
+    Ok(())
 }
@@ -0,0 +1,153 @@
+//! Per-server rules for the requests the proxy forwards (see `ServerOptions::allowed_methods` and
+//! `ServerOptions::blocked_paths`), e.g. to only allow reading or to keep a folder private. They are checked as soon as
+//! the server is resolved, before anything is forwarded. Paths are compared decoded and normalized, so that e.g.
+//! `/%2Eprivate/` and `/a/../.private/` are blocked by `/.private/*` too, and ignoring case.
+
+use dioxus::server::axum::http::{HeaderMap, Method, StatusCode};
+
+use crate::{backend::server_proxy, common::ServerSpec};
+
+/// The status to reject the request to `path` (relative to the server's root) with, if any.
+pub(crate) fn check(
+    spec: &ServerSpec,
+    method: &Method,
+    path: &str,
+    headers: &HeaderMap,
+) -> Option<StatusCode> {
+    let options = &spec.options;
+    if !options.allowed_methods.is_empty()
+        && !options
+            .allowed_methods
+            .iter()
+            .any(|allowed| allowed == method.as_str())
+    {
+        return Some(StatusCode::METHOD_NOT_ALLOWED);
+    }
+    if options.blocked_paths.is_empty() {
+        return None;
+    }
+    // WebDAV's COPY and MOVE also write to the destination
+    let destination = match headers.get("destination") {
+        Some(value) => match value.to_str().ok().and_then(destination_path) {
+            Some(destination) => Some(destination),
+            None => return Some(StatusCode::FORBIDDEN),
+        },
+        None => None,
+    };
+    std::iter::once(path)
+        .chain(destination.as_deref())
+        .any(|path| is_blocked(&options.blocked_paths, &server_proxy::decode_path(path)))
+        .then_some(StatusCode::FORBIDDEN)
+}
+
+/// Whether the (decoded) path relative to the server's root matches any of the patterns. A pattern ending with "/*"
+/// also matches the directory itself.
+pub(crate) fn is_blocked(patterns: &[String], path: &str) -> bool {
+    if patterns.is_empty() {
+        return false;
+    }
+    let path = normalize(path).to_lowercase();
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.to_lowercase();
+        matches(&pattern, &path)
+            || pattern
+                .strip_suffix("/*")
+                .is_some_and(|dir| matches(dir, &path))
+    })
+}
+
+/// Resolves "." and ".." segments, e.g. "a/./b/../.c/" to "/a/.c".
+fn normalize(path: &str) -> String {
+    let mut segments = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    format!("/{}", segments.join("/"))
+}
+
+/// The path of a `Destination` header relative to the server's root, e.g. "b/c" for "https://relay/s/abcd/b/c".
+fn destination_path(destination: &str) -> Option<String> {
+    let path = match destination.split_once("://") {
+        Some((_, rest)) => &rest[rest.find('/')?..],
+        None => destination,
+    };
+    let (_, path) = path.strip_prefix("/s/")?.split_once('/')?;
+    Some(path.to_string())
+}
+
+/// Whether the text matches the pattern, where `*` matches any characters (including "/") and `?` any single one.
+fn matches(pattern: &str, text: &str) -> bool {
+    let (pattern, text) = (pattern.as_bytes(), text.as_bytes());
+    let (mut p, mut t) = (0, 0);
+    // the position after the last `*` in the pattern, and up to where it matched the text
+    let mut star = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p + 1, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // let the `*` match one more character
+            p = star_p;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches("/.private/*", "/.private/a/b.txt"));
+        assert!(matches("*.log", "/logs/today.log"));
+        assert!(matches("/file?.txt", "/file1.txt"));
+        assert!(matches("/a*b*c", "/axxbyyc"));
+        assert!(!matches("/file?.txt", "/file.txt"));
+        assert!(!matches("/.private/*", "/public/.private"));
+        assert!(!matches("/a*b", "/acb/c"));
+    }
+
+    #[test]
+    fn normalize_resolves_dots() {
+        assert_eq!(normalize("a/./b/../.c/"), "/a/.c");
+        assert_eq!(normalize("/../../etc/passwd"), "/etc/passwd");
+        assert_eq!(normalize("a//b"), "/a/b");
+        assert_eq!(normalize(""), "/");
+    }
+
+    #[test]
+    fn destination_path_strips_server_prefix() {
+        assert_eq!(
+            destination_path("https://relay.example/s/abcd/b/c").as_deref(),
+            Some("b/c")
+        );
+        assert_eq!(destination_path("/s/abcd/b").as_deref(), Some("b"));
+        assert_eq!(destination_path("https://relay.example/s/abcd"), None);
+        assert_eq!(destination_path("https://relay.example/other/b"), None);
+        assert_eq!(destination_path("https://relay.example"), None);
+    }
+
+    #[test]
+    fn is_blocked_normalizes_and_ignores_case() {
+        let patterns = vec!["/.private/*".to_string(), "/secret.html".to_string()];
+        assert!(is_blocked(&patterns, "/.private"));
+        assert!(is_blocked(&patterns, "a/../.Private/x"));
+        assert!(is_blocked(&patterns, "SECRET.html"));
+        assert!(!is_blocked(&patterns, "/public/secret.html"));
+        assert!(!is_blocked(&[], "/.private"));
+    }
+}
@@ -0,0 +1,59 @@
+//! The relay-wide activity feed of the admin: servers starting, stopping and failing, logins and exceeded quotas.
+//! Only the most recent events are kept, in memory.
+
+use std::sync::{LazyLock, Mutex};
+
+use tokio::sync::broadcast;
+
+use crate::{
+    common::{ActivityEvent, ActivityKind, ServerSpec},
+    util::IncrementalVec,
+};
+
+/// How many past events are sent to the activity feed when it is opened.
+const HISTORY_LENGTH: usize = 500;
+
+static ACTIVITY: LazyLock<Mutex<IncrementalVec<ActivityEvent>>> =
+    LazyLock::new(|| Mutex::new(IncrementalVec::new(HISTORY_LENGTH)));
+
+pub(crate) fn record(kind: ActivityKind, message: String) {
+    let mut activity = ACTIVITY.lock().unwrap();
+    let index = activity.get().0.last().map_or(0, |event| event.index + 1);
+    activity.push(ActivityEvent {
+        index,
+        timestamp: chrono::Utc::now(),
+        kind,
+        message,
+    });
+    activity.keep_last(HISTORY_LENGTH);
+}
+
+/// Records that a server started or stopped.
+pub(crate) fn record_server(kind: ActivityKind, spec: &ServerSpec) {
+    let verb = match kind {
+        ActivityKind::ServerStarted => "started",
+        ActivityKind::ServerStopped => "stopped",
+        _ => "changed",
+    };
+    record(
+        kind,
+        format!(
+            "{} server \"{}\" of {} {}",
+            spec.server_type, spec.name, spec.filen_email, verb
+        ),
+    );
+}
+
+/// Returns the recent events after `cursor` (an index), and a receiver for the following ones.
+pub(crate) fn subscribe(
+    cursor: Option<u64>,
+) -> (Vec<ActivityEvent>, broadcast::Receiver<ActivityEvent>) {
+    let activity = ACTIVITY.lock().unwrap();
+    let (history, rx) = activity.get();
+    let history = history
+        .iter()
+        .filter(|event| cursor.is_none_or(|cursor| event.index > cursor))
+        .cloned()
+        .collect();
+    (history, rx)
+}
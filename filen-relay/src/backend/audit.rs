@@ -0,0 +1,87 @@
+//! The audit log: the admin's actions (changes to allowed users, settings, teams, ...), kept in the database
+//! for `RETENTION_DAYS` and shown on the admin's audit page.
+
+use dioxus::{
+    logger::tracing,
+    server::axum::{
+        extract::Query,
+        http::{header, StatusCode},
+        response::IntoResponse,
+    },
+};
+
+use crate::{
+    backend::{auth, db::DB},
+    common::{AuditAction, AuditEntry, AuditFilter},
+};
+
+/// How long entries are kept.
+const RETENTION_DAYS: i64 = 365;
+
+/// Records an action taken by the admin. Failing to record it is logged, but doesn't fail the action.
+pub(crate) async fn record(
+    session: &auth::Session,
+    action: AuditAction,
+    details: impl Into<String>,
+) {
+    let now = chrono::Utc::now();
+    let entry = AuditEntry {
+        timestamp: now,
+        actor: session.filen_email.clone(),
+        action,
+        details: details.into(),
+    };
+    if let Err(e) = DB
+        .record_audit_entry(&entry, now - chrono::Duration::days(RETENTION_DAYS))
+        .await
+    {
+        tracing::error!("Failed to record audit log entry: {}", e);
+    }
+}
+
+/// `GET /api/audit/export`: downloads the audit log entries matching the filter (see `AuditFilter`) as a CSV file
+/// for the admin, newest first.
+pub(crate) async fn export_audit_log(
+    session: auth::Session,
+    Query(filter): Query<AuditFilter>,
+) -> Result<impl IntoResponse, StatusCode> {
+    if !session.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let entries = DB.get_audit_log(&filter, None).await.map_err(|e| {
+        tracing::error!("Failed to get audit log for export: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"filen-relay-audit-log.csv\"",
+            ),
+        ],
+        to_csv(&entries),
+    ))
+}
+
+fn to_csv(entries: &[AuditEntry]) -> String {
+    /// Quotes a field if it contains characters with a special meaning in CSV.
+    fn field(value: &str) -> String {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+    let mut csv = String::from("timestamp,actor,action,details\n");
+    for entry in entries {
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            entry.timestamp.to_rfc3339(),
+            field(&entry.actor),
+            entry.action.as_str(),
+            field(&entry.details),
+        ));
+    }
+    csv
+}
@@ -1,7 +1,12 @@
-use std::{fmt::Display, sync::OnceLock};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{LazyLock, Mutex, OnceLock},
+};
 
 pub(crate) static ADMIN_EMAIL: OnceLock<String> = OnceLock::new();
 
+use anyhow::Context as _;
 use dioxus::{
     fullstack::extract::{FromRequestParts, Request},
     prelude::*,
@@ -11,14 +16,60 @@ use dioxus::{
     },
 };
 use filen_sdk_rs::auth::Client;
-use std::sync::{LazyLock, Mutex};
+use rand::{rngs::OsRng, Rng as _};
 
-use crate::backend::db::DB;
+use crate::{
+    backend::{
+        db::DB,
+        drive, email, ephemeral,
+        forwarded::ClientInfo,
+        mock,
+        notifications::{self, Notification},
+        secrets, teams,
+    },
+    common::{ApiError, ApiErrorCode, ApiErrorDetails, RegistrationMode, ServerSpec, ServerState},
+};
 
-static SESSIONS: LazyLock<Mutex<Vec<Session>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+/// Sessions are stored in the database (or Redis, see `ephemeral`), so that replicas sharing it accept each other's sessions.
+/// They expire after this long.
+pub(crate) const SESSION_LIFETIME: chrono::Duration = chrono::Duration::days(30);
+/// How long the code sent to verify the email of a user logging in for the first time is valid.
+const VERIFICATION_CODE_LIFETIME: chrono::Duration = chrono::Duration::minutes(15);
+/// Wrong codes after which the login has to be started over.
+const MAX_VERIFICATION_ATTEMPTS: u32 = 5;
 
 #[derive(Clone, PartialEq)]
-pub(crate) struct SessionToken(String);
+pub(crate) struct SessionToken(pub(crate) String);
+
+impl SessionToken {
+    /// The hash the session is stored under in the database, so that its copies (like backups) don't contain usable
+    /// tokens.
+    pub(crate) fn hash(&self) -> String {
+        secrets::hash(&self.0)
+    }
+
+    /// Identifies the session to its user (e.g. to log it out), without revealing the token.
+    pub(crate) fn id(&self) -> String {
+        session_id(&self.hash())
+    }
+}
+
+/// Whether the user may see and manage the given server (see `Session::can_access`), for when they aren't the one
+/// making the request, e.g. the creator of a status page.
+pub(crate) fn user_can_access(filen_email: &str, spec: &ServerSpec) -> bool {
+    ADMIN_EMAIL.get().map(String::as_str) == Some(filen_email)
+        || spec.filen_email == filen_email
+        || spec.collaborators.iter().any(|c| c == filen_email)
+        || spec
+            .team_id
+            .as_deref()
+            .is_some_and(|team_id| teams::is_member(team_id, filen_email))
+}
+
+/// The ID of the session stored under the hash, see `SessionToken::id`.
+pub(crate) fn session_id(hash: &str) -> String {
+    hash[..16].to_string()
+}
 
 impl Display for SessionToken {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -30,11 +81,47 @@ impl Display for SessionToken {
 pub(crate) struct Session {
     pub token: SessionToken,
     pub filen_email: String,
-    pub filen_password: String,
-    pub filen_2fa_code: Option<String>,
+    /// The serialized Filen auth config of the user's client, so that neither password nor 2FA code need to be kept.
+    /// Empty in mock mode.
+    pub filen_auth_config: String,
     pub is_admin: bool,
 }
 
+impl Session {
+    /// Reconstructs the user's Filen client.
+    pub(crate) fn filen_client(&self) -> anyhow::Result<Client> {
+        client_from_auth_config(&self.filen_auth_config)
+    }
+
+    /// Whether this session's user may see and manage the given server, as its owner, a collaborator or a team member.
+    pub(crate) fn can_access(&self, spec: &ServerSpec) -> bool {
+        self.is_admin || user_can_access(&self.filen_email, spec)
+    }
+
+    /// Whether this session's user may change who the given server is shared with.
+    pub(crate) fn can_share(&self, spec: &ServerSpec) -> bool {
+        self.is_admin || spec.filen_email == self.filen_email
+    }
+
+    /// The server states this session's user may see, with process details only for admins.
+    pub(crate) fn visible_server_states(
+        &self,
+        server_states: &[ServerState<ServerSpec>],
+    ) -> Vec<ServerState> {
+        server_states
+            .iter()
+            .filter(|s| self.can_access(&s.spec))
+            .map(|s| {
+                let mut s = s.to_public();
+                if !self.is_admin {
+                    s.process = None;
+                }
+                s
+            })
+            .collect()
+    }
+}
+
 /// Axum middleware to extract session token from cookies
 pub(crate) async fn middleware_extract_session_token(
     mut request: Request,
@@ -67,20 +154,18 @@ where
     type Rejection = StatusCode;
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        parts
+        let token = parts
             .extensions
             .get::<SessionToken>()
-            .and_then(|token| {
-                SESSIONS
-                    .lock()
-                    .unwrap()
-                    .iter()
-                    .find(|s| s.token == *token)
-                    .cloned()
-                    .ok_or_else(|| anyhow::anyhow!("Invalid session token"))
-                    .ok()
-            })
-            .ok_or(StatusCode::UNAUTHORIZED)
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+        match ephemeral::get_session(token).await {
+            Ok(Some(session)) => Ok(session),
+            Ok(None) => Err(StatusCode::UNAUTHORIZED),
+            Err(e) => {
+                dioxus::logger::tracing::error!("Failed to get session: {}", e);
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
     }
 }
 
@@ -101,9 +186,12 @@ pub(crate) async fn authenticate_filen_client(
         Err(e) if e.kind() == ErrorKind::Server => match e.downcast::<ResponseError>() {
             Ok(ResponseError::ApiError { code, .. }) => {
                 if code.as_deref() == Some("enter_2fa") {
-                    Err(anyhow::anyhow!("2FA required"))
+                    Err(ApiError::new(ApiErrorCode::TwoFactorRequired, "2FA required").into())
                 } else if code.as_deref() == Some("email_or_password_wrong") {
-                    Err(anyhow::anyhow!("Email or password wrong"))
+                    Err(
+                        ApiError::new(ApiErrorCode::InvalidCredentials, "Email or password wrong")
+                            .into(),
+                    )
                 } else {
                     Err(anyhow::anyhow!(
                         "Failed to log in (code {})",
@@ -118,35 +206,264 @@ pub(crate) async fn authenticate_filen_client(
     }
 }
 
+/// Reconstructs a Filen client from an auth config serialized after logging in.
+pub(crate) fn client_from_auth_config(auth_config: &str) -> anyhow::Result<Client> {
+    filen_cli::deserialize_auth_config(auth_config)
+        .map_err(|e| anyhow::anyhow!("Failed to deserialize Filen auth config: {}", e))
+}
+
+/// Replaces the email, password and 2FA code stored with servers by older versions with an auth config.
+/// Servers whose credentials don't work anymore (e.g. because of a stale 2FA code) are left as they are and fail to start.
+pub(crate) async fn migrate_legacy_server_credentials() {
+    let servers = match DB.get_legacy_server_credentials().await {
+        Ok(servers) => servers,
+        Err(e) => {
+            dioxus::logger::tracing::error!("Failed to get legacy server credentials: {}", e);
+            return;
+        }
+    };
+    for (id, email, password, two_factor_code) in servers {
+        let auth_config = match authenticate_filen_client(email, &password, two_factor_code).await {
+            Ok(client) => filen_cli::serialize_auth_config(&client)
+                .map_err(|e| anyhow::anyhow!("Failed to serialize Filen auth config: {}", e)),
+            Err(e) => Err(e),
+        };
+        let result = match auth_config {
+            Ok(auth_config) => DB.set_server_auth_config(&id, &auth_config).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            dioxus::logger::tracing::warn!(
+                "Failed to migrate credentials of server {}: {:#}",
+                id,
+                e
+            );
+        }
+    }
+}
+
 pub(crate) async fn login_and_get_session_token(
     email: String,
     password: String,
     two_factor_code: Option<String>,
+    client: &ClientInfo,
 ) -> anyhow::Result<SessionToken> {
-    match authenticate_filen_client(email.clone(), &password, two_factor_code.clone()).await {
+    let authenticated = if mock::is_enabled() {
+        mock::authenticate(&email, &password).map(|()| String::new())
+    } else {
+        match authenticate_filen_client(email.clone(), &password, two_factor_code).await {
+            Ok(client) => filen_cli::serialize_auth_config(&client)
+                .map_err(|e| anyhow::anyhow!("Failed to serialize Filen auth config: {}", e)),
+            Err(e) => Err(e),
+        }
+    };
+    match authenticated {
         Err(e) => Err(e.context("Failed to log in")),
-        Ok(_client) => {
-            let allowed_users = DB
-                .get_allowed_users()
-                .map_err(|e| anyhow::anyhow!("Failed to get allowed users from database: {}", e))?;
-            let is_allowed = if allowed_users.is_empty() {
-                true
-            } else {
-                allowed_users.contains(&email) || (ADMIN_EMAIL.get() == Some(&email))
-            };
-            if is_allowed {
-                let token = SessionToken(uuid::Uuid::new_v4().to_string());
-                SESSIONS.lock().unwrap().push(Session {
-                    token: token.clone(),
-                    filen_email: email.to_string(),
-                    filen_password: password,
-                    filen_2fa_code: two_factor_code,
-                    is_admin: Some(email.to_string()) == ADMIN_EMAIL.get().cloned(),
-                });
-                Ok(token)
-            } else {
-                Err(anyhow::anyhow!("User is not allowed"))
+        Ok(filen_auth_config) => start_session(email, filen_auth_config, client).await,
+    }
+}
+
+/// Logs in with an auth config exported via the Filen CLI instead of email, password and 2FA code.
+pub(crate) async fn login_with_auth_config_and_get_session_token(
+    auth_config: &str,
+    client: &ClientInfo,
+) -> anyhow::Result<SessionToken> {
+    let invalid = || {
+        ApiError::new(
+            ApiErrorCode::InvalidCredentials,
+            "Invalid or expired auth config",
+        )
+    };
+    let filen_client = client_from_auth_config(auth_config.trim()).map_err(|_| invalid())?;
+    // the auth config may belong to an expired session, so check that it still works
+    if drive::directory_exists(&filen_client, "/").await.is_err() {
+        return Err(invalid().into());
+    }
+    let filen_auth_config = filen_cli::serialize_auth_config(&filen_client)
+        .map_err(|e| anyhow::anyhow!("Failed to serialize Filen auth config: {}", e))?;
+    start_session(filen_client.email().to_string(), filen_auth_config, client).await
+}
+
+/// Creates a session for an authenticated user, if they may log in. If they log in for the first time and the admin
+/// requires it, a code is sent to their email instead, with which they complete the login
+/// (see `verify_email_and_get_session_token`).
+async fn start_session(
+    email: String,
+    filen_auth_config: String,
+    client: &ClientInfo,
+) -> anyhow::Result<SessionToken> {
+    if !is_user_allowed(&email).await? {
+        return Err(ApiError::new(ApiErrorCode::Forbidden, "User is not allowed").into());
+    }
+    if requires_email_verification(&email).await? {
+        return Err(start_email_verification(email, filen_auth_config)
+            .await?
+            .into());
+    }
+    create_session(email, filen_auth_config, client).await
+}
+
+/// Stores a new session, along with the device (user agent and address) it was created from.
+async fn create_session(
+    email: String,
+    filen_auth_config: String,
+    client: &ClientInfo,
+) -> anyhow::Result<SessionToken> {
+    let is_admin = ADMIN_EMAIL.get() == Some(&email);
+    let token = SessionToken(uuid::Uuid::new_v4().to_string());
+    let now = chrono::Utc::now();
+    ephemeral::create_session(
+        &Session {
+            token: token.clone(),
+            filen_email: email.to_string(),
+            filen_auth_config,
+            is_admin,
+        },
+        client,
+        now,
+    )
+    .await
+    .map_err(|e| anyhow::anyhow!("Failed to store session: {}", e))?;
+    if let Err(e) = DB.record_login(&email, now).await {
+        dioxus::logger::tracing::error!("Failed to record login of {}: {}", email, e);
+    }
+    notifications::notify(Notification::UserLoggedIn { email });
+    Ok(token)
+}
+
+/// A login waiting for the user to enter the code sent to their email.
+struct PendingVerification {
+    email: String,
+    filen_auth_config: String,
+    code: String,
+    expires: chrono::DateTime<chrono::Utc>,
+    attempts: u32,
+}
+
+/// Pending verifications by their ID, which the browser sends along with the code. Only kept in memory, so the code
+/// must be entered on the replica that sent it (with `leader`, all API requests reach the leader).
+static PENDING_VERIFICATIONS: LazyLock<Mutex<HashMap<String, PendingVerification>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Whether the user logs in for the first time while the admin requires verifying their email then.
+/// The admin is exempt, since their email is configured at startup rather than entered in the allowed users list.
+async fn requires_email_verification(email: &str) -> anyhow::Result<bool> {
+    if ADMIN_EMAIL.get().map(String::as_str) == Some(email) {
+        return Ok(false);
+    }
+    let settings = DB
+        .get_settings()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get settings from database: {}", e))?;
+    if !settings.verify_email_on_first_login {
+        return Ok(false);
+    }
+    Ok(!DB
+        .has_logged_in(email)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get logins from database: {}", e))?)
+}
+
+/// Sends a code to the user's email, returning the error telling the browser to ask for it.
+async fn start_email_verification(
+    email: String,
+    filen_auth_config: String,
+) -> anyhow::Result<ApiError> {
+    // six random digits
+    let code = format!("{:06}", OsRng.gen_range(0..1_000_000));
+    email::send(
+        &email,
+        "Your Filen Relay verification code",
+        &format!(
+            "Your verification code is {}. It expires in {} minutes.\n\nIf you didn't just log in to Filen Relay{}, you can ignore this email.",
+            code,
+            VERIFICATION_CODE_LIFETIME.num_minutes(),
+            crate::backend::public_url().map(|url| format!(" at {}", url)).unwrap_or_default()
+        ),
+    )
+    .await
+    .context("Failed to send verification code")?;
+    let verification_id = uuid::Uuid::new_v4().to_string();
+    let now = chrono::Utc::now();
+    let mut pending = PENDING_VERIFICATIONS.lock().unwrap();
+    pending.retain(|_, verification| verification.expires > now);
+    pending.insert(
+        verification_id.clone(),
+        PendingVerification {
+            email,
+            filen_auth_config,
+            code,
+            expires: now + VERIFICATION_CODE_LIFETIME,
+            attempts: 0,
+        },
+    );
+    Ok(ApiError::new(
+        ApiErrorCode::EmailVerificationRequired,
+        "Enter the code sent to your email",
+    )
+    .with_details(ApiErrorDetails::EmailVerification { verification_id }))
+}
+
+/// Completes a login started with `start_session` by checking the code sent to the user's email.
+pub(crate) async fn verify_email_and_get_session_token(
+    verification_id: &str,
+    code: &str,
+    client: &ClientInfo,
+) -> anyhow::Result<SessionToken> {
+    let expired = || {
+        ApiError::new(
+            ApiErrorCode::InvalidCredentials,
+            "The verification code has expired or was entered wrong too often, please log in again",
+        )
+    };
+    let (email, filen_auth_config) = {
+        let mut pending = PENDING_VERIFICATIONS.lock().unwrap();
+        let Some(verification) = pending.get_mut(verification_id) else {
+            return Err(expired().into());
+        };
+        if verification.expires <= chrono::Utc::now() {
+            pending.remove(verification_id);
+            return Err(expired().into());
+        }
+        if !secrets::matches(code.trim(), &verification.code) {
+            verification.attempts += 1;
+            if verification.attempts >= MAX_VERIFICATION_ATTEMPTS {
+                pending.remove(verification_id);
+                return Err(expired().into());
             }
+            return Err(
+                ApiError::new(ApiErrorCode::InvalidCredentials, "Wrong verification code").into(),
+            );
         }
+        let verification = pending
+            .remove(verification_id)
+            .expect("Verification exists");
+        (verification.email, verification.filen_auth_config)
+    };
+    // the user may have been removed from the allowed users in the meantime
+    if !is_user_allowed(&email).await? {
+        return Err(ApiError::new(ApiErrorCode::Forbidden, "User is not allowed").into());
     }
+    create_session(email, filen_auth_config, client).await
+}
+
+/// Whether the user with the given email may log in, according to the registration mode.
+pub(crate) async fn is_user_allowed(email: &str) -> anyhow::Result<bool> {
+    let allowed_users = DB
+        .get_allowed_users()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get allowed users from database: {}", e))?;
+    let registration_mode = DB
+        .get_settings()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get settings from database: {}", e))?
+        .registration_mode;
+    let is_admin = ADMIN_EMAIL.get().map(String::as_str) == Some(email);
+    Ok(match registration_mode {
+        RegistrationMode::Open => true,
+        RegistrationMode::AllowedUsers => {
+            allowed_users.is_empty() || allowed_users.iter().any(|u| u == email) || is_admin
+        }
+        RegistrationMode::AdminOnly => is_admin,
+    })
 }
@@ -0,0 +1,127 @@
+//! Loading of the options from a TOML config file (`--config`), e.g.
+//!
+//! ```toml
+//! admin-email = "admin@example.com"
+//! db-dir = "/var/lib/filen-relay"
+//! short-id-length = 12
+//! ```
+//!
+//! Keys are the long names of the command line options. Options given on the command line or as environment variables take precedence.
+//!
+//! On SIGHUP (or via the admin's "Reload Config" button), the file is read again and `--public-url` and
+//! `--trusted-proxies` are applied without a restart. Other options only take effect after a restart.
+
+use std::ffi::OsString;
+
+use anyhow::{Context, Result};
+use clap::{parser::ValueSource, CommandFactory, FromArgMatches};
+use dioxus::logger::tracing;
+
+use crate::{
+    backend::{self, forwarded, teams},
+    Args,
+};
+
+/// Parses the command line and environment variables, filling in options that weren't set from the config file (if there is one).
+pub(crate) fn parse_args() -> Args {
+    try_parse_args().unwrap_or_else(|e| match e.downcast::<clap::Error>() {
+        Ok(e) => e.exit(),
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(2);
+        }
+    })
+}
+
+fn try_parse_args() -> Result<Args> {
+    let command = Args::command();
+    let matches = command.clone().try_get_matches()?;
+    let args = Args::from_arg_matches(&matches)?;
+    // subcommands don't use the other options, and options appended after them wouldn't parse
+    if args.command.is_some() {
+        return Ok(args);
+    }
+    let Some(config_path) = args.config else {
+        return Ok(args);
+    };
+    let file_args = file_args(&command, &matches, &config_path)?;
+    // the options from the file are appended, so that clap validates them like any other
+    let argv = std::env::args_os().chain(file_args).collect::<Vec<_>>();
+    let matches = command.try_get_matches_from(argv)?;
+    Ok(Args::from_arg_matches(&matches)?)
+}
+
+/// Reads the config file again and applies the options that can change at runtime, and reloads the teams from the database.
+/// Settings (like quotas) and notification channels are read from the database whenever they are used, so they always apply live.
+/// Running servers are not restarted.
+pub(crate) async fn reload() -> Result<()> {
+    let args = try_parse_args()?;
+    forwarded::init(&args.trusted_proxies).map_err(anyhow::Error::msg)?;
+    backend::set_public_url(args.public_url.as_deref());
+    teams::reload()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to reload teams: {}", e))?;
+    tracing::info!("Reloaded config");
+    Ok(())
+}
+
+/// Reloads the config whenever the process receives SIGHUP. Runs forever.
+#[cfg(unix)]
+pub(crate) async fn reload_on_sighup() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            tracing::error!("Failed to listen for SIGHUP: {}", e);
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        if let Err(e) = reload().await {
+            tracing::error!("Failed to reload config: {:#}", e);
+        }
+    }
+}
+
+/// Turns the options in the config file into command line arguments, skipping those already set.
+fn file_args(
+    command: &clap::Command,
+    matches: &clap::ArgMatches,
+    config_path: &str,
+) -> Result<Vec<OsString>> {
+    let content = std::fs::read_to_string(config_path)
+        .with_context(|| format!("Failed to read config file {}", config_path))?;
+    let table = content
+        .parse::<toml::Table>()
+        .with_context(|| format!("Failed to parse config file {}", config_path))?;
+    let mut args = Vec::new();
+    for (key, value) in table {
+        let long = key.replace('_', "-");
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long.as_str()) && long != "config")
+            .with_context(|| format!("Unknown option \"{}\" in config file", key))?;
+        if matches!(
+            matches.value_source(arg.get_id().as_str()),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        ) {
+            continue;
+        }
+        let values = match value {
+            toml::Value::Array(values) => values,
+            value => vec![value],
+        };
+        for value in values {
+            match value {
+                toml::Value::Boolean(true) => args.push(format!("--{}", long).into()),
+                toml::Value::Boolean(false) => {}
+                toml::Value::String(value) => args.push(format!("--{}={}", long, value).into()),
+                toml::Value::Integer(value) => args.push(format!("--{}={}", long, value).into()),
+                toml::Value::Float(value) => args.push(format!("--{}={}", long, value).into()),
+                _ => anyhow::bail!("Unsupported value for option \"{}\" in config file", key),
+            }
+        }
+    }
+    Ok(args)
+}
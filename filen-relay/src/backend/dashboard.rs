@@ -0,0 +1,96 @@
+//! Hourly time series of the requests and transferred bytes of each server, shown as charts in the dashboard.
+//! Requests are counted by the proxy and bytes by the usage accounting (see `usage::run_usage_accounting`); the counts
+//! are added to the database every few minutes and kept for a week.
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+use dioxus::logger::tracing;
+
+use crate::{
+    backend::db::DB,
+    common::{ServerId, ServerSpec, ServerStatsSeries, StatsRange},
+};
+
+/// How often the counts are added to the database.
+const FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+/// How long the counts are kept, i.e. the longest `StatsRange`.
+const RETENTION_HOURS: i64 = 7 * 24;
+
+/// Requests and bytes per server since the last flush.
+static PENDING: LazyLock<Mutex<HashMap<ServerId, (u64, u64)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Records a request proxied to the server.
+pub(crate) fn record_request(id: &ServerId) {
+    PENDING.lock().unwrap().entry(id.clone()).or_default().0 += 1;
+}
+
+/// Records bytes transferred by the server.
+pub(crate) fn record_bytes(id: &ServerId, bytes: u64) {
+    PENDING.lock().unwrap().entry(id.clone()).or_default().1 += bytes;
+}
+
+/// Adds the counts to the database every `FLUSH_INTERVAL`, deleting those older than `RETENTION_HOURS`. Runs forever.
+pub(crate) async fn run_collector() {
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let pending = std::mem::take(&mut *PENDING.lock().unwrap())
+            .into_iter()
+            .map(|(id, (requests, bytes))| (id, requests, bytes))
+            .collect::<Vec<_>>();
+        let hour = current_hour();
+        if !pending.is_empty() {
+            if let Err(e) = DB.record_server_stats(hour, &pending).await {
+                tracing::error!("Failed to record server stats: {}", e);
+            }
+        }
+        if let Err(e) = DB.prune_server_stats(hour - RETENTION_HOURS * 3600).await {
+            tracing::error!("Failed to prune server stats: {}", e);
+        }
+    }
+}
+
+/// The series of the servers over the range, with a point per hour.
+pub(crate) async fn get_series(
+    servers: &[ServerSpec],
+    range: StatsRange,
+) -> anyhow::Result<Vec<ServerStatsSeries>> {
+    let hours = range.hours();
+    let first_hour = current_hour() - (hours as i64 - 1) * 3600;
+    let stats = DB
+        .get_server_stats(first_hour)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get server stats: {}", e))?;
+    let mut series = servers
+        .iter()
+        .map(|spec| ServerStatsSeries {
+            server_id: spec.id.clone(),
+            name: spec.name.clone(),
+            filen_email: spec.filen_email.clone(),
+            requests: vec![0; hours],
+            bytes: vec![0; hours],
+        })
+        .collect::<Vec<_>>();
+    for (id, hour, requests, bytes) in stats {
+        let Some(series) = series.iter_mut().find(|series| series.server_id == id) else {
+            continue;
+        };
+        let index = ((hour - first_hour) / 3600) as usize;
+        if index < hours {
+            series.requests[index] += requests;
+            series.bytes[index] += bytes;
+        }
+    }
+    Ok(series)
+}
+
+/// The start of the current hour, as a Unix timestamp.
+fn current_hour() -> i64 {
+    let now = chrono::Utc::now().timestamp();
+    now - now.rem_euclid(3600)
+}
@@ -1,26 +1,252 @@
-use std::sync::Mutex;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
 
+use chrono::{DateTime, NaiveDate, Utc};
 use dioxus::prelude::*;
 use filen_sdk_rs::{
     auth::Client,
-    fs::{file::enums::RemoteFileType, FSObject, HasUUID},
+    fs::{dir::DirectoryType, file::enums::RemoteFileType, FSObject, HasName, HasUUID},
 };
 use filen_types::fs::UuidStr;
 use rusqlite::Connection;
 
 use crate::{
-    common::{ServerId, ServerSpec},
+    backend::{
+        auth::{Session, SessionToken, ADMIN_EMAIL},
+        obscure, secrets,
+        usage::UsageRecord,
+    },
+    common::{
+        AllowedUser, AuditEntry, AuditFilter, LogLevel, MaintenanceTask, NotificationChannel,
+        RegistrationMode, ServerDefaults, ServerId, ServerSpec, ServerTemplate, Settings,
+        StatusPage, Team, VfsCacheMode,
+    },
     util::UnwrapOnceLock,
 };
 
-// todo: is it good (or safe) that this needs to be .lock().unwrap() everywhere?
 pub(crate) static DB: UnwrapOnceLock<DbViaOfflineOrRemoteFile> = UnwrapOnceLock::new();
 
 const DB_FILE_NAME: &str = "filen-relay.db";
+/// Backups are uploaded here, encrypted by Filen like any other file in the admin's drive
+/// (or under this prefix in the S3 bucket, see `S3Config`).
+const BACKUPS_DIR: &str = ".filen-relay/backups";
+const BACKUP_FILE_PREFIX: &str = "filen-relay-";
+/// Number of backups to keep.
+const BACKUP_RETENTION: usize = 50;
+/// How long to wait for further changes before uploading the database.
+const UPLOAD_DEBOUNCE: Duration = Duration::from_secs(2);
+/// How often changes whose backup failed are uploaded again, see `upload_pending_changes`.
+pub(crate) const PENDING_CHANGES_INTERVAL: Duration = Duration::from_secs(30 * 60);
+/// How long a query waits for a lock held by another connection (e.g. while taking a snapshot).
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An admin auth config rotated at runtime (see `rotate_admin_auth_config`) is kept in this file next to the local
+/// database file, so that it is used after restarting too.
+const ROTATED_AUTH_CONFIG_FILE_NAME: &str = "rotated-admin-auth-config.json";
+
+/// Hash of the admin auth config passed on startup, see `RotatedAuthConfig`.
+static STARTUP_AUTH_CONFIG: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+
+/// An admin auth config rotated at runtime, which replaces the one passed on startup until that is changed.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct RotatedAuthConfig {
+    /// Hash of the auth config passed on startup it replaces, see `secrets::hash`.
+    replaces: String,
+    /// Encrypted with the master key, see `secrets`.
+    auth_config: String,
+}
+
+const SETTING_REGISTRATION_MODE: &str = "registration_mode";
+const SETTING_CACHE_QUOTA_GB: &str = "cache_quota_gb";
+const SETTING_AUTO_PAUSE_DAYS: &str = "auto_pause_days";
+const SETTING_MAX_RUNNING_SERVERS: &str = "max_running_servers";
+const SETTING_DISABLED_MAINTENANCE_TASKS: &str = "disabled_maintenance_tasks";
+const SETTING_DEFAULT_READ_ONLY: &str = "default_read_only";
+const SETTING_DEFAULT_VFS_CACHE_MODE: &str = "default_vfs_cache_mode";
+const SETTING_DEFAULT_BWLIMIT: &str = "default_bwlimit";
+const SETTING_DEFAULT_LOG_LEVEL: &str = "default_log_level";
+const SETTING_VERIFY_EMAIL_ON_FIRST_LOGIN: &str = "verify_email_on_first_login";
+const SETTING_ANNOUNCEMENT: &str = "announcement";
+
 pub(crate) struct DbViaOfflineOrRemoteFile {
-    conn: Mutex<rusqlite::Connection>,
-    filen_client: Option<Client>,
-    remote_db_dir: Option<UuidStr>,
+    /// Only locked on blocking threads, see `call`.
+    conn: Arc<Mutex<rusqlite::Connection>>,
+    /// Set if the database is backed up remotely. Can be swapped at runtime, see `rotate_admin_auth_config`.
+    remote: Mutex<Option<BackupTarget>>,
+    /// The newest backup known to this instance (restored or uploaded by it), held while uploading.
+    last_backup: tokio::sync::Mutex<Option<Backup>>,
+    /// A newer backup written by someone else (e.g. another relay instance), if one was detected.
+    conflict: Mutex<Option<String>>,
+    /// Notified whenever the database was changed and needs to be uploaded, see `upload_changes`.
+    changed: tokio::sync::Notify,
+    /// Counts the changes, and the ones included in the last uploaded backup, see `upload_pending_changes`.
+    changes: AtomicU64,
+    backed_up_changes: AtomicU64,
+}
+
+/// The admin's Filen client and the backups directory in their drive.
+#[derive(Clone)]
+struct FilenSync {
+    client: Arc<Client>,
+    backups_dir: UuidStr,
+}
+
+/// Connection details of an S3-compatible bucket (e.g. on MinIO) to store the database backups in,
+/// instead of the admin's drive.
+pub(crate) struct S3Config {
+    /// e.g. "https://s3.fr-par.scw.cloud" or "http://minio:9000"
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+/// Where the database backups are stored.
+#[derive(Clone)]
+enum BackupTarget {
+    Filen(FilenSync),
+    S3(Arc<s3::Bucket>),
+}
+
+/// A database backup, identified by its name and the version of the remote file, so that a backup replaced under
+/// the same name (e.g. by another relay instance) is recognized as well.
+#[derive(Clone, PartialEq)]
+struct Backup {
+    name: String,
+    /// The file's UUID in Filen, or its modification time and ETag in S3.
+    version: String,
+}
+
+impl BackupTarget {
+    /// Lists the database backups, newest first.
+    async fn list_backups(&self) -> anyhow::Result<Vec<Backup>> {
+        let mut backups = match self {
+            BackupTarget::Filen(FilenSync { client, .. }) => {
+                let dir = match client
+                    .find_item_at_path(&format!("/{}", BACKUPS_DIR))
+                    .await?
+                {
+                    Some(FSObject::Dir(dir)) => DirectoryType::Dir(dir),
+                    _ => return Ok(Vec::new()),
+                };
+                let (_dirs, files) = client
+                    .list_dir(&dir)
+                    .await
+                    .context("Failed to list database backups")?;
+                files
+                    .iter()
+                    .filter_map(|file| {
+                        Some(Backup {
+                            name: file.name()?.to_string(),
+                            version: file.uuid().to_string(),
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            }
+            BackupTarget::S3(bucket) => bucket
+                .list(format!("{}/", BACKUPS_DIR), None)
+                .await
+                .context("Failed to list database backups")?
+                .into_iter()
+                .flat_map(|page| page.contents)
+                .filter_map(|object| {
+                    Some(Backup {
+                        name: object.key.rsplit('/').next()?.to_string(),
+                        version: format!(
+                            "{} {}",
+                            object.last_modified,
+                            object.e_tag.unwrap_or_default()
+                        ),
+                    })
+                })
+                .collect::<Vec<_>>(),
+        };
+        backups.retain(|backup| {
+            backup.name.starts_with(BACKUP_FILE_PREFIX) && backup.name.ends_with(".db")
+        });
+        // the timestamps in the names sort chronologically
+        backups.sort_by(|a, b| b.name.cmp(&a.name));
+        Ok(backups)
+    }
+
+    /// Downloads a backup to `file`. Returns false if it doesn't exist.
+    async fn download_backup(&self, name: &str, file: &std::path::Path) -> anyhow::Result<bool> {
+        match self {
+            BackupTarget::Filen(FilenSync { client, .. }) => {
+                let Some(FSObject::File(remote_file)) = client
+                    .find_item_at_path(&format!("/{}/{}", BACKUPS_DIR, name))
+                    .await?
+                else {
+                    return Ok(false);
+                };
+                client
+                    .download_file_to_path(
+                        &RemoteFileType::File(remote_file),
+                        file.to_path_buf(),
+                        None,
+                    )
+                    .await?;
+            }
+            BackupTarget::S3(bucket) => {
+                let response = bucket
+                    .get_object(format!("{}/{}", BACKUPS_DIR, name))
+                    .await
+                    .context("Failed to download database backup")?;
+                tokio::fs::write(file, response.bytes()).await?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Uploads `file` as a backup, named like the file.
+    async fn upload_backup(&self, file: &std::path::Path) -> anyhow::Result<()> {
+        match self {
+            BackupTarget::Filen(FilenSync {
+                client,
+                backups_dir,
+            }) => {
+                client
+                    .upload_file_from_path(backups_dir, file.to_path_buf(), None)
+                    .await
+                    .context("Failed to upload database backup to admin Filen account")?;
+            }
+            BackupTarget::S3(bucket) => {
+                let name = file.file_name().unwrap_or_default().to_string_lossy();
+                let content = tokio::fs::read(file).await?;
+                bucket
+                    .put_object(format!("{}/{}", BACKUPS_DIR, name), &content)
+                    .await
+                    .context("Failed to upload database backup to S3 bucket")?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_backup(&self, name: &str) -> anyhow::Result<()> {
+        match self {
+            BackupTarget::Filen(FilenSync { client, .. }) => {
+                if let Some(FSObject::File(file)) = client
+                    .find_item_at_path(&format!("/{}/{}", BACKUPS_DIR, name))
+                    .await?
+                {
+                    client.trash_file(&file).await?;
+                }
+            }
+            BackupTarget::S3(bucket) => {
+                bucket
+                    .delete_object(format!("{}/{}", BACKUPS_DIR, name))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl DbViaOfflineOrRemoteFile {
@@ -28,6 +254,7 @@ impl DbViaOfflineOrRemoteFile {
         filen_email: String,
         filen_password: &str,
         filen_two_factor_code: Option<&str>,
+        restore_backup: Option<&str>,
     ) -> Result<Self> {
         let client = filen_sdk_rs::auth::Client::login(
             filen_email,
@@ -36,33 +263,79 @@ impl DbViaOfflineOrRemoteFile {
         )
         .await
         .context("Failed to log in to admin Filen")?;
-        let remote_db_dir = Self::initialize_from_filen(&client).await?;
-        let db = Self {
-            conn: Mutex::new(Self::init(None)),
-            filen_client: Some(client),
-            remote_db_dir: Some(remote_db_dir),
-        };
-        Ok(db)
+        let target = Self::filen_backup_target(client).await?;
+        Self::new_from_backup_target(target, restore_backup).await
     }
 
-    pub(crate) async fn new_from_auth_config(filen_auth_config: String) -> Result<(String, Self)> {
+    pub(crate) async fn new_from_auth_config(
+        filen_auth_config: String,
+        restore_backup: Option<&str>,
+    ) -> Result<(String, Self)> {
+        let startup_auth_config = secrets::hash(filen_auth_config.trim());
+        let filen_auth_config = match Self::read_rotated_auth_config(&startup_auth_config) {
+            Some(rotated) => {
+                dioxus::logger::tracing::info!("Using the admin auth config rotated at runtime");
+                rotated
+            }
+            None => filen_auth_config,
+        };
+        let _ = STARTUP_AUTH_CONFIG.set(startup_auth_config);
         let client = filen_cli::deserialize_auth_config(&filen_auth_config)
             .context("Failed to deserialize admin Filen auth config")?;
         let admin_email = client.email().to_string();
-        let remote_db_dir = Self::initialize_from_filen(&client).await?;
-        let db = Self {
-            conn: Mutex::new(Self::init(None)),
-            filen_client: Some(client),
-            remote_db_dir: Some(remote_db_dir),
-        };
+        let target = Self::filen_backup_target(client).await?;
+        let db = Self::new_from_backup_target(target, restore_backup).await?;
         Ok((admin_email, db))
     }
 
+    pub(crate) async fn new_from_s3(
+        config: S3Config,
+        restore_backup: Option<&str>,
+    ) -> Result<Self> {
+        let credentials = s3::creds::Credentials::new(
+            Some(&config.access_key),
+            Some(&config.secret_key),
+            None,
+            None,
+            None,
+        )
+        .context("Invalid S3 credentials")?;
+        let region = s3::Region::Custom {
+            region: config.region,
+            endpoint: config.endpoint,
+        };
+        // path-style URLs work with MinIO and other S3-compatible storage, not only AWS
+        let bucket = s3::Bucket::new(&config.bucket, region, credentials)
+            .context("Failed to configure S3 bucket")?
+            .with_path_style();
+        Self::new_from_backup_target(BackupTarget::S3(Arc::from(bucket)), restore_backup).await
+    }
+
+    async fn new_from_backup_target(
+        target: BackupTarget,
+        restore_backup: Option<&str>,
+    ) -> Result<Self> {
+        let last_backup = Self::restore(&target, restore_backup).await?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(Self::init(None))),
+            remote: Mutex::new(Some(target)),
+            last_backup: tokio::sync::Mutex::new(last_backup),
+            conflict: Mutex::new(None),
+            changed: tokio::sync::Notify::new(),
+            changes: AtomicU64::new(0),
+            backed_up_changes: AtomicU64::new(0),
+        })
+    }
+
     pub(crate) async fn new_from_offline_location(db_dir: Option<&str>) -> Result<Self> {
         Ok(Self {
-            conn: Mutex::new(Self::init(db_dir)),
-            filen_client: None,
-            remote_db_dir: None,
+            conn: Arc::new(Mutex::new(Self::init(db_dir))),
+            remote: Mutex::new(None),
+            last_backup: tokio::sync::Mutex::new(None),
+            conflict: Mutex::new(None),
+            changed: tokio::sync::Notify::new(),
+            changes: AtomicU64::new(0),
+            backed_up_changes: AtomicU64::new(0),
         })
     }
 
@@ -70,6 +343,11 @@ impl DbViaOfflineOrRemoteFile {
         let db_dir = db_dir.unwrap_or(".").trim_end_matches('/').to_string();
         let conn = rusqlite::Connection::open(format!("{}/{}", db_dir, DB_FILE_NAME))
             .expect("Failed to open database");
+        // readers aren't blocked by writers (or snapshots) in WAL mode
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))
+            .expect("Failed to enable WAL mode");
+        conn.busy_timeout(BUSY_TIMEOUT)
+            .expect("Failed to set busy timeout");
         conn.execute_batch(
             "
             CREATE TABLE IF NOT EXISTS allowed_users (
@@ -87,138 +365,1688 @@ impl DbViaOfflineOrRemoteFile {
                 filen_password TEXT NOT NULL,
                 filen_2fa_code TEXT
             );
+            CREATE TABLE IF NOT EXISTS settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS usage (
+                server_id TEXT NOT NULL,
+                day TEXT NOT NULL,
+                bytes_transferred INTEGER NOT NULL DEFAULT 0,
+                running_seconds INTEGER NOT NULL DEFAULT 0,
+                filen_email TEXT,
+                PRIMARY KEY (server_id, day)
+            );
+            CREATE TABLE IF NOT EXISTS teams (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                owner TEXT NOT NULL,
+                members TEXT NOT NULL DEFAULT '[]',
+                max_servers INTEGER
+            );
+            CREATE TABLE IF NOT EXISTS sessions (
+                -- the hash of the token, see `SessionToken::hash`
+                token TEXT PRIMARY KEY,
+                filen_email TEXT NOT NULL,
+                filen_auth_config TEXT NOT NULL,
+                is_admin BOOLEAN NOT NULL,
+                created TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS leader_lease (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                instance_id TEXT NOT NULL,
+                instance_url TEXT NOT NULL,
+                expires TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS last_logins (
+                filen_email TEXT PRIMARY KEY,
+                last_login TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS server_stats (
+                server_id TEXT NOT NULL,
+                hour INTEGER NOT NULL,
+                requests INTEGER NOT NULL DEFAULT 0,
+                bytes INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (server_id, hour)
+            );
+            CREATE TABLE IF NOT EXISTS egress_caps (
+                filen_email TEXT PRIMARY KEY,
+                monthly_gb INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS notification_channels (
+                id TEXT PRIMARY KEY,
+                kind TEXT NOT NULL,
+                webhook_url TEXT NOT NULL,
+                owner TEXT
+            );
+            CREATE TABLE IF NOT EXISTS declared_servers (
+                id TEXT PRIMARY KEY
+            );
+            CREATE TABLE IF NOT EXISTS server_templates (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                server_type TEXT NOT NULL,
+                read_only BOOLEAN NOT NULL,
+                options TEXT NOT NULL DEFAULT '{}'
+            );
+            CREATE TABLE IF NOT EXISTS status_pages (
+                token TEXT PRIMARY KEY,
+                sealed_token TEXT NOT NULL,
+                name TEXT NOT NULL,
+                owner TEXT NOT NULL,
+                server_ids TEXT NOT NULL DEFAULT '[]'
+            );
+            CREATE TABLE IF NOT EXISTS audit_log (
+                id INTEGER PRIMARY KEY,
+                timestamp TEXT NOT NULL,
+                actor TEXT NOT NULL,
+                action TEXT NOT NULL,
+                details TEXT NOT NULL
+            );
             ",
         )
         .unwrap();
-    conn
+        Self::add_column_if_missing(&conn, "servers", "archived", "BOOLEAN NOT NULL DEFAULT 0");
+        Self::add_column_if_missing(&conn, "servers", "options", "TEXT NOT NULL DEFAULT '{}'");
+        Self::add_column_if_missing(&conn, "servers", "paused", "BOOLEAN NOT NULL DEFAULT 0");
+        Self::add_column_if_missing(&conn, "servers", "last_accessed", "TEXT");
+        Self::add_column_if_missing(
+            &conn,
+            "servers",
+            "collaborators",
+            "TEXT NOT NULL DEFAULT '[]'",
+        );
+        Self::add_column_if_missing(&conn, "servers", "team_id", "TEXT");
+        Self::add_column_if_missing(&conn, "sessions", "user_agent", "TEXT");
+        Self::add_column_if_missing(&conn, "sessions", "ip", "TEXT");
+        // replaces filen_password and filen_2fa_code, see `auth::migrate_legacy_server_credentials`
+        Self::add_column_if_missing(
+            &conn,
+            "servers",
+            "filen_auth_config",
+            "TEXT NOT NULL DEFAULT ''",
+        );
+        // sessions of older versions were stored with their token instead of its hash
+        Self::hash_session_tokens(&conn);
+        // status pages are looked up by the hash of their token, which is only kept encrypted
+        if !Self::column_exists(&conn, "status_pages", "sealed_token") {
+            Self::add_column_if_missing(
+                &conn,
+                "status_pages",
+                "sealed_token",
+                "TEXT NOT NULL DEFAULT ''",
+            );
+            Self::hash_status_page_tokens(&conn);
+        }
+        // the owner is stored with the usage, so that it still counts for them after the server is purged
+        if !Self::column_exists(&conn, "usage", "filen_email") {
+            Self::add_column_if_missing(&conn, "usage", "filen_email", "TEXT");
+            conn.execute(
+                "UPDATE usage SET filen_email = (SELECT filen_email FROM servers WHERE servers.id = usage.server_id)",
+                [],
+            )
+            .expect("Failed to migrate database");
+        }
+        // replaces password, see `obscure`
+        if !Self::column_exists(&conn, "servers", "obscured_password") {
+            Self::add_column_if_missing(&conn, "servers", "obscured_password", "TEXT");
+            Self::obscure_server_passwords(&conn);
+        }
+        conn
     }
 
-    async fn initialize_from_filen(client: &Client) -> anyhow::Result<UuidStr> {
-        let local_db_file = std::env::current_dir()?.join(DB_FILE_NAME);
-        if tokio::fs::try_exists(&local_db_file).await.context("Failed to check if local database file exists")? {
-            tokio::fs::remove_file(&local_db_file).await.context("Failed to remove existing local database file")?;
+    fn column_exists(conn: &Connection, table: &str, column: &str) -> bool {
+        conn.prepare(&format!(
+            "SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1",
+            table
+        ))
+        .and_then(|mut stmt| stmt.exists(rusqlite::params![column]))
+        .expect("Failed to inspect database schema")
+    }
+
+    /// Migrates databases created by older versions, which lack a newly added column.
+    fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) {
+        if !Self::column_exists(conn, table, column) {
+            conn.execute_batch(&format!(
+                "ALTER TABLE {} ADD COLUMN {} {};",
+                table, column, definition
+            ))
+            .expect("Failed to migrate database");
         }
-        match client
-            .find_item_at_path(&format!("/.filen-relay/{}", DB_FILE_NAME))
-            .await?
-        {
-            Some(FSObject::File(file)) => {
-                let db_file = RemoteFileType::File(file);
-                client
-                    .download_file_to_path(
-                        &db_file,
-                        local_db_file,
-                        None,
-                    )
-                    .await?;
-            }
+    }
+
+    /// Moves the plaintext passwords of servers stored by older versions to `obscured_password`.
+    fn obscure_server_passwords(conn: &Connection) {
+        let passwords = conn
+            .prepare("SELECT id, password FROM servers WHERE password IS NOT NULL")
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .expect("Failed to read server passwords");
+        for (id, password) in passwords {
+            conn.execute(
+                "UPDATE servers SET obscured_password = ?1, password = NULL WHERE id = ?2",
+                rusqlite::params![obscure::obscure(&password), id],
+            )
+            .expect("Failed to obscure server password");
+        }
+    }
+
+    fn hash_session_tokens(conn: &Connection) {
+        let tokens = conn
+            .prepare("SELECT token FROM sessions WHERE length(token) != 64")
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .expect("Failed to read sessions");
+        for token in tokens {
+            conn.execute(
+                "UPDATE sessions SET token = ?1 WHERE token = ?2",
+                rusqlite::params![SessionToken(token.clone()).hash(), token],
+            )
+            .expect("Failed to hash session token");
+        }
+    }
+
+    fn hash_status_page_tokens(conn: &Connection) {
+        let tokens = conn
+            .prepare("SELECT token FROM status_pages")
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .expect("Failed to read status pages");
+        for token in tokens {
+            conn.execute(
+                "UPDATE status_pages SET token = ?1, sealed_token = ?2 WHERE token = ?2",
+                rusqlite::params![secrets::hash(&token), token],
+            )
+            .expect("Failed to hash status page token");
+        }
+    }
+
+    /// The auth config rotated at runtime that replaces the one passed on startup (identified by its hash), if any.
+    /// One that replaced another auth config is removed, since the admin has updated the one passed on startup since.
+    fn read_rotated_auth_config(startup_auth_config: &str) -> Option<String> {
+        let file = std::env::current_dir()
+            .ok()?
+            .join(ROTATED_AUTH_CONFIG_FILE_NAME);
+        let rotated = std::fs::read_to_string(&file).ok()?;
+        let rotated = match serde_json::from_str::<RotatedAuthConfig>(&rotated) {
+            Ok(rotated) if rotated.replaces == startup_auth_config => rotated,
             _ => {
+                let _ = std::fs::remove_file(&file);
+                return None;
+            }
+        };
+        match secrets::open(&rotated.auth_config) {
+            Ok(auth_config) => Some(auth_config),
+            Err(e) => {
                 dioxus::logger::tracing::warn!(
-                    "Filen relay database not found at /.filen-relay/{} in admin Filen account, starting with empty database",
-                    DB_FILE_NAME
+                    "Failed to read the admin auth config rotated at runtime: {:#}",
+                    e
                 );
+                None
             }
+        }
+    }
+
+    /// Keeps the rotated auth config for restarts, see `read_rotated_auth_config`.
+    fn write_rotated_auth_config(filen_auth_config: &str) -> anyhow::Result<()> {
+        let Some(startup_auth_config) = STARTUP_AUTH_CONFIG.get() else {
+            return Ok(()); // the admin logged in with email and password on startup, which works again after restarting
         };
-        Ok(*client
-            .find_or_create_dir(".filen-relay")
+        let rotated = serde_json::to_string(&RotatedAuthConfig {
+            replaces: startup_auth_config.clone(),
+            auth_config: secrets::seal(filen_auth_config),
+        })?;
+        let file = std::env::current_dir()?.join(ROTATED_AUTH_CONFIG_FILE_NAME);
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt as _;
+            options.mode(0o600);
+        }
+        std::io::Write::write_all(&mut options.open(&file)?, rotated.as_bytes())?;
+        Ok(())
+    }
+
+    /// Creates the backups directory in the admin's drive, if needed.
+    async fn filen_backup_target(client: Client) -> anyhow::Result<BackupTarget> {
+        let backups_dir = client
+            .find_or_create_dir(BACKUPS_DIR)
             .await
-            .context("Failed to create .filen-relay dir in admin Filen account")?
-            .uuid())
+            .context("Failed to create backups dir in admin Filen account")?;
+        Ok(BackupTarget::Filen(FilenSync {
+            client: Arc::new(client),
+            backups_dir: *backups_dir.uuid(),
+        }))
+    }
+
+    /// Restores the newest intact backup (or `restore_backup`, if given) and returns the newest backup.
+    async fn restore(
+        target: &BackupTarget,
+        restore_backup: Option<&str>,
+    ) -> anyhow::Result<Option<Backup>> {
+        let local_db_file = std::env::current_dir()?.join(DB_FILE_NAME);
+        if tokio::fs::try_exists(&local_db_file)
+            .await
+            .context("Failed to check if local database file exists")?
+        {
+            tokio::fs::remove_file(&local_db_file)
+                .await
+                .context("Failed to remove existing local database file")?;
+        }
+        // leftovers of the previous database's write-ahead log must not be applied to the restored one
+        for suffix in ["-wal", "-shm"] {
+            let file = std::env::current_dir()?.join(format!("{}{}", DB_FILE_NAME, suffix));
+            if tokio::fs::try_exists(&file).await.unwrap_or(false) {
+                tokio::fs::remove_file(&file)
+                    .await
+                    .context("Failed to remove existing local database file")?;
+            }
+        }
+        let mut backups = target.list_backups().await?;
+        // later uploads are compared against the newest backup, even if it had to be skipped as corrupted
+        let newest_backup = backups.first().cloned();
+        if let Some(restore_backup) = restore_backup {
+            if !backups.iter().any(|backup| backup.name == restore_backup) {
+                anyhow::bail!("Backup {} not found in {}", restore_backup, BACKUPS_DIR);
+            }
+            backups.retain(|backup| backup.name == restore_backup);
+        }
+        for Backup { name: backup, .. } in backups {
+            if !target.download_backup(&backup, &local_db_file).await? {
+                continue;
+            }
+            if Self::is_intact(&local_db_file) {
+                dioxus::logger::tracing::info!("Restored database from backup {}", backup);
+                return Ok(newest_backup);
+            }
+            dioxus::logger::tracing::warn!(
+                "Database backup {} is corrupted, trying an older one",
+                backup
+            );
+            tokio::fs::remove_file(&local_db_file).await?;
+        }
+        // databases of older versions were stored as a single, overwritten file in the admin's drive
+        if let BackupTarget::Filen(FilenSync { client, .. }) = target {
+            if let Some(FSObject::File(file)) = client
+                .find_item_at_path(&format!("/.filen-relay/{}", DB_FILE_NAME))
+                .await?
+            {
+                let db_file = RemoteFileType::File(file);
+                client
+                    .download_file_to_path(&db_file, local_db_file, None)
+                    .await?;
+                return Ok(newest_backup);
+            }
+        }
+        dioxus::logger::tracing::warn!(
+            "No Filen relay database found in {}, starting with empty database",
+            BACKUPS_DIR
+        );
+        Ok(newest_backup)
+    }
+
+    /// Whether the database file can be opened and passes SQLite's integrity check.
+    fn is_intact(db_file: &std::path::Path) -> bool {
+        rusqlite::Connection::open(db_file)
+            .and_then(|conn| {
+                conn.query_row("PRAGMA integrity_check", [], |row| row.get::<_, String>(0))
+            })
+            .is_ok_and(|result| result == "ok")
+    }
+
+    /// Lists the names of the database backups (in the admin's drive or the S3 bucket), newest first.
+    pub(crate) async fn list_backups(&self) -> anyhow::Result<Vec<String>> {
+        match self.remote() {
+            Some(target) => Ok(target
+                .list_backups()
+                .await?
+                .into_iter()
+                .map(|backup| backup.name)
+                .collect()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn remote(&self) -> Option<BackupTarget> {
+        self.remote.lock().unwrap().clone()
     }
 
-    // todo: make this more async so that other things can be resumed until the upload is done (can be done at call site probably)
-    async fn write_to_filen(&self) -> anyhow::Result<()> {
-        let Some(client) = &self.filen_client else {
+    /// The admin's Filen client, if the database is stored in their drive.
+    pub(crate) fn admin_client(&self) -> Option<Arc<Client>> {
+        match self.remote() {
+            Some(BackupTarget::Filen(FilenSync { client, .. })) => Some(client),
+            _ => None,
+        }
+    }
+
+    /// Replaces the admin's Filen client used for syncing the database, e.g. after their session expired.
+    /// The new auth config must belong to the admin and be able to access the backups directory. It is kept (encrypted
+    /// with the master key) for restarts, until the auth config passed on startup is changed.
+    pub(crate) async fn rotate_admin_auth_config(
+        &self,
+        filen_auth_config: &str,
+    ) -> anyhow::Result<()> {
+        if !matches!(self.remote(), Some(BackupTarget::Filen(_))) {
+            anyhow::bail!("The database is not stored in the admin's Filen drive");
+        }
+        let client = filen_cli::deserialize_auth_config(filen_auth_config)
+            .context("Failed to deserialize admin Filen auth config")?;
+        let email = client.email().to_string();
+        if ADMIN_EMAIL.get() != Some(&email) {
+            anyhow::bail!("The auth config belongs to {}, not to the admin", email);
+        }
+        let target = Self::filen_backup_target(client).await?;
+        target.list_backups().await?;
+        Self::write_rotated_auth_config(filen_auth_config)
+            .context("Failed to store the rotated auth config")?;
+        *self.remote.lock().unwrap() = Some(target);
+        dioxus::logger::tracing::info!("Rotated admin Filen auth config");
+        Ok(())
+    }
+
+    /// The newer backup written by someone else, if one was detected when uploading.
+    pub(crate) fn get_conflict(&self) -> Option<String> {
+        self.conflict.lock().unwrap().clone()
+    }
+
+    /// Resolves a conflict by uploading this instance's database as the newest backup anyway.
+    pub(crate) async fn overwrite_conflict(&self) -> anyhow::Result<()> {
+        self.upload_backup(true).await
+    }
+
+    /// Uploads a snapshot of the database as a new backup and removes backups beyond `BACKUP_RETENTION`.
+    /// Unless `force` is set, this is refused if someone else uploaded a newer backup in the meantime.
+    async fn upload_backup(&self, force: bool) -> anyhow::Result<()> {
+        let Some(target) = self.remote() else {
             return Ok(()); // it is not needed
         };
-        client
-            .upload_file_from_path(
-                self.remote_db_dir.as_ref().unwrap(),
-                std::env::current_dir()?.join(DB_FILE_NAME),
-                None,
-            )
-            .await
-            .context("Failed to upload database file to admin Filen account")?;
+        let mut last_backup = self.last_backup.lock().await;
+        let newest_backup = target.list_backups().await?.into_iter().next();
+        if !force && newest_backup != *last_backup {
+            let newest_backup = newest_backup.map(|backup| backup.name).unwrap_or_default();
+            dioxus::logger::tracing::error!(
+                "Database backup {} was written by someone else (e.g. another relay instance), not overwriting it",
+                newest_backup
+            );
+            *self.conflict.lock().unwrap() = Some(newest_backup.clone());
+            anyhow::bail!(
+                "The database backups were changed elsewhere (backup {}). Changes are only saved locally until an admin resolves this.",
+                newest_backup
+            );
+        }
+        let changes = self.changes.load(Ordering::SeqCst);
+        let snapshot_name = format!(
+            "{}{}.db",
+            BACKUP_FILE_PREFIX,
+            Utc::now().format("%Y%m%dT%H%M%S%3fZ")
+        );
+        let snapshot_file = std::env::temp_dir().join(&snapshot_name);
+        // a consistent copy of the database, even while it is being written to
+        let snapshot_path = snapshot_file.to_string_lossy().to_string();
+        self.call(move |db| {
+            db.execute("VACUUM INTO ?1", rusqlite::params![snapshot_path.clone()])?;
+            // sessions and the lease only matter to the running instances: restoring them would revive logged out
+            // sessions, and backups (e.g. in the admin's drive) shouldn't contain the users' Filen auth configs
+            let snapshot = Connection::open(&snapshot_path)?;
+            snapshot.execute_batch("DELETE FROM sessions; DELETE FROM leader_lease; VACUUM;")
+        })
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to take database snapshot: {}", e))?;
+        let upload = target.upload_backup(&snapshot_file).await;
+        tokio::fs::remove_file(&snapshot_file).await?;
+        upload?;
+        let backups = target.list_backups().await?;
+        *last_backup = backups
+            .iter()
+            .find(|backup| backup.name == snapshot_name)
+            .cloned();
+        self.backed_up_changes.fetch_max(changes, Ordering::SeqCst);
+        *self.conflict.lock().unwrap() = None;
+
+        for backup in backups.into_iter().skip(BACKUP_RETENTION) {
+            target
+                .delete_backup(&backup.name)
+                .await
+                .context("Failed to remove old database backup")?;
+        }
         Ok(())
     }
 
-    pub(crate) fn get_allowed_users(&self) -> Result<Vec<String>> {
-        let db = self.conn.lock().unwrap();
-        let mut stmt = db.prepare("SELECT email FROM allowed_users")?;
-        let user_iter = stmt.query_map([], |row| row.get(0))?;
-        let mut users = Vec::new();
-        for user in user_iter {
-            users.push(user?);
+    /// Runs `f` with the connection on a blocking thread, so that slow queries don't block the runtime.
+    async fn call<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        let result = tokio::task::spawn_blocking(move || f(&conn.lock().unwrap()))
+            .await
+            .map_err(|e| anyhow::anyhow!("Database task failed: {}", e))?;
+        Ok(result?)
+    }
+
+    /// Like `call`, but for changes, which are then backed up in the background.
+    async fn call_mut<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let result = self.call(f).await?;
+        self.mark_changed();
+        Ok(result)
+    }
+
+    /// Notes a change of the database, to be uploaded as a backup.
+    fn mark_changed(&self) {
+        self.changes.fetch_add(1, Ordering::SeqCst);
+        self.changed.notify_one();
+    }
+
+    /// Uploads a backup right away (if the database is backed up remotely), e.g. before the relay is stopped.
+    pub(crate) async fn flush(&self) -> anyhow::Result<()> {
+        self.upload_backup(false).await
+    }
+
+    /// Uploads the database as a backup whenever it has changed. Runs forever.
+    pub(crate) async fn upload_changes(&self) {
+        if self.remote().is_none() {
+            return; // it is not needed
+        }
+        loop {
+            self.changed.notified().await;
+            // changes often come in bursts, which only need to be uploaded once
+            tokio::time::sleep(UPLOAD_DEBOUNCE).await;
+            if let Err(e) = self.upload_backup(false).await {
+                dioxus::logger::tracing::error!("Failed to upload database: {:#}", e);
+            }
+        }
+    }
+
+    /// Uploads a backup if there are changes that weren't backed up, e.g. because uploading them failed
+    /// (run by the scheduler as `MaintenanceTask::Backup`). Returns a summary for the admin.
+    pub(crate) async fn upload_pending_changes(&self) -> anyhow::Result<String> {
+        if self.remote().is_none() {
+            return Ok("The database isn't backed up remotely".to_string());
+        }
+        if self.changes.load(Ordering::SeqCst) <= self.backed_up_changes.load(Ordering::SeqCst) {
+            return Ok("All changes are backed up".to_string());
         }
-        Ok(users)
+        self.upload_backup(false).await?;
+        Ok("Uploaded a backup of pending changes".to_string())
+    }
+
+    /// Encrypts the secrets not yet encrypted with the current master key (stored without one, or with the previous
+    /// one), see `secrets`. Returns how many were encrypted.
+    ///
+    /// Fails if any secret can't be decrypted (without a master key, or with a wrong one), since servers whose secrets
+    /// can't be read couldn't be started.
+    pub(crate) async fn seal_secrets(&self) -> anyhow::Result<usize> {
+        // (table, primary key, column) of every secret
+        const SECRET_COLUMNS: [(&str, &str, &str); 5] = [
+            ("servers", "id", "obscured_password"),
+            ("servers", "id", "filen_auth_config"),
+            ("sessions", "token", "filen_auth_config"),
+            ("notification_channels", "id", "webhook_url"),
+            ("status_pages", "token", "sealed_token"),
+        ];
+        let stored = self
+            .call(|db| {
+                let mut stored = Vec::new();
+                for (table, key, column) in SECRET_COLUMNS {
+                    let values = db
+                        .prepare(&format!(
+                            "SELECT {}, {} FROM {} WHERE {} IS NOT NULL",
+                            key, column, table, column
+                        ))?
+                        .query_map([], |row| {
+                            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                        })?
+                        .collect::<rusqlite::Result<Vec<_>>>()?;
+                    stored.extend(
+                        values
+                            .into_iter()
+                            .map(|(id, value)| (table, key, column, id, value)),
+                    );
+                }
+                Ok(stored)
+            })
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read secrets: {}", e))?;
+        let mut resealed = Vec::new();
+        for (table, key, column, id, value) in stored {
+            let plaintext = secrets::open(&value).map_err(|e| {
+                anyhow::anyhow!("Failed to decrypt {}.{} of {}: {:#}", table, column, id, e)
+            })?;
+            if secrets::needs_sealing(&value) {
+                resealed.push((table, key, column, id, secrets::seal(&plaintext)));
+            }
+        }
+        if resealed.is_empty() {
+            return Ok(0);
+        }
+        self.call_mut(move |db| {
+            let tx = db.unchecked_transaction()?;
+            for (table, key, column, id, sealed) in &resealed {
+                tx.execute(
+                    &format!("UPDATE {} SET {} = ?1 WHERE {} = ?2", table, column, key),
+                    rusqlite::params![sealed, id],
+                )?;
+            }
+            tx.commit()?;
+            Ok(resealed.len())
+        })
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to encrypt secrets: {}", e))
+    }
+
+    pub(crate) async fn get_allowed_users(&self) -> Result<Vec<String>> {
+        self.call(|db| {
+            let mut stmt = db.prepare("SELECT email FROM allowed_users")?;
+            let users = stmt
+                .query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()?;
+            Ok(users)
+        })
+        .await
     }
 
     pub(crate) async fn add_allowed_user(&self, email: &str) -> Result<()> {
-        self.conn.lock().unwrap().execute(
-            "INSERT INTO allowed_users (email) VALUES (?1)",
-            rusqlite::params![email],
-        )?;
-        self.write_to_filen().await?;
+        let email = email.to_string();
+        self.call_mut(move |db| {
+            db.execute(
+                "INSERT INTO allowed_users (email) VALUES (?1)",
+                rusqlite::params![email],
+            )
+        })
+        .await?;
         Ok(())
     }
 
+    /// Returns the allowed users with their last login, servers and sessions created since `session_cutoff`.
+    pub(crate) async fn get_allowed_users_activity(
+        &self,
+        session_cutoff: DateTime<Utc>,
+    ) -> Result<Vec<AllowedUser>> {
+        self.call(move |db| {
+            let mut stmt = db.prepare(
+                "SELECT a.email, l.last_login,
+                    (SELECT COUNT(*) FROM servers s WHERE s.filen_email = a.email AND s.archived = 0),
+                    (SELECT COUNT(*) FROM sessions se WHERE se.filen_email = a.email AND se.created >= ?1)
+                FROM allowed_users a LEFT JOIN last_logins l ON l.filen_email = a.email ORDER BY a.email",
+            )?;
+            let users = stmt
+                .query_map(rusqlite::params![format_timestamp(&session_cutoff)], |row| {
+                    Ok(AllowedUser {
+                        email: row.get(0)?,
+                        last_login: row
+                            .get::<_, Option<String>>(1)?
+                            .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+                            .map(|t| t.with_timezone(&Utc)),
+                        servers: row.get::<_, i64>(2)? as usize,
+                        active_sessions: row.get::<_, i64>(3)? as usize,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<AllowedUser>>>()?;
+            Ok(users)
+        })
+        .await
+    }
+
+    pub(crate) async fn record_login(&self, email: &str, now: DateTime<Utc>) -> Result<()> {
+        let email = email.to_string();
+        self.call_mut(move |db| {
+            db.execute(
+                "INSERT INTO last_logins (filen_email, last_login) VALUES (?1, ?2) ON CONFLICT(filen_email) DO UPDATE SET last_login = excluded.last_login",
+                rusqlite::params![email, format_timestamp(&now)],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Whether the user has logged in before (on this instance, since last logins are recorded).
+    pub(crate) async fn has_logged_in(&self, email: &str) -> Result<bool> {
+        let email = email.to_string();
+        self.call(move |db| {
+            db.query_row(
+                "SELECT EXISTS(SELECT 1 FROM last_logins WHERE filen_email = ?1)",
+                rusqlite::params![email],
+                |row| row.get(0),
+            )
+        })
+        .await
+    }
+
+    pub(crate) async fn add_allowed_users(&self, emails: &[String]) -> Result<()> {
+        let emails = emails.to_vec();
+        self.call_mut(move |db| {
+            for email in emails {
+                db.execute(
+                    "INSERT OR IGNORE INTO allowed_users (email) VALUES (?1)",
+                    rusqlite::params![email],
+                )?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
     pub(crate) async fn remove_allowed_user(&self, email: &str) -> Result<()> {
-        self.conn.lock().unwrap().execute(
-            "DELETE FROM allowed_users WHERE email = ?1",
-            rusqlite::params![email],
-        )?;
-        self.write_to_filen().await?;
+        let email = email.to_string();
+        self.call_mut(move |db| {
+            db.execute(
+                "DELETE FROM allowed_users WHERE email = ?1",
+                rusqlite::params![email],
+            )
+        })
+        .await?;
         Ok(())
     }
 
     pub(crate) async fn clear_allowed_users(&self) -> Result<()> {
-        self.conn
-            .lock()
-            .unwrap()
-            .execute("DELETE FROM allowed_users", [])?;
-        self.write_to_filen().await?;
-        Ok(())
-    }
-
-    pub(crate) fn get_servers(&self) -> Result<Vec<ServerSpec>> {
-        let db = self.conn.lock().unwrap();
-        let mut stmt = 
-            db.prepare("SELECT id, name, server_type, root, read_only, password, filen_email, filen_password, filen_2fa_code FROM servers")?;
-        let server_iter = stmt.query_map([], |row| {
-            Ok(ServerSpec {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                server_type: row.get::<_, String>(2)?.as_str().into(),
-                root: row.get(3)?,
-                read_only: row.get(4)?,
-                password: row.get(5)?,
-                filen_email: row.get(6)?,
-                filen_password: row.get(7)?,
-                filen_2fa_code: row.get(8)?,
+        self.call_mut(|db| db.execute("DELETE FROM allowed_users", []))
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the specs of all servers that are not archived.
+    pub(crate) async fn get_servers(&self) -> Result<Vec<ServerSpec>> {
+        self.query_servers(false).await
+    }
+
+    pub(crate) async fn get_archived_servers(&self) -> Result<Vec<ServerSpec>> {
+        self.query_servers(true).await
+    }
+
+    async fn query_servers(&self, archived: bool) -> Result<Vec<ServerSpec>> {
+        self.call(move |db| {
+            let mut stmt = db.prepare(
+                "SELECT id, name, server_type, root, read_only, obscured_password, filen_email, filen_auth_config, options, collaborators, team_id FROM servers WHERE archived = ?1")?;
+            let servers = stmt.query_map(rusqlite::params![archived], |row| {
+                Ok(ServerSpec {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    server_type: row.get::<_, String>(2)?.as_str().into(),
+                    root: row.get(3)?,
+                    read_only: row.get(4)?,
+                    password: row
+                        .get::<_, Option<String>>(5)?
+                        .map(|password| obscure::reveal(&secrets::open(&password)?))
+                        .transpose()
+                        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, e.into()))?,
+                    filen_email: row.get(6)?,
+                    filen_auth_config: open_secret(row, 7)?,
+                    options: serde_json::from_str(&row.get::<_, String>(8)?).unwrap_or_default(),
+                    collaborators: serde_json::from_str(&row.get::<_, String>(9)?).unwrap_or_default(),
+                    team_id: row.get(10)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<ServerSpec>>>()?;
+            Ok(servers)
+        })
+        .await
+    }
+
+    pub(crate) async fn create_server(&self, spec: &ServerSpec) -> Result<()> {
+        let spec = spec.clone();
+        let options = serde_json::to_string(&spec.options)?;
+        let collaborators = serde_json::to_string(&spec.collaborators)?;
+        let obscured_password = spec
+            .password
+            .as_deref()
+            .map(|password| secrets::seal(&obscure::obscure(password)));
+        let filen_auth_config = secrets::seal(&spec.filen_auth_config);
+        self.call_mut(move |db| {
+            db.execute(
+                "INSERT INTO servers (id, name, server_type, root, read_only, obscured_password, filen_email, filen_password, filen_auth_config, options, collaborators, team_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, '', ?8, ?9, ?10, ?11)",
+                rusqlite::params![spec.id, spec.name, spec.server_type.to_string(), spec.root, spec.read_only, obscured_password, spec.filen_email, filen_auth_config, options, collaborators, spec.team_id],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Replaces the name, type, root, password and options of a server.
+    pub(crate) async fn update_server(&self, spec: &ServerSpec) -> Result<()> {
+        let spec = spec.clone();
+        let options = serde_json::to_string(&spec.options)?;
+        let obscured_password = spec
+            .password
+            .as_deref()
+            .map(|password| secrets::seal(&obscure::obscure(password)));
+        self.call_mut(move |db| {
+            db.execute(
+                "UPDATE servers SET name = ?1, server_type = ?2, root = ?3, read_only = ?4, obscured_password = ?5, options = ?6 WHERE id = ?7",
+                rusqlite::params![spec.name, spec.server_type.to_string(), spec.root, spec.read_only, obscured_password, options, spec.id],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Returns the IDs of the (not archived) servers created from the servers file, see `declared`.
+    pub(crate) async fn get_declared_server_ids(&self) -> Result<Vec<ServerId>> {
+        self.call(|db| {
+            let mut stmt = db.prepare(
+                "SELECT declared_servers.id FROM declared_servers JOIN servers ON servers.id = declared_servers.id WHERE servers.archived = 0",
+            )?;
+            let ids = stmt
+                .query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<ServerId>>>()?;
+            Ok(ids)
+        })
+        .await
+    }
+
+    /// Notes that a server was created from the servers file (possibly before the server itself is stored).
+    pub(crate) async fn add_declared_server(&self, id: &ServerId) -> Result<()> {
+        let id = id.clone();
+        self.call_mut(move |db| {
+            db.execute(
+                "INSERT OR IGNORE INTO declared_servers (id) VALUES (?1)",
+                rusqlite::params![id],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Marks a server as archived (or restores it), instead of deleting it.
+    pub(crate) async fn set_server_archived(&self, id: &ServerId, archived: bool) -> Result<()> {
+        let id = id.clone();
+        self.call_mut(move |db| {
+            db.execute(
+                "UPDATE servers SET archived = ?1 WHERE id = ?2",
+                rusqlite::params![archived, id],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Returns the IDs, emails, passwords and 2FA codes of servers (including archived ones) created by older versions.
+    pub(crate) async fn get_legacy_server_credentials(
+        &self,
+    ) -> Result<Vec<(ServerId, String, String, Option<String>)>> {
+        self.call(|db| {
+            let mut stmt = db.prepare(
+                "SELECT id, filen_email, filen_password, filen_2fa_code FROM servers WHERE filen_auth_config = '' AND filen_password != ''",
+            )?;
+            let credentials = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(credentials)
+        })
+        .await
+    }
+
+    /// Stores the auth config of a server, forgetting its password and 2FA code.
+    pub(crate) async fn set_server_auth_config(
+        &self,
+        id: &ServerId,
+        auth_config: &str,
+    ) -> Result<()> {
+        let id = id.clone();
+        let auth_config = secrets::seal(auth_config);
+        self.call_mut(move |db| {
+            db.execute(
+                "UPDATE servers SET filen_auth_config = ?1, filen_password = '', filen_2fa_code = NULL WHERE id = ?2",
+                rusqlite::params![auth_config, id],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn set_server_collaborators(
+        &self,
+        id: &ServerId,
+        collaborators: &[String],
+    ) -> Result<()> {
+        let id = id.clone();
+        let collaborators = serde_json::to_string(collaborators)?;
+        self.call_mut(move |db| {
+            db.execute(
+                "UPDATE servers SET collaborators = ?1 WHERE id = ?2",
+                rusqlite::params![collaborators, id],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Returns the IDs of all servers that are paused (and not archived).
+    pub(crate) async fn get_paused_server_ids(&self) -> Result<Vec<ServerId>> {
+        self.call(|db| {
+            let mut stmt =
+                db.prepare("SELECT id FROM servers WHERE paused = 1 AND archived = 0")?;
+            let ids = stmt
+                .query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<ServerId>>>()?;
+            Ok(ids)
+        })
+        .await
+    }
+
+    /// Marks a server as paused (process stopped, spec kept) or resumes it.
+    pub(crate) async fn set_server_paused(&self, id: &ServerId, paused: bool) -> Result<()> {
+        let id = id.clone();
+        self.call_mut(move |db| {
+            db.execute(
+                "UPDATE servers SET paused = ?1 WHERE id = ?2",
+                rusqlite::params![paused, id],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Stores the last access times of servers. Servers without one yet count as accessed `now`.
+    pub(crate) async fn record_server_accesses(
+        &self,
+        accesses: &[(ServerId, DateTime<Utc>)],
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        let accesses = accesses.to_vec();
+        let updated = self
+            .call(move |db| {
+                let mut updated = 0;
+                for (id, accessed) in accesses {
+                    updated += db.execute(
+                        "UPDATE servers SET last_accessed = ?1 WHERE id = ?2",
+                        rusqlite::params![format_timestamp(&accessed), id],
+                    )?;
+                }
+                updated += db.execute(
+                    "UPDATE servers SET last_accessed = ?1 WHERE last_accessed IS NULL",
+                    rusqlite::params![format_timestamp(&now)],
+                )?;
+                Ok(updated)
             })
-        })?;
-        let mut servers = Vec::new();
-        for server in server_iter {
-            servers.push(server?);
+            .await?;
+        // most of the time, no server was accessed, which needs no backup
+        if updated > 0 {
+            self.mark_changed();
         }
-        Ok(servers)
+        Ok(())
     }
 
-    pub(crate) async fn create_server(&self, spec: &ServerSpec) -> Result<()> {
-        self.conn.lock().unwrap().execute(
-            "INSERT INTO servers (id, name, server_type, root, read_only, password, filen_email, filen_password, filen_2fa_code) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-            rusqlite::params![spec.id, spec.name, spec.server_type.to_string(), spec.root, spec.read_only, spec.password, spec.filen_email, spec.filen_password, spec.filen_2fa_code],
-        )?;
-        self.write_to_filen().await?;
+    /// Adds transferred bytes and running seconds to the servers' usage on `day`, along with their owners (the owner
+    /// at the start of the day is kept).
+    pub(crate) async fn record_usage(
+        &self,
+        day: NaiveDate,
+        usage: &[(ServerId, String, u64, u64)],
+    ) -> Result<()> {
+        let usage = usage.to_vec();
+        let day = day.format("%Y-%m-%d").to_string();
+        self.call_mut(move |db| {
+            for (id, filen_email, bytes_transferred, running_seconds) in usage {
+                db.execute(
+                    "INSERT INTO usage (server_id, day, bytes_transferred, running_seconds, filen_email) VALUES (?1, ?2, ?3, ?4, ?5)
+                    ON CONFLICT(server_id, day) DO UPDATE SET bytes_transferred = bytes_transferred + excluded.bytes_transferred, running_seconds = running_seconds + excluded.running_seconds",
+                    rusqlite::params![id, day, bytes_transferred as i64, running_seconds as i64, filen_email],
+                )?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Adds requests and transferred bytes to the servers' stats in the hour starting at `hour` (a Unix timestamp).
+    /// Like sessions, the stats don't trigger a backup, since they change every few minutes.
+    pub(crate) async fn record_server_stats(
+        &self,
+        hour: i64,
+        stats: &[(ServerId, u64, u64)],
+    ) -> Result<()> {
+        let stats = stats.to_vec();
+        self.call(move |db| {
+            for (id, requests, bytes) in stats {
+                db.execute(
+                    "INSERT INTO server_stats (server_id, hour, requests, bytes) VALUES (?1, ?2, ?3, ?4)
+                    ON CONFLICT(server_id, hour) DO UPDATE SET requests = requests + excluded.requests, bytes = bytes + excluded.bytes",
+                    rusqlite::params![id, hour, requests as i64, bytes as i64],
+                )?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Deletes the stats of the hours before `before` (a Unix timestamp).
+    pub(crate) async fn prune_server_stats(&self, before: i64) -> Result<()> {
+        self.call(move |db| {
+            db.execute(
+                "DELETE FROM server_stats WHERE hour < ?1",
+                rusqlite::params![before],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Returns the requests and transferred bytes of each server per hour, since `since` (a Unix timestamp).
+    pub(crate) async fn get_server_stats(
+        &self,
+        since: i64,
+    ) -> Result<Vec<(ServerId, i64, u64, u64)>> {
+        self.call(move |db| {
+            let mut stmt = db.prepare(
+                "SELECT server_id, hour, requests, bytes FROM server_stats WHERE hour >= ?1",
+            )?;
+            let stats = stmt
+                .query_map(rusqlite::params![since], |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get::<_, i64>(2)? as u64,
+                        row.get::<_, i64>(3)? as u64,
+                    ))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(stats)
+        })
+        .await
+    }
+
+    /// Returns the usage of all servers (including archived and purged ones) per day, optionally summed up per user.
+    pub(crate) async fn get_usage(&self, by_user: bool) -> Result<Vec<UsageRecord>> {
+        let query = if by_user {
+            "SELECT day, NULL, NULL, filen_email, SUM(bytes_transferred), SUM(running_seconds) FROM usage GROUP BY day, filen_email ORDER BY day, filen_email"
+        } else {
+            "SELECT u.day, u.server_id, s.name, u.filen_email, u.bytes_transferred, u.running_seconds FROM usage u LEFT JOIN servers s ON s.id = u.server_id ORDER BY u.day, u.filen_email, s.name"
+        };
+        self.call(move |db| {
+            let mut stmt = db.prepare(query)?;
+            let records = stmt
+                .query_map([], |row| {
+                    Ok(UsageRecord {
+                        day: row.get(0)?,
+                        server_id: row.get(1)?,
+                        server_name: row.get(2)?,
+                        filen_email: row.get(3)?,
+                        bytes_transferred: row.get::<_, i64>(4)? as u64,
+                        running_hours: row.get::<_, i64>(5)? as f64 / 3600.0,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<UsageRecord>>>()?;
+            Ok(records)
+        })
+        .await
+    }
+
+    /// Returns the bytes transferred by the servers of each user since `day` (inclusive).
+    pub(crate) async fn get_bytes_transferred_per_user_since(
+        &self,
+        day: NaiveDate,
+    ) -> Result<HashMap<String, u64>> {
+        let day = day.format("%Y-%m-%d").to_string();
+        self.call(move |db| {
+            let mut stmt = db.prepare(
+                "SELECT filen_email, SUM(bytes_transferred) FROM usage WHERE day >= ?1 AND filen_email IS NOT NULL GROUP BY filen_email",
+            )?;
+            let bytes = stmt
+                .query_map(rusqlite::params![day], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64)))?
+                .collect::<rusqlite::Result<HashMap<String, u64>>>()?;
+            Ok(bytes)
+        })
+        .await
+    }
+
+    /// Returns the monthly egress caps (in GB) per user.
+    pub(crate) async fn get_egress_caps(&self) -> Result<Vec<(String, u64)>> {
+        self.call(|db| {
+            let mut stmt =
+                db.prepare("SELECT filen_email, monthly_gb FROM egress_caps ORDER BY filen_email")?;
+            let caps = stmt
+                .query_map([], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+                })?
+                .collect::<rusqlite::Result<Vec<(String, u64)>>>()?;
+            Ok(caps)
+        })
+        .await
+    }
+
+    /// Sets a user's monthly egress cap (in GB), or removes it if `monthly_gb` is `None`.
+    pub(crate) async fn set_egress_cap(
+        &self,
+        filen_email: &str,
+        monthly_gb: Option<u64>,
+    ) -> Result<()> {
+        let filen_email = filen_email.to_string();
+        self.call_mut(move |db| match monthly_gb {
+            Some(monthly_gb) => db.execute(
+                "INSERT INTO egress_caps (filen_email, monthly_gb) VALUES (?1, ?2) ON CONFLICT(filen_email) DO UPDATE SET monthly_gb = excluded.monthly_gb",
+                rusqlite::params![filen_email, monthly_gb as i64],
+            ),
+            None => db.execute("DELETE FROM egress_caps WHERE filen_email = ?1", rusqlite::params![filen_email]),
+        })
+        .await?;
         Ok(())
     }
 
-    pub(crate) async fn delete_server(&self, id: &ServerId) -> Result<()> {
-        self.conn
-            .lock()
-            .unwrap()
-            .execute("DELETE FROM servers WHERE id = ?1", rusqlite::params![id])?;
-        self.write_to_filen().await?;
+    /// Returns the IDs of running (not paused or archived) servers last accessed before `cutoff`.
+    pub(crate) async fn get_servers_inactive_since(
+        &self,
+        cutoff: DateTime<Utc>,
+    ) -> Result<Vec<ServerId>> {
+        self.call(move |db| {
+            let mut stmt = db.prepare(
+                "SELECT id FROM servers WHERE paused = 0 AND archived = 0 AND last_accessed < ?1",
+            )?;
+            let ids = stmt
+                .query_map(rusqlite::params![format_timestamp(&cutoff)], |row| {
+                    row.get(0)
+                })?
+                .collect::<rusqlite::Result<Vec<ServerId>>>()?;
+            Ok(ids)
+        })
+        .await
+    }
+
+    /// Permanently deletes an archived server.
+    pub(crate) async fn purge_server(&self, id: &ServerId) -> Result<()> {
+        let deleted = {
+            let id = id.clone();
+            self.call_mut(move |db| {
+                db.execute(
+                    "DELETE FROM servers WHERE id = ?1 AND archived = 1",
+                    rusqlite::params![id],
+                )
+            })
+            .await?
+        };
+        if deleted == 0 {
+            return Err(anyhow::anyhow!("No archived server found with id: {}", id))?;
+        }
         Ok(())
     }
+
+    pub(crate) async fn get_settings(&self) -> Result<Settings> {
+        self.call(|db| {
+            let defaults = Settings::default();
+            Ok(Settings {
+                registration_mode: get_setting(db, SETTING_REGISTRATION_MODE)?
+                    .map(|v| RegistrationMode::from(v.as_str()))
+                    .unwrap_or(defaults.registration_mode),
+                cache_quota_gb: get_setting(db, SETTING_CACHE_QUOTA_GB)?
+                    .and_then(|v| v.parse().ok()),
+                auto_pause_days: get_setting(db, SETTING_AUTO_PAUSE_DAYS)?
+                    .and_then(|v| v.parse().ok()),
+                max_running_servers: get_setting(db, SETTING_MAX_RUNNING_SERVERS)?
+                    .and_then(|v| v.parse().ok()),
+                // comma-separated
+                disabled_maintenance_tasks: get_setting(db, SETTING_DISABLED_MAINTENANCE_TASKS)?
+                    .map(|v| v.split(',').filter_map(MaintenanceTask::parse).collect())
+                    .unwrap_or(defaults.disabled_maintenance_tasks),
+                server_defaults: ServerDefaults {
+                    read_only: get_setting(db, SETTING_DEFAULT_READ_ONLY)?
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(defaults.server_defaults.read_only),
+                    vfs_cache_mode: get_setting(db, SETTING_DEFAULT_VFS_CACHE_MODE)?
+                        .map(|v| VfsCacheMode::from(v.as_str())),
+                    bwlimit: get_setting(db, SETTING_DEFAULT_BWLIMIT)?,
+                    log_level: get_setting(db, SETTING_DEFAULT_LOG_LEVEL)?
+                        .map(|v| LogLevel::from(v.as_str())),
+                },
+                verify_email_on_first_login: get_setting(db, SETTING_VERIFY_EMAIL_ON_FIRST_LOGIN)?
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(defaults.verify_email_on_first_login),
+            })
+        })
+        .await
+    }
+
+    pub(crate) async fn update_settings(&self, settings: &Settings) -> Result<()> {
+        let settings = settings.clone();
+        self.call_mut(move |db| {
+            set_setting(
+                db,
+                SETTING_REGISTRATION_MODE,
+                Some(&settings.registration_mode.to_string()),
+            )?;
+            set_setting(
+                db,
+                SETTING_CACHE_QUOTA_GB,
+                settings.cache_quota_gb.map(|v| v.to_string()).as_deref(),
+            )?;
+            set_setting(
+                db,
+                SETTING_AUTO_PAUSE_DAYS,
+                settings.auto_pause_days.map(|v| v.to_string()).as_deref(),
+            )?;
+            set_setting(
+                db,
+                SETTING_MAX_RUNNING_SERVERS,
+                settings
+                    .max_running_servers
+                    .map(|v| v.to_string())
+                    .as_deref(),
+            )?;
+            set_setting(
+                db,
+                SETTING_DISABLED_MAINTENANCE_TASKS,
+                Some(
+                    &settings
+                        .disabled_maintenance_tasks
+                        .iter()
+                        .map(|task| task.as_str())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                ),
+            )?;
+            let server_defaults = &settings.server_defaults;
+            set_setting(
+                db,
+                SETTING_DEFAULT_READ_ONLY,
+                Some(&server_defaults.read_only.to_string()),
+            )?;
+            set_setting(
+                db,
+                SETTING_DEFAULT_VFS_CACHE_MODE,
+                server_defaults
+                    .vfs_cache_mode
+                    .map(|v| v.to_string())
+                    .as_deref(),
+            )?;
+            set_setting(
+                db,
+                SETTING_DEFAULT_BWLIMIT,
+                server_defaults.bwlimit.as_deref(),
+            )?;
+            set_setting(
+                db,
+                SETTING_DEFAULT_LOG_LEVEL,
+                server_defaults.log_level.map(|v| v.to_string()).as_deref(),
+            )?;
+            set_setting(
+                db,
+                SETTING_VERIFY_EMAIL_ON_FIRST_LOGIN,
+                Some(&settings.verify_email_on_first_login.to_string()),
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn get_server_templates(&self) -> Result<Vec<ServerTemplate>> {
+        self.call(|db| {
+            let mut stmt = db.prepare(
+                "SELECT id, name, server_type, read_only, options FROM server_templates ORDER BY name",
+            )?;
+            let templates = stmt
+                .query_map([], |row| {
+                    Ok(ServerTemplate {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        server_type: row.get::<_, String>(2)?.as_str().into(),
+                        read_only: row.get(3)?,
+                        options: serde_json::from_str(&row.get::<_, String>(4)?).unwrap_or_default(),
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<ServerTemplate>>>()?;
+            Ok(templates)
+        })
+        .await
+    }
+
+    pub(crate) async fn create_server_template(&self, template: &ServerTemplate) -> Result<()> {
+        let template = template.clone();
+        let options = serde_json::to_string(&template.options)?;
+        self.call_mut(move |db| {
+            db.execute(
+                "INSERT INTO server_templates (id, name, server_type, read_only, options) VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![template.id, template.name, template.server_type.to_string(), template.read_only, options],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn delete_server_template(&self, id: &str) -> Result<()> {
+        let id = id.to_string();
+        self.call_mut(move |db| {
+            db.execute(
+                "DELETE FROM server_templates WHERE id = ?1",
+                rusqlite::params![id],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn get_notification_channels(&self) -> Result<Vec<NotificationChannel>> {
+        self.call(|db| {
+            let mut stmt = db.prepare("SELECT id, kind, webhook_url, owner FROM notification_channels ORDER BY owner, kind")?;
+            let channels = stmt
+                .query_map([], |row| {
+                    Ok(NotificationChannel {
+                        id: row.get(0)?,
+                        kind: row.get::<_, String>(1)?.as_str().into(),
+                        webhook_url: open_secret(row, 2)?,
+                        owner: row.get(3)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<NotificationChannel>>>()?;
+            Ok(channels)
+        })
+        .await
+    }
+
+    pub(crate) async fn create_notification_channel(
+        &self,
+        channel: &NotificationChannel,
+    ) -> Result<()> {
+        let channel = channel.clone();
+        let webhook_url = secrets::seal(&channel.webhook_url);
+        self.call_mut(move |db| {
+            db.execute(
+                "INSERT INTO notification_channels (id, kind, webhook_url, owner) VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![channel.id, channel.kind.to_string(), webhook_url, channel.owner],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn delete_notification_channel(&self, id: &str) -> Result<()> {
+        let id = id.to_string();
+        self.call_mut(move |db| {
+            db.execute(
+                "DELETE FROM notification_channels WHERE id = ?1",
+                rusqlite::params![id],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn get_status_pages(&self) -> Result<Vec<StatusPage>> {
+        self.call(|db| {
+            let mut stmt =
+                db.prepare("SELECT sealed_token, name, owner, server_ids FROM status_pages ORDER BY owner, name")?;
+            let pages = stmt.query_map([], status_page_from_row)?.collect::<rusqlite::Result<Vec<StatusPage>>>()?;
+            Ok(pages)
+        })
+        .await
+    }
+
+    /// Looks a status page up by its token, which is only stored hashed (and encrypted).
+    pub(crate) async fn get_status_page(&self, token: &str) -> Result<Option<StatusPage>> {
+        let hash = secrets::hash(token);
+        self.call(move |db| {
+            let mut stmt = db.prepare(
+                "SELECT sealed_token, name, owner, server_ids FROM status_pages WHERE token = ?1",
+            )?;
+            let mut rows = stmt.query(rusqlite::params![hash])?;
+            rows.next()?.map(status_page_from_row).transpose()
+        })
+        .await
+    }
+
+    pub(crate) async fn create_status_page(&self, page: &StatusPage) -> Result<()> {
+        let page = page.clone();
+        let server_ids = serde_json::to_string(&page.server_ids)?;
+        let (hash, sealed_token) = (secrets::hash(&page.token), secrets::seal(&page.token));
+        self.call_mut(move |db| {
+            db.execute(
+                "INSERT INTO status_pages (token, sealed_token, name, owner, server_ids) VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![hash, sealed_token, page.name, page.owner, server_ids],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn delete_status_page(&self, token: &str) -> Result<()> {
+        let hash = secrets::hash(token);
+        self.call_mut(move |db| {
+            db.execute(
+                "DELETE FROM status_pages WHERE token = ?1",
+                rusqlite::params![hash],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn get_teams(&self) -> Result<Vec<Team>> {
+        self.call(|db| {
+            let mut stmt = db
+                .prepare("SELECT id, name, owner, members, max_servers FROM teams ORDER BY name")?;
+            let teams = stmt
+                .query_map([], |row| {
+                    Ok(Team {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        owner: row.get(2)?,
+                        members: serde_json::from_str(&row.get::<_, String>(3)?)
+                            .unwrap_or_default(),
+                        max_servers: row.get::<_, Option<i64>>(4)?.map(|v| v as u64),
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<Team>>>()?;
+            Ok(teams)
+        })
+        .await
+    }
+
+    pub(crate) async fn create_team(&self, team: &Team) -> Result<()> {
+        let team = team.clone();
+        let members = serde_json::to_string(&team.members)?;
+        self.call_mut(move |db| {
+            db.execute(
+                "INSERT INTO teams (id, name, owner, members, max_servers) VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![team.id, team.name, team.owner, members, team.max_servers.map(|v| v as i64)],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn update_team(&self, team: &Team) -> Result<()> {
+        let team = team.clone();
+        let members = serde_json::to_string(&team.members)?;
+        self.call_mut(move |db| {
+            db.execute(
+                "UPDATE teams SET name = ?1, owner = ?2, members = ?3, max_servers = ?4 WHERE id = ?5",
+                rusqlite::params![team.name, team.owner, members, team.max_servers.map(|v| v as i64), team.id],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Deletes a team, unless it still owns servers (including archived ones).
+    pub(crate) async fn delete_team(&self, id: &str) -> Result<()> {
+        let deleted = {
+            let id = id.to_string();
+            self.call_mut(move |db| {
+                db.execute(
+                    "DELETE FROM teams WHERE id = ?1 AND NOT EXISTS (SELECT 1 FROM servers WHERE team_id = ?1)",
+                    rusqlite::params![id],
+                )
+            })
+            .await?
+        };
+        if deleted == 0 {
+            return Err(anyhow::anyhow!(
+                "Team {} not found or still owns servers",
+                id
+            ))?;
+        }
+        Ok(())
+    }
+
+    // sessions are short-lived, so changes to them don't trigger a backup (thus `call` instead of `call_mut`)
+
+    pub(crate) async fn create_session(
+        &self,
+        session: &Session,
+        user_agent: Option<&str>,
+        ip: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        let session = session.clone();
+        let filen_auth_config = secrets::seal(&session.filen_auth_config);
+        let (user_agent, ip) = (user_agent.map(str::to_string), ip.map(str::to_string));
+        self.call(move |db| {
+            db.execute(
+                "INSERT INTO sessions (token, filen_email, filen_auth_config, is_admin, created, user_agent, ip) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                rusqlite::params![session.token.hash(), session.filen_email, filen_auth_config, session.is_admin, format_timestamp(&now), user_agent, ip],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Gets the session with the given token, unless it was created before `cutoff`.
+    pub(crate) async fn get_session(
+        &self,
+        token: &SessionToken,
+        cutoff: DateTime<Utc>,
+    ) -> Result<Option<Session>> {
+        let token = token.clone();
+        self.call(move |db| {
+            let mut stmt = db.prepare("SELECT filen_email, filen_auth_config, is_admin FROM sessions WHERE token = ?1 AND created >= ?2")?;
+            let mut rows = stmt.query(rusqlite::params![token.hash(), format_timestamp(&cutoff)])?;
+            match rows.next()? {
+                Some(row) => Ok(Some(Session {
+                    token,
+                    filen_email: row.get(0)?,
+                    filen_auth_config: open_secret(row, 1)?,
+                    is_admin: row.get(2)?,
+                })),
+                None => Ok(None),
+            }
+        })
+        .await
+    }
+
+    /// Returns the token hash, user agent, address and creation time of the user's sessions created since `cutoff`,
+    /// newest first.
+    pub(crate) async fn get_user_sessions(
+        &self,
+        filen_email: &str,
+        cutoff: DateTime<Utc>,
+    ) -> Result<Vec<(String, Option<String>, Option<String>, DateTime<Utc>)>> {
+        let filen_email = filen_email.to_string();
+        self.call(move |db| {
+            let mut stmt = db.prepare(
+                "SELECT token, user_agent, ip, created FROM sessions WHERE filen_email = ?1 AND created >= ?2 ORDER BY created DESC",
+            )?;
+            let sessions = stmt
+                .query_map(rusqlite::params![filen_email, format_timestamp(&cutoff)], |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?)
+                            .map(|t| t.with_timezone(&Utc))
+                            .unwrap_or_default(),
+                    ))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(sessions)
+        })
+        .await
+    }
+
+    /// Deletes the session stored under the hash, see `SessionToken::hash`.
+    pub(crate) async fn delete_session(&self, hash: &str) -> Result<()> {
+        let hash = hash.to_string();
+        self.call(move |db| {
+            db.execute(
+                "DELETE FROM sessions WHERE token = ?1",
+                rusqlite::params![hash],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Deletes all sessions of the user, returning how many there were.
+    pub(crate) async fn delete_user_sessions(&self, filen_email: &str) -> Result<usize> {
+        let filen_email = filen_email.to_string();
+        self.call(move |db| {
+            db.execute(
+                "DELETE FROM sessions WHERE filen_email = ?1",
+                rusqlite::params![filen_email],
+            )
+        })
+        .await
+    }
+
+    /// Deletes the sessions created before `cutoff`.
+    pub(crate) async fn delete_sessions_created_before(&self, cutoff: DateTime<Utc>) -> Result<()> {
+        self.call(move |db| {
+            db.execute(
+                "DELETE FROM sessions WHERE created < ?1",
+                rusqlite::params![format_timestamp(&cutoff)],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Acquires or renews the leader lease for the instance, unless another instance holds an unexpired one.
+    /// Returns the URL of that instance in that case. Like sessions, the lease doesn't trigger a backup.
+    pub(crate) async fn try_acquire_leadership(
+        &self,
+        instance_id: &str,
+        instance_url: &str,
+        now: DateTime<Utc>,
+        lease_duration: chrono::Duration,
+    ) -> Result<Option<String>> {
+        let instance_id = instance_id.to_string();
+        let instance_url = instance_url.to_string();
+        self.call(move |db| {
+            db.execute(
+                "INSERT INTO leader_lease (id, instance_id, instance_url, expires) VALUES (1, ?1, ?2, ?3)
+                ON CONFLICT(id) DO UPDATE SET instance_id = excluded.instance_id, instance_url = excluded.instance_url, expires = excluded.expires
+                WHERE leader_lease.instance_id = ?1 OR leader_lease.expires < ?4",
+                rusqlite::params![instance_id, instance_url, format_timestamp(&(now + lease_duration)), format_timestamp(&now)],
+            )?;
+            let (leader_id, leader_url) = db.query_row(
+                "SELECT instance_id, instance_url FROM leader_lease WHERE id = 1",
+                [],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )?;
+            Ok((leader_id != instance_id).then_some(leader_url))
+        })
+        .await
+    }
+
+    /// Adds an entry to the audit log, deleting the entries older than `cutoff`.
+    pub(crate) async fn record_audit_entry(
+        &self,
+        entry: &AuditEntry,
+        cutoff: DateTime<Utc>,
+    ) -> Result<()> {
+        let entry = entry.clone();
+        self.call_mut(move |db| {
+            db.execute(
+                "INSERT INTO audit_log (timestamp, actor, action, details) VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![
+                    format_timestamp(&entry.timestamp),
+                    entry.actor,
+                    entry.action.as_str(),
+                    entry.details
+                ],
+            )?;
+            db.execute(
+                "DELETE FROM audit_log WHERE timestamp < ?1",
+                rusqlite::params![format_timestamp(&cutoff)],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Returns the audit log entries matching `filter`, newest first, limited to the newest `limit` of them.
+    pub(crate) async fn get_audit_log(
+        &self,
+        filter: &AuditFilter,
+        limit: Option<usize>,
+    ) -> Result<Vec<AuditEntry>> {
+        let actor = filter
+            .actor
+            .as_deref()
+            .map(str::trim)
+            .filter(|a| !a.is_empty())
+            .map(|a| a.to_lowercase());
+        let action = filter.action.map(|a| a.as_str());
+        // timestamps are stored like "2025-01-31T12:00:00Z", so they compare with the days as strings
+        let from = filter.from.map(|day| day.format("%Y-%m-%d").to_string());
+        let until = filter
+            .to
+            .and_then(|day| day.succ_opt())
+            .map(|day| day.format("%Y-%m-%d").to_string());
+        let limit = limit.map_or(-1, |limit| limit as i64);
+        self.call(move |db| {
+            let mut stmt = db.prepare(
+                "SELECT timestamp, actor, action, details FROM audit_log
+                WHERE (?1 IS NULL OR lower(actor) = ?1) AND (?2 IS NULL OR action = ?2) AND (?3 IS NULL OR timestamp >= ?3) AND (?4 IS NULL OR timestamp < ?4)
+                ORDER BY id DESC LIMIT ?5",
+            )?;
+            let entries = stmt
+                .query_map(rusqlite::params![actor, action, from, until, limit], |row| {
+                    Ok(AuditEntry {
+                        timestamp: DateTime::parse_from_rfc3339(&row.get::<_, String>(0)?)
+                            .map(|t| t.with_timezone(&Utc))
+                            .unwrap_or_default(),
+                        actor: row.get(1)?,
+                        action: row.get::<_, String>(2)?.as_str().into(),
+                        details: row.get(3)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<AuditEntry>>>()?;
+            Ok(entries)
+        })
+        .await
+    }
+
+    pub(crate) async fn get_announcement(&self) -> Result<Option<String>> {
+        self.call(|db| get_setting(db, SETTING_ANNOUNCEMENT)).await
+    }
+
+    /// Sets the announcement shown to all users, or removes it if `announcement` is `None`.
+    pub(crate) async fn set_announcement(&self, announcement: Option<&str>) -> Result<()> {
+        let announcement = announcement.map(|a| a.to_string());
+        self.call_mut(move |db| set_setting(db, SETTING_ANNOUNCEMENT, announcement.as_deref()))
+            .await
+    }
+}
+
+fn get_setting(db: &Connection, key: &str) -> rusqlite::Result<Option<String>> {
+    let mut stmt = db.prepare("SELECT value FROM settings WHERE key = ?1")?;
+    let mut rows = stmt.query(rusqlite::params![key])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
+/// Sets a setting, or removes it if `value` is `None`.
+fn set_setting(db: &Connection, key: &str, value: Option<&str>) -> rusqlite::Result<()> {
+    match value {
+        Some(value) => db.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            rusqlite::params![key, value],
+        )?,
+        None => db.execute("DELETE FROM settings WHERE key = ?1", rusqlite::params![key])?,
+    };
+    Ok(())
+}
+
+fn status_page_from_row(row: &rusqlite::Row) -> rusqlite::Result<StatusPage> {
+    Ok(StatusPage {
+        token: open_secret(row, 0)?,
+        name: row.get(1)?,
+        owner: row.get(2)?,
+        server_ids: serde_json::from_str(&row.get::<_, String>(3)?).unwrap_or_default(),
+    })
+}
+
+/// Reads a column encrypted with `secrets::seal`.
+fn open_secret(row: &rusqlite::Row, index: usize) -> rusqlite::Result<String> {
+    secrets::open(&row.get::<_, String>(index)?).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, e.into())
+    })
+}
+
+/// Formats timestamps uniformly, so that they can be compared as strings in SQL.
+fn format_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
 }
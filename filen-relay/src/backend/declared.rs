@@ -0,0 +1,290 @@
+//! Declarative server configuration (`--servers-file`): a YAML list of server specs in the format of
+//! `/api/servers/export` (so that an export can serve as a starting point), e.g.
+//!
+//! ```yaml
+//! - name: docs
+//!   server_type: Webdav
+//!   root: /Documents
+//!   read_only: true
+//! ```
+//!
+//! The file is read from the local filesystem, or from the admin's drive if prefixed with "filen:"
+//! (e.g. "filen:/.filen-relay/servers.yaml"). Declared servers are owned by the admin and run with their Filen account.
+//!
+//! On startup and whenever the file changes, the servers are reconciled with it: declared servers are created or
+//! updated (matched by name), and servers created from the file that are no longer declared are removed (archived).
+//! Servers created in the web interface are left alone.
+//!
+//! Servers can also be declared via `--server` (e.g. "name=docs,type=webdav,root=/Documents,read_only=true"),
+//! in addition to those in the file, so that a relay can be run without ever using the web interface.
+
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use dioxus::logger::tracing;
+use strum::IntoEnumIterator as _;
+
+use crate::{
+    backend::{
+        self,
+        auth::ADMIN_EMAIL,
+        db::DB,
+        drive, mock,
+        server_manager::{ServerSpecUpdate, SERVER_MANAGER},
+    },
+    common::{validate_server_spec, ServerOptions, ServerSpec, ServerSpecExport, ServerType},
+};
+
+/// How often the servers file is checked for changes.
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+const FILEN_PREFIX: &str = "filen:";
+
+struct Declarations {
+    servers_file: Option<String>,
+    /// The servers declared via `--server`.
+    servers: Vec<ServerSpecExport>,
+}
+
+static DECLARATIONS: OnceLock<Declarations> = OnceLock::new();
+
+pub(crate) fn enable(servers_file: Option<&str>, servers: &[ServerSpecExport]) {
+    if servers_file.is_none() && servers.is_empty() {
+        return;
+    }
+    DECLARATIONS
+        .set(Declarations {
+            servers_file: servers_file.map(|f| f.to_string()),
+            servers: servers.to_vec(),
+        })
+        .ok();
+}
+
+/// Reconciles the servers with the declared ones whenever the servers file has changed. Runs forever (on the leader).
+pub(crate) async fn run_reconcile() {
+    let Some(declarations) = DECLARATIONS.get() else {
+        return;
+    };
+    let Some(servers_file) = &declarations.servers_file else {
+        tracing::info!("Reconciling servers with --server");
+        if let Err(e) = reconcile(declarations.servers.clone()).await {
+            tracing::error!("Failed to reconcile servers: {:#}", e);
+        }
+        return;
+    };
+    let mut last_content = None;
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        let content = match read(servers_file).await {
+            Ok(content) => content,
+            Err(e) => {
+                // reconciling with nothing would remove all declared servers
+                tracing::error!("Failed to read servers file: {:#}", e);
+                continue;
+            }
+        };
+        if last_content.as_ref() == Some(&content) {
+            continue;
+        }
+        let mut declared = match serde_yaml::from_str::<Vec<ServerSpecExport>>(&content) {
+            Ok(declared) => declared,
+            Err(e) => {
+                tracing::error!("Failed to parse servers file: {}", e);
+                last_content = Some(content);
+                continue;
+            }
+        };
+        declared.extend(declarations.servers.iter().cloned());
+        tracing::info!("Reconciling servers with {}", servers_file);
+        match reconcile(declared).await {
+            Ok(()) => last_content = Some(content),
+            Err(e) => tracing::error!("Failed to reconcile servers: {:#}", e),
+        }
+    }
+}
+
+/// Parses a `--server` value like "name=docs,type=webdav,root=/Documents,read_only=true,password=secret".
+pub(crate) fn parse_server_flag(value: &str) -> Result<ServerSpecExport, String> {
+    let mut server = ServerSpecExport {
+        name: String::new(),
+        server_type: ServerType::Http,
+        root: "/".to_string(),
+        read_only: false,
+        password: None,
+        options: ServerOptions::default(),
+    };
+    for pair in value.split(',').filter(|pair| !pair.trim().is_empty()) {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| format!("Expected key=value, got \"{}\"", pair))?;
+        let value = value.trim();
+        match key.trim() {
+            "name" => server.name = value.to_string(),
+            "type" => {
+                server.server_type = ServerType::iter()
+                    .find(|t| t.to_string().eq_ignore_ascii_case(value))
+                    .ok_or_else(|| format!("Unknown server type \"{}\"", value))?
+            }
+            "root" => server.root = value.to_string(),
+            "read_only" => {
+                server.read_only = value.parse().map_err(|_| {
+                    format!("Expected true or false for read_only, got \"{}\"", value)
+                })?
+            }
+            "password" => server.password = Some(value.to_string()),
+            key => return Err(format!("Unknown key \"{}\"", key)),
+        }
+    }
+    if server.name.is_empty() {
+        return Err("Missing name".to_string());
+    }
+    Ok(server)
+}
+
+async fn read(servers_file: &str) -> Result<String> {
+    let Some(path) = servers_file.strip_prefix(FILEN_PREFIX) else {
+        return tokio::fs::read_to_string(servers_file)
+            .await
+            .with_context(|| format!("Failed to read {}", servers_file));
+    };
+    let client = DB
+        .admin_client()
+        .context("Reading the servers file from Filen requires the database to be stored in the admin's drive")?;
+    drive::read_file(&client, path)
+        .await?
+        .with_context(|| format!("No file at {} in the admin's drive", path))
+}
+
+async fn reconcile(declared: Vec<ServerSpecExport>) -> Result<()> {
+    let admin_email = ADMIN_EMAIL.get().context("Admin email not set")?.clone();
+    let admin_auth_config = admin_auth_config()?;
+    let declared_ids = DB
+        .get_declared_server_ids()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get declared servers: {}", e))?;
+    let (managed, others): (Vec<_>, Vec<_>) = DB
+        .get_servers()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get servers: {}", e))?
+        .into_iter()
+        .partition(|s| declared_ids.contains(&s.id));
+    let mut existing_names = others
+        .into_iter()
+        .filter(|s| s.filen_email == admin_email)
+        .map(|s| s.name)
+        .collect::<Vec<_>>();
+    let mut kept = Vec::new();
+    for entry in declared {
+        let password = entry.password.filter(|p| !p.is_empty());
+        let errors = validate_server_spec(
+            &entry.name,
+            &entry.server_type,
+            &entry.root,
+            entry.read_only,
+            password.as_deref(),
+            &entry.options,
+            &existing_names,
+        );
+        if !errors.is_empty() {
+            let errors = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+            // an invalid edit leaves the running server alone instead of removing it
+            match managed.iter().find(|s| s.name == entry.name.trim()) {
+                Some(current) => {
+                    tracing::warn!(
+                        "Keeping the current declared server \"{}\", the declaration is invalid: {}",
+                        current.name,
+                        errors.join(", ")
+                    );
+                    existing_names.push(current.name.clone());
+                    kept.push(current.id.clone());
+                }
+                None => tracing::warn!(
+                    "Skipping declared server \"{}\": {}",
+                    entry.name,
+                    errors.join(", ")
+                ),
+            }
+            continue;
+        }
+        let name = entry.name.trim().to_string();
+        existing_names.push(name.clone());
+        let current = managed.iter().find(|s| s.name == name);
+        let spec = ServerSpec {
+            id: match current {
+                Some(current) => current.id.clone(),
+                None => backend::generate_server_id().await?,
+            },
+            name,
+            server_type: entry.server_type,
+            root: entry.root,
+            read_only: entry.read_only,
+            password,
+            filen_email: admin_email.clone(),
+            filen_auth_config: admin_auth_config.clone(),
+            options: entry.options,
+            collaborators: current.map(|s| s.collaborators.clone()).unwrap_or_default(),
+            team_id: current.and_then(|s| s.team_id.clone()),
+        };
+        kept.push(spec.id.clone());
+        match current {
+            None => {
+                tracing::info!("Creating declared server \"{}\"", spec.name);
+                DB.add_declared_server(&spec.id)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Failed to store declared server: {}", e))?;
+                // a server failing to start doesn't keep the others from being reconciled
+                if let Err(e) = SERVER_MANAGER
+                    .update_server_spec(ServerSpecUpdate::Add(spec))
+                    .await
+                {
+                    tracing::error!("Failed to create declared server: {:#}", e);
+                }
+            }
+            Some(current) if differs(current, &spec) => {
+                tracing::info!("Updating declared server \"{}\"", spec.name);
+                if let Err(e) = SERVER_MANAGER
+                    .update_server_spec(ServerSpecUpdate::Update(spec))
+                    .await
+                {
+                    tracing::error!("Failed to update declared server: {:#}", e);
+                }
+            }
+            Some(_) => {}
+        }
+    }
+    for server in managed.into_iter().filter(|s| !kept.contains(&s.id)) {
+        tracing::info!(
+            "Removing server \"{}\", which is no longer declared",
+            server.name
+        );
+        if let Err(e) = SERVER_MANAGER
+            .update_server_spec(ServerSpecUpdate::Remove(server.id))
+            .await
+        {
+            tracing::error!("Failed to remove undeclared server: {:#}", e);
+        }
+    }
+    Ok(())
+}
+
+/// The admin's serialized auth config, which declared servers run with.
+fn admin_auth_config() -> Result<String> {
+    if mock::is_enabled() {
+        return Ok(String::new());
+    }
+    let client = DB
+        .admin_client()
+        .context("Declared servers run with the admin's Filen account, which requires the database to be stored in the admin's drive")?;
+    filen_cli::serialize_auth_config(&client)
+        .map_err(|e| anyhow::anyhow!("Failed to serialize Filen auth config: {}", e))
+}
+
+/// Whether the declared spec differs from the current one (apart from the owner's credentials).
+fn differs(current: &ServerSpec, declared: &ServerSpec) -> bool {
+    current.server_type != declared.server_type
+        || current.root != declared.root
+        || current.read_only != declared.read_only
+        || current.password != declared.password
+        || current.options != declared.options
+}
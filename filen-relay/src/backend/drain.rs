@@ -0,0 +1,76 @@
+//! Drain mode for zero-downtime redeploys: the relay stops accepting logins and new servers, keeps proxying
+//! existing traffic and backs up the database, after which it can be stopped safely.
+//! Started by the admin, or on SIGTERM (then exiting once done).
+
+use std::sync::Mutex;
+
+use dioxus::logger::tracing;
+
+use crate::{
+    backend::db::DB,
+    common::{ApiError, ApiErrorCode, DrainStatus},
+};
+
+static STATUS: Mutex<DrainStatus> = Mutex::new(DrainStatus {
+    draining: false,
+    safe_to_stop: false,
+    error: None,
+});
+
+pub(crate) fn status() -> DrainStatus {
+    STATUS.lock().unwrap().clone()
+}
+
+/// Fails while draining, for actions that would create new state (logins, servers).
+pub(crate) fn check_not_draining() -> Result<(), ApiError> {
+    if STATUS.lock().unwrap().draining {
+        return Err(ApiError::new(
+            ApiErrorCode::Unavailable,
+            "The relay is about to be stopped. Try again in a moment.",
+        ));
+    }
+    Ok(())
+}
+
+/// Starts draining (if not yet started) and backs up the database. Can be called again to retry the backup.
+pub(crate) async fn start() -> DrainStatus {
+    {
+        let mut status = STATUS.lock().unwrap();
+        if !status.draining {
+            status.draining = true;
+            tracing::warn!("Draining: no longer accepting logins and new servers");
+        }
+    }
+    let result = DB.flush().await;
+    let mut status = STATUS.lock().unwrap();
+    match result {
+        Ok(()) => {
+            tracing::info!("Database backed up, the relay can be stopped safely");
+            status.safe_to_stop = true;
+            status.error = None;
+        }
+        Err(e) => {
+            tracing::error!("Failed to back up database while draining: {:#}", e);
+            status.error = Some(format!("{:#}", e));
+        }
+    }
+    status.clone()
+}
+
+/// Drains and exits when the process receives SIGTERM (e.g. from `docker stop`).
+#[cfg(unix)]
+pub(crate) async fn drain_on_sigterm() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminations = match signal(SignalKind::terminate()) {
+        Ok(terminations) => terminations,
+        Err(e) => {
+            tracing::error!("Failed to listen for SIGTERM: {}", e);
+            return;
+        }
+    };
+    terminations.recv().await;
+    tracing::info!("Received SIGTERM, draining before exiting");
+    let status = start().await;
+    std::process::exit(if status.safe_to_stop { 0 } else { 1 });
+}
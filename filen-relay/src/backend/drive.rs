@@ -0,0 +1,69 @@
+//! Browsing of users' Filen drives, e.g. to pick and check server root paths.
+
+use anyhow::{Context, Result};
+use filen_sdk_rs::{
+    auth::Client,
+    fs::{dir::DirectoryType, file::enums::RemoteFileType, FSObject, HasName},
+};
+
+/// Lists the names of the directories directly inside the directory at `path`.
+pub(crate) async fn list_directories(client: &Client, path: &str) -> Result<Vec<String>> {
+    let dir = match client
+        .find_item_at_path(path)
+        .await
+        .context("Failed to look up path in Filen drive")?
+    {
+        Some(FSObject::Root(root)) => DirectoryType::Root(root),
+        Some(FSObject::Dir(dir)) => DirectoryType::Dir(dir),
+        _ => anyhow::bail!("Not a directory: {}", path),
+    };
+    let (dirs, _files) = client
+        .list_dir(&dir)
+        .await
+        .context("Failed to list directory in Filen drive")?;
+    let mut names = dirs
+        .iter()
+        .filter_map(|dir| dir.name().map(|name| name.to_string()))
+        .collect::<Vec<_>>();
+    names.sort_by_key(|name| name.to_lowercase());
+    Ok(names)
+}
+
+/// Whether there is a directory at `path`.
+pub(crate) async fn directory_exists(client: &Client, path: &str) -> Result<bool> {
+    Ok(matches!(
+        client
+            .find_item_at_path(path)
+            .await
+            .context("Failed to look up path in Filen drive")?,
+        Some(FSObject::Root(_)) | Some(FSObject::Dir(_))
+    ))
+}
+
+/// Creates the directory at `path` (including missing parents), if it doesn't exist yet.
+pub(crate) async fn create_directory(client: &Client, path: &str) -> Result<()> {
+    client
+        .find_or_create_dir(path.trim_start_matches('/'))
+        .await
+        .context("Failed to create directory in Filen drive")?;
+    Ok(())
+}
+
+/// Reads the (text) file at `path`, if there is one.
+pub(crate) async fn read_file(client: &Client, path: &str) -> Result<Option<String>> {
+    let Some(FSObject::File(file)) = client
+        .find_item_at_path(path)
+        .await
+        .context("Failed to look up path in Filen drive")?
+    else {
+        return Ok(None);
+    };
+    let temp_file = std::env::temp_dir().join(format!("filen-relay-{}", uuid::Uuid::new_v4()));
+    client
+        .download_file_to_path(&RemoteFileType::File(file), temp_file.clone(), None)
+        .await
+        .context("Failed to download file from Filen drive")?;
+    let content = tokio::fs::read_to_string(&temp_file).await;
+    let _ = tokio::fs::remove_file(&temp_file).await;
+    Ok(Some(content?))
+}
@@ -0,0 +1,115 @@
+//! Drop box mode of WebDAV servers (see `ServerOptions::drop_box`), which lets external collaborators submit files
+//! into a folder without being able to read it. rclone serves the folder without HTML listings, and the proxy only
+//! forwards uploads (and creating folders for them): opening the server's URL in a browser shows an upload page
+//! instead, and uploads never replace existing files, whose contents would otherwise be lost to the owner.
+
+use dioxus::{
+    fullstack::extract::Request,
+    server::axum::{
+        self,
+        body::Body,
+        http::{header, HeaderMap, Method, StatusCode},
+        response::{Html, IntoResponse as _},
+    },
+};
+
+use crate::{
+    backend::leader,
+    common::{ServerSpec, ServerType},
+};
+
+/// Uploads the selected files next to the page (i.e. into the server's root) one after another.
+const UPLOAD_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Upload Files</title>
+</head>
+<body style="font-family: sans-serif; max-width: 40rem; margin: 2rem auto; padding: 0 1rem">
+<h1>Upload Files</h1>
+<p>Files uploaded here are sent to the owner of this drop box. You can't see files uploaded before.</p>
+<input id="files" type="file" multiple>
+<button id="upload">Upload</button>
+<ul id="results"></ul>
+<script>
+document.getElementById("upload").onclick = async () => {
+    const results = document.getElementById("results");
+    for (const file of document.getElementById("files").files) {
+        const item = document.createElement("li");
+        item.textContent = file.name + ": uploading...";
+        results.appendChild(item);
+        const url = location.pathname.replace(/\/?$/, "/") + encodeURIComponent(file.name);
+        const response = await fetch(url, { method: "PUT", body: file });
+        item.textContent = file.name + ": " + (response.ok ? "uploaded"
+            : response.status === 409 ? "a file with this name exists already, please rename it"
+            : "failed (" + response.status + ")");
+    }
+};
+</script>
+</body>
+</html>
+"#;
+
+/// Whether the server is served as a drop box: read-write WebDAV servers that opted in.
+pub(crate) fn is_drop_box(spec: &ServerSpec) -> bool {
+    spec.options.drop_box && matches!(spec.server_type, ServerType::Webdav) && !spec.read_only
+}
+
+/// Forwards uploads (`PUT`) of new files and creating folders (`MKCOL`) to the server at `base_url`, and answers
+/// `GET` of the root with the upload page. Other requests, like listing or downloading files, are rejected.
+pub(crate) async fn forward(
+    client: &reqwest::Client,
+    request: Request,
+    base_url: &str,
+    path: &str,
+) -> reqwest::Result<axum::http::Response<Body>> {
+    let url = format!("{}/{}", base_url, path);
+    match request.method().clone() {
+        // the page needs no password, but ask the server anyway, so that the browser prompts for its password (if
+        // any) before uploading
+        Method::GET if path.is_empty() => {
+            let check = check_request(Method::OPTIONS, request.headers());
+            let response = leader::forward(client, check, url).await?;
+            if response.status() == StatusCode::UNAUTHORIZED {
+                return Ok(response);
+            }
+            Ok(Html(UPLOAD_PAGE).into_response())
+        }
+        Method::PUT => {
+            let check = check_request(Method::HEAD, request.headers());
+            let response = leader::forward(client, check, url.clone()).await?;
+            match response.status() {
+                StatusCode::NOT_FOUND => leader::forward(client, request, url).await,
+                status if status.is_success() => Ok(StatusCode::CONFLICT.into_response()),
+                // e.g. without the password
+                _ => Ok(response),
+            }
+        }
+        method if method == Method::OPTIONS || method.as_str() == "MKCOL" => {
+            leader::forward(client, request, url).await
+        }
+        _ => Ok(axum::http::Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header(header::ALLOW, "PUT, MKCOL, OPTIONS")
+            .body(Body::empty())
+            .unwrap()),
+    }
+}
+
+/// A request without body asking the server about the path, with the client's credentials.
+fn check_request(method: Method, headers: &HeaderMap) -> Request {
+    let mut headers = headers.clone();
+    for name in [
+        header::CONTENT_LENGTH,
+        header::CONTENT_TYPE,
+        header::TRANSFER_ENCODING,
+        header::EXPECT,
+    ] {
+        headers.remove(name);
+    }
+    let mut request = Request::new(Body::empty());
+    *request.method_mut() = method;
+    *request.headers_mut() = headers;
+    request
+}
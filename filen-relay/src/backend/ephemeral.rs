@@ -0,0 +1,329 @@
+//! Short-lived state: sessions and the last access times of servers.
+//! It is kept in Redis if `--redis-url` is set (so that it survives restarts of e.g. scale-to-zero containers
+//! without being written to the database file), and in the database otherwise. With Redis, access times are still
+//! written to the database by the maintenance task, as a fallback for servers whose entry Redis lost.
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex, OnceLock},
+};
+
+use chrono::{DateTime, Utc};
+use dioxus::logger::tracing;
+use redis::{aio::ConnectionManager, AsyncCommands as _};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backend::{
+        auth::{self, Session, SessionToken, SESSION_LIFETIME},
+        db::DB,
+        forwarded::ClientInfo,
+        secrets,
+    },
+    common::{AllowedUser, ServerId, UserSession},
+};
+
+const KEY_PREFIX: &str = "filen-relay";
+
+/// Accesses of a server are written to Redis at most this often (and at the latest by the maintenance task).
+const ACCESS_WRITE_INTERVAL: chrono::Duration = chrono::Duration::minutes(1);
+
+static REDIS: OnceLock<ConnectionManager> = OnceLock::new();
+
+/// When the access of each server was last written to Redis.
+static WRITTEN_ACCESSES: LazyLock<Mutex<HashMap<ServerId, DateTime<Utc>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Connects to Redis, e.g. at "redis://localhost:6379".
+pub(crate) async fn connect(url: &str) -> anyhow::Result<()> {
+    let client = redis::Client::open(url)?;
+    let connection = ConnectionManager::new(client).await?;
+    REDIS.set(connection).ok();
+    tracing::info!("Storing sessions and server accesses in Redis");
+    Ok(())
+}
+
+fn redis() -> Option<ConnectionManager> {
+    REDIS.get().cloned()
+}
+
+/// Sessions are stored under the hash of their token (see `SessionToken::hash`), like in the database.
+fn session_key(hash: &str) -> String {
+    format!("{}:session:{}", KEY_PREFIX, hash)
+}
+
+/// The token hashes of a user's sessions, scored by their creation time.
+fn user_sessions_key(filen_email: &str) -> String {
+    format!("{}:user-sessions:{}", KEY_PREFIX, filen_email)
+}
+
+fn server_accesses_key() -> String {
+    format!("{}:server-accesses", KEY_PREFIX)
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredSession {
+    filen_email: String,
+    filen_auth_config: String,
+    is_admin: bool,
+    // missing in sessions stored by older versions
+    #[serde(default)]
+    user_agent: Option<String>,
+    #[serde(default)]
+    ip: Option<String>,
+}
+
+/// Stores a new session created by `client` (and removes expired ones).
+pub(crate) async fn create_session(
+    session: &Session,
+    client: &ClientInfo,
+    now: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    let ip = client.ip.map(|ip| ip.to_string());
+    let Some(mut redis) = redis() else {
+        DB.delete_sessions_created_before(now - SESSION_LIFETIME)
+            .await?;
+        DB.create_session(session, client.user_agent.as_deref(), ip.as_deref(), now)
+            .await?;
+        return Ok(());
+    };
+    let stored = serde_json::to_string(&StoredSession {
+        filen_email: session.filen_email.clone(),
+        filen_auth_config: secrets::seal(&session.filen_auth_config),
+        is_admin: session.is_admin,
+        user_agent: client.user_agent.clone(),
+        ip,
+    })?;
+    let lifetime = SESSION_LIFETIME.num_seconds();
+    // Redis expires the session itself
+    redis
+        .set_ex::<_, _, ()>(session_key(&session.token.hash()), stored, lifetime as u64)
+        .await?;
+    let user_sessions = user_sessions_key(&session.filen_email);
+    redis
+        .zadd::<_, _, _, ()>(&user_sessions, session.token.hash(), now.timestamp())
+        .await?;
+    redis
+        .zrembyscore::<_, _, _, ()>(&user_sessions, "-inf", now.timestamp() - lifetime)
+        .await?;
+    redis.expire::<_, ()>(&user_sessions, lifetime).await?;
+    Ok(())
+}
+
+/// Gets the session with the given token, unless it has expired.
+pub(crate) async fn get_session(token: &SessionToken) -> anyhow::Result<Option<Session>> {
+    let Some(mut redis) = redis() else {
+        return Ok(DB.get_session(token, Utc::now() - SESSION_LIFETIME).await?);
+    };
+    let Some(stored) = redis
+        .get::<_, Option<String>>(session_key(&token.hash()))
+        .await?
+    else {
+        return Ok(None);
+    };
+    let stored = serde_json::from_str::<StoredSession>(&stored)?;
+    Ok(Some(Session {
+        token: token.clone(),
+        filen_email: stored.filen_email,
+        filen_auth_config: secrets::open(&stored.filen_auth_config)?,
+        is_admin: stored.is_admin,
+    }))
+}
+
+/// Returns the unexpired sessions of the user, newest first. `current` is the session asking.
+pub(crate) async fn get_user_sessions(
+    filen_email: &str,
+    current: &SessionToken,
+) -> anyhow::Result<Vec<UserSession>> {
+    let now = Utc::now();
+    let sessions = match redis() {
+        None => {
+            DB.get_user_sessions(filen_email, now - SESSION_LIFETIME)
+                .await?
+        }
+        Some(mut redis) => {
+            let cutoff = now.timestamp() - SESSION_LIFETIME.num_seconds();
+            let hashes = redis
+                .zrevrangebyscore_withscores::<_, _, _, Vec<(String, i64)>>(
+                    user_sessions_key(filen_email),
+                    "+inf",
+                    cutoff,
+                )
+                .await?;
+            let mut sessions = Vec::new();
+            for (hash, created) in hashes {
+                // the session may have been logged out
+                let Some(stored) = redis.get::<_, Option<String>>(session_key(&hash)).await? else {
+                    continue;
+                };
+                let stored = serde_json::from_str::<StoredSession>(&stored)?;
+                let created = DateTime::from_timestamp(created, 0).unwrap_or_default();
+                sessions.push((hash, stored.user_agent, stored.ip, created));
+            }
+            sessions
+        }
+    };
+    let current = current.hash();
+    Ok(sessions
+        .into_iter()
+        .map(|(hash, user_agent, ip, created)| UserSession {
+            id: auth::session_id(&hash),
+            user_agent,
+            ip,
+            created,
+            current: hash == current,
+        })
+        .collect())
+}
+
+/// Logs out the user's session with the given ID (see `SessionToken::id`). Returns whether it existed.
+pub(crate) async fn delete_user_session(filen_email: &str, id: &str) -> anyhow::Result<bool> {
+    let Some(mut redis) = redis() else {
+        let Some(hash) = DB
+            .get_user_sessions(filen_email, Utc::now() - SESSION_LIFETIME)
+            .await?
+            .into_iter()
+            .map(|(hash, _, _, _)| hash)
+            .find(|hash| auth::session_id(hash) == id)
+        else {
+            return Ok(false);
+        };
+        DB.delete_session(&hash).await?;
+        return Ok(true);
+    };
+    let Some(hash) = redis
+        .zrange::<_, Vec<String>>(user_sessions_key(filen_email), 0, -1)
+        .await?
+        .into_iter()
+        .find(|hash| auth::session_id(hash) == id)
+    else {
+        return Ok(false);
+    };
+    redis.del::<_, ()>(session_key(&hash)).await?;
+    redis
+        .zrem::<_, _, ()>(user_sessions_key(filen_email), &hash)
+        .await?;
+    Ok(true)
+}
+
+/// Logs out all sessions of the user, returning how many there were.
+pub(crate) async fn delete_user_sessions(filen_email: &str) -> anyhow::Result<usize> {
+    let Some(mut redis) = redis() else {
+        return Ok(DB.delete_user_sessions(filen_email).await?);
+    };
+    let user_sessions = user_sessions_key(filen_email);
+    let keys = redis
+        .zrange::<_, Vec<String>>(&user_sessions, 0, -1)
+        .await?
+        .iter()
+        .map(|hash| session_key(hash))
+        .collect::<Vec<_>>();
+    let deleted = if keys.is_empty() {
+        0
+    } else {
+        redis.del::<_, usize>(keys).await?
+    };
+    redis.del::<_, ()>(&user_sessions).await?;
+    Ok(deleted)
+}
+
+/// Returns the allowed users with their activity, see `DbViaOfflineOrRemoteFile::get_allowed_users_activity`.
+pub(crate) async fn get_allowed_users_activity() -> anyhow::Result<Vec<AllowedUser>> {
+    let now = Utc::now();
+    let mut users = DB
+        .get_allowed_users_activity(now - SESSION_LIFETIME)
+        .await?;
+    let Some(mut redis) = redis() else {
+        return Ok(users);
+    };
+    // the database doesn't know about the sessions then
+    let cutoff = now.timestamp() - SESSION_LIFETIME.num_seconds();
+    for user in &mut users {
+        user.active_sessions = redis
+            .zcount::<_, _, _, usize>(user_sessions_key(&user.email), cutoff, "+inf")
+            .await?;
+    }
+    Ok(users)
+}
+
+/// Notes the access of a server in Redis right away, so that it isn't lost on a restart (see `record_server_accesses`).
+pub(crate) fn record_server_access(id: &ServerId, now: DateTime<Utc>) {
+    let Some(mut redis) = redis() else {
+        return;
+    };
+    {
+        let mut written = WRITTEN_ACCESSES.lock().unwrap();
+        if written
+            .get(id)
+            .is_some_and(|written| now - *written < ACCESS_WRITE_INTERVAL)
+        {
+            return;
+        }
+        written.insert(id.clone(), now);
+    }
+    let id = id.clone();
+    tokio::spawn(async move {
+        if let Err(e) = redis
+            .hset::<_, _, _, ()>(server_accesses_key(), &id, now.to_rfc3339())
+            .await
+        {
+            tracing::error!("Failed to record access of server {} in Redis: {}", id, e);
+        }
+    });
+}
+
+/// Stores the last access times of servers. Servers without one yet count as accessed `now`.
+pub(crate) async fn record_server_accesses(
+    accesses: &[(ServerId, DateTime<Utc>)],
+    now: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    DB.record_server_accesses(accesses, now).await?;
+    let Some(mut redis) = redis() else {
+        return Ok(());
+    };
+    if !accesses.is_empty() {
+        let accesses = accesses
+            .iter()
+            .map(|(id, accessed)| (id.clone(), accessed.to_rfc3339()))
+            .collect::<Vec<_>>();
+        redis
+            .hset_multiple::<_, _, _, ()>(server_accesses_key(), &accesses)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Returns the IDs of running (not paused or archived) servers last accessed before `cutoff`. The access times in
+/// Redis are newer than the ones in the database, which are used for servers without one in Redis.
+pub(crate) async fn get_servers_inactive_since(
+    cutoff: DateTime<Utc>,
+) -> anyhow::Result<Vec<ServerId>> {
+    let ids = DB.get_servers_inactive_since(cutoff).await?;
+    let Some(mut redis) = redis() else {
+        return Ok(ids);
+    };
+    if ids.is_empty() {
+        return Ok(ids);
+    }
+    let accesses = redis::cmd("HMGET")
+        .arg(server_accesses_key())
+        .arg(&ids)
+        .query_async::<Vec<Option<String>>>(&mut redis)
+        .await?;
+    Ok(ids
+        .into_iter()
+        .zip(accesses)
+        .filter(|(_, accessed)| {
+            match accessed
+                .as_deref()
+                .and_then(|accessed| DateTime::parse_from_rfc3339(accessed).ok())
+            {
+                Some(accessed) => accessed < cutoff,
+                // unknown to Redis (e.g. after it lost its data), so the database's access time applies, which is
+                // before the cutoff already
+                None => true,
+            }
+        })
+        .map(|(id, _)| id)
+        .collect())
+}
@@ -0,0 +1,131 @@
+use dioxus::server::axum::{
+    extract::{Path, Query},
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use serde::Deserialize;
+
+use crate::{
+    backend::{auth, db::DB, server_manager::SERVER_MANAGER},
+    common::{LogLineContent, ServerSpecExport},
+};
+
+#[derive(Deserialize)]
+pub(crate) struct ExportQuery {
+    #[serde(default)]
+    include_credentials: bool,
+}
+
+/// `GET /api/servers/export`: downloads the user's server specs as a JSON file.
+/// Server passwords are only included with `?include_credentials=true`.
+pub(crate) async fn export_servers(
+    session: auth::Session,
+    Query(query): Query<ExportQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let specs = DB
+        .get_servers()
+        .await
+        .map_err(|e| {
+            dioxus::logger::tracing::error!("Failed to get servers for export: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .filter(|s| s.filen_email == session.filen_email)
+        .map(|s| ServerSpecExport {
+            name: s.name,
+            server_type: s.server_type,
+            root: s.root,
+            read_only: s.read_only,
+            password: if query.include_credentials {
+                s.password
+            } else {
+                None
+            },
+            options: s.options,
+        })
+        .collect::<Vec<_>>();
+    let json =
+        serde_json::to_string_pretty(&specs).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"filen-relay-servers.json\"",
+            ),
+        ],
+        json,
+    ))
+}
+
+/// `GET /api/allowedUsers/export`: downloads the allowed users as a CSV file for the admin, e.g. to import them elsewhere.
+pub(crate) async fn export_allowed_users(
+    session: auth::Session,
+) -> Result<impl IntoResponse, StatusCode> {
+    if !session.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let emails = DB.get_allowed_users().await.map_err(|e| {
+        dioxus::logger::tracing::error!("Failed to get allowed users for export: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let csv = std::iter::once("email".to_string())
+        .chain(emails)
+        .map(|line| line + "\n")
+        .collect::<String>();
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"filen-relay-allowed-users.csv\"",
+            ),
+        ],
+        csv,
+    ))
+}
+
+/// `GET /api/logs/{server_id}/download`: downloads the log history of a server (as far as it is kept) as a text file.
+pub(crate) async fn export_logs(
+    session: auth::Session,
+    Path(server_id): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let logs = SERVER_MANAGER
+        .get_logs(&server_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if !session.can_access(&logs.server_spec) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let (history, _) = logs.subscribe(None, None);
+    let text = history
+        .into_iter()
+        .map(|line| {
+            let content = match line.content {
+                LogLineContent::Event(content) => format!("--- {}", content),
+                LogLineContent::ServerProcess(content) => content,
+            };
+            format!(
+                "{} [run {}] {}\n",
+                line.timestamp.to_rfc3339(),
+                line.run,
+                content
+            )
+        })
+        .collect::<String>();
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                "text/plain; charset=utf-8".to_string(),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"filen-relay-logs-{}.txt\"",
+                    server_id
+                ),
+            ),
+        ],
+        text,
+    ))
+}
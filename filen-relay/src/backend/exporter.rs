@@ -0,0 +1,162 @@
+//! Per-server counters in the Prometheus text format at `/metrics`, so that dashboards can break traffic down by
+//! server. Enabled by setting a metrics token, which scrapers send as a bearer token.
+
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    sync::{LazyLock, Mutex, OnceLock},
+};
+
+use dioxus::server::axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+
+use crate::{
+    backend::{secrets, server_manager::SERVER_MANAGER},
+    common::ServerId,
+};
+
+/// Servers beyond this many (by ID) are summed up under `server_id="other"`, so that a scrape stays small no matter
+/// how many servers there are.
+const MAX_LABELED_SERVERS: usize = 500;
+
+static METRICS_TOKEN: OnceLock<Option<String>> = OnceLock::new();
+
+#[derive(Clone, Copy, Default)]
+struct Counters {
+    requests: u64,
+    bytes: u64,
+    starts: u64,
+}
+
+impl Counters {
+    fn add(&mut self, other: &Counters) {
+        self.requests += other.requests;
+        self.bytes += other.bytes;
+        self.starts += other.starts;
+    }
+}
+
+static COUNTERS: LazyLock<Mutex<HashMap<ServerId, Counters>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+pub(crate) fn init(metrics_token: Option<&str>) {
+    METRICS_TOKEN
+        .set(metrics_token.map(str::to_string))
+        .expect("Exporter initialized twice");
+}
+
+fn record(id: &ServerId, update: impl FnOnce(&mut Counters)) {
+    update(COUNTERS.lock().unwrap().entry(id.clone()).or_default());
+}
+
+/// Records a request proxied to the server.
+pub(crate) fn record_request(id: &ServerId) {
+    record(id, |counters| counters.requests += 1);
+}
+
+/// Records bytes transferred by the server (see `usage::run_usage_accounting`).
+pub(crate) fn record_bytes(id: &ServerId, bytes: u64) {
+    record(id, |counters| counters.bytes += bytes);
+}
+
+/// Records a start of the server's process. All but the first count as restarts.
+pub(crate) fn record_start(id: &ServerId) {
+    record(id, |counters| counters.starts += 1);
+}
+
+/// `GET /metrics`: the counters of the servers, as long as they exist (counters of removed servers are dropped).
+pub(crate) async fn metrics(headers: HeaderMap) -> Result<impl IntoResponse, StatusCode> {
+    let Some(token) = METRICS_TOKEN.get().and_then(Option::as_deref) else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let authorized = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|value| secrets::matches(value, token));
+    if !authorized {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let mut servers = SERVER_MANAGER
+        .get_server_states()
+        .borrow()
+        .iter()
+        .map(|s| {
+            (
+                s.spec.id.clone(),
+                s.spec.filen_email.clone(),
+                s.spec.server_type.to_string().to_lowercase(),
+            )
+        })
+        .collect::<Vec<_>>();
+    servers.sort_by(|a, b| a.0.to_string().cmp(&b.0.to_string()));
+    let mut series = Vec::new();
+    {
+        let mut counters = COUNTERS.lock().unwrap();
+        counters.retain(|id, _| servers.iter().any(|(server_id, _, _)| server_id == id));
+        let mut other = None::<Counters>;
+        for (i, (id, owner, server_type)) in servers.into_iter().enumerate() {
+            let server_counters = counters.get(&id).copied().unwrap_or_default();
+            if i < MAX_LABELED_SERVERS {
+                series.push((id.to_string(), owner, server_type, server_counters));
+            } else {
+                other.get_or_insert_default().add(&server_counters);
+            }
+        }
+        if let Some(other) = other {
+            series.push(("other".to_string(), String::new(), String::new(), other));
+        }
+    }
+
+    let mut body = String::new();
+    let families: [(&str, &str, fn(&Counters) -> u64); 3] = [
+        (
+            "filen_relay_server_requests_total",
+            "Requests proxied to the server.",
+            |c| c.requests,
+        ),
+        (
+            "filen_relay_server_transferred_bytes_total",
+            "Bytes transferred by the server.",
+            |c| c.bytes,
+        ),
+        (
+            "filen_relay_server_restarts_total",
+            "Restarts of the server's process.",
+            |c| c.starts.saturating_sub(1),
+        ),
+    ];
+    for (name, help, value) in families {
+        let _ = writeln!(body, "# HELP {} {}", name, help);
+        let _ = writeln!(body, "# TYPE {} counter", name);
+        for (id, owner, server_type, counters) in &series {
+            let _ = writeln!(
+                body,
+                "{}{{server_id=\"{}\",owner=\"{}\",type=\"{}\"}} {}",
+                name,
+                escape(id),
+                escape(owner),
+                escape(server_type),
+                value(counters)
+            );
+        }
+    }
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        body,
+    ))
+}
+
+/// Escapes a label value, see https://prometheus.io/docs/instrumenting/exposition_formats/.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
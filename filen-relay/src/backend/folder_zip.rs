@@ -0,0 +1,223 @@
+//! Downloads of whole folders of HTTP servers as ZIP files (see `ServerOptions::zip_downloads`), requested with
+//! `?download=zip` on a folder's URL. The proxy builds the ZIP itself, streaming the files from the owner's Filen
+//! drive into it while it is sent, so neither rclone nor the relay keeps a copy. Files are stored uncompressed, since
+//! most large files (like photos and videos) are compressed already.
+//!
+//! The server itself still decides whether the client may see the folder: the proxy first asks it for the folder
+//! with the client's headers, and only builds the ZIP if it answers successfully.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+use anyhow::{Context, Result};
+use async_zip::{base::write::ZipFileWriter, Compression, ZipEntryBuilder};
+use dioxus::{
+    fullstack::extract::Request,
+    logger::tracing,
+    server::axum::{
+        self,
+        body::Body,
+        http::{header, Method, StatusCode},
+        response::IntoResponse as _,
+    },
+};
+use filen_sdk_rs::{
+    auth::Client,
+    fs::{dir::DirectoryType, file::enums::RemoteFileType, FSObject, HasName},
+};
+use tokio_stream::StreamExt as _;
+use tokio_util::io::ReaderStream;
+
+use crate::{
+    backend::{access_rules, auth, leader, server_proxy},
+    common::{ServerId, ServerSpec, ServerType},
+};
+
+/// How much of the ZIP is buffered between building it and sending it to the client.
+const STREAM_BUFFER_SIZE: usize = 256 * 1024;
+
+/// Bytes of ZIPs sent per server since they were last added to its usage (see `take_bytes`).
+static SENT_BYTES: LazyLock<Mutex<HashMap<ServerId, u64>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Whether the request asks for a folder of the server as a ZIP.
+pub(crate) fn is_requested(spec: &ServerSpec, method: &Method, query: Option<&str>) -> bool {
+    spec.options.zip_downloads
+        && matches!(spec.server_type, ServerType::Http)
+        // which root a path belongs to is up to rclone
+        && spec.options.additional_roots.is_empty()
+        && *method == Method::GET
+        && query.is_some_and(|query| query.split('&').any(|param| param == "download=zip"))
+}
+
+/// The bytes of ZIPs sent for the server since the last call, which `usage` adds to those counted by the server.
+pub(crate) fn take_bytes(id: &ServerId) -> u64 {
+    SENT_BYTES.lock().unwrap().remove(id).unwrap_or(0)
+}
+
+/// Responds with the folder at `path` (relative to the server's root) as a ZIP, or with the server's response if it
+/// doesn't serve the folder to the client (e.g. without the password).
+pub(crate) async fn forward(
+    client: &reqwest::Client,
+    request: Request,
+    spec: &ServerSpec,
+    base_url: &str,
+    path: &str,
+) -> reqwest::Result<axum::http::Response<Body>> {
+    let path = match path {
+        "" => String::new(),
+        path => format!("{}/", path.trim_end_matches('/')),
+    };
+    let (mut parts, _) = request.into_parts();
+    parts.method = Method::HEAD;
+    let check = Request::from_parts(parts, Body::empty());
+    let response = leader::forward(client, check, format!("{}/{}", base_url, path)).await?;
+    if !response.status().is_success() {
+        return Ok(response);
+    }
+
+    let dir_path =
+        server_proxy::decode_path(&format!("{}/{}", spec.root.trim_end_matches('/'), path));
+    let dir_path = match dir_path.trim_end_matches('/') {
+        "" => "/".to_string(),
+        dir_path => dir_path.to_string(),
+    };
+    let file_name = match dir_path.rsplit('/').next() {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => spec.name.clone(),
+    };
+    let filen_client = match auth::client_from_auth_config(&spec.filen_auth_config) {
+        Ok(filen_client) => filen_client,
+        Err(e) => {
+            tracing::error!("Failed to create Filen client for ZIP download: {}", e);
+            return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+        }
+    };
+
+    let (writer, reader) = tokio::io::duplex(STREAM_BUFFER_SIZE);
+    {
+        let dir_path = dir_path.clone();
+        let skip = Skip {
+            path: server_proxy::decode_path(&path),
+            blocked_paths: spec.options.blocked_paths.clone(),
+        };
+        tokio::spawn(async move {
+            // fails (and is logged) if the client disconnects before the ZIP is complete
+            if let Err(e) = write_zip(&filen_client, &dir_path, &skip, writer).await {
+                tracing::warn!("Failed to send {} as ZIP: {:#}", dir_path, e);
+            }
+        });
+    }
+    let id = spec.id.clone();
+    let stream = ReaderStream::new(reader).map(move |chunk| {
+        if let Ok(chunk) = &chunk {
+            *SENT_BYTES.lock().unwrap().entry(id.clone()).or_default() += chunk.len() as u64;
+        }
+        chunk
+    });
+    Ok(axum::http::Response::builder()
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"{}.zip\"",
+                sanitize_file_name(&file_name)
+            ),
+        )
+        .body(Body::from_stream(stream))
+        .unwrap())
+}
+
+/// The files and folders left out of a ZIP, since the server's `blocked_paths` match them.
+struct Skip {
+    /// The path of the zipped folder relative to the server's root, with a trailing slash (unless it's the root).
+    path: String,
+    blocked_paths: Vec<String>,
+}
+
+impl Skip {
+    /// Whether to skip the entry at `entry_path` (relative to the zipped folder).
+    fn skips(&self, entry_path: &str) -> bool {
+        access_rules::is_blocked(&self.blocked_paths, &format!("{}{}", self.path, entry_path))
+    }
+}
+
+/// Writes the files in the folder (and its subfolders) to a ZIP, with paths relative to the folder.
+async fn write_zip(
+    client: &Client,
+    dir_path: &str,
+    skip: &Skip,
+    writer: tokio::io::DuplexStream,
+) -> Result<()> {
+    let mut zip = ZipFileWriter::with_tokio(writer);
+    let mut dirs = vec![(dir_path.to_string(), String::new())];
+    while let Some((path, prefix)) = dirs.pop() {
+        let dir = match client
+            .find_item_at_path(&path)
+            .await
+            .context("Failed to look up folder in Filen drive")?
+        {
+            Some(FSObject::Root(root)) => DirectoryType::Root(root),
+            Some(FSObject::Dir(dir)) => DirectoryType::Dir(dir),
+            _ => continue,
+        };
+        let (subdirs, files) = client
+            .list_dir(&dir)
+            .await
+            .context("Failed to list folder in Filen drive")?;
+        let parent = path.trim_end_matches('/');
+        for file in &files {
+            let Some(name) = file.name() else {
+                continue;
+            };
+            if skip.skips(&format!("{}{}", prefix, name)) {
+                continue;
+            }
+            let entry =
+                ZipEntryBuilder::new(format!("{}{}", prefix, name).into(), Compression::Stored);
+            let mut entry_writer = zip
+                .write_entry_stream(entry)
+                .await
+                .context("Failed to start ZIP entry")?;
+            client
+                .download_file_to_writer(
+                    &RemoteFileType::File(Cow::Borrowed(file)),
+                    &mut entry_writer,
+                    None,
+                )
+                .await
+                .with_context(|| format!("Failed to download {}/{}", parent, name))?;
+            entry_writer
+                .close()
+                .await
+                .context("Failed to finish ZIP entry")?;
+        }
+        for subdir in &subdirs {
+            if let Some(name) = subdir
+                .name()
+                .filter(|name| !skip.skips(&format!("{}{}", prefix, name)))
+            {
+                dirs.push((
+                    format!("{}/{}", parent, name),
+                    format!("{}{}/", prefix, name),
+                ));
+            }
+        }
+    }
+    zip.close().await.context("Failed to finish ZIP")?;
+    Ok(())
+}
+
+/// Replaces characters that can't be used in the `Content-Disposition` header.
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            ' ' | '-' | '_' | '.' | '(' | ')' => c,
+            c if c.is_ascii_alphanumeric() => c,
+            _ => '_',
+        })
+        .collect()
+}
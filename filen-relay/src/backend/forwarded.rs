@@ -0,0 +1,268 @@
+//! Handling of the `X-Forwarded-*` headers set by reverse proxies (e.g. nginx or Traefik) in front of the relay.
+//! They are only honored for requests coming from one of the `--trusted-proxies`, since anyone else could forge them.
+//! Also assigns every request an ID (or keeps the one set by a trusted proxy), see [`REQUEST_ID_HEADER`].
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::RwLock,
+};
+
+use dioxus::{
+    fullstack::extract::{FromRequestParts, Request},
+    logger::tracing::{self, Instrument as _},
+    server::{
+        axum::{self, extract::ConnectInfo, middleware::Next, response::IntoResponse},
+        http::{request::Parts, HeaderMap, HeaderValue, StatusCode},
+    },
+};
+
+/// Carries the ID of a request: passed on to the servers (and the leader) it is proxied to, returned to the client,
+/// and logged in the access log and the relay's traces, so that a failed request reported by a user can be traced.
+pub(crate) const REQUEST_ID_HEADER: &str = "X-Request-Id";
+/// Longest request ID accepted from a trusted proxy.
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// Can be changed by reloading the config, see `config::reload`.
+static TRUSTED_PROXIES: RwLock<Vec<TrustedProxy>> = RwLock::new(Vec::new());
+
+/// An entry of `--trusted-proxies`: an IP address, a network like "10.0.0.0/8", or "*" for any address.
+#[derive(Clone)]
+enum TrustedProxy {
+    Any,
+    Network(IpAddr, u8),
+}
+
+impl TrustedProxy {
+    fn parse(value: &str) -> Result<Self, String> {
+        if value == "*" {
+            return Ok(TrustedProxy::Any);
+        }
+        let (ip, prefix) = value.split_once('/').unwrap_or((value, ""));
+        let ip = ip
+            .parse::<IpAddr>()
+            .map_err(|_| format!("Invalid trusted proxy address \"{}\"", value))?;
+        let max_prefix = if ip.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            "" => max_prefix,
+            prefix => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max_prefix)
+                .ok_or_else(|| format!("Invalid trusted proxy network \"{}\"", value))?,
+        };
+        Ok(TrustedProxy::Network(ip, prefix))
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self, ip.to_canonical()) {
+            (TrustedProxy::Any, _) => true,
+            (TrustedProxy::Network(IpAddr::V4(network), prefix), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - *prefix as u32).unwrap_or(0);
+                u32::from(*network) & mask == u32::from(ip) & mask
+            }
+            (TrustedProxy::Network(IpAddr::V6(network), prefix), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - *prefix as u32).unwrap_or(0);
+                u128::from(*network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Sets the trusted proxies from `--trusted-proxies`. Fails (keeping the previous ones) if one of them is invalid.
+pub(crate) fn init(trusted_proxies: &[String]) -> Result<(), String> {
+    let trusted_proxies = trusted_proxies
+        .iter()
+        .map(|p| TrustedProxy::parse(p.trim()))
+        .collect::<Result<Vec<_>, _>>()?;
+    *TRUSTED_PROXIES.write().unwrap() = trusted_proxies;
+    Ok(())
+}
+
+fn is_trusted(ip: IpAddr) -> bool {
+    TRUSTED_PROXIES
+        .read()
+        .unwrap()
+        .iter()
+        .any(|p| p.contains(ip))
+}
+
+/// Whether a peer is a trusted proxy. If its address is unknown, only "*" trusts it.
+fn is_trusted_peer(peer: Option<IpAddr>) -> bool {
+    match peer {
+        Some(ip) => is_trusted(ip),
+        None => TRUSTED_PROXIES
+            .read()
+            .unwrap()
+            .iter()
+            .any(|p| matches!(p, TrustedProxy::Any)),
+    }
+}
+
+/// Where a request came from, taking into account the `X-Forwarded-*` headers of trusted proxies.
+#[derive(Clone, Default)]
+pub(crate) struct ClientInfo {
+    /// The address of the client (unset if the connection address is unknown).
+    pub ip: Option<IpAddr>,
+    /// Whether the client used HTTPS (as reported by a trusted proxy).
+    pub https: bool,
+    /// The host the client requested (as reported by a trusted proxy).
+    pub forwarded_host: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl ClientInfo {
+    fn from_headers(peer: Option<IpAddr>, headers: &HeaderMap) -> Self {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                // multiple proxies may each have appended their value
+                .and_then(|v| v.split(',').next())
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        let user_agent = headers
+            .get("User-Agent")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        if !is_trusted_peer(peer) {
+            return ClientInfo {
+                ip: peer,
+                user_agent,
+                ..Default::default()
+            };
+        }
+        // the client is the rightmost address that wasn't added by a trusted proxy
+        let forwarded_for = headers
+            .get_all("X-Forwarded-For")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter_map(|ip| ip.trim().parse::<IpAddr>().ok())
+            .collect::<Vec<_>>();
+        let ip = forwarded_for
+            .iter()
+            .rev()
+            .find(|ip| !is_trusted(**ip))
+            .or(forwarded_for.first())
+            .copied()
+            .or(peer);
+        ClientInfo {
+            ip,
+            https: header("X-Forwarded-Proto").is_some_and(|p| p.eq_ignore_ascii_case("https")),
+            forwarded_host: header("X-Forwarded-Host"),
+            user_agent,
+        }
+    }
+
+    /// The origin (e.g. "https://relay.example.com") the client used, if reported by a trusted proxy.
+    pub(crate) fn forwarded_origin(&self) -> Option<String> {
+        self.forwarded_host
+            .as_ref()
+            .map(|host| format!("{}://{}", if self.https { "https" } else { "http" }, host))
+    }
+}
+
+/// Replaces the `X-Forwarded-*` headers of a request forwarded to another instance (the leader, see `leader`) with the
+/// client info determined by this one. The other instance only honors them if this one is one of its
+/// `--trusted-proxies`.
+pub(crate) fn set_forwarded_headers(headers: &mut HeaderMap, client: &ClientInfo) {
+    for name in ["X-Forwarded-For", "X-Forwarded-Proto", "X-Forwarded-Host"] {
+        headers.remove(name);
+    }
+    if let Some(value) = client
+        .ip
+        .and_then(|ip| HeaderValue::from_str(&ip.to_string()).ok())
+    {
+        headers.insert("X-Forwarded-For", value);
+    }
+    headers.insert(
+        "X-Forwarded-Proto",
+        HeaderValue::from_static(if client.https { "https" } else { "http" }),
+    );
+    if let Some(value) = client
+        .forwarded_host
+        .as_deref()
+        .and_then(|host| HeaderValue::from_str(host).ok())
+    {
+        headers.insert("X-Forwarded-Host", value);
+    }
+}
+
+/// Determines the [`ClientInfo`] and the ID of every request and writes an access log line for it.
+pub(crate) async fn middleware_client_info(
+    mut request: Request,
+    next: Next,
+) -> axum::http::Response<axum::body::Body> {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let client = ClientInfo::from_headers(peer, request.headers());
+    let request_id = request_id(&request)
+        .filter(|id| is_trusted_peer(peer) && id.len() <= MAX_REQUEST_ID_LENGTH)
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().as_simple().to_string()[..16].to_string());
+    let request_id_value = HeaderValue::from_str(&request_id).ok();
+    if let Some(value) = &request_id_value {
+        request
+            .headers_mut()
+            .insert(REQUEST_ID_HEADER, value.clone());
+    }
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    request.extensions_mut().insert(client.clone());
+    let mut response = next
+        .run(request)
+        .instrument(tracing::info_span!("request", id = %request_id))
+        .await;
+    if let Some(value) = request_id_value {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    tracing::debug!(
+        "{} {} {} {} {}",
+        client
+            .ip
+            .map(|ip| ip.to_string())
+            .unwrap_or("-".to_string()),
+        method,
+        path,
+        response.status().as_u16(),
+        request_id
+    );
+    response
+}
+
+/// The ID of a request, as set by `middleware_client_info`.
+pub(crate) fn request_id(request: &Request) -> Option<&str> {
+    request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty())
+}
+
+/// A plain-text error page for a request the relay failed to proxy, showing its ID for users to report.
+pub(crate) fn proxy_error(
+    status: StatusCode,
+    request_id: Option<&str>,
+) -> axum::http::Response<axum::body::Body> {
+    let message = format!("{}\n\nRequest ID: {}\n", status, request_id.unwrap_or("-"));
+    (status, message).into_response()
+}
+
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<ClientInfo>()
+            .cloned()
+            .unwrap_or_default())
+    }
+}
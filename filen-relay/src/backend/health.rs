@@ -0,0 +1,112 @@
+//! The health of each server for external uptime monitors (like UptimeRobot), pointed at
+//! `/api/servers/{id}/health`: the latest health probe of the watchdog (see `server_manager::run_watchdog`),
+//! the last successfully proxied request and the uptime of the process. Without a login, only the status is shown.
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+use chrono::{DateTime, Utc};
+use dioxus::server::axum::{extract::Path, http::StatusCode, response::IntoResponse, Json};
+use serde::Serialize;
+
+use crate::{
+    backend::auth,
+    common::{ServerId, ServerStatus},
+};
+
+#[derive(Clone, Default)]
+struct Record {
+    /// When the server was last probed, and whether it was healthy.
+    last_probe: Option<(DateTime<Utc>, bool)>,
+    last_successful_request: Option<DateTime<Utc>>,
+}
+
+static RECORDS: LazyLock<Mutex<HashMap<ServerId, Record>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+pub(crate) fn record_probe(id: &ServerId, healthy: bool) {
+    RECORDS
+        .lock()
+        .unwrap()
+        .entry(id.clone())
+        .or_default()
+        .last_probe = Some((Utc::now(), healthy));
+}
+
+/// Records a proxied request the server answered without a server error.
+pub(crate) fn record_successful_request(id: &ServerId) {
+    RECORDS
+        .lock()
+        .unwrap()
+        .entry(id.clone())
+        .or_default()
+        .last_successful_request = Some(Utc::now());
+}
+
+#[derive(Serialize)]
+pub(crate) struct ServerHealth {
+    /// "healthy", "unhealthy" (the latest probe failed) or "stopped".
+    status: &'static str,
+    /// Details only shown to users who can access the server.
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    details: Option<ServerHealthDetails>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct ServerHealthDetails {
+    last_probe: Option<DateTime<Utc>>,
+    last_probe_healthy: Option<bool>,
+    last_successful_request: Option<DateTime<Utc>>,
+    /// Seconds since the server process started, while running.
+    uptime_seconds: Option<i64>,
+}
+
+/// `GET /api/servers/{id}/health`: the health of the server with the ID used in its URL, with status 200 if it is
+/// running and healthy (or not probed yet) and 503 otherwise. It doesn't count as an access of the server
+/// (e.g. for pausing unused servers), and needs no login, like the server itself. The details beyond the status are
+/// only included for logged-in users who can access the server.
+pub(crate) async fn server_health(
+    session: Result<auth::Session, StatusCode>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let state = super::find_server(&id).ok_or(StatusCode::NOT_FOUND)?;
+    let show_details = session.is_ok_and(|session| session.can_access(&state.spec));
+    let record = RECORDS
+        .lock()
+        .unwrap()
+        .get(&state.spec.id)
+        .cloned()
+        .unwrap_or_default();
+    let process = match state.status {
+        ServerStatus::Running => state.process,
+        _ => None,
+    };
+    // probes of a previous process don't count
+    let last_probe = record.last_probe.filter(|(probed, _)| {
+        process
+            .as_ref()
+            .is_some_and(|process| *probed >= process.started)
+    });
+    let status = match (&process, last_probe) {
+        (None, _) => "stopped",
+        (Some(_), Some((_, false))) => "unhealthy",
+        (Some(_), _) => "healthy",
+    };
+    let health = ServerHealth {
+        status,
+        details: show_details.then(|| ServerHealthDetails {
+            last_probe: last_probe.map(|(probed, _)| probed),
+            last_probe_healthy: last_probe.map(|(_, healthy)| healthy),
+            last_successful_request: record.last_successful_request,
+            uptime_seconds: process.map(|process| (Utc::now() - process.started).num_seconds()),
+        }),
+    };
+    let status_code = if status == "healthy" {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok((status_code, Json(health)))
+}
@@ -0,0 +1,257 @@
+//! The `install` subcommand, setting Filen Relay up as a system service on a bare-metal host.
+//! With `--systemd`, it writes a hardened unit file running the current binary as an unprivileged system user,
+//! an environment file with the admin's auth config (prompted for), creates the data directory and enables the service.
+
+use std::{
+    io::{BufRead as _, Write as _},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{bail, Context, Result};
+
+const SERVICE_NAME: &str = "filen-relay";
+
+#[derive(clap::Args, Clone)]
+pub(crate) struct InstallArgs {
+    #[arg(
+        long,
+        help = "Install as a systemd service (currently the only supported init system)"
+    )]
+    systemd: bool,
+    #[arg(
+        long,
+        default_value = "/var/lib/filen-relay",
+        help = "Directory the service runs in, containing the rclone binary and configs"
+    )]
+    data_dir: PathBuf,
+    #[arg(
+        long,
+        default_value = "filen-relay",
+        help = "System user the service runs as, created if it doesn't exist"
+    )]
+    user: String,
+    #[arg(
+        long,
+        default_value = "0.0.0.0:8080",
+        help = "Address the service listens on"
+    )]
+    listen: std::net::SocketAddr,
+    #[arg(
+        long,
+        help = "Only write the files, without enabling and starting the service"
+    )]
+    no_start: bool,
+}
+
+/// Runs `filen-relay install`. Needs to be run as root.
+pub(crate) fn run(args: &InstallArgs) -> Result<()> {
+    if !args.systemd {
+        bail!("Specify the init system to install for, e.g. --systemd");
+    }
+    let binary = std::env::current_exe()
+        .context("Failed to get the path of the binary")?
+        .canonicalize()
+        .context("Failed to resolve the path of the binary")?;
+    let unit_path = PathBuf::from(format!("/etc/systemd/system/{}.service", SERVICE_NAME));
+    let env_dir = PathBuf::from(format!("/etc/{}", SERVICE_NAME));
+    let env_path = env_dir.join(format!("{}.env", SERVICE_NAME));
+
+    ensure_user(&args.user)?;
+
+    std::fs::create_dir_all(&args.data_dir).with_context(|| {
+        format!(
+            "Failed to create {} (are you root?)",
+            args.data_dir.display()
+        )
+    })?;
+    run_command(
+        "chown",
+        &[
+            format!("{0}:{0}", args.user).as_str(),
+            args.data_dir.to_string_lossy().as_ref(),
+        ],
+    )?;
+    std::fs::set_permissions(&args.data_dir, permissions(0o700))
+        .with_context(|| format!("Failed to restrict {}", args.data_dir.display()))?;
+    println!("Created data directory {}", args.data_dir.display());
+
+    if env_path.exists() {
+        println!("Keeping existing environment file {}", env_path.display());
+    } else {
+        let auth_config =
+            prompt("Auth config of the admin's Filen account (exported from the Filen CLI): ")?;
+        if auth_config.is_empty() {
+            bail!("An auth config is required");
+        }
+        let public_url =
+            prompt("Public URL of the relay, e.g. https://relay.example.com (optional): ")?;
+        std::fs::create_dir_all(&env_dir)
+            .with_context(|| format!("Failed to create {}", env_dir.display()))?;
+        write_file(
+            &env_path,
+            &environment_file(&auth_config, args.listen, &public_url),
+            0o600,
+        )?;
+        println!("Wrote environment file {}", env_path.display());
+    }
+
+    write_file(
+        &unit_path,
+        &unit_file(&binary, &env_path, &args.data_dir, &args.user),
+        0o644,
+    )?;
+    println!("Wrote unit file {}", unit_path.display());
+
+    run_command("systemctl", &["daemon-reload"])?;
+    if args.no_start {
+        println!(
+            "Start the service with: systemctl enable --now {}",
+            SERVICE_NAME
+        );
+    } else {
+        run_command("systemctl", &["enable", "--now", SERVICE_NAME])?;
+        println!(
+            "Filen Relay is running on {}. Follow its logs with: journalctl -u {} -f",
+            args.listen, SERVICE_NAME
+        );
+    }
+    Ok(())
+}
+
+/// Creates the system user (without a home directory or login shell) unless it exists.
+fn ensure_user(user: &str) -> Result<()> {
+    let exists = Command::new("id")
+        .arg(user)
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if exists {
+        return Ok(());
+    }
+    run_command(
+        "useradd",
+        &[
+            "--system",
+            "--no-create-home",
+            "--shell",
+            "/usr/sbin/nologin",
+            user,
+        ],
+    )?;
+    println!("Created system user {}", user);
+    Ok(())
+}
+
+fn unit_file(binary: &Path, env_path: &Path, data_dir: &Path, user: &str) -> String {
+    format!(
+        r#"[Unit]
+Description=Filen Relay
+Documentation=https://github.com/JupiterPi/filen-relay
+After=network-online.target
+Wants=network-online.target
+
+[Service]
+Type=simple
+User={user}
+Group={user}
+EnvironmentFile={env_path}
+WorkingDirectory={data_dir}
+ExecStart={binary}
+ExecReload=/bin/kill -HUP $MAINPID
+Restart=on-failure
+RestartSec=5
+
+# Hardening: only the data directory is writable (for the rclone binary and configs)
+NoNewPrivileges=yes
+ProtectSystem=strict
+ReadWritePaths={data_dir}
+ProtectHome=yes
+PrivateTmp=yes
+PrivateDevices=yes
+ProtectKernelTunables=yes
+ProtectKernelModules=yes
+ProtectKernelLogs=yes
+ProtectControlGroups=yes
+ProtectClock=yes
+ProtectHostname=yes
+RestrictSUIDSGID=yes
+RestrictRealtime=yes
+RestrictNamespaces=yes
+RestrictAddressFamilies=AF_INET AF_INET6 AF_UNIX
+LockPersonality=yes
+CapabilityBoundingSet=
+AmbientCapabilities=
+SystemCallArchitectures=native
+UMask=0077
+
+[Install]
+WantedBy=multi-user.target
+"#,
+        user = user,
+        env_path = env_path.display(),
+        data_dir = data_dir.display(),
+        binary = binary.display(),
+    )
+}
+
+fn environment_file(auth_config: &str, listen: std::net::SocketAddr, public_url: &str) -> String {
+    let mut lines = vec![
+        "# Environment of the filen-relay service, see `filen-relay --help` for all options"
+            .to_string(),
+        format!("FILEN_RELAY_ADMIN_AUTH_CONFIG={}", quote(auth_config)),
+        format!("FILEN_RELAY_LISTEN={}", listen),
+    ];
+    if !public_url.is_empty() {
+        lines.push(format!("FILEN_RELAY_PUBLIC_URL={}", quote(public_url)));
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Quotes a value for a systemd environment file.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn prompt(message: &str) -> Result<String> {
+    print!("{}", message);
+    std::io::stdout().flush()?;
+    let mut line = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .context("Failed to read from stdin")?;
+    Ok(line.trim().to_string())
+}
+
+/// Writes the file with the given mode, which is set before the content is written, so that e.g. the environment
+/// file is never readable by others (even if it existed with a broader mode).
+fn write_file(path: &Path, content: &str, mode: u32) -> Result<()> {
+    use std::os::unix::fs::OpenOptionsExt as _;
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(mode)
+        .open(path)
+        .with_context(|| format!("Failed to write {} (are you root?)", path.display()))?;
+    file.set_permissions(permissions(mode))
+        .with_context(|| format!("Failed to set permissions of {}", path.display()))?;
+    file.write_all(content.as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn permissions(mode: u32) -> std::fs::Permissions {
+    use std::os::unix::fs::PermissionsExt as _;
+    std::fs::Permissions::from_mode(mode)
+}
+
+fn run_command(program: &str, args: &[&str]) -> Result<()> {
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !status.success() {
+        bail!("{} {} failed with {}", program, args.join(" "), status);
+    }
+    Ok(())
+}
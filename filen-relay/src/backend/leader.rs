@@ -0,0 +1,180 @@
+//! Leader election among multiple instances sharing a database (`--instance-url`).
+//! Only the leader runs servers; the other instances forward API and server requests to it.
+//!
+//! The lease is a row in the database, so it only works if the instances open the same database file, i.e. a local
+//! `--db-dir` on the same machine. With the database stored remotely, each instance has its own copy of it.
+
+use std::sync::{LazyLock, Mutex, OnceLock};
+
+use dioxus::{
+    fullstack::extract::Request,
+    logger::tracing,
+    server::axum::{self, body::Body, http::StatusCode, middleware::Next},
+};
+
+use crate::backend::{
+    db::DB,
+    forwarded::{self, ClientInfo},
+};
+
+/// How long the leader's lease lasts unless renewed.
+const LEASE_DURATION: chrono::Duration = chrono::Duration::seconds(30);
+
+/// How often the lease is renewed by the leader, and tried to acquire by the other instances.
+const RENEW_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+#[derive(Debug)]
+struct Instance {
+    id: String,
+    /// The URL under which the other instances reach this one.
+    url: String,
+}
+
+static INSTANCE: OnceLock<Instance> = OnceLock::new();
+
+/// The URL of the leader, while this instance isn't the leader.
+static LEADER_URL: Mutex<Option<String>> = Mutex::new(None);
+
+/// Passes redirects on to the client instead of following them.
+static FORWARD_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("Failed to build HTTP client")
+});
+
+/// Enables leader election, with `instance_url` being the URL under which the other instances reach this one.
+/// Without it, this instance always leads.
+pub(crate) fn enable(instance_url: &str) {
+    INSTANCE
+        .set(Instance {
+            id: uuid::Uuid::new_v4().to_string(),
+            url: instance_url.trim_end_matches('/').to_string(),
+        })
+        .unwrap();
+}
+
+/// The URL of the leader to forward requests to, unless this instance is the leader.
+pub(crate) fn leader_url() -> Option<String> {
+    LEADER_URL.lock().unwrap().clone()
+}
+
+/// Waits until this instance has become the leader, then keeps renewing its lease in the background.
+/// Exits the process if the lease is lost (e.g. after the database was unreachable), so that servers never run twice.
+pub(crate) async fn wait_for_leadership() {
+    let Some(instance) = INSTANCE.get() else {
+        return;
+    };
+    let mut interval = tokio::time::interval(RENEW_INTERVAL);
+    loop {
+        interval.tick().await;
+        match DB
+            .try_acquire_leadership(
+                &instance.id,
+                &instance.url,
+                chrono::Utc::now(),
+                LEASE_DURATION,
+            )
+            .await
+        {
+            Ok(None) => break,
+            Ok(Some(leader_url)) => {
+                let mut current = LEADER_URL.lock().unwrap();
+                if current.as_ref() != Some(&leader_url) {
+                    tracing::info!("Following the leader instance at {}", leader_url);
+                    *current = Some(leader_url);
+                }
+            }
+            Err(e) => tracing::error!("Failed to acquire leadership: {}", e),
+        }
+    }
+    *LEADER_URL.lock().unwrap() = None;
+    tracing::info!("This instance is now the leader");
+    tokio::spawn(async move {
+        loop {
+            interval.tick().await;
+            match DB
+                .try_acquire_leadership(
+                    &instance.id,
+                    &instance.url,
+                    chrono::Utc::now(),
+                    LEASE_DURATION,
+                )
+                .await
+            {
+                Ok(None) => {}
+                Ok(Some(leader_url)) => {
+                    tracing::error!(
+                        "Lost leadership to the instance at {}, exiting to stop servers",
+                        leader_url
+                    );
+                    std::process::exit(1);
+                }
+                Err(e) => tracing::error!("Failed to renew leadership: {}", e),
+            }
+        }
+    });
+}
+
+/// Axum middleware forwarding API and server requests to the leader, while this instance isn't the leader.
+pub(crate) async fn middleware_forward_to_leader(
+    mut request: Request,
+    next: Next,
+) -> axum::http::Response<Body> {
+    let path = request.uri().path();
+    let Some(leader_url) =
+        leader_url().filter(|_| path.starts_with("/api/") || path.starts_with("/s/"))
+    else {
+        return next.run(request).await;
+    };
+    let url = format!(
+        "{}{}",
+        leader_url,
+        request
+            .uri()
+            .path_and_query()
+            .map(|p| p.as_str())
+            .unwrap_or("/")
+    );
+    let request_id = forwarded::request_id(&request).map(str::to_string);
+    // the leader would see this instance as the client otherwise
+    if let Some(client) = request.extensions().get::<ClientInfo>().cloned() {
+        forwarded::set_forwarded_headers(request.headers_mut(), &client);
+    }
+    match forward(&FORWARD_CLIENT, request, url).await {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::error!(
+                "Failed to forward request to the leader at {}: {}",
+                leader_url,
+                e
+            );
+            forwarded::proxy_error(StatusCode::BAD_GATEWAY, request_id.as_deref())
+        }
+    }
+}
+
+/// Sends the request to `url` with the client and streams back the response. Also used by `server_proxy`.
+pub(crate) async fn forward(
+    client: &reqwest::Client,
+    request: Request,
+    url: String,
+) -> reqwest::Result<axum::http::Response<Body>> {
+    let (parts, body) = request.into_parts();
+    let mut headers = parts.headers;
+    headers.remove(axum::http::header::HOST);
+    let response = client
+        .request(parts.method, url)
+        .headers(headers)
+        .body(reqwest::Body::wrap_stream(body.into_data_stream()))
+        .send()
+        .await?;
+    let mut builder = axum::http::Response::builder().status(response.status());
+    if let Some(headers) = builder.headers_mut() {
+        *headers = response.headers().clone();
+    }
+    // streamed, e.g. for server-sent events
+    Ok(builder
+        .body(Body::from_stream(response.bytes_stream()))
+        .unwrap())
+}
@@ -0,0 +1,181 @@
+//! The log history of a server: the most recent lines are kept in memory, older ones are spilled to disk in segments
+//! (as JSON lines, in `logs/{server_id}/`), of which only the most recent are kept. This keeps the memory per server
+//! constant, while the history can still be queried (and downloaded) beyond what is in memory.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    io::{BufRead as _, Write as _},
+    path::PathBuf,
+};
+
+use dioxus::logger::tracing;
+use tokio::sync::broadcast;
+
+use crate::common::{LogLine, ServerId};
+
+/// Lines kept in memory. When exceeded, the oldest `SEGMENT_LINES` of them are spilled to disk.
+const MEMORY_LINES: usize = 1000;
+const SEGMENT_LINES: usize = 500;
+/// Segments kept on disk; older ones are deleted.
+const MAX_SEGMENTS: usize = 20;
+
+pub(crate) struct LogBuffer {
+    dir: PathBuf,
+    recent: VecDeque<LogLine>,
+    /// Indices of the first lines of the segments on disk (which are also their file names), oldest first.
+    segments: VecDeque<u64>,
+    next_index: u64,
+    tx: broadcast::Sender<LogLine>,
+}
+
+impl LogBuffer {
+    /// Creates an empty buffer, removing any segments left over from a previous process.
+    pub(crate) fn new(server_id: &ServerId) -> Self {
+        let dir = logs_dir().join(server_id.to_string());
+        let _ = std::fs::remove_dir_all(&dir);
+        Self {
+            dir,
+            recent: VecDeque::with_capacity(MEMORY_LINES),
+            segments: VecDeque::new(),
+            next_index: 0,
+            tx: broadcast::channel(100).0,
+        }
+    }
+
+    /// Returns the index the next pushed line gets.
+    pub(crate) fn next_index(&self) -> u64 {
+        self.next_index
+    }
+
+    pub(crate) fn push(&mut self, line: LogLine) {
+        self.next_index = line.index + 1;
+        self.recent.push_back(line.clone());
+        let _ = self.tx.send(line);
+        if self.recent.len() > MEMORY_LINES {
+            let segment = self.recent.drain(..SEGMENT_LINES).collect::<Vec<_>>();
+            if let Err(e) = self.spill(&segment) {
+                tracing::warn!(
+                    "Failed to write logs to {}, dropping them: {}",
+                    self.dir.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    fn spill(&mut self, segment: &[LogLine]) -> anyhow::Result<()> {
+        let Some(first) = segment.first() else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(&self.dir)?;
+        let mut file =
+            std::io::BufWriter::new(std::fs::File::create(self.segment_path(first.index))?);
+        for line in segment {
+            serde_json::to_writer(&mut file, line)?;
+            file.write_all(b"\n")?;
+        }
+        file.flush()?;
+        self.segments.push_back(first.index);
+        while self.segments.len() > MAX_SEGMENTS {
+            if let Some(oldest) = self.segments.pop_front() {
+                let _ = std::fs::remove_file(self.segment_path(oldest));
+            }
+        }
+        Ok(())
+    }
+
+    fn segment_path(&self, first_index: u64) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", first_index))
+    }
+
+    fn read_segment(&self, first_index: u64) -> anyhow::Result<Vec<LogLine>> {
+        let file = std::io::BufReader::new(std::fs::File::open(self.segment_path(first_index))?);
+        file.lines()
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect()
+    }
+
+    /// Returns a receiver for future lines.
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<LogLine> {
+        self.tx.subscribe()
+    }
+
+    /// Returns the last (up to) `n` lines in memory.
+    pub(crate) fn last(&self, n: usize) -> Vec<LogLine> {
+        self.recent
+            .iter()
+            .skip(self.recent.len().saturating_sub(n))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the lines matching `filter` (which has to match a suffix of the history, like "after some point"),
+    /// limited to the last `tail` of them. Segments on disk are only read as far back as needed.
+    pub(crate) fn history(
+        &self,
+        filter: impl Fn(&LogLine) -> bool,
+        tail: Option<usize>,
+    ) -> Vec<LogLine> {
+        let tail = tail.unwrap_or(usize::MAX);
+        let mut lines = self
+            .recent
+            .iter()
+            .filter(|line| filter(line))
+            .cloned()
+            .collect::<VecDeque<_>>();
+        let mut complete = lines.len() < self.recent.len();
+        for first_index in self.segments.iter().rev() {
+            if complete || lines.len() >= tail {
+                break;
+            }
+            let segment = match self.read_segment(*first_index) {
+                Ok(segment) => segment,
+                Err(e) => {
+                    tracing::warn!("Failed to read logs from {}: {}", self.dir.display(), e);
+                    break;
+                }
+            };
+            for line in segment.into_iter().rev() {
+                if filter(&line) {
+                    lines.push_front(line);
+                } else {
+                    complete = true;
+                }
+            }
+        }
+        let skip = lines.len().saturating_sub(tail);
+        lines.into_iter().skip(skip).collect()
+    }
+}
+
+impl Drop for LogBuffer {
+    fn drop(&mut self) {
+        if !self.segments.is_empty() {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+/// Deletes the segments of servers whose ID isn't in `live` (the servers with a log history), which are left over
+/// when the relay didn't exit cleanly. Returns the number of servers whose segments were deleted.
+pub(crate) fn prune(live: &HashSet<String>) -> anyhow::Result<usize> {
+    let entries = match std::fs::read_dir(logs_dir()) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    let mut pruned = 0;
+    for entry in entries {
+        let entry = entry?;
+        if live.contains(entry.file_name().to_string_lossy().as_ref()) {
+            continue;
+        }
+        std::fs::remove_dir_all(entry.path())?;
+        pruned += 1;
+    }
+    Ok(pruned)
+}
+
+fn logs_dir() -> PathBuf {
+    std::env::current_dir().unwrap_or_default().join("logs")
+}
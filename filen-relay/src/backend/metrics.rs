@@ -0,0 +1,79 @@
+//! Periodic sampling of the resource usage of the relay's host (or container), shown to the admin,
+//! so that they know when the instance needs more resources.
+
+use std::sync::Mutex;
+
+use dioxus::logger::tracing;
+use sysinfo::{Disks, System};
+
+use crate::{
+    backend::server_manager::{self, SERVER_MANAGER},
+    common::SystemMetrics,
+};
+
+/// How often to sample. CPU usage is averaged over this interval.
+const SAMPLE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+static LATEST_METRICS: Mutex<Option<SystemMetrics>> = Mutex::new(None);
+
+/// The most recent sample, if one has been taken yet.
+pub(crate) fn latest() -> Option<SystemMetrics> {
+    LATEST_METRICS.lock().unwrap().clone()
+}
+
+/// Samples the metrics every `SAMPLE_INTERVAL`. Runs forever.
+pub(crate) async fn run_sampler() {
+    let mut system = System::new();
+    let mut disks = Disks::new_with_refreshed_list();
+    let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let rclone_dir = match server_manager::rclone_dir() {
+            Ok(dir) => dir,
+            Err(e) => {
+                tracing::warn!("Failed to sample system metrics: {:#}", e);
+                continue;
+            }
+        };
+        let rclone_configs_bytes = {
+            let rclone_dir = rclone_dir.clone();
+            tokio::task::spawn_blocking(move || server_manager::dir_size(&rclone_dir))
+                .await
+                .unwrap_or(0)
+        };
+
+        system.refresh_cpu_usage();
+        system.refresh_memory();
+        disks.refresh(true);
+
+        // inside a container with a memory limit, the host's memory is irrelevant
+        let (memory_used_bytes, memory_total_bytes) = match system.cgroup_limits() {
+            Some(limits) => (limits.rss, limits.total_memory),
+            None => (system.used_memory(), system.total_memory()),
+        };
+        // the disk mounted closest to the rclone directory contains it
+        let disk = disks
+            .list()
+            .iter()
+            .filter(|disk| rclone_dir.starts_with(disk.mount_point()))
+            .max_by_key(|disk| disk.mount_point().as_os_str().len());
+        let open_ports = SERVER_MANAGER
+            .get_server_states()
+            .borrow()
+            .iter()
+            .filter(|s| s.process.as_ref().is_some_and(|p| p.port.is_some()))
+            .count();
+
+        *LATEST_METRICS.lock().unwrap() = Some(SystemMetrics {
+            sampled_at: chrono::Utc::now(),
+            cpu_usage_percent: system.global_cpu_usage(),
+            cpu_count: system.cpus().len(),
+            memory_used_bytes,
+            memory_total_bytes,
+            rclone_configs_bytes,
+            disk_available_bytes: disk.map_or(0, |disk| disk.available_space()),
+            disk_total_bytes: disk.map_or(0, |disk| disk.total_space()),
+            open_ports,
+        });
+    }
+}
@@ -0,0 +1,214 @@
+//! Mock mode (`--mock`), for developing the frontend and API without Filen credentials, network access or rclone.
+//! Test accounts log in without Filen, and servers are dummies that emit fake logs.
+
+use std::{future::IntoFuture, sync::OnceLock, time::Duration};
+
+use anyhow::Context as _;
+use dioxus::server::axum;
+use tokio::{select, sync::oneshot};
+
+use crate::{
+    backend::{
+        rclone::RcloneBackend,
+        serve_backend::{
+            BoxFuture, LogOutput, RunningServer, ServeBackend, ServeStats, ServerExit, SpawnError,
+        },
+    },
+    common::{ApiError, ApiErrorCode, LogLevel, ServerProcess, ServerSpec},
+};
+
+static ENABLED: OnceLock<bool> = OnceLock::new();
+
+/// The admin's email in mock mode, unless `--admin-email` is given.
+pub(crate) const MOCK_ADMIN_EMAIL: &str = "admin@mock.local";
+/// The password of all test accounts.
+pub(crate) const MOCK_PASSWORD: &str = "mock";
+
+/// How often dummy servers emit a fake log line.
+const FAKE_LOG_INTERVAL: Duration = Duration::from_secs(5);
+const FAKE_LOGS: [&str; 4] = [
+    "INFO  : vfs cache: cleaned: objects 0 (was 0) in use 0, to upload 0, uploading 0, total size 0 (was 0)",
+    "INFO  : Documents/report.pdf: Copied (new)",
+    "DEBUG : Photos/holiday.jpg: Open: flags=O_RDONLY",
+    "INFO  : Music/song.mp3: Copied (replaced existing)",
+];
+
+pub(crate) fn enable() {
+    let _ = ENABLED.set(true);
+}
+
+pub(crate) fn is_enabled() -> bool {
+    ENABLED.get().copied().unwrap_or(false)
+}
+
+/// Checks the credentials of a test account: any email with `MOCK_PASSWORD`.
+pub(crate) fn authenticate(email: &str, password: &str) -> anyhow::Result<()> {
+    if email.trim().is_empty() || password != MOCK_PASSWORD {
+        return Err(ApiError::new(
+            ApiErrorCode::InvalidCredentials,
+            format!(
+                "Email or password wrong (the password is \"{}\" in mock mode)",
+                MOCK_PASSWORD
+            ),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Fake directories of every test account's drive.
+pub(crate) fn list_directories(path: &str) -> Vec<String> {
+    match path.trim_end_matches('/') {
+        "" => vec!["Documents", "Music", "Photos"],
+        "/Documents" => vec!["Invoices", "Projects"],
+        "/Photos" => vec!["2025", "2026"],
+        _ => vec![],
+    }
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+/// Serves dummy servers instead of rclone.
+pub(crate) struct MockBackend;
+
+impl ServeBackend for MockBackend {
+    fn spawn<'a>(
+        &'a self,
+        spec: &'a ServerSpec,
+        log_output: LogOutput,
+    ) -> BoxFuture<'a, Result<Box<dyn RunningServer>, SpawnError>> {
+        Box::pin(async move {
+            let port = port_check::free_local_ipv4_port()
+                .context("Failed to find free local port")
+                .map_err(SpawnError::Failed)?;
+            let (stop_tx, stop_rx) = oneshot::channel();
+            let spec = spec.clone();
+            let task = tokio::spawn(async move {
+                run_dummy_server(&spec, port, |line| log_output(line), stop_rx).await
+            });
+            Ok(Box::new(DummyServer {
+                port,
+                started: chrono::Utc::now(),
+                stop: Some(stop_tx),
+                task,
+            }) as Box<dyn RunningServer>)
+        })
+    }
+
+    // the dummy servers answer rclone's remote control API
+    fn probe<'a>(&'a self, process: &'a ServerProcess) -> BoxFuture<'a, bool> {
+        RcloneBackend.probe(process)
+    }
+
+    fn stats<'a>(
+        &'a self,
+        process: &'a ServerProcess,
+    ) -> BoxFuture<'a, anyhow::Result<ServeStats>> {
+        RcloneBackend.stats(process)
+    }
+
+    fn set_log_level<'a>(
+        &'a self,
+        process: &'a ServerProcess,
+        level: Option<LogLevel>,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        RcloneBackend.set_log_level(process, level)
+    }
+}
+
+struct DummyServer {
+    port: u16,
+    started: chrono::DateTime<chrono::Utc>,
+    stop: Option<oneshot::Sender<()>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl RunningServer for DummyServer {
+    fn process(&self) -> ServerProcess {
+        ServerProcess {
+            pid: None,
+            port: Some(self.port),
+            socket: None,
+            rc_port: Some(self.port),
+            started: self.started,
+        }
+    }
+
+    fn wait(&mut self) -> BoxFuture<'_, anyhow::Result<ServerExit>> {
+        Box::pin(async move {
+            // only returns by itself if it failed to start serving
+            (&mut self.task).await?;
+            Ok(ServerExit {
+                success: false,
+                code: None,
+                description: "Dummy server exited.".to_string(),
+            })
+        })
+    }
+
+    fn stop(&mut self) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            if let Some(stop) = self.stop.take() {
+                let _ = stop.send(());
+            }
+            (&mut self.task).await?;
+            Ok(())
+        })
+    }
+}
+
+/// Stands in for rclone: serves a placeholder page (and fake rclone stats) on `port`
+/// and emits fake log lines until `stop` fires.
+async fn run_dummy_server(
+    spec: &ServerSpec,
+    port: u16,
+    log_output: impl Fn(&str),
+    mut stop: oneshot::Receiver<()>,
+) {
+    let listener = match tokio::net::TcpListener::bind(("127.0.0.1", port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            log_output(&format!("ERROR : Failed to bind port {}: {}", port, e));
+            return;
+        }
+    };
+    let page = format!(
+        "Mock {} server \"{}\" serving {}",
+        spec.server_type, spec.name, spec.root
+    );
+    let router = axum::Router::new()
+        .route(
+            "/core/stats",
+            axum::routing::post(|| async {
+                axum::Json(serde_json::json!({
+                    "bytes": 1024 * 1024,
+                    "transferring": [{
+                        "name": "Documents/report.pdf",
+                        "bytes": 1024 * 1024,
+                        "size": 4 * 1024 * 1024,
+                        "speed": 512.0 * 1024.0,
+                        "percentage": 25,
+                    }],
+                }))
+            }),
+        )
+        .fallback(move || {
+            let page = page.clone();
+            async move { page }
+        });
+    let server = tokio::spawn(axum::serve(listener, router).into_future());
+    log_output(&format!(
+        "NOTICE: Serving mock {} on 127.0.0.1:{}",
+        spec.server_type, port
+    ));
+    let mut interval = tokio::time::interval(FAKE_LOG_INTERVAL);
+    let mut lines = FAKE_LOGS.iter().cycle();
+    loop {
+        select! {
+            _ = &mut stop => break,
+            _ = interval.tick() => log_output(lines.next().unwrap()),
+        }
+    }
+    server.abort();
+}
@@ -1,4 +1,4 @@
-use std::sync::Mutex;
+use std::sync::{Mutex, OnceLock, RwLock};
 
 use dioxus::prelude::*;
 use dioxus::server::axum;
@@ -9,18 +9,144 @@ use crate::{
         db::{DbViaOfflineOrRemoteFile, DB},
         server_manager::{ServerManager, SERVER_MANAGER},
     },
+    common::{MaintenanceTask, ServerId, ServerSpec},
     Args,
 };
 
+pub(crate) mod access_rules;
+pub(crate) mod activity;
+pub(crate) mod audit;
 pub(crate) mod auth;
+pub(crate) mod config;
+pub(crate) mod dashboard;
 pub(crate) mod db;
+pub(crate) mod declared;
+pub(crate) mod drain;
+pub(crate) mod drive;
+pub(crate) mod drop_box;
+pub(crate) mod email;
+pub(crate) mod ephemeral;
+pub(crate) mod export;
+pub(crate) mod exporter;
+pub(crate) mod folder_zip;
+pub(crate) mod forwarded;
+pub(crate) mod health;
+#[cfg(unix)]
+pub(crate) mod install;
+pub(crate) mod leader;
+pub(crate) mod log_buffer;
+pub(crate) mod metrics;
+pub(crate) mod mock;
+pub(crate) mod native_http;
+pub(crate) mod notifications;
+pub(crate) mod obscure;
+pub(crate) mod pwa;
+pub(crate) mod rclone;
+pub(crate) mod redact;
+pub(crate) mod scheduler;
+pub(crate) mod secrets;
+pub(crate) mod serve_backend;
 pub(crate) mod server_manager;
+pub(crate) mod server_proxy;
+pub(crate) mod sse;
+pub(crate) mod teams;
+pub(crate) mod tls;
+pub(crate) mod undo;
+pub(crate) mod updates;
+pub(crate) mod usage;
+pub(crate) mod website;
+
+static SHORT_ID_LENGTH: OnceLock<usize> = OnceLock::new();
+/// Can be changed by reloading the config, see `config::reload`.
+static PUBLIC_URL: RwLock<Option<String>> = RwLock::new(None);
+
+/// Number of characters of a server's ID used in its URL.
+pub(crate) fn short_id_length() -> usize {
+    *SHORT_ID_LENGTH.get().unwrap_or(&8)
+}
+
+/// The URL under which users reach this relay (without trailing slash), if configured.
+pub(crate) fn public_url() -> Option<String> {
+    PUBLIC_URL.read().unwrap().clone()
+}
+
+pub(crate) fn set_public_url(public_url: Option<&str>) {
+    *PUBLIC_URL.write().unwrap() = public_url.map(|url| url.trim_end_matches('/').to_string());
+}
+
+/// The URL under which this instance reaches itself, e.g. to test servers through the proxy.
+pub(crate) fn local_url() -> String {
+    // set by `dx serve`, the Dockerfile or `--listen`; dioxus::serve falls back to 8080
+    let port = std::env::var("PORT").unwrap_or("8080".to_string());
+    format!("http://127.0.0.1:{}", port)
+}
+
+/// Generates a new server ID whose short ID doesn't collide with that of any existing (or archived) server.
+pub(crate) async fn generate_server_id() -> anyhow::Result<ServerId> {
+    let mut taken = DB
+        .get_servers()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get servers: {}", e))?;
+    taken.extend(
+        DB.get_archived_servers()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to get archived servers: {}", e))?,
+    );
+    let taken = taken
+        .iter()
+        .map(|s| s.id.prefix(short_id_length()))
+        .collect::<Vec<_>>();
+    loop {
+        let id = ServerId::new();
+        if !taken.contains(&id.prefix(short_id_length())) {
+            return Ok(id);
+        }
+    }
+}
 
 pub(crate) fn serve(args: Args) {
+    redact::init_tracing();
+    SHORT_ID_LENGTH.set(args.short_id_length as usize).unwrap();
+    set_public_url(args.public_url.as_deref());
+    forwarded::init(&args.trusted_proxies).unwrap_or_else(|e| panic!("{}", e));
+    exporter::init(args.metrics_token.as_deref());
+    email::init(args.smtp_url.as_deref(), args.smtp_from.as_deref())
+        .unwrap_or_else(|e| panic!("{:#}", e));
+    tls::init(
+        args.tls_cert.as_deref().map(std::path::Path::new),
+        args.tls_key.as_deref().map(std::path::Path::new),
+    )
+    .unwrap_or_else(|e| panic!("{:#}", e));
+    let master_key = match (&args.master_key, &args.admin_auth_config) {
+        (Some(master_key), _) => Some(master_key.clone()),
+        (None, Some(auth_config)) if args.master_key_from_auth_config => {
+            Some(secrets::derive_key(auth_config))
+        }
+        _ => None,
+    };
+    secrets::init(master_key.as_deref(), args.previous_master_key.as_deref())
+        .unwrap_or_else(|e| panic!("{:#}", e));
+    if let Some(instance_url) = &args.instance_url {
+        leader::enable(instance_url);
+    }
+    declared::enable(args.servers_file.as_deref(), &args.servers);
+    if let Some(listen) = args.listen {
+        // dioxus::serve binds to the address given by these (as set by `dx serve` or the Dockerfile)
+        std::env::set_var("IP", listen.ip().to_string());
+        std::env::set_var("PORT", listen.port().to_string());
+    }
+    if args.mock {
+        mock::enable();
+    }
     dioxus::serve(move || {
         let args = args.clone();
         async move {
-            let (admin_email, db) = match (
+            let restore_backup = args.restore_backup.as_deref();
+            let (admin_email, db) = if mock::is_enabled() {
+                let db = DbViaOfflineOrRemoteFile::new_from_offline_location(args.db_dir.as_deref()).await;
+                db.map(|db| (args.admin_email.unwrap_or(mock::MOCK_ADMIN_EMAIL.to_string()), db))
+            } else {
+                match (
                     args.admin_email,
                     args.admin_password,
                     args.admin_2fa_code,
@@ -31,89 +157,149 @@ pub(crate) fn serve(args: Args) {
                         let db = DbViaOfflineOrRemoteFile::new_from_offline_location(Some(&db_dir)).await;
                         db.map(|db| (email, db))
                     }
+                    (Some(email), _, _, _, _) if args.s3_bucket.is_some() => {
+                        let config = db::S3Config {
+                            endpoint: args.s3_endpoint.unwrap(),
+                            region: args.s3_region,
+                            bucket: args.s3_bucket.unwrap(),
+                            access_key: args.s3_access_key.unwrap(),
+                            secret_key: args.s3_secret_key.unwrap(),
+                        };
+                        let db = DbViaOfflineOrRemoteFile::new_from_s3(config, restore_backup).await;
+                        db.map(|db| (email, db))
+                    }
                     (_, _, _, Some(auth_config), _) => {
-                        DbViaOfflineOrRemoteFile::new_from_auth_config(auth_config).await
+                        DbViaOfflineOrRemoteFile::new_from_auth_config(auth_config, restore_backup).await
                     }
                     (Some(email), Some(password), two_fa_code, _, _) => {
                         let db = DbViaOfflineOrRemoteFile::new_from_email_and_password(
                             email.clone(),
                             &password,
                             two_fa_code.as_deref(),
+                            restore_backup,
                         )
                         .await;
                         db.map(|db| (email, db))
                     }
                     _ => panic!(
-                        "Either admin email and local db dir, email/password or auth config must be provided"
+                        "Either admin email and local db dir, admin email and S3 bucket, email/password or auth config must be provided"
                     ),
-                }.expect("Failed to initialize database");
+                }
+            }
+            .expect("Failed to initialize database");
+            if mock::is_enabled() {
+                dioxus::logger::tracing::warn!(
+                    "Running in mock mode: any email can log in with the password \"{}\", servers are dummies",
+                    mock::MOCK_PASSWORD
+                );
+            } else {
+                tokio::spawn(updates::run_update_check());
+            }
+            if let Some(redis_url) = &args.redis_url {
+                ephemeral::connect(redis_url)
+                    .await
+                    .expect("Failed to connect to Redis");
+            }
             ADMIN_EMAIL.set(admin_email).unwrap();
             DB.init(db);
-
-            use axum_reverse_proxy::ProxyRouterExt;
+            match DB.seal_secrets().await {
+                Ok(0) => {}
+                Ok(sealed) => dioxus::logger::tracing::info!("Encrypted {} secrets with the master key", sealed),
+                // rather than starting without the servers whose secrets can't be read
+                Err(e) if args.master_key_from_auth_config => panic!(
+                    "{:#} (if --admin-auth-config was replaced, pass the master key derived from the previous one as --previous-master-key)",
+                    e
+                ),
+                Err(e) => panic!("{:#} (is the right --master-key set?)", e),
+            }
+            tokio::spawn(DB.upload_changes());
+            tokio::spawn(scheduler::run_periodically(
+                MaintenanceTask::Backup,
+                db::PENDING_CHANGES_INTERVAL,
+                || DB.upload_pending_changes(),
+            ));
+            teams::reload().await.expect("Failed to load teams");
+            if !mock::is_enabled() {
+                auth::migrate_legacy_server_credentials().await;
+            }
 
             SERVER_MANAGER.init(ServerManager::new_api());
+            tokio::spawn(metrics::run_sampler());
+            #[cfg(unix)]
+            tokio::spawn(config::reload_on_sighup());
+            #[cfg(unix)]
+            tokio::spawn(drain::drain_on_sigterm());
 
             Ok(dioxus::server::router(crate::frontend::App)
+                .route("/api/sse/servers", axum::routing::get(sse::servers))
+                .route("/api/sse/logs/{server_id}", axum::routing::get(sse::logs))
+                .route(
+                    "/api/servers/export",
+                    axum::routing::get(export::export_servers),
+                )
+                .route("/api/usage/export", axum::routing::get(usage::export_usage))
+                .route(
+                    "/api/allowedUsers/export",
+                    axum::routing::get(export::export_allowed_users),
+                )
+                .route(
+                    "/api/logs/{server_id}/download",
+                    axum::routing::get(export::export_logs),
+                )
+                .route(
+                    "/api/audit/export",
+                    axum::routing::get(audit::export_audit_log),
+                )
+                .route(
+                    "/api/servers/{id}/health",
+                    axum::routing::get(health::server_health),
+                )
+                .route("/metrics", axum::routing::get(exporter::metrics))
+                .route("/manifest.webmanifest", axum::routing::get(pwa::manifest))
+                .route("/sw.js", axum::routing::get(pwa::service_worker))
+                .route("/icon.svg", axum::routing::get(pwa::icon))
                 .layer(axum::middleware::from_fn(
                     auth::middleware_extract_session_token,
                 ))
-                .proxy_route(
-                    "/s/{id}",
-                    ServerResolver {
-                        with_rest: false,
-                        append_slash: false,
-                    },
-                )
-                .proxy_route(
-                    "/s/{id}/",
-                    ServerResolver {
-                        with_rest: false,
-                        append_slash: true,
-                    },
-                )
-                .proxy_route(
-                    "/s/{id}/{*rest}",
-                    ServerResolver {
-                        with_rest: true,
-                        append_slash: false,
-                    },
+                .layer(axum::middleware::from_fn(
+                    server_proxy::middleware_proxy_to_server,
                 ))
+                .layer(axum::middleware::from_fn(
+                    leader::middleware_forward_to_leader,
+                ))
+                .layer(axum::middleware::from_fn(forwarded::middleware_client_info)))
         }
     });
 }
 
-#[derive(Clone)]
-struct ServerResolver {
-    with_rest: bool,
-    append_slash: bool,
+/// Finds the server addressed by `id` in a `/s/{id}` path and records the access. Returns its spec and process
+/// if it's running.
+pub(crate) fn resolve_server(id: &str) -> Option<(ServerSpec, crate::common::ServerProcess)> {
+    let server_state = find_server(id)?;
+    SERVER_MANAGER.record_access(&server_state.spec.id);
+    match (server_state.status, server_state.process) {
+        (crate::common::ServerStatus::Running, Some(process)) => Some((server_state.spec, process)),
+        _ => None,
+    }
 }
 
-impl axum_reverse_proxy::TargetResolver for ServerResolver {
-    fn resolve(
-        &self,
-        _req: &axum::http::Request<axum::body::Body>,
-        params: &[(String, String)],
-    ) -> String {
-        let id = params[0].1.as_str();
-        if id.len() < 4 {
-            return "https://postman-echo.com/get/status/404".to_string();
-        }
-        let rest = if self.with_rest {
-            "/".to_string() + params.get(1).map(|(_, v)| v.as_str()).unwrap_or("")
-        } else {
-            "".to_string()
-        };
-        let server_states = SERVER_MANAGER.get_server_states().borrow().clone();
-        let Some(server_state) = server_states.iter().find(|s| s.spec.id.short() == id) else {
-            return "https://postman-echo.com/get/status/404".to_string();
-        };
-        let crate::common::ServerStatus::Running { port, .. } = server_state.status else {
-            return "https://postman-echo.com/get/status/404".to_string();
-        };
-        let extra_slash = if self.append_slash { "/" } else { "" };
-        format!("http://127.0.0.1:{}{}{}", port, rest, extra_slash)
+/// Finds the server addressed by `id` in a `/s/{id}` path, without recording an access.
+pub(crate) fn find_server(id: &str) -> Option<crate::common::ServerState<ServerSpec>> {
+    if id.len() < 4 {
+        return None;
     }
+    let server_states = SERVER_MANAGER.get_server_states().borrow().clone();
+    // besides the short ID, any longer prefix of the full ID may be used, which disambiguates
+    // colliding short IDs (e.g. after shortening them); otherwise the lowest full ID wins
+    server_states
+        .into_iter()
+        .filter(|s| {
+            s.spec
+                .id
+                .prefix(usize::MAX)
+                .starts_with(&id.replace('-', ""))
+        })
+        .min_by_key(|s| s.spec.id.to_string())
 }
 
 pub(crate) static READY_ALL_SERVERS: Mutex<bool> = Mutex::new(false);
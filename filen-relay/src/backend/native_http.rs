@@ -0,0 +1,557 @@
+//! Serves read-only HTTP servers in-process, streaming files directly from the owner's Filen drive via filen-sdk-rs
+//! instead of running an rclone process for them. Like rclone's `serve http`, it lists directories as simple HTML
+//! pages and serves files as they are. rclone's options (like the VFS cache, bandwidth limit and extra flags) don't
+//! apply, so servers only use it if they opt in with the `native` option (see `supports`).
+
+use std::{
+    collections::HashMap,
+    future::IntoFuture as _,
+    ops::Range,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, LazyLock, Mutex,
+    },
+};
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use dioxus::server::axum::{
+    self,
+    body::Body,
+    extract::{Path, State},
+    http::{header, HeaderMap, Method, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use filen_sdk_rs::{
+    auth::Client,
+    fs::{
+        dir::DirectoryType,
+        file::{enums::RemoteFileType, traits::HasFileInfo as _},
+        FSObject, HasName,
+    },
+};
+use tokio_stream::StreamExt as _;
+use tokio_util::{compat::TokioAsyncWriteCompatExt as _, io::ReaderStream};
+
+use crate::{
+    backend::{
+        auth, secrets,
+        serve_backend::{
+            BoxFuture, LogOutput, RunningServer, ServeBackend, ServeStats, ServerExit, SpawnError,
+        },
+    },
+    common::{LogLevel, ServerProcess, ServerSpec, ServerType},
+};
+
+/// How much of a file is buffered between downloading it and sending it to the client.
+const STREAM_BUFFER_SIZE: usize = 256 * 1024;
+
+/// The bytes served by each running server, by port.
+static SERVED_BYTES: LazyLock<Mutex<HashMap<u16, Arc<AtomicU64>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Whether the spec is served natively: read-only HTTP servers with a single root that opted in.
+pub(crate) fn supports(spec: &ServerSpec) -> bool {
+    spec.options.native
+        && matches!(spec.server_type, ServerType::Http)
+        && spec.read_only
+        && spec.options.additional_roots.is_empty()
+}
+
+pub(crate) struct NativeHttpBackend;
+
+impl ServeBackend for NativeHttpBackend {
+    fn spawn<'a>(
+        &'a self,
+        spec: &'a ServerSpec,
+        log_output: LogOutput,
+    ) -> BoxFuture<'a, Result<Box<dyn RunningServer>, SpawnError>> {
+        Box::pin(async move {
+            let client = auth::client_from_auth_config(&spec.filen_auth_config)
+                .map_err(SpawnError::AuthFailure)?;
+            spawn(spec, client, log_output)
+                .await
+                .map_err(SpawnError::Failed)
+        })
+    }
+
+    fn probe<'a>(&'a self, process: &'a ServerProcess) -> BoxFuture<'a, bool> {
+        Box::pin(async move {
+            match process.port {
+                Some(port) => tokio::net::TcpStream::connect(("127.0.0.1", port))
+                    .await
+                    .is_ok(),
+                None => false,
+            }
+        })
+    }
+
+    fn stats<'a>(&'a self, process: &'a ServerProcess) -> BoxFuture<'a, Result<ServeStats>> {
+        Box::pin(async move {
+            let bytes = process
+                .port
+                .and_then(|port| SERVED_BYTES.lock().unwrap().get(&port).cloned())
+                .context("The server is not running")?;
+            // files are streamed to the clients as they are downloaded, so there are no transfers to show
+            Ok(ServeStats {
+                bytes: bytes.load(Ordering::Relaxed),
+                ..Default::default()
+            })
+        })
+    }
+
+    /// Only notices and errors are logged, whatever the level.
+    fn set_log_level<'a>(
+        &'a self,
+        _process: &'a ServerProcess,
+        _level: Option<LogLevel>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[derive(Clone)]
+struct ServeState {
+    client: Arc<Client>,
+    /// The root without a trailing slash ("" for the drive's root directory).
+    root: String,
+    password: Option<String>,
+    /// Whether listings link to the folder as a ZIP (see `folder_zip`).
+    zip_downloads: bool,
+    bytes: Arc<AtomicU64>,
+    log_output: LogOutput,
+}
+
+async fn spawn(
+    spec: &ServerSpec,
+    client: Client,
+    log_output: LogOutput,
+) -> Result<Box<dyn RunningServer>> {
+    let port = port_check::free_local_ipv4_port().context("Failed to find free local port")?;
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
+        .await
+        .with_context(|| format!("Failed to bind port {}", port))?;
+    let bytes = Arc::new(AtomicU64::new(0));
+    let state = ServeState {
+        client: Arc::new(client),
+        root: spec.root.trim_end_matches('/').to_string(),
+        password: spec.password.clone(),
+        zip_downloads: spec.options.zip_downloads,
+        bytes: bytes.clone(),
+        log_output: log_output.clone(),
+    };
+    let router = axum::Router::new()
+        .route("/", axum::routing::get(serve_root))
+        .route("/{*path}", axum::routing::get(serve_path))
+        .with_state(state);
+    let task = tokio::spawn(axum::serve(listener, router).into_future());
+    SERVED_BYTES.lock().unwrap().insert(port, bytes.clone());
+    log_output(&format!(
+        "NOTICE: Serving {} read-only over HTTP on 127.0.0.1:{}",
+        spec.root, port
+    ));
+    Ok(Box::new(NativeHttpServer {
+        port,
+        started: chrono::Utc::now(),
+        bytes,
+        task,
+    }))
+}
+
+struct NativeHttpServer {
+    port: u16,
+    started: chrono::DateTime<chrono::Utc>,
+    bytes: Arc<AtomicU64>,
+    task: tokio::task::JoinHandle<std::io::Result<()>>,
+}
+
+impl RunningServer for NativeHttpServer {
+    fn process(&self) -> ServerProcess {
+        ServerProcess {
+            pid: None,
+            port: Some(self.port),
+            socket: None,
+            rc_port: None,
+            started: self.started,
+        }
+    }
+
+    fn wait(&mut self) -> BoxFuture<'_, Result<ServerExit>> {
+        Box::pin(async move {
+            // only returns by itself if accepting connections failed
+            let description = match (&mut self.task).await? {
+                Ok(()) => "Server stopped accepting connections.".to_string(),
+                Err(e) => format!("Server stopped accepting connections: {}", e),
+            };
+            Ok(ServerExit {
+                success: false,
+                code: None,
+                description,
+            })
+        })
+    }
+
+    fn stop(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            // downloads in progress are finished by their own tasks
+            self.task.abort();
+            match (&mut self.task).await {
+                Err(e) if !e.is_cancelled() => Err(e.into()),
+                _ => Ok(()),
+            }
+        })
+    }
+}
+
+impl Drop for NativeHttpServer {
+    fn drop(&mut self) {
+        self.task.abort();
+        let mut served_bytes = SERVED_BYTES.lock().unwrap();
+        // the port may have been taken by another server since
+        if served_bytes
+            .get(&self.port)
+            .is_some_and(|bytes| Arc::ptr_eq(bytes, &self.bytes))
+        {
+            served_bytes.remove(&self.port);
+        }
+    }
+}
+
+async fn serve_root(state: State<ServeState>, method: Method, headers: HeaderMap) -> Response {
+    serve(state, method, headers, String::new()).await
+}
+
+async fn serve_path(
+    state: State<ServeState>,
+    method: Method,
+    headers: HeaderMap,
+    Path(path): Path<String>,
+) -> Response {
+    serve(state, method, headers, path).await
+}
+
+async fn serve(
+    State(state): State<ServeState>,
+    method: Method,
+    headers: HeaderMap,
+    path: String,
+) -> Response {
+    if !is_authorized(&state, &headers) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Basic realm=\"Filen Relay\"")],
+        )
+            .into_response();
+    }
+    // the path is already decoded, and must not lead outside the root
+    if path
+        .split('/')
+        .any(|segment| segment == "." || segment == "..")
+    {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    match respond(&state, &method, &headers, &path).await {
+        Ok(response) => response,
+        Err(e) => {
+            (state.log_output)(&format!("ERROR : /{}: {:#}", path, e));
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Clients may use any username, like with rclone (which is started without one).
+fn is_authorized(state: &ServeState, headers: &HeaderMap) -> bool {
+    let Some(password) = &state.password else {
+        return true;
+    };
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|credentials| STANDARD.decode(credentials).ok())
+        .and_then(|credentials| String::from_utf8(credentials).ok())
+        .is_some_and(|credentials| {
+            credentials
+                .split_once(':')
+                .is_some_and(|(_, given)| secrets::matches(given, password))
+        })
+}
+
+async fn respond(
+    state: &ServeState,
+    method: &Method,
+    headers: &HeaderMap,
+    path: &str,
+) -> Result<Response> {
+    let full_path = format!("{}/{}", state.root, path);
+    let full_path = match full_path.trim_end_matches('/') {
+        "" => "/",
+        full_path => full_path,
+    };
+    let dir = match state
+        .client
+        .find_item_at_path(full_path)
+        .await
+        .context("Failed to look up path in Filen drive")?
+    {
+        Some(FSObject::File(file)) => {
+            return Ok(serve_file(
+                state,
+                method,
+                headers,
+                path,
+                RemoteFileType::File(file),
+            ))
+        }
+        Some(FSObject::Root(root)) => DirectoryType::Root(root),
+        Some(FSObject::Dir(dir)) => DirectoryType::Dir(dir),
+        _ => return Ok(StatusCode::NOT_FOUND.into_response()),
+    };
+
+    // relative links in the listing need the trailing slash
+    if !path.is_empty() && !path.ends_with('/') {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        return Ok((
+            StatusCode::MOVED_PERMANENTLY,
+            [(header::LOCATION, format!("{}/", encode_path_segment(name)))],
+        )
+            .into_response());
+    }
+    if *method == Method::HEAD {
+        return Ok(Html("").into_response());
+    }
+    let (dirs, files) = state
+        .client
+        .list_dir(&dir)
+        .await
+        .context("Failed to list directory in Filen drive")?;
+    let mut dir_names = dirs
+        .iter()
+        .filter_map(|dir| dir.name().map(|name| format!("{}/", name)))
+        .collect::<Vec<_>>();
+    let mut file_names = files
+        .iter()
+        .filter_map(|file| file.name().map(|name| name.to_string()))
+        .collect::<Vec<_>>();
+    dir_names.sort_by_key(|name| name.to_lowercase());
+    file_names.sort_by_key(|name| name.to_lowercase());
+    let title = escape_html(&format!("/{}", path));
+    let entries = dir_names
+        .iter()
+        .chain(&file_names)
+        .map(|name| {
+            let href = match name.strip_suffix('/') {
+                Some(name) => format!("{}/", encode_path_segment(name)),
+                None => encode_path_segment(name),
+            };
+            format!("<li><a href=\"{}\">{}</a></li>", href, escape_html(name))
+        })
+        .collect::<String>();
+    Ok(Html(format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{title}</title></head>\
+         <body><h1>{title}</h1>{}<ul>{}{entries}</ul></body></html>",
+        if state.zip_downloads {
+            "<p><a href=\"?download=zip\">Download as ZIP</a></p>"
+        } else {
+            ""
+        },
+        if path.is_empty() {
+            ""
+        } else {
+            "<li><a href=\"../\">../</a></li>"
+        },
+    ))
+    .into_response())
+}
+
+/// Streams the file (or the requested range of it) to the client while it is downloaded (and decrypted) in a separate
+/// task.
+fn serve_file(
+    state: &ServeState,
+    method: &Method,
+    headers: &HeaderMap,
+    path: &str,
+    file: RemoteFileType<'static>,
+) -> Response {
+    let size = file.size();
+    let range = match requested_range(headers, size) {
+        Ok(range) => range,
+        Err(()) => {
+            return (
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{}", size))],
+            )
+                .into_response()
+        }
+    };
+    let (status, content_length) = match &range {
+        Some(range) => (StatusCode::PARTIAL_CONTENT, range.end - range.start),
+        None => (StatusCode::OK, size),
+    };
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(header::CONTENT_TYPE, content_type(path).parse().unwrap());
+    response_headers.insert(header::ACCEPT_RANGES, "bytes".parse().unwrap());
+    response_headers.insert(header::CONTENT_LENGTH, content_length.into());
+    if let Some(range) = &range {
+        response_headers.insert(
+            header::CONTENT_RANGE,
+            format!("bytes {}-{}/{}", range.start, range.end - 1, size)
+                .parse()
+                .unwrap(),
+        );
+    }
+    if *method == Method::HEAD {
+        return (status, response_headers).into_response();
+    }
+    let (writer, reader) = tokio::io::duplex(STREAM_BUFFER_SIZE);
+    {
+        let client = state.client.clone();
+        let log_output = state.log_output.clone();
+        let path = path.to_string();
+        tokio::spawn(async move {
+            let mut writer = writer.compat_write();
+            // fails (and is logged) if the client disconnects before the file is complete
+            let result = match range {
+                Some(range) => {
+                    client
+                        .download_file_to_writer_for_range(
+                            &file,
+                            &mut writer,
+                            range.start,
+                            range.end,
+                            None,
+                        )
+                        .await
+                }
+                None => {
+                    client
+                        .download_file_to_writer(&file, &mut writer, None)
+                        .await
+                }
+            };
+            if let Err(e) = result {
+                log_output(&format!(
+                    "ERROR : /{}: Failed to download file: {}",
+                    path, e
+                ));
+            }
+        });
+    }
+    let bytes = state.bytes.clone();
+    let stream = ReaderStream::new(reader).map(move |chunk| {
+        if let Ok(chunk) = &chunk {
+            bytes.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+        }
+        chunk
+    });
+    (status, response_headers, Body::from_stream(stream)).into_response()
+}
+
+/// The single byte range requested by the `Range` header (`bytes=start-end`, `bytes=start-` or `bytes=-suffix`),
+/// `None` to serve the whole file (without a header, or one asking for multiple ranges, which aren't supported), or
+/// an error if the range lies outside the file.
+fn requested_range(headers: &HeaderMap, size: u64) -> Result<Option<Range<u64>>, ()> {
+    let Some(range) = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().strip_prefix("bytes="))
+        .filter(|range| !range.contains(','))
+    else {
+        return Ok(None);
+    };
+    let Some((start, end)) = range.split_once('-') else {
+        return Ok(None);
+    };
+    let range = match (start.trim().parse::<u64>(), end.trim().parse::<u64>()) {
+        (Ok(start), Ok(end)) if start <= end => start..end.saturating_add(1).min(size),
+        (Ok(start), Err(_)) if end.trim().is_empty() => start..size,
+        (Err(_), Ok(suffix)) if start.trim().is_empty() => size.saturating_sub(suffix)..size,
+        _ => return Ok(None),
+    };
+    if range.start >= range.end {
+        return Err(());
+    }
+    Ok(Some(range))
+}
+
+/// The content type of a file, by its extension (like rclone, which doesn't sniff the content either).
+fn content_type(path: &str) -> &'static str {
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "txt" | "md" | "log" => "text/plain; charset=utf-8",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "js" => "application/javascript",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "mp3" => "audio/mpeg",
+        "flac" => "audio/flac",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mkv" => "video/x-matroska",
+        _ => "application/octet-stream",
+    }
+}
+
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(value: &str, size: u64) -> Result<Option<Range<u64>>, ()> {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, value.parse().unwrap());
+        requested_range(&headers, size)
+    }
+
+    #[test]
+    fn parses_single_ranges() {
+        assert_eq!(range("bytes=0-99", 1000), Ok(Some(0..100)));
+        assert_eq!(range("bytes=900-", 1000), Ok(Some(900..1000)));
+        assert_eq!(range("bytes=-100", 1000), Ok(Some(900..1000)));
+        assert_eq!(range("bytes=900-5000", 1000), Ok(Some(900..1000)));
+    }
+
+    #[test]
+    fn serves_whole_file_for_unsupported_ranges() {
+        assert_eq!(requested_range(&HeaderMap::new(), 1000), Ok(None));
+        assert_eq!(range("bytes=0-1,5-6", 1000), Ok(None));
+        assert_eq!(range("items=0-1", 1000), Ok(None));
+        assert_eq!(range("bytes=5-1", 1000), Ok(None));
+    }
+
+    #[test]
+    fn rejects_ranges_outside_the_file() {
+        assert_eq!(range("bytes=1000-", 1000), Err(()));
+        assert_eq!(range("bytes=-0", 1000), Err(()));
+    }
+}
@@ -0,0 +1,154 @@
+//! Notifications about failed servers, exceeded quotas and logins, sent to the Discord and Slack webhooks
+//! configured by the admin (receiving all notifications) and by users (receiving those concerning them).
+
+use anyhow::{Context, Result};
+use dioxus::logger::tracing;
+
+use crate::{
+    backend::{activity, db::DB, mock},
+    common::{
+        ActivityKind, NotificationChannel, NotificationChannelKind, ServerErrorReason, ServerSpec,
+    },
+};
+
+pub(crate) enum Notification {
+    ServerFailed {
+        spec: ServerSpec,
+        reason: ServerErrorReason,
+    },
+    QuotaExceeded {
+        email: String,
+        message: String,
+    },
+    UserLoggedIn {
+        email: String,
+    },
+}
+
+impl Notification {
+    fn title(&self) -> String {
+        match self {
+            Notification::ServerFailed { spec, reason } => {
+                format!("Server \"{}\" failed: {}", spec.name, reason)
+            }
+            Notification::QuotaExceeded { .. } => "Quota exceeded".to_string(),
+            Notification::UserLoggedIn { email } => format!("{} logged in", email),
+        }
+    }
+
+    fn details(&self) -> Option<String> {
+        match self {
+            Notification::ServerFailed { spec, .. } => Some(format!(
+                "{} server of {} serving {}",
+                spec.server_type, spec.filen_email, spec.root
+            )),
+            Notification::QuotaExceeded { email, message } => {
+                Some(format!("{} (for {})", message, email))
+            }
+            Notification::UserLoggedIn { .. } => None,
+        }
+    }
+
+    /// The user concerned by the notification, who receives it through their own channels.
+    fn user(&self) -> Option<&str> {
+        match self {
+            Notification::ServerFailed { spec, .. } => Some(&spec.filen_email),
+            Notification::QuotaExceeded { email, .. } => Some(email),
+            Notification::UserLoggedIn { .. } => None,
+        }
+    }
+
+    /// The entry in the admin's activity feed.
+    fn activity(&self) -> (ActivityKind, String) {
+        match self {
+            Notification::ServerFailed { spec, reason } => (
+                ActivityKind::ServerFailed,
+                format!(
+                    "{} server \"{}\" of {} failed: {}",
+                    spec.server_type, spec.name, spec.filen_email, reason
+                ),
+            ),
+            Notification::QuotaExceeded { email, message } => (
+                ActivityKind::QuotaExceeded,
+                format!("{} exceeded a quota: {}", email, message),
+            ),
+            Notification::UserLoggedIn { email } => {
+                (ActivityKind::UserLoggedIn, format!("{} logged in", email))
+            }
+        }
+    }
+
+    /// The message in the markup of the channel kind.
+    fn format(&self, kind: NotificationChannelKind) -> String {
+        let title = match kind {
+            NotificationChannelKind::Discord => format!("**{}**", self.title()),
+            NotificationChannelKind::Slack => format!("*{}*", self.title()),
+        };
+        let link = crate::backend::public_url().map(|url| match kind {
+            NotificationChannelKind::Discord => format!("<{}>", url),
+            NotificationChannelKind::Slack => format!("<{}|Filen Relay>", url),
+        });
+        [Some(title), self.details(), link]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Sends the notification to all channels receiving it in the background, and adds it to the activity feed.
+pub(crate) fn notify(notification: Notification) {
+    let (kind, message) = notification.activity();
+    activity::record(kind, message);
+    tokio::spawn(async move {
+        let channels = match DB.get_notification_channels().await {
+            Ok(channels) => channels,
+            Err(e) => {
+                tracing::error!("Failed to get notification channels: {}", e);
+                return;
+            }
+        };
+        for channel in channels {
+            let receives = match &channel.owner {
+                None => true,
+                Some(owner) => notification.user() == Some(owner.as_str()),
+            };
+            if !receives {
+                continue;
+            }
+            if let Err(e) = send(&channel, &notification.format(channel.kind)).await {
+                tracing::warn!(
+                    "Failed to send notification to {} channel {}: {:#}",
+                    channel.kind,
+                    channel.id,
+                    e
+                );
+            }
+        }
+    });
+}
+
+async fn send(channel: &NotificationChannel, message: &str) -> Result<()> {
+    if mock::is_enabled() {
+        tracing::info!(
+            "Notification to {} channel {}: {}",
+            channel.kind,
+            channel.id,
+            message
+        );
+        return Ok(());
+    }
+    let body = match channel.kind {
+        NotificationChannelKind::Discord => serde_json::json!({ "content": message }),
+        NotificationChannelKind::Slack => serde_json::json!({ "text": message }),
+    };
+    reqwest::Client::new()
+        .post(&channel.webhook_url)
+        .json(&body)
+        .send()
+        .await
+        .context("Failed to call webhook")?
+        .error_for_status()
+        .context("Webhook rejected the notification")?;
+    Ok(())
+}
@@ -0,0 +1,32 @@
+//! The files making the web interface installable as an app (see `assets/pwa`). They are served at fixed paths
+//! rather than as bundled assets, since a service worker only controls pages below its own path.
+
+use dioxus::server::axum::{http::header, response::IntoResponse};
+
+const MANIFEST: &str = include_str!("../../assets/pwa/manifest.webmanifest");
+const SERVICE_WORKER: &str = include_str!("../../assets/pwa/sw.js");
+const ICON: &str = include_str!("../../assets/pwa/icon.svg");
+
+/// `GET /manifest.webmanifest`
+pub(crate) async fn manifest() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "application/manifest+json")],
+        MANIFEST,
+    )
+}
+
+/// `GET /sw.js`: not cached by the browser, so that changes to the service worker are picked up right away.
+pub(crate) async fn service_worker() -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, "text/javascript"),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        SERVICE_WORKER,
+    )
+}
+
+/// `GET /icon.svg`
+pub(crate) async fn icon() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "image/svg+xml")], ICON)
+}
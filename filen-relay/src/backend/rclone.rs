@@ -0,0 +1,347 @@
+//! Serves servers with rclone processes (in the build of rclone with Filen support), controlled via rclone's
+//! remote control API. The API only listens on localhost and requires a password generated on every start of the
+//! relay, so that other local users can't control the processes (see `rc_request`).
+//!
+//! The PID of each process is written to `rclone.pid` in its config directory (`rclone_configs/server_{id}`) while
+//! it runs, so that processes left behind by a crashed relay can be killed on the next start (see
+//! `kill_orphaned_processes`).
+
+use std::{sync::LazyLock, time::Duration};
+
+use aes_gcm::aead::{rand_core::RngCore as _, OsRng};
+use anyhow::{Context, Result};
+use dioxus::logger::tracing;
+use filen_rclone_wrapper::rclone_installation::RcloneInstallationConfig;
+use filen_rclone_wrapper::serve::BasicServerOptions;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::BufReader;
+use tokio::process::Child;
+use tokio::task::JoinHandle;
+
+use crate::backend::auth;
+use crate::backend::serve_backend::BoxFuture;
+use crate::backend::serve_backend::LogOutput;
+use crate::backend::serve_backend::RunningServer;
+use crate::backend::serve_backend::ServeBackend;
+use crate::backend::serve_backend::ServeStats;
+use crate::backend::serve_backend::ServerExit;
+use crate::backend::serve_backend::SpawnError;
+use crate::backend::server_manager;
+use crate::common::LogLevel;
+use crate::common::ServerId;
+use crate::common::ServerProcess;
+use crate::common::ServerSpec;
+use crate::common::ServerType;
+
+/// Name of the file in a server's config directory holding the PID of its rclone process.
+const PID_FILE: &str = "rclone.pid";
+/// How long to wait for the rest of the output of an exited process, e.g. if a child of it keeps the pipes open.
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+/// User of the remote control API of the rclone processes.
+const RC_USER: &str = "filen-relay";
+/// Password of the remote control API of the rclone processes, random for every run of the relay.
+static RC_PASSWORD: LazyLock<String> = LazyLock::new(|| {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+});
+
+pub(crate) struct RcloneBackend;
+
+impl ServeBackend for RcloneBackend {
+    fn spawn<'a>(
+        &'a self,
+        spec: &'a ServerSpec,
+        log_output: LogOutput,
+    ) -> BoxFuture<'a, Result<Box<dyn RunningServer>, SpawnError>> {
+        Box::pin(async move {
+            let client = auth::client_from_auth_config(&spec.filen_auth_config)
+                .map_err(SpawnError::AuthFailure)?;
+            spawn(spec, &client, log_output)
+                .await
+                .map_err(SpawnError::Failed)
+        })
+    }
+
+    /// Checks that the remote control API answers and that the server accepts connections on its port (if it has one).
+    fn probe<'a>(&'a self, process: &'a ServerProcess) -> BoxFuture<'a, bool> {
+        Box::pin(async move {
+            let Some(rc_port) = process.rc_port else {
+                return false;
+            };
+            let rc = rc_request(rc_port, "rc/noop")
+                .send()
+                .await
+                .is_ok_and(|response| response.status().is_success());
+            let port = match process.port {
+                Some(port) => tokio::net::TcpStream::connect(("127.0.0.1", port))
+                    .await
+                    .is_ok(),
+                None => true,
+            };
+            rc && port
+        })
+    }
+
+    fn stats<'a>(&'a self, process: &'a ServerProcess) -> BoxFuture<'a, Result<ServeStats>> {
+        Box::pin(async move {
+            let rc_port = process
+                .rc_port
+                .context("The server has no remote control API")?;
+            query_stats(rc_port).await
+        })
+    }
+
+    fn set_log_level<'a>(
+        &'a self,
+        process: &'a ServerProcess,
+        level: Option<LogLevel>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let rc_port = process
+                .rc_port
+                .context("The server has no remote control API")?;
+            // notice is rclone's default level
+            let level = level.unwrap_or(LogLevel::Notice).to_string().to_uppercase();
+            rc_request(rc_port, "options/set")
+                .json(&serde_json::json!({ "main": { "LogLevel": level } }))
+                .send()
+                .await
+                .context("Failed to set rclone log level")?
+                .error_for_status()
+                .context("Failed to set rclone log level")?;
+            Ok(())
+        })
+    }
+}
+
+async fn spawn(
+    spec: &ServerSpec,
+    client: &filen_sdk_rs::auth::Client,
+    log_output: LogOutput,
+) -> Result<Box<dyn RunningServer>> {
+    let config_dir = server_manager::rclone_dir()?;
+    let (address, port, socket) = match server_manager::socket_path(spec)? {
+        Some(socket) => (
+            format!("unix://{}", socket.display()),
+            None,
+            Some(socket.display().to_string()),
+        ),
+        None => {
+            let port =
+                port_check::free_local_ipv4_port().context("Failed to find free local port")?;
+            match spec.server_type {
+                // clients connect to FTP and SFTP servers directly
+                ServerType::Ftp | ServerType::Sftp => (format!(":{}", port), Some(port), None),
+                // others are only reached through the relay's proxy, which applies e.g. their access rules
+                ServerType::Http | ServerType::Webdav | ServerType::S3 => {
+                    (format!("127.0.0.1:{}", port), Some(port), None)
+                }
+            }
+        }
+    };
+    let rc_port = port_check::free_local_ipv4_port().context("Failed to find free local port")?;
+    let mut args = server_manager::rclone_args(spec, server_manager::cache_quota_share().await?)?;
+    args.extend([
+        "--rc".to_string(),
+        format!("--rc-addr=127.0.0.1:{}", rc_port),
+        format!("--rc-user={}", RC_USER),
+        format!("--rc-pass={}", *RC_PASSWORD),
+    ]);
+    let server_config_dir = config_dir.join(format!("server_{}", spec.id));
+    let mut server = filen_rclone_wrapper::serve::start_basic_server(
+        client,
+        &RcloneInstallationConfig {
+            rclone_binary_dir: config_dir.clone(),
+            config_dir: server_config_dir.clone(),
+        },
+        match spec.server_type {
+            ServerType::Http => "http",
+            ServerType::Webdav => "webdav",
+            ServerType::S3 => "s3",
+            ServerType::Ftp => "ftp",
+            ServerType::Sftp => "sftp",
+        },
+        BasicServerOptions {
+            address,
+            root: Some(server_manager::served_root(spec)),
+            user: None,
+            password: spec.password.clone(),
+            read_only: spec.read_only,
+            cache_size: None,
+            transfers: None,
+        },
+        args,
+    )
+    .await
+    .context("Failed to start rclone server")?;
+    let pid_file = server_config_dir.join(PID_FILE);
+    if let Some(pid) = server.process.id() {
+        if let Err(e) = std::fs::write(&pid_file, pid.to_string()) {
+            tracing::warn!("Failed to write PID file {}: {}", pid_file.display(), e);
+        }
+    }
+
+    // handle logs
+    let stdout_reader = {
+        let log_output = log_output.clone();
+        let process_stdout = server.process.stdout.take().unwrap();
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(process_stdout).lines();
+            while let Ok(Some(line)) = reader.next_line().await {
+                log_output(&line);
+            }
+        })
+    };
+    let stderr_reader = {
+        let process_stderr = server.process.stderr.take().unwrap();
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(process_stderr).lines();
+            while let Ok(Some(line)) = reader.next_line().await {
+                log_output(&line);
+            }
+        })
+    };
+
+    Ok(Box::new(RcloneServer {
+        process: ServerProcess {
+            pid: server.process.id(),
+            port,
+            socket,
+            rc_port: Some(rc_port),
+            started: chrono::Utc::now(),
+        },
+        server,
+        child: |server| &mut server.process,
+        pid_file,
+        output_readers: vec![stdout_reader, stderr_reader],
+    }))
+}
+
+/// A running rclone process. The server handle returned by the wrapper is kept as it is, reaching its process
+/// through `child`.
+struct RcloneServer<S> {
+    process: ServerProcess,
+    server: S,
+    child: fn(&mut S) -> &mut Child,
+    pid_file: std::path::PathBuf,
+    /// Tasks logging the output of the process, which end once all of it has been read.
+    output_readers: Vec<JoinHandle<()>>,
+}
+
+impl<S> RcloneServer<S> {
+    fn remove_pid_file(&self) {
+        let _ = std::fs::remove_file(&self.pid_file);
+    }
+}
+
+impl<S: Send> RunningServer for RcloneServer<S> {
+    fn process(&self) -> ServerProcess {
+        self.process.clone()
+    }
+
+    fn wait(&mut self) -> BoxFuture<'_, Result<ServerExit>> {
+        Box::pin(async move {
+            let status = (self.child)(&mut self.server)
+                .wait()
+                .await
+                .context("Server process wait failed")?;
+            self.remove_pid_file();
+            // the last lines (e.g. the error that made it exit) are needed to classify the failure
+            let readers = std::mem::take(&mut self.output_readers);
+            let _ = tokio::time::timeout(OUTPUT_DRAIN_TIMEOUT, async {
+                for reader in readers {
+                    let _ = reader.await;
+                }
+            })
+            .await;
+            Ok(ServerExit {
+                success: status.success(),
+                code: status.code(),
+                description: format!("Server process exited with status: {}", status),
+            })
+        })
+    }
+
+    fn stop(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            (self.child)(&mut self.server).kill().await?;
+            self.remove_pid_file();
+            Ok(())
+        })
+    }
+}
+
+/// A call of `method` of the remote control API of the rclone process listening on `rc_port`.
+fn rc_request(rc_port: u16, method: &str) -> reqwest::RequestBuilder {
+    reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{}/{}", rc_port, method))
+        .basic_auth(RC_USER, Some(&*RC_PASSWORD))
+}
+
+/// Queries the transfer statistics (`core/stats`) of a running server via its rclone remote control API.
+async fn query_stats(rc_port: u16) -> Result<ServeStats> {
+    rc_request(rc_port, "core/stats")
+        .send()
+        .await
+        .context("Failed to query rclone stats")?
+        .error_for_status()
+        .context("Failed to query rclone stats")?
+        .json::<ServeStats>()
+        .await
+        .context("Failed to parse rclone stats")
+}
+
+/// Kills the rclone processes left behind by a previous run of the relay (e.g. one that crashed), which would
+/// otherwise keep serving, possibly on ports now assigned to other servers. `servers` are the IDs of the servers in
+/// the database, which are started again afterwards; processes of other servers are only logged differently.
+/// Returns the number of processes killed.
+pub(crate) fn kill_orphaned_processes(servers: &[ServerId]) -> Result<usize> {
+    let entries = match std::fs::read_dir(server_manager::rclone_dir()?) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e).context("Failed to list rclone_configs"),
+    };
+    let mut system = sysinfo::System::new();
+    let mut killed = 0;
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(id) = name.strip_prefix("server_") else {
+            continue;
+        };
+        let pid_file = entry.path().join(PID_FILE);
+        let Ok(pid) = std::fs::read_to_string(&pid_file) else {
+            continue;
+        };
+        let _ = std::fs::remove_file(&pid_file);
+        let Ok(pid) = pid.trim().parse::<u32>() else {
+            continue;
+        };
+        let pid = sysinfo::Pid::from_u32(pid);
+        system.refresh_processes(sysinfo::ProcessesToUpdate::Some(&[pid]), true);
+        // the PID may have been reused by another process since
+        let Some(process) = system
+            .process(pid)
+            .filter(|process| process.name().to_string_lossy().starts_with("rclone"))
+        else {
+            continue;
+        };
+        if servers.iter().any(|server| server.to_string() == id) {
+            tracing::warn!("Killing orphaned rclone process {} of server {}", pid, id);
+        } else {
+            tracing::warn!(
+                "Killing orphaned rclone process {} of removed server {}",
+                pid,
+                id
+            );
+        }
+        if process.kill() {
+            process.wait();
+            killed += 1;
+        } else {
+            tracing::error!("Failed to kill orphaned rclone process {}", pid);
+        }
+    }
+    Ok(killed)
+}
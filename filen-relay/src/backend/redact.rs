@@ -0,0 +1,230 @@
+//! Scrubbing of secrets from log lines before they are stored in `Logs` (and thus streamed to browsers) or traced.
+//! Everything traced goes through `init_tracing`'s subscriber, which scrubs the values of well-known secret keys
+//! from each line, whichever module logged it.
+
+use std::io::Write;
+
+pub(crate) const REDACTED: &str = "[redacted]";
+
+/// Keys whose values are secret, e.g. in "--pass=...", "secret_access_key = ..." or "Cookie: Session=...".
+/// Matched case-insensitively at the start of a word, so "pass" also covers "password" and "passphrase".
+/// Values follow "=", or a space after flags. A colon only separates values of quoted keys (as in JSON) and of
+/// `HEADER_KEYS`, as it mostly ends prose like "Failed to get session: ..." otherwise.
+const SECRET_KEYS: &[&str] = &[
+    "pass",
+    "secret",
+    "auth-key",
+    "auth_key",
+    "apikey",
+    "api-key",
+    "api_key",
+    "authorization",
+    "session",
+    "token",
+];
+
+/// Keys whose values follow a colon, as in HTTP headers (e.g. "Authorization: Bearer ...").
+const HEADER_KEYS: &[&str] = &["authorization"];
+
+/// Words starting like a secret key, but without secret values (e.g. rclone's "--passive-port").
+const NOT_SECRET_KEYS: &[&str] = &["passive"];
+
+/// Secrets shorter than this aren't replaced verbatim, as that would garble unrelated text.
+const MIN_SECRET_LENGTH: usize = 4;
+
+/// Replaces the given `secrets` (e.g. the server's password) wherever they appear verbatim,
+/// as well as the values of well-known secret keys.
+pub(crate) fn redact(line: &str, secrets: &[&str]) -> String {
+    let mut line = line.to_string();
+    for secret in secrets {
+        if secret.len() >= MIN_SECRET_LENGTH {
+            line = line.replace(secret, REDACTED);
+        }
+    }
+    redact_secret_values(&line)
+}
+
+/// Installs the tracing subscriber (before dioxus would install its own), which redacts what is traced.
+pub(crate) fn init_tracing() {
+    let _ = tracing_subscriber::fmt()
+        .with_max_level(dioxus::logger::tracing::Level::INFO)
+        .with_ansi(false)
+        .with_writer(|| RedactingWriter(std::io::stdout()))
+        .try_init();
+}
+
+/// Writes the formatted events (each one written as a whole) with secret values redacted.
+struct RedactingWriter<W>(W);
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .write_all(redact_secret_values(&String::from_utf8_lossy(buf)).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+fn redact_secret_values(line: &str) -> String {
+    // same byte offsets as `line`, as only ASCII characters are lowercased
+    let lowercase = line.to_ascii_lowercase().into_bytes();
+    let bytes = line.as_bytes();
+    let is_word_char = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || b == b'-';
+    let mut result = String::with_capacity(line.len());
+    let mut copied = 0;
+    let mut i = 0;
+    while i < line.len() {
+        let Some(key) = SECRET_KEYS.iter().find(|key| {
+            lowercase[i..].starts_with(key.as_bytes())
+                && (i == 0 || !bytes[i - 1].is_ascii_alphanumeric())
+                && !NOT_SECRET_KEYS
+                    .iter()
+                    .any(|word| lowercase[i..].starts_with(word.as_bytes()))
+        }) else {
+            i += 1;
+            continue;
+        };
+        let is_flag = i > 0 && bytes[i - 1] == b'-';
+        // skip the rest of the key (e.g. "word" in "password"), a closing quote and the separator
+        let mut j = i + key.len();
+        while j < bytes.len() && is_word_char(bytes[j]) {
+            j += 1;
+        }
+        let is_header = HEADER_KEYS
+            .iter()
+            .any(|key| lowercase[i..j] == *key.as_bytes());
+        let is_quoted = j < bytes.len() && (bytes[j] == b'"' || bytes[j] == b'\'');
+        if is_quoted {
+            j += 1;
+        }
+        let separator_start = j;
+        while j < bytes.len() && bytes[j] == b' ' {
+            j += 1;
+        }
+        if j < bytes.len() && (bytes[j] == b'=' || (bytes[j] == b':' && (is_header || is_quoted))) {
+            j += 1;
+            while j < bytes.len() && bytes[j] == b' ' {
+                j += 1;
+            }
+        } else if !(is_flag && j > separator_start) {
+            // not followed by a value
+            i = j;
+            continue;
+        }
+        if j < bytes.len() && (bytes[j] == b'"' || bytes[j] == b'\'') {
+            j += 1;
+        }
+        let value_start = j;
+        // flag values may contain delimiters, e.g. "--auth-key access_key,secret_key"
+        let mut value_end = find_value_end(bytes, value_start, is_flag);
+        // e.g. "Authorization: Bearer ..."
+        let scheme = &lowercase[value_start..value_end];
+        if (scheme == b"bearer" || scheme == b"basic") && value_end < bytes.len() {
+            value_end = find_value_end(bytes, value_end + 1, false);
+        }
+        if value_end > value_start {
+            result.push_str(&line[copied..value_start]);
+            result.push_str(REDACTED);
+            copied = value_end;
+        }
+        i = value_end.max(i + 1);
+    }
+    result.push_str(&line[copied..]);
+    result
+}
+
+/// The end of a value starting at `start`, which runs until whitespace, a quote or (unless `is_flag`) a delimiter.
+fn find_value_end(bytes: &[u8], start: usize, is_flag: bool) -> usize {
+    let mut end = start;
+    while end < bytes.len()
+        && !matches!(bytes[end], b' ' | b'\t' | b'"' | b'\'')
+        && (is_flag || !matches!(bytes[end], b',' | b';' | b'&'))
+    {
+        end += 1;
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_given_secrets() {
+        assert_eq!(
+            redact("connecting with hunter22", &["hunter22"]),
+            "connecting with [redacted]"
+        );
+        // too short to be replaced verbatim
+        assert_eq!(redact("a b c", &["b"]), "a b c");
+    }
+
+    #[test]
+    fn redacts_values_of_secret_keys() {
+        assert_eq!(
+            redact("--pass=abc123 --port=80", &[]),
+            "--pass=[redacted] --port=80"
+        );
+        assert_eq!(redact("--password abc123", &[]), "--password [redacted]");
+        assert_eq!(
+            redact("secret_access_key = \"xyz\"", &[]),
+            "secret_access_key = \"[redacted]\""
+        );
+        assert_eq!(
+            redact("Cookie: Session=abc; Path=/", &[]),
+            "Cookie: Session=[redacted]; Path=/"
+        );
+        assert_eq!(
+            redact("Authorization: Bearer abc.def", &[]),
+            "Authorization: [redacted]"
+        );
+        assert_eq!(
+            redact("--auth-key access,secret next", &[]),
+            "--auth-key [redacted] next"
+        );
+    }
+
+    #[test]
+    fn keeps_similar_keys_without_secret_values() {
+        assert_eq!(
+            redact("--passive-port=30000-30010", &[]),
+            "--passive-port=30000-30010"
+        );
+        assert_eq!(
+            redact("password reset requested", &[]),
+            "password reset requested"
+        );
+        assert_eq!(redact("bypass=1", &[]), "bypass=1");
+    }
+
+    #[test]
+    fn keeps_prose_ending_in_a_colon() {
+        assert_eq!(
+            redact("Failed to get session: connection refused", &[]),
+            "Failed to get session: connection refused"
+        );
+        assert_eq!(
+            redact("Failed to obscure server password: invalid input", &[]),
+            "Failed to obscure server password: invalid input"
+        );
+        assert_eq!(
+            redact("{\"token\": \"abc123\", \"port\": 80}", &[]),
+            "{\"token\": \"[redacted]\", \"port\": 80}"
+        );
+    }
+
+    #[test]
+    fn redacting_writer_redacts_each_write() {
+        let mut output = Vec::new();
+        RedactingWriter(&mut output)
+            .write_all(b"INFO token=abc123 done\n")
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "INFO token=[redacted] done\n"
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Runs the periodic maintenance tasks (pausing unused servers, retrying backups, pruning logs, evicting caches and
+//! checking for updates), each in its own loop started by the subsystem it belongs to (see `run_periodically`).
+//! Runs are spread out with jitter, so that tasks with the same interval (and relay instances started together)
+//! don't all run at once. The admin can turn tasks off in the settings and sees their latest results.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use dioxus::logger::tracing;
+use strum::IntoEnumIterator as _;
+
+use crate::{
+    backend::db::DB,
+    common::{MaintenanceTask, MaintenanceTaskRun, MaintenanceTaskStatus, Settings},
+};
+
+/// Intervals are varied by up to this fraction in either direction.
+const JITTER: f64 = 0.1;
+/// Tasks first run after a random delay of up to this (or their interval, if shorter).
+const MAX_STARTUP_DELAY: Duration = Duration::from_secs(5 * 60);
+
+struct Schedule {
+    interval: Duration,
+    next_run: Option<DateTime<Utc>>,
+    last_run: Option<MaintenanceTaskRun>,
+}
+
+/// The tasks scheduled on this instance.
+static SCHEDULES: LazyLock<Mutex<HashMap<MaintenanceTask, Schedule>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Runs `run` about every `interval`, unless the admin turned the task off. It returns a summary of what was done,
+/// which is shown to the admin. Runs forever.
+pub(crate) async fn run_periodically<F, Fut>(task: MaintenanceTask, interval: Duration, mut run: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<String>>,
+{
+    SCHEDULES.lock().unwrap().insert(
+        task,
+        Schedule {
+            interval,
+            next_run: None,
+            last_run: None,
+        },
+    );
+    let mut delay = interval.min(MAX_STARTUP_DELAY).mul_f64(random());
+    loop {
+        set_next_run(
+            task,
+            Utc::now() + chrono::Duration::from_std(delay).unwrap_or_default(),
+        );
+        tokio::time::sleep(delay).await;
+        delay = interval.mul_f64(1.0 + JITTER * (2.0 * random() - 1.0));
+        match DB.get_settings().await {
+            Ok(settings) if settings.disabled_maintenance_tasks.contains(&task) => continue,
+            Ok(_) => {}
+            // better run it than skip it
+            Err(e) => tracing::warn!("Failed to get settings: {}", e),
+        }
+        let started = Utc::now();
+        let result = run().await.map_err(|e| {
+            tracing::error!("Maintenance task \"{}\" failed: {:#}", task, e);
+            format!("{:#}", e)
+        });
+        let finished = Utc::now();
+        if let Some(schedule) = SCHEDULES.lock().unwrap().get_mut(&task) {
+            schedule.last_run = Some(MaintenanceTaskRun {
+                started,
+                duration_ms: (finished - started).num_milliseconds().max(0) as u64,
+                result,
+            });
+        }
+    }
+}
+
+fn set_next_run(task: MaintenanceTask, next_run: DateTime<Utc>) {
+    if let Some(schedule) = SCHEDULES.lock().unwrap().get_mut(&task) {
+        schedule.next_run = Some(next_run);
+    }
+}
+
+/// A random number in [0, 1). The first (random) 48 bits of a v4 UUID suffice for jitter.
+fn random() -> f64 {
+    (uuid::Uuid::new_v4().as_u128() >> 80) as f64 / (1u64 << 48) as f64
+}
+
+/// The tasks scheduled on this instance, in the order of `MaintenanceTask`.
+pub(crate) fn statuses(settings: &Settings) -> Vec<MaintenanceTaskStatus> {
+    let schedules = SCHEDULES.lock().unwrap();
+    MaintenanceTask::iter()
+        .filter_map(|task| {
+            let schedule = schedules.get(&task)?;
+            let enabled = !settings.disabled_maintenance_tasks.contains(&task);
+            Some(MaintenanceTaskStatus {
+                task,
+                enabled,
+                interval_seconds: schedule.interval.as_secs(),
+                // it would be skipped
+                next_run: schedule.next_run.filter(|_| enabled),
+                last_run: schedule.last_run.clone(),
+            })
+        })
+        .collect()
+}
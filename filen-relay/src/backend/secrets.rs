@@ -0,0 +1,158 @@
+//! Encryption of the sensitive columns of the database (the Filen auth configs of servers and sessions, server
+//! passwords and notification webhook URLs) with a master key from `--master-key`, using AES-256-GCM.
+//! Without a master key, these are stored as they are (server passwords only obscured, see `obscure`).
+//!
+//! To rotate the key, restart with the new key as `--master-key` and the old one as `--previous-master-key`:
+//! all secrets are then re-encrypted with the new key on startup (see `db::seal_secrets`), after which the previous
+//! key isn't needed anymore.
+//!
+//! Alternatively, the key can be derived from the admin's auth config (`--master-key-from-auth-config`), so that
+//! deployments configured with only an auth config don't need another secret. Replacing the auth config then changes
+//! the key: rotate it by passing the key derived from the old auth config (see `filen-relay derive-master-key`) as
+//! `--previous-master-key`. An auth config rotated at runtime (see `db::rotate_admin_auth_config`) doesn't change the
+//! key, which is always derived from the one passed on startup.
+
+use std::sync::RwLock;
+
+use aes_gcm::{
+    aead::{Aead as _, AeadCore as _, OsRng},
+    Aes256Gcm, KeyInit as _, Nonce,
+};
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+/// Prefix of encrypted values, which distinguishes them from values stored without a master key.
+const PREFIX: &str = "sealed:v1:";
+const NONCE_LENGTH: usize = 12;
+/// Separates keys derived from the auth config from other uses of it.
+const DERIVATION_INFO: &[u8] = b"filen-relay secrets master key v1";
+
+struct Keys {
+    current: Option<Aes256Gcm>,
+    previous: Option<Aes256Gcm>,
+}
+
+static KEYS: RwLock<Keys> = RwLock::new(Keys {
+    current: None,
+    previous: None,
+});
+
+/// Sets the master key (and the previous one, while rotating), each a base64-encoded 32-byte key.
+pub(crate) fn init(master_key: Option<&str>, previous_master_key: Option<&str>) -> Result<()> {
+    let current = master_key
+        .map(|key| parse_key(key).context("Invalid master key"))
+        .transpose()?;
+    let previous = previous_master_key
+        .map(|key| parse_key(key).context("Invalid previous master key"))
+        .transpose()?;
+    if current.is_none() && previous.is_some() {
+        bail!("A previous master key requires a master key to rotate to");
+    }
+    *KEYS.write().unwrap() = Keys { current, previous };
+    Ok(())
+}
+
+/// Derives a master key (base64-encoded, like `--master-key`) from the admin's auth config, which contains the
+/// account's key material, using HKDF-SHA256.
+pub(crate) fn derive_key(auth_config: &str) -> String {
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(None, auth_config.trim().as_bytes())
+        .expand(DERIVATION_INFO, &mut key)
+        .expect("32 bytes are a valid HKDF-SHA256 output length");
+    STANDARD.encode(key)
+}
+
+/// Hashes a value (hex-encoded SHA-256), e.g. to recognize it again without storing it.
+pub(crate) fn hash(value: &str) -> String {
+    use sha2::Digest as _;
+    Sha256::digest(value.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Compares a given secret (e.g. a password or token) to the expected one in constant time, so that the time taken
+/// doesn't reveal how much of it matched. Both are hashed first, so their lengths don't matter either.
+pub(crate) fn matches(given: &str, expected: &str) -> bool {
+    use sha2::Digest as _;
+    Sha256::digest(given.as_bytes())
+        .iter()
+        .zip(Sha256::digest(expected.as_bytes()).iter())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+fn parse_key(key: &str) -> Result<Aes256Gcm> {
+    let key = STANDARD
+        .decode(key.trim())
+        .context("Key must be base64-encoded (e.g. generated with `openssl rand -base64 32`)")?;
+    Aes256Gcm::new_from_slice(&key).map_err(|_| anyhow::anyhow!("Key must be 32 bytes long"))
+}
+
+/// Encrypts a value for storing it, unless no master key is set. Empty values are stored as they are.
+pub(crate) fn seal(plaintext: &str) -> String {
+    let keys = KEYS.read().unwrap();
+    let Some(key) = &keys.current else {
+        return plaintext.to_string();
+    };
+    if plaintext.is_empty() {
+        return String::new();
+    }
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = key
+        .encrypt(&nonce, plaintext.as_bytes())
+        .expect("Failed to encrypt secret");
+    format!(
+        "{}{}",
+        PREFIX,
+        STANDARD.encode([nonce.as_slice(), &ciphertext].concat())
+    )
+}
+
+/// Decrypts a stored value (with the master key, or the previous one). Values stored without a master key are
+/// returned as they are.
+pub(crate) fn open(stored: &str) -> Result<String> {
+    let Some(encoded) = stored.strip_prefix(PREFIX) else {
+        return Ok(stored.to_string());
+    };
+    let keys = KEYS.read().unwrap();
+    if keys.current.is_none() {
+        bail!("The database contains encrypted secrets, but no master key is set");
+    }
+    let data = STANDARD
+        .decode(encoded)
+        .context("Invalid encrypted secret")?;
+    if data.len() < NONCE_LENGTH {
+        bail!("Encrypted secret too short");
+    }
+    let (nonce, ciphertext) = data.split_at(NONCE_LENGTH);
+    let plaintext = [&keys.current, &keys.previous]
+        .into_iter()
+        .flatten()
+        .find_map(|key| key.decrypt(Nonce::from_slice(nonce), ciphertext).ok())
+        .context("Failed to decrypt secret (wrong master key?)")?;
+    String::from_utf8(plaintext).context("Invalid encrypted secret")
+}
+
+/// Whether a stored value isn't encrypted with the current master key yet (while one is set),
+/// i.e. it was stored without a master key or with the previous one.
+pub(crate) fn needs_sealing(stored: &str) -> bool {
+    let keys = KEYS.read().unwrap();
+    let Some(key) = &keys.current else {
+        return false;
+    };
+    if stored.is_empty() {
+        return false;
+    }
+    let Some(data) = stored
+        .strip_prefix(PREFIX)
+        .and_then(|encoded| STANDARD.decode(encoded).ok())
+        .filter(|data| data.len() >= NONCE_LENGTH)
+    else {
+        return true;
+    };
+    let (nonce, ciphertext) = data.split_at(NONCE_LENGTH);
+    key.decrypt(Nonce::from_slice(nonce), ciphertext).is_err()
+}
@@ -0,0 +1,87 @@
+//! The ways a server can be served. A `ServeBackend` starts serving a spec and can probe the running server and
+//! query its statistics, while the `ServerManager` handles the states, logs and restarts of servers the same way
+//! for every backend. rclone (see `rclone::RcloneBackend`) serves all server types, while read-only HTTP servers can
+//! opt into being served in-process (see `native_http`). The dummy servers of mock mode stand in for both.
+
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::{
+    backend::{mock, native_http, rclone},
+    common::{LogLevel, ServerProcess, ServerSpec, Transfer},
+};
+
+pub(crate) type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Receives each line a server outputs (shown in its logs).
+pub(crate) type LogOutput = Arc<dyn Fn(&str) + Send + Sync>;
+
+pub(crate) enum SpawnError {
+    /// The owner's stored Filen credentials don't work (anymore).
+    AuthFailure(anyhow::Error),
+    Failed(anyhow::Error),
+}
+
+/// How a server exited on its own (rather than being stopped).
+pub(crate) struct ServerExit {
+    pub success: bool,
+    /// The exit code, if the server is a process that has one.
+    pub code: Option<i32>,
+    /// Shown in the server's logs, e.g. "Server process exited with status: exit status: 1".
+    pub description: String,
+}
+
+/// Transfer statistics since the server started.
+#[derive(Default, Deserialize)]
+pub(crate) struct ServeStats {
+    #[serde(default)]
+    pub bytes: u64,
+    /// The files currently being transferred.
+    #[serde(default)]
+    pub transferring: Vec<Transfer>,
+}
+
+pub(crate) trait ServeBackend: Send + Sync {
+    /// Starts serving the spec, passing the lines the server outputs to `log_output`.
+    fn spawn<'a>(
+        &'a self,
+        spec: &'a ServerSpec,
+        log_output: LogOutput,
+    ) -> BoxFuture<'a, Result<Box<dyn RunningServer>, SpawnError>>;
+
+    /// Whether the running server responds. Probes that take too long are counted as failed by the caller.
+    fn probe<'a>(&'a self, process: &'a ServerProcess) -> BoxFuture<'a, bool>;
+
+    fn stats<'a>(&'a self, process: &'a ServerProcess) -> BoxFuture<'a, Result<ServeStats>>;
+
+    /// Changes the log level of the running server without restarting it (`None` for the default level).
+    fn set_log_level<'a>(
+        &'a self,
+        process: &'a ServerProcess,
+        level: Option<LogLevel>,
+    ) -> BoxFuture<'a, Result<()>>;
+}
+
+/// A server started by a `ServeBackend`.
+pub(crate) trait RunningServer: Send {
+    /// Where the server can be reached locally.
+    fn process(&self) -> ServerProcess;
+
+    /// Waits until the server exits on its own.
+    fn wait(&mut self) -> BoxFuture<'_, Result<ServerExit>>;
+
+    fn stop(&mut self) -> BoxFuture<'_, Result<()>>;
+}
+
+/// The backend serving the spec.
+pub(crate) fn for_spec(spec: &ServerSpec) -> &'static dyn ServeBackend {
+    if mock::is_enabled() {
+        &mock::MockBackend
+    } else if native_http::supports(spec) {
+        &native_http::NativeHttpBackend
+    } else {
+        &rclone::RcloneBackend
+    }
+}
@@ -1,27 +1,51 @@
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::sync::Mutex;
 
 use anyhow::{Context, Result};
 use dioxus::logger::tracing;
-use filen_rclone_wrapper::rclone_installation::RcloneInstallationConfig;
-use filen_rclone_wrapper::serve::BasicServerOptions;
-use tokio::io::AsyncBufReadExt;
-use tokio::io::BufReader;
 use tokio::select;
 use tokio::sync::oneshot;
 
-use crate::backend::auth;
+use crate::backend::activity;
+use crate::backend::dashboard;
 use crate::backend::db::DB;
+use crate::backend::declared;
+use crate::backend::drain;
+use crate::backend::drop_box;
+use crate::backend::ephemeral;
+use crate::backend::exporter;
+use crate::backend::health;
+use crate::backend::leader;
+use crate::backend::log_buffer::{self, LogBuffer};
+use crate::backend::mock;
+use crate::backend::notifications::{self, Notification};
+use crate::backend::rclone;
+use crate::backend::redact;
+use crate::backend::scheduler;
+use crate::backend::serve_backend::{self, SpawnError};
+use crate::backend::tls;
+use crate::backend::usage;
 use crate::backend::READY_ALL_SERVERS;
+use crate::common::ActivityKind;
+use crate::common::ApiError;
+use crate::common::ApiErrorCode;
+use crate::common::CacheUsage;
+use crate::common::LogLevel;
 use crate::common::LogLine;
 use crate::common::LogLineContent;
+use crate::common::MaintenanceTask;
+use crate::common::ServerErrorReason;
 use crate::common::ServerId;
+use crate::common::ServerProcess;
 use crate::common::ServerSpec;
 use crate::common::ServerState;
 use crate::common::ServerStatus;
+use crate::common::ServerTestResult;
 use crate::common::ServerType;
-use crate::util::IncrementalVec;
+use crate::common::Transfer;
+use crate::common::VfsCacheMode;
 use crate::util::UnwrapOnceLock;
 
 pub(crate) static SERVER_MANAGER: UnwrapOnceLock<ServerManagerApi> =
@@ -30,24 +54,76 @@ pub(crate) static SERVER_MANAGER: UnwrapOnceLock<ServerManagerApi> =
 #[derive(Clone)]
 pub(crate) struct Logs {
     pub server_spec: ServerSpec,
-    pub logs: Arc<Mutex<IncrementalVec<LogLine>>>,
+    pub logs: Arc<Mutex<LogBuffer>>,
+    pub runs: u32,
+}
+
+impl Logs {
+    /// Returns the matching history and a receiver for future lines:
+    /// the lines after `cursor` (an index), limited to the last `tail` of them.
+    pub(crate) fn subscribe(
+        &self,
+        tail: Option<usize>,
+        cursor: Option<u64>,
+    ) -> (Vec<LogLine>, tokio::sync::broadcast::Receiver<LogLine>) {
+        let logs = self.logs.lock().unwrap();
+        let history = logs.history(|line| cursor.is_none_or(|cursor| line.index > cursor), tail);
+        (history, logs.subscribe())
+    }
+}
+
+fn push_log(logs: &Mutex<LogBuffer>, run: u32, level: LogLevel, content: LogLineContent) {
+    let mut logs = logs.lock().unwrap();
+    let index = logs.next_index();
+    logs.push(LogLine {
+        index,
+        timestamp: chrono::Utc::now(),
+        run,
+        level,
+        content,
+    });
 }
 
 pub(crate) struct ServerManagerApi {
-    server_states_rx: tokio::sync::watch::Receiver<Vec<ServerState>>,
+    server_states_rx: tokio::sync::watch::Receiver<Vec<ServerState<ServerSpec>>>,
     logs: Arc<Mutex<HashMap<String, Logs>>>,
-    updates_tx: tokio::sync::mpsc::Sender<ServerSpecUpdate>,
+    updates_tx: UpdatesSender,
+    /// Last access times not yet persisted to the database.
+    accesses: Arc<Mutex<HashMap<ServerId, chrono::DateTime<chrono::Utc>>>>,
 }
 
 pub(crate) enum ServerSpecUpdate {
     Add(ServerSpec),
+    /// Archives the server spec and stops the server.
     Remove(ServerId),
+    /// Restores an archived server spec and starts the server.
+    Restore(ServerId),
+    /// Permanently deletes an archived server spec.
+    Purge(ServerId),
+    /// Restarts all servers with a VFS cache, clearing their caches in between.
+    PurgeCaches,
+    /// Stops a server (e.g. one that hasn't been accessed for a long time), keeping its spec.
+    Pause(ServerId),
+    /// Starts a paused server again.
+    Resume(ServerId),
+    /// Stops a server and starts it again.
+    Restart(ServerId),
+    /// Replaces the users a server is shared with.
+    SetCollaborators(ServerId, Vec<String>),
+    /// Replaces the spec of a server (keeping its owner, collaborators and team) and restarts it, unless paused.
+    Update(ServerSpec),
+    /// Changes the log level of a server, applying it to the running server without restarting it.
+    SetLogLevel(ServerId, Option<LogLevel>),
 }
 
+/// Sends server spec updates to the manager, each with a channel for the outcome of applying it.
+type UpdatesSender = tokio::sync::mpsc::Sender<(ServerSpecUpdate, oneshot::Sender<Result<()>>)>;
+type UpdatesReceiver = tokio::sync::mpsc::Receiver<(ServerSpecUpdate, oneshot::Sender<Result<()>>)>;
+
 type StopServerHandle = oneshot::Sender<()>;
 
 pub(crate) struct ServerManager {
-    server_states_tx: tokio::sync::watch::Sender<Vec<ServerState>>,
+    server_states_tx: tokio::sync::watch::Sender<Vec<ServerState<ServerSpec>>>,
     logs: Arc<Mutex<HashMap<String, Logs>>>,
     stop_handles: HashMap<ServerId, StopServerHandle>,
 }
@@ -55,16 +131,29 @@ pub(crate) struct ServerManager {
 impl ServerManager {
     pub(crate) fn new_api() -> ServerManagerApi {
         let (server_states_tx, server_states_rx) =
-            tokio::sync::watch::channel(Vec::<ServerState>::new());
-        let (updates_tx, mut updates_rx) = tokio::sync::mpsc::channel::<ServerSpecUpdate>(100);
+            tokio::sync::watch::channel(Vec::<ServerState<ServerSpec>>::new());
+        let (updates_tx, mut updates_rx) = tokio::sync::mpsc::channel(100);
 
         let logs = Arc::new(Mutex::new(HashMap::new()));
+        let accesses = Arc::new(Mutex::new(HashMap::new()));
         let api = ServerManagerApi {
-            updates_tx,
+            updates_tx: updates_tx.clone(),
             logs: logs.clone(),
-            server_states_rx,
+            server_states_rx: server_states_rx.clone(),
+            accesses: accesses.clone(),
         };
         tokio::spawn(async move {
+            // other instances forward requests to the leader meanwhile
+            leader::wait_for_leadership().await;
+            run_maintenance(updates_tx.clone(), accesses, logs.clone());
+            tokio::spawn(run_watchdog(
+                updates_tx,
+                server_states_rx.clone(),
+                logs.clone(),
+            ));
+            tokio::spawn(declared::run_reconcile());
+            tokio::spawn(usage::run_usage_accounting(server_states_rx));
+            tokio::spawn(dashboard::run_collector());
             Self {
                 server_states_tx,
                 logs: logs.clone(),
@@ -76,112 +165,307 @@ impl ServerManager {
         api
     }
 
-    async fn run(mut self, updates_rx: &mut tokio::sync::mpsc::Receiver<ServerSpecUpdate>) {
+    async fn run(mut self, updates_rx: &mut UpdatesReceiver) {
         // load existing servers from the database and start them
-        let servers = match DB.get_servers() {
+        let servers = match DB.get_servers().await {
             Ok(servers) => servers,
             Err(e) => {
                 tracing::error!("Failed to load server specs from database: {}", e);
                 return;
             }
         };
+        // processes left behind by a previous run would serve the same servers twice
+        if !mock::is_enabled() {
+            let ids = servers.iter().map(|s| s.id.clone()).collect::<Vec<_>>();
+            match tokio::task::spawn_blocking(move || rclone::kill_orphaned_processes(&ids)).await {
+                Ok(Ok(0)) => {}
+                Ok(Ok(killed)) => tracing::info!("Killed {} orphaned rclone processes", killed),
+                Ok(Err(e)) => tracing::error!("Failed to kill orphaned rclone processes: {:#}", e),
+                Err(e) => tracing::error!("Failed to kill orphaned rclone processes: {}", e),
+            }
+        }
+        let paused_ids = match DB.get_paused_server_ids().await {
+            Ok(ids) => ids,
+            Err(e) => {
+                tracing::error!("Failed to load paused servers from database: {}", e);
+                Vec::new()
+            }
+        };
         for server in servers {
+            if paused_ids.contains(&server.id) {
+                self.set_paused_state(&server);
+                continue;
+            }
             if let Err(e) = self.start_server(&server).await {
                 tracing::error!("Failed to start server {}: {}", server.name, e);
             }
         }
         *READY_ALL_SERVERS.lock().unwrap() = true;
 
-        loop {
-            // listen for updates
-            // on update, persist changes to the database and start/stop servers accordingly
-            if let Some(update) = updates_rx.recv().await {
-                match update {
-                    ServerSpecUpdate::Add(spec) => {
-                        tracing::info!("Adding server spec: {}", spec.name);
-                        if let Err(e) = DB.create_server(&spec).await {
-                            tracing::error!("Failed to create server spec in database: {}", e);
-                            continue;
-                        };
-                        if let Err(e) = self.start_server(&spec).await {
-                            tracing::error!("Failed to start server: {}", e);
-                        };
+        // on update, persist changes to the database and start/stop servers accordingly,
+        // then report the outcome to the sender
+        while let Some((update, reply_tx)) = updates_rx.recv().await {
+            let result = self.apply_update(update).await;
+            if let Err(e) = &result {
+                tracing::error!("Failed to apply server spec update: {:#}", e);
+            }
+            let _ = reply_tx.send(result);
+        }
+        tracing::error!("Server spec updates channel closed");
+    }
+
+    async fn apply_update(&mut self, update: ServerSpecUpdate) -> Result<()> {
+        match update {
+            ServerSpecUpdate::Add(spec) => {
+                tracing::info!("Adding server spec: {}", spec.name);
+                DB.create_server(&spec).await.map_err(|e| {
+                    anyhow::anyhow!("Failed to create server spec in database: {}", e)
+                })?;
+                self.start_server(&spec)
+                    .await
+                    .context("The server was created, but failed to start")?;
+            }
+            ServerSpecUpdate::Remove(id) => {
+                let spec = self
+                    .find_spec(&id)
+                    .with_context(|| format!("Server spec with id {} not found", id))?;
+                DB.set_server_archived(&id, true).await.map_err(|e| {
+                    anyhow::anyhow!("Failed to archive server spec in database: {}", e)
+                })?;
+                tracing::info!("Archiving server spec with id: {}", id);
+                // waiting keeps the stopping server from clobbering the state of a server restored right after
+                self.stop_server_and_wait(&spec)
+                    .await
+                    .context("The server was archived, but failed to stop")?;
+            }
+            ServerSpecUpdate::Restore(id) => {
+                let spec = DB
+                    .get_archived_servers()
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Failed to load archived server specs: {}", e))?
+                    .into_iter()
+                    .find(|s| s.id == id)
+                    .with_context(|| format!("Archived server spec with id {} not found", id))?;
+                DB.set_server_archived(&id, false).await.map_err(|e| {
+                    anyhow::anyhow!("Failed to restore server spec in database: {}", e)
+                })?;
+                if let Err(e) = DB.set_server_paused(&id, false).await {
+                    tracing::error!("Failed to resume server spec in database: {}", e);
+                }
+                tracing::info!("Restoring server spec: {}", spec.name);
+                self.start_server(&spec)
+                    .await
+                    .context("The server was restored, but failed to start")?;
+            }
+            ServerSpecUpdate::Purge(id) => {
+                DB.purge_server(&id).await.map_err(|e| {
+                    anyhow::anyhow!("Failed to purge server spec from database: {}", e)
+                })?;
+                tracing::info!("Purged server spec with id: {}", id);
+                self.logs.lock().unwrap().remove(&id.to_string());
+            }
+            ServerSpecUpdate::PurgeCaches => {
+                let specs = self
+                    .server_states_tx
+                    .borrow()
+                    .iter()
+                    .filter(|s| !matches!(s.status, ServerStatus::Paused))
+                    .map(|s| s.spec.clone())
+                    .filter(uses_vfs_cache)
+                    .collect::<Vec<_>>();
+                // every server is restarted even if another one fails
+                let mut failed = Vec::new();
+                for spec in specs {
+                    tracing::info!("Purging VFS cache of server: {}", spec.name);
+                    if let Err(e) = self.purge_cache(&spec).await {
+                        tracing::error!("Failed to purge VFS cache: {}", e);
                     }
-                    ServerSpecUpdate::Remove(id) => {
-                        let spec = {
-                            let states = self.server_states_tx.borrow();
-                            match states.iter().find(|s| s.spec.id == id) {
-                                Some(s) => s.spec.clone(),
-                                None => {
-                                    tracing::error!("Server spec with id {} not found", id);
-                                    continue;
-                                }
-                            }
-                        };
-                        match DB.delete_server(&id).await {
-                            Ok(_) => (),
-                            Err(e) => {
-                                tracing::error!(
-                                    "Failed to delete server spec from database: {}",
-                                    e
-                                );
-                                continue;
-                            }
-                        };
-                        tracing::info!("Removing server spec with id: {}", id);
-                        if let Err(e) = self.stop_server(&spec).await {
-                            tracing::error!("Failed to stop server: {}", e);
+                    if let Err(e) = self.start_server(&spec).await {
+                        tracing::error!("Failed to start server: {}", e);
+                        failed.push(spec.name);
+                    }
+                }
+                if !failed.is_empty() {
+                    anyhow::bail!("Failed to restart servers: {}", failed.join(", "));
+                }
+            }
+            ServerSpecUpdate::Pause(id) => {
+                let spec = self
+                    .find_spec(&id)
+                    .with_context(|| format!("Server spec with id {} not found", id))?;
+                DB.set_server_paused(&id, true).await.map_err(|e| {
+                    anyhow::anyhow!("Failed to pause server spec in database: {}", e)
+                })?;
+                tracing::info!("Pausing server: {}", spec.name);
+                let stopped = self.stop_server_and_wait(&spec).await;
+                self.set_paused_state(&spec);
+                stopped.context("Failed to stop server")?;
+            }
+            ServerSpecUpdate::Resume(id) => {
+                let spec = self
+                    .find_spec(&id)
+                    .with_context(|| format!("Server spec with id {} not found", id))?;
+                DB.set_server_paused(&id, false).await.map_err(|e| {
+                    anyhow::anyhow!("Failed to resume server spec in database: {}", e)
+                })?;
+                if let Err(e) = DB
+                    .record_server_accesses(&[(id.clone(), chrono::Utc::now())], chrono::Utc::now())
+                    .await
+                {
+                    tracing::error!("Failed to record server access: {}", e);
+                }
+                tracing::info!("Resuming server: {}", spec.name);
+                self.server_states_tx.send_modify(|server_states| {
+                    server_states.retain(|s| s.spec.id != id);
+                });
+                self.start_server(&spec)
+                    .await
+                    .context("Failed to start server")?;
+            }
+            ServerSpecUpdate::Restart(id) => {
+                let spec = self
+                    .find_spec(&id)
+                    .with_context(|| format!("Server spec with id {} not found", id))?;
+                tracing::info!("Restarting server: {}", spec.name);
+                if let Err(e) = self.stop_server_and_wait(&spec).await {
+                    tracing::error!("Failed to stop server: {}", e);
+                }
+                self.start_server(&spec)
+                    .await
+                    .context("Failed to start server")?;
+            }
+            ServerSpecUpdate::SetCollaborators(id, collaborators) => {
+                DB.set_server_collaborators(&id, &collaborators)
+                    .await
+                    .map_err(|e| {
+                        anyhow::anyhow!("Failed to update server collaborators in database: {}", e)
+                    })?;
+                if let Some(logs) = self.logs.lock().unwrap().get_mut(&id.to_string()) {
+                    logs.server_spec.collaborators = collaborators.clone();
+                }
+                self.server_states_tx.send_modify(|server_states| {
+                    if let Some(state) = server_states.iter_mut().find(|s| s.spec.id == id) {
+                        state.spec.collaborators = collaborators;
+                    }
+                });
+            }
+            ServerSpecUpdate::SetLogLevel(id, level) => {
+                let mut spec = self
+                    .find_spec(&id)
+                    .with_context(|| format!("Server spec with id {} not found", id))?;
+                spec.options.log_level = level;
+                DB.update_server(&spec).await.map_err(|e| {
+                    anyhow::anyhow!("Failed to update server spec in database: {}", e)
+                })?;
+                if let Some(logs) = self.logs.lock().unwrap().get_mut(&id.to_string()) {
+                    logs.server_spec = spec.clone();
+                }
+                let mut process = None;
+                self.server_states_tx.send_modify(|server_states| {
+                    if let Some(state) = server_states.iter_mut().find(|s| s.spec.id == id) {
+                        state.spec.options.log_level = level;
+                        if matches!(state.status, ServerStatus::Running) {
+                            process = state.process.clone();
                         }
                     }
+                });
+                if let Some(process) = process {
+                    serve_backend::for_spec(&spec)
+                        .set_log_level(&process, level)
+                        .await
+                        .context(
+                            "The log level was saved, but failed to apply to the running server",
+                        )?;
+                }
+            }
+            ServerSpecUpdate::Update(spec) => {
+                let old_spec = self
+                    .find_spec(&spec.id)
+                    .with_context(|| format!("Server spec with id {} not found", spec.id))?;
+                DB.update_server(&spec).await.map_err(|e| {
+                    anyhow::anyhow!("Failed to update server spec in database: {}", e)
+                })?;
+                tracing::info!("Updating server spec: {}", spec.name);
+                if let Some(logs) = self.logs.lock().unwrap().get_mut(&spec.id.to_string()) {
+                    logs.server_spec = spec.clone();
+                }
+                let paused = self
+                    .server_states_tx
+                    .borrow()
+                    .iter()
+                    .any(|s| s.spec.id == spec.id && matches!(s.status, ServerStatus::Paused));
+                if let Err(e) = self.stop_server_and_wait(&old_spec).await {
+                    tracing::error!("Failed to stop server: {}", e);
+                }
+                if paused {
+                    self.set_paused_state(&spec);
+                } else {
+                    self.start_server(&spec)
+                        .await
+                        .context("The server was updated, but failed to start")?;
                 }
-            } else {
-                tracing::error!("Server spec updates channel closed");
-                break;
             }
         }
+        Ok(())
     }
 
     async fn start_server(&mut self, spec: &ServerSpec) -> Result<()> {
-        // setup logs
-        let logs_id = format!("logs_{}_{}", spec.id.short(), uuid::Uuid::new_v4());
-        let logs = {
-            let logs = Logs {
+        // setup logs (reusing the history of previous runs of the same server)
+        let (logs, run) = {
+            let mut all_logs = self.logs.lock().unwrap();
+            let logs = all_logs.entry(spec.id.to_string()).or_insert_with(|| Logs {
                 server_spec: spec.clone(),
-                logs: Arc::new(Mutex::new(IncrementalVec::<LogLine>::new(100))),
-            };
-            let logs_ = logs.logs.clone();
-            self.logs.lock().unwrap().insert(logs_id.clone(), logs);
-            logs_
+                logs: Arc::new(Mutex::new(LogBuffer::new(&spec.id))),
+                runs: 0,
+            });
+            logs.runs += 1;
+            (logs.logs.clone(), logs.runs)
+        };
+        exporter::record_start(&spec.id);
+        // log lines are shown to users (and traced), so the server's secrets are scrubbed from them
+        let scrub = {
+            let secrets = [
+                spec.password.clone().unwrap_or_default(),
+                spec.filen_auth_config.clone(),
+            ];
+            move |message: &str| redact::redact(message, &secrets.each_ref().map(String::as_str))
         };
         let log_info = {
             let logs = logs.clone();
             let spec = spec.clone();
+            let scrub = scrub.clone();
             move |message: &str| {
-                logs.lock().unwrap().push(LogLine {
-                    timestamp: chrono::Utc::now(),
-                    content: LogLineContent::Event(message.to_string()),
-                });
+                let message = scrub(message);
+                push_log(
+                    &logs,
+                    run,
+                    LogLevel::Info,
+                    LogLineContent::Event(message.clone()),
+                );
                 tracing::info!("Server {} ({}): {}", spec.name, spec.id, message);
             }
         };
         let log_err = {
             let logs = logs.clone();
             let spec = spec.clone();
+            let scrub = scrub.clone();
             move |message: &str| {
-                logs.lock().unwrap().push(LogLine {
-                    timestamp: chrono::Utc::now(),
-                    content: LogLineContent::Event(message.to_string()),
-                });
+                let message = scrub(message);
+                push_log(
+                    &logs,
+                    run,
+                    LogLevel::Error,
+                    LogLineContent::Event(message.clone()),
+                );
                 tracing::info!("Server {} ({}) ERR: {}", spec.name, spec.id, message);
             }
         };
         let log_output = {
             let logs = logs.clone();
             move |message: &str| {
-                logs.lock().unwrap().push(LogLine {
-                    timestamp: chrono::Utc::now(),
-                    content: LogLineContent::ServerProcess(message.to_string()),
-                });
+                let message = scrub(message);
+                let level = LogLevel::from_rclone_output(&message);
+                push_log(&logs, run, level, LogLineContent::ServerProcess(message));
             }
         };
 
@@ -190,81 +474,72 @@ impl ServerManager {
         self.server_states_tx.send_modify(|server_states| {
             server_states.push(ServerState {
                 spec: spec.clone(),
+                short_id: spec.id.prefix(crate::backend::short_id_length()),
                 status: ServerStatus::Starting,
-                logs_id: logs_id.clone(),
+                process: None,
             });
         });
 
-        // start server process
-        let client = auth::authenticate_filen_client(
-            spec.filen_email.clone(),
-            &spec.filen_password,
-            spec.filen_2fa_code.clone(),
-        )
-        .await
-        .context("Failed to authenticate Filen client using previously entered credentials")?;
-        let config_dir = std::env::current_dir()
-            .context("Failed to get current directory")?
-            .join("rclone_configs");
-        let port = port_check::free_local_ipv4_port().context("Failed to find free local port")?;
-        let mut server = filen_rclone_wrapper::serve::start_basic_server(
-            &client,
-            &RcloneInstallationConfig {
-                rclone_binary_dir: config_dir.clone(),
-                config_dir: config_dir.join(format!("server_{}", spec.id)),
-            },
-            match spec.server_type {
-                ServerType::Http => "http",
-                ServerType::Webdav => "webdav",
-                ServerType::S3 => "s3",
-                ServerType::Ftp => "ftp",
-                ServerType::Sftp => "sftp",
-            },
-            BasicServerOptions {
-                address: format!(":{}", port),
-                root: Some(spec.root.clone()),
-                user: None,
-                password: spec.password.clone(),
-                read_only: spec.read_only,
-                cache_size: None,
-                transfers: None,
-            },
-            vec![],
-        )
-        .await
-        .context("Failed to start rclone server")?;
+        // enforce the global cap on running servers (counting this one)
+        if let Some(max_running_servers) = max_running_servers().await {
+            if running_servers(&self.server_states_tx.borrow()) as u64 > max_running_servers {
+                log_err(&format!(
+                    "Not starting server: this relay already runs the maximum of {} servers.",
+                    max_running_servers
+                ));
+                set_error_status(
+                    &self.server_states_tx,
+                    &spec.id,
+                    ServerErrorReason::CapacityReached,
+                    None,
+                    &logs,
+                );
+                anyhow::bail!("Maximum number of running servers reached");
+            }
+        }
+
+        // start serving
+        let backend = serve_backend::for_spec(spec);
+        let mut server = match backend.spawn(spec, Arc::new(log_output)).await {
+            Ok(server) => server,
+            Err(SpawnError::AuthFailure(e)) => {
+                log_err(&format!("Failed to authenticate Filen client: {}", e));
+                set_error_status(
+                    &self.server_states_tx,
+                    &spec.id,
+                    ServerErrorReason::AuthFailure,
+                    None,
+                    &logs,
+                );
+                return Err(
+                    e.context("Failed to authenticate Filen client using the stored auth config")
+                );
+            }
+            Err(SpawnError::Failed(e)) => {
+                log_err(&format!("{:#}", e));
+                set_error_status(
+                    &self.server_states_tx,
+                    &spec.id,
+                    classify_failure(&logs),
+                    None,
+                    &logs,
+                );
+                return Err(e);
+            }
+        };
 
         // set "running" state
         log_info("Server started successfully.");
+        activity::record_server(ActivityKind::ServerStarted, &spec);
+        let process = server.process();
         self.server_states_tx.send_modify(|server_states| {
             if let Some(s) = server_states.iter_mut().find(|s| s.spec.id == spec.id) {
-                s.status = ServerStatus::Running { port };
+                s.status = ServerStatus::Running;
+                s.process = Some(process);
             }
         });
 
         let spec = spec.clone();
-
-        // handle logs
-        {
-            let log_output = log_output.clone();
-            let process_stdout = server.process.stdout.take().unwrap();
-            tokio::spawn(async move {
-                let mut reader = BufReader::new(process_stdout).lines();
-                while let Ok(Some(line)) = reader.next_line().await {
-                    log_output(&line);
-                }
-            });
-        }
-        {
-            let process_stderr = server.process.stderr.take().unwrap();
-            tokio::spawn(async move {
-                let mut reader = BufReader::new(process_stderr).lines();
-                while let Ok(Some(line)) = reader.next_line().await {
-                    log_output(&line);
-                }
-            });
-        }
-
         let (stop_server_tx, stop_server_rx) = oneshot::channel::<()>();
         self.stop_handles.insert(spec.id.clone(), stop_server_tx);
         let server_states_tx = self.server_states_tx.clone();
@@ -272,40 +547,44 @@ impl ServerManager {
             select! {
                 _ = stop_server_rx => {
                     // handle stopping the server
-                    if let Err(e) = server.process.kill().await {
+                    if let Err(e) = server.stop().await {
                         log_err(&format!("Failed to stop server: {}", e));
                     } else {
                         log_info("Server stopped.");
+                        activity::record_server(ActivityKind::ServerStopped, &spec);
                     }
                     server_states_tx.send_modify(|server_states| {
                         server_states.retain(|s| s.spec.id != spec.id);
                     });
                 }
-                status = server.process.wait() => {
-                    // handle process exit
-                    match status {
-                        Ok(status) => {
-                            log_err(&format!("Server process exited with status: {}", status));
-                            if status.success() {
+                exit = server.wait() => {
+                    // handle the server exiting by itself
+                    match exit {
+                        Ok(exit) => {
+                            log_err(&exit.description);
+                            if exit.success {
                                 server_states_tx.send_modify(|server_states| {
                                     server_states.retain(|s| s.spec.id != spec.id);
                                 });
                             } else {
-                                server_states_tx.send_modify(|server_states| {
-                                    if let Some(s) = server_states.iter_mut().find(|s| s.spec.id == spec.id)
-                                    {
-                                        s.status = ServerStatus::Error;
-                                    }
-                                });
+                                set_error_status(
+                                    &server_states_tx,
+                                    &spec.id,
+                                    classify_failure(&logs),
+                                    exit.code,
+                                    &logs,
+                                );
                             }
                         }
                         Err(e) => {
-                            log_err(&format!("Server process wait failed: {}", e));
-                            server_states_tx.send_modify(|server_states| {
-                                if let Some(s) = server_states.iter_mut().find(|s| s.spec.id == spec.id) {
-                                    s.status = ServerStatus::Error;
-                                }
-                            });
+                            log_err(&format!("{:#}", e));
+                            set_error_status(
+                                &server_states_tx,
+                                &spec.id,
+                                ServerErrorReason::Crash,
+                                None,
+                                &logs,
+                            );
                         }
                     };
                 }
@@ -317,34 +596,824 @@ impl ServerManager {
 
     async fn stop_server(&mut self, spec: &ServerSpec) -> Result<()> {
         // send stop process
-        let _ = self
-            .stop_handles
-            .remove(&spec.id)
-            .ok_or_else(|| anyhow::anyhow!("No running server found with id: {} to stop", spec.id))?
-            .send(()); // ignore failure, means the server is already stopped
+        let stopped = match self.stop_handles.remove(&spec.id) {
+            Some(stop_handle) => stop_handle.send(()).is_ok(),
+            None => false,
+        };
+        if !stopped {
+            // the server never started or has already exited, so only its state needs to go
+            self.server_states_tx.send_modify(|server_states| {
+                server_states.retain(|s| s.spec.id != spec.id);
+            });
+        }
+        Ok(())
+    }
+
+    /// Stops the server and waits until it has exited.
+    async fn stop_server_and_wait(&mut self, spec: &ServerSpec) -> Result<()> {
+        let mut server_states_rx = self.server_states_tx.subscribe();
+        self.stop_server(spec).await?;
+        server_states_rx
+            .wait_for(|states| !states.iter().any(|s| s.spec.id == spec.id))
+            .await
+            .context("Server states channel closed")?;
+        Ok(())
+    }
+
+    /// Stops the server, waits for it to exit and deletes its VFS cache.
+    async fn purge_cache(&mut self, spec: &ServerSpec) -> Result<()> {
+        self.stop_server_and_wait(spec).await?;
+        let dir = cache_dir(spec)?;
+        if tokio::fs::try_exists(&dir).await.unwrap_or(false) {
+            tokio::fs::remove_dir_all(&dir)
+                .await
+                .with_context(|| format!("Failed to delete {}", dir.display()))?;
+        }
         Ok(())
     }
     // todo: at some point also delete the directory?
+
+    fn find_spec(&self, id: &ServerId) -> Option<ServerSpec> {
+        self.server_states_tx
+            .borrow()
+            .iter()
+            .find(|s| s.spec.id == *id)
+            .map(|s| s.spec.clone())
+    }
+
+    /// Shows a (stopped) server as paused, noting it in its logs.
+    fn set_paused_state(&mut self, spec: &ServerSpec) {
+        if let Some(logs) = self.logs.lock().unwrap().get(&spec.id.to_string()) {
+            push_log(
+                &logs.logs,
+                logs.runs,
+                LogLevel::Info,
+                LogLineContent::Event("Server paused.".to_string()),
+            );
+        }
+        self.server_states_tx.send_modify(|server_states| {
+            server_states.push(ServerState {
+                spec: spec.clone(),
+                short_id: spec.id.prefix(crate::backend::short_id_length()),
+                status: ServerStatus::Paused,
+                process: None,
+            });
+        });
+    }
+}
+
+/// How long `test_server` waits for a server to start.
+const TEST_START_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How often server accesses are persisted and unused servers are paused.
+const IDLE_STOP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+/// How often log segments of servers that no longer exist are deleted.
+const LOG_PRUNING_INTERVAL: std::time::Duration = std::time::Duration::from_secs(6 * 60 * 60);
+/// How often VFS caches of servers that were archived or purged are deleted.
+const CACHE_EVICTION_INTERVAL: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+/// How often the TLS certificate of FTPS servers is checked for renewal (see `tls`).
+const CERTIFICATE_RENEWAL_INTERVAL: std::time::Duration =
+    std::time::Duration::from_secs(12 * 60 * 60);
+
+/// Schedules the maintenance tasks of the manager (see `scheduler`).
+fn run_maintenance(
+    updates_tx: UpdatesSender,
+    accesses: Arc<Mutex<HashMap<ServerId, chrono::DateTime<chrono::Utc>>>>,
+    logs: Arc<Mutex<HashMap<String, Logs>>>,
+) {
+    tokio::spawn(scheduler::run_periodically(
+        MaintenanceTask::CertificateRenewal,
+        CERTIFICATE_RENEWAL_INTERVAL,
+        {
+            let updates_tx = updates_tx.clone();
+            move || restart_on_certificate_renewal(updates_tx.clone())
+        },
+    ));
+    tokio::spawn(scheduler::run_periodically(
+        MaintenanceTask::IdleStop,
+        IDLE_STOP_INTERVAL,
+        move || pause_idle_servers(updates_tx.clone(), accesses.clone()),
+    ));
+    tokio::spawn(scheduler::run_periodically(
+        MaintenanceTask::LogPruning,
+        LOG_PRUNING_INTERVAL,
+        move || {
+            let live = logs.lock().unwrap().keys().cloned().collect::<HashSet<_>>();
+            async move {
+                let pruned =
+                    tokio::task::spawn_blocking(move || log_buffer::prune(&live)).await??;
+                anyhow::Ok(format!("Deleted logs of {} removed servers", pruned))
+            }
+        },
+    ));
+    tokio::spawn(scheduler::run_periodically(
+        MaintenanceTask::CacheEviction,
+        CACHE_EVICTION_INTERVAL,
+        evict_stale_caches,
+    ));
+}
+
+/// Persists server accesses and pauses servers that haven't been accessed for `auto_pause_days`.
+async fn pause_idle_servers(
+    updates_tx: UpdatesSender,
+    accesses: Arc<Mutex<HashMap<ServerId, chrono::DateTime<chrono::Utc>>>>,
+) -> Result<String> {
+    let now = chrono::Utc::now();
+    let accesses = std::mem::take(&mut *accesses.lock().unwrap())
+        .into_iter()
+        .collect::<Vec<_>>();
+    if let Err(e) = ephemeral::record_server_accesses(&accesses, now).await {
+        tracing::error!("Failed to record server accesses: {}", e);
+    }
+    let auto_pause_days = DB
+        .get_settings()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get settings: {}", e))?
+        .auto_pause_days;
+    let Some(auto_pause_days) = auto_pause_days else {
+        return Ok("Pausing unused servers is turned off".to_string());
+    };
+    let cutoff = now - chrono::Duration::days(auto_pause_days as i64);
+    let ids = ephemeral::get_servers_inactive_since(cutoff)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get inactive servers: {}", e))?;
+    let count = ids.len();
+    for id in ids {
+        // the outcome is logged by the manager
+        let (reply_tx, _) = oneshot::channel();
+        let _ = updates_tx
+            .send((ServerSpecUpdate::Pause(id), reply_tx))
+            .await;
+    }
+    Ok(format!("Paused {} unused servers", count))
+}
+
+/// Renews the TLS certificate if it's due, and restarts the running FTPS servers that started with an older one.
+async fn restart_on_certificate_renewal(updates_tx: UpdatesSender) -> Result<String> {
+    let running = SERVER_MANAGER
+        .get_server_states()
+        .borrow()
+        .iter()
+        .filter(|s| {
+            matches!(s.spec.server_type, ServerType::Ftp)
+                && s.spec.options.ftp_tls
+                && matches!(s.status, ServerStatus::Running)
+        })
+        .map(|s| s.spec.id.clone())
+        .collect::<Vec<_>>();
+    let ids = tls::renew(running)?;
+    if ids.is_empty() {
+        return Ok("The certificate is up to date".to_string());
+    }
+    let count = ids.len();
+    for id in ids {
+        // the outcome is logged by the manager
+        let (reply_tx, _) = oneshot::channel();
+        let _ = updates_tx
+            .send((ServerSpecUpdate::Restart(id), reply_tx))
+            .await;
+    }
+    Ok(format!(
+        "Restarted {} FTPS servers with the renewed certificate",
+        count
+    ))
+}
+
+/// Deletes the VFS caches in `rclone_configs` of servers that were archived or purged, which would only be outdated
+/// when an archived server is restored. Caches in custom cache directories are left alone.
+async fn evict_stale_caches() -> Result<String> {
+    let servers = DB
+        .get_servers()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get servers: {}", e))?;
+    let mut entries = match tokio::fs::read_dir(rclone_dir()?).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok("No caches".to_string()),
+        Err(e) => return Err(e).context("Failed to list rclone_configs"),
+    };
+    let mut evicted = 0;
+    let mut freed_bytes = 0;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(id) = name.strip_prefix("cache_") else {
+            continue;
+        };
+        if servers.iter().any(|s| s.id.to_string() == id) {
+            continue;
+        }
+        let dir = entry.path();
+        freed_bytes += {
+            let dir = dir.clone();
+            tokio::task::spawn_blocking(move || dir_size(&dir)).await?
+        };
+        tokio::fs::remove_dir_all(&dir)
+            .await
+            .with_context(|| format!("Failed to delete {}", dir.display()))?;
+        evicted += 1;
+    }
+    Ok(format!(
+        "Deleted {} stale caches ({})",
+        evicted,
+        crate::util::format_bytes(freed_bytes)
+    ))
+}
+
+/// How often running servers are probed by the watchdog.
+const WATCHDOG_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+/// How long a probe may take before it counts as failed.
+const WATCHDOG_PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// Consecutive failed probes after which a server is considered hung and restarted.
+const WATCHDOG_MAX_FAILURES: u32 = 3;
+
+/// Restarts running servers whose process is alive but unresponsive (which some rclone serve modes do instead of exiting).
+async fn run_watchdog(
+    updates_tx: UpdatesSender,
+    server_states_rx: tokio::sync::watch::Receiver<Vec<ServerState<ServerSpec>>>,
+    logs: Arc<Mutex<HashMap<String, Logs>>>,
+) {
+    // consecutive failed probes per server, reset when its process changes
+    let mut failures = HashMap::<ServerId, (ServerProcess, u32)>::new();
+    let mut interval = tokio::time::interval(WATCHDOG_INTERVAL);
+    loop {
+        interval.tick().await;
+        let running = server_states_rx
+            .borrow()
+            .iter()
+            .filter(|s| matches!(s.status, ServerStatus::Running))
+            .filter_map(|s| {
+                Some((
+                    s.spec.id.clone(),
+                    serve_backend::for_spec(&s.spec),
+                    s.process.clone()?,
+                ))
+            })
+            .collect::<Vec<_>>();
+        failures.retain(|id, _| running.iter().any(|(running_id, _, _)| running_id == id));
+        for (id, backend, process) in running {
+            let healthy = tokio::time::timeout(WATCHDOG_PROBE_TIMEOUT, backend.probe(&process))
+                .await
+                .unwrap_or(false);
+            health::record_probe(&id, healthy);
+            if healthy {
+                failures.remove(&id);
+                continue;
+            }
+            let count = match failures.get_mut(&id) {
+                Some((failed_process, count)) if *failed_process == process => {
+                    *count += 1;
+                    *count
+                }
+                _ => {
+                    failures.insert(id.clone(), (process, 1));
+                    1
+                }
+            };
+            if count < WATCHDOG_MAX_FAILURES {
+                continue;
+            }
+            failures.remove(&id);
+            let message = format!(
+                "Server is unresponsive ({} failed health probes), restarting it.",
+                count
+            );
+            tracing::warn!("Server {}: {}", id, message);
+            if let Some(logs) = logs.lock().unwrap().get(&id.to_string()) {
+                push_log(
+                    &logs.logs,
+                    logs.runs,
+                    LogLevel::Error,
+                    LogLineContent::Event(message),
+                );
+            }
+            // the outcome is logged by the manager
+            let (reply_tx, _) = oneshot::channel();
+            let _ = updates_tx
+                .send((ServerSpecUpdate::Restart(id), reply_tx))
+                .await;
+        }
+    }
+}
+
+/// The directory containing the rclone binary and the per-server rclone configs.
+pub(crate) fn rclone_dir() -> Result<std::path::PathBuf> {
+    Ok(std::env::current_dir()
+        .context("Failed to get current directory")?
+        .join("rclone_configs"))
+}
+
+/// Returns the version of the rclone binary in use (e.g. "rclone v1.70.0-filen.14"), if there is one.
+pub(crate) async fn rclone_version() -> Option<String> {
+    static RCLONE_VERSION: tokio::sync::OnceCell<Option<String>> =
+        tokio::sync::OnceCell::const_new();
+    RCLONE_VERSION
+        .get_or_init(|| async {
+            let mut entries = tokio::fs::read_dir(rclone_dir().ok()?).await.ok()?;
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let is_rclone_binary = entry.file_name().to_string_lossy().starts_with("rclone")
+                    && entry.file_type().await.is_ok_and(|t| t.is_file());
+                if !is_rclone_binary {
+                    continue;
+                }
+                let output = tokio::process::Command::new(entry.path())
+                    .arg("version")
+                    .output()
+                    .await
+                    .ok()?;
+                return String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .next()
+                    .map(|line| line.trim().to_string());
+            }
+            None
+        })
+        .await
+        .clone()
+}
+
+/// The unix socket an HTTP or WebDAV server listens on, which the relay proxies requests to (see `server_proxy`).
+/// This avoids running out of (or colliding on) local ports, and other processes on the host can't reach the
+/// server directly. Other servers (and all servers on other platforms) listen on a port instead.
+#[cfg(target_os = "linux")]
+pub(crate) fn socket_path(spec: &ServerSpec) -> Result<Option<std::path::PathBuf>> {
+    use std::os::unix::fs::PermissionsExt as _;
+
+    if !matches!(spec.server_type, ServerType::Http | ServerType::Webdav) {
+        return Ok(None);
+    }
+    let dir = rclone_dir()?.join("sockets");
+    let path = dir.join(format!("{}.sock", spec.id));
+    // longer paths don't fit into a socket address
+    if path.as_os_str().len() >= 108 {
+        return Ok(None);
+    }
+    std::fs::create_dir_all(&dir).context("Failed to create sockets directory")?;
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))
+        .context("Failed to restrict sockets directory")?;
+    // left over if rclone didn't exit cleanly
+    match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(e).context("Failed to remove stale socket")
+        }
+        _ => {}
+    }
+    Ok(Some(path))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn socket_path(_spec: &ServerSpec) -> Result<Option<std::path::PathBuf>> {
+    Ok(None)
+}
+
+/// The directory the VFS cache of a server is kept in.
+fn cache_dir(spec: &ServerSpec) -> Result<std::path::PathBuf> {
+    Ok(match &spec.options.cache_dir {
+        // servers may share a custom cache directory, so each one gets its own subdirectory
+        Some(dir) => std::path::Path::new(dir).join(spec.id.to_string()),
+        None => rclone_dir()?.join(format!("cache_{}", spec.id)),
+    })
+}
+
+/// The effective VFS cache mode of a server, if it sets one.
+fn vfs_cache_mode(spec: &ServerSpec) -> Option<VfsCacheMode> {
+    let windows_compatibility =
+        matches!(spec.server_type, ServerType::Webdav) && spec.options.windows_compatibility;
+    // the Windows WebDAV redirector tolerates no partial writes
+    spec.options
+        .vfs_cache_mode
+        .or(windows_compatibility.then_some(VfsCacheMode::Writes))
+}
+
+fn uses_vfs_cache(spec: &ServerSpec) -> bool {
+    vfs_cache_mode(spec).is_some_and(|mode| mode != VfsCacheMode::Off)
+}
+
+/// The share of the global cache quota (in bytes) that each server with a VFS cache may use.
+/// Shares are computed when a server starts, so running servers keep theirs until restarted.
+pub(crate) async fn cache_quota_share() -> Result<Option<u64>> {
+    let settings = DB
+        .get_settings()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get settings: {}", e))?;
+    let Some(quota_gb) = settings.cache_quota_gb else {
+        return Ok(None);
+    };
+    let caching_servers = DB
+        .get_servers()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get servers: {}", e))?
+        .iter()
+        .filter(|s| uses_vfs_cache(s))
+        .count()
+        .max(1) as u64;
+    Ok(Some(quota_gb * 1024 * 1024 * 1024 / caching_servers))
+}
+
+/// Current disk usage of the VFS caches of all (non-archived) servers.
+pub(crate) async fn cache_usage() -> Result<CacheUsage> {
+    let settings = DB
+        .get_settings()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get settings: {}", e))?;
+    let specs = DB
+        .get_servers()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get servers: {}", e))?;
+    let mut servers = Vec::new();
+    for spec in specs.iter().filter(|s| uses_vfs_cache(s)) {
+        let dir = cache_dir(spec)?;
+        let bytes = tokio::task::spawn_blocking(move || dir_size(&dir)).await?;
+        servers.push((spec.name.clone(), bytes));
+    }
+    Ok(CacheUsage {
+        total_bytes: servers.iter().map(|(_, bytes)| bytes).sum(),
+        quota_bytes: settings.cache_quota_gb.map(|gb| gb * 1024 * 1024 * 1024),
+        servers,
+    })
+}
+
+/// Total size of the files in a directory (recursively), or 0 if it doesn't exist.
+pub(crate) fn dir_size(dir: &std::path::Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(t) if t.is_dir() => dir_size(&entry.path()),
+            Ok(t) if t.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Additional rclone flags derived from the server's options.
+pub(crate) fn rclone_args(spec: &ServerSpec, cache_max_size: Option<u64>) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    if matches!(spec.server_type, ServerType::Webdav) && spec.options.windows_compatibility {
+        // the Windows WebDAV redirector needs ETags and has long-running requests
+        args.extend(
+            [
+                "--etag-hash=auto",
+                "--server-read-timeout=1h",
+                "--server-write-timeout=1h",
+            ]
+            .map(String::from),
+        );
+    }
+    if drop_box::is_drop_box(spec) {
+        // besides the proxy only forwarding uploads
+        args.push("--disable-dir-list".to_string());
+    }
+    if let Some(mode) = vfs_cache_mode(spec) {
+        args.push(format!("--vfs-cache-mode={}", mode));
+        if mode != VfsCacheMode::Off {
+            args.push(format!("--cache-dir={}", cache_dir(spec)?.display()));
+            if let Some(max_size) = cache_max_size {
+                args.push(format!("--vfs-cache-max-size={}", max_size));
+            }
+        }
+    }
+    if let (ServerType::Ftp, Some((start, end))) =
+        (&spec.server_type, spec.options.ftp_passive_port_range())
+    {
+        args.push(format!("--passive-port={}-{}", start, end));
+    }
+    if matches!(spec.server_type, ServerType::Ftp) && spec.options.ftp_tls {
+        let (cert, key) = tls::certificate(&spec.id)?;
+        args.push(format!("--cert={}", cert.display()));
+        args.push(format!("--key={}", key.display()));
+    }
+    if let Some(bwlimit) = &spec.options.bwlimit {
+        args.push(format!("--bwlimit={}", bwlimit));
+    }
+    if let Some(level) = spec.options.log_level {
+        args.push(format!("--log-level={}", level.to_string().to_uppercase()));
+    }
+    args.extend(root_filters(spec));
+    args.extend(spec.options.extra_flags.iter().cloned());
+    Ok(args)
+}
+
+/// The directory rclone serves: the root, or with additional roots their common parent directory
+/// (narrowed down to the roots by `root_filters`).
+pub(crate) fn served_root(spec: &ServerSpec) -> String {
+    let mut parent = root_segments(&spec.root);
+    for root in &spec.options.additional_roots {
+        let segments = root_segments(root);
+        let common = parent
+            .iter()
+            .zip(&segments)
+            .take_while(|(a, b)| a == b)
+            .count();
+        parent.truncate(common);
+    }
+    format!("/{}", parent.join("/"))
+}
+
+/// Filters hiding everything in the served directory except the roots, which thus appear side by side
+/// (e.g. "/Photos" and "/Documents", when both are in the drive's root directory).
+fn root_filters(spec: &ServerSpec) -> Vec<String> {
+    if spec.options.additional_roots.is_empty() {
+        return Vec::new();
+    }
+    let parent_len = root_segments(&served_root(spec)).len();
+    let relative_roots = std::iter::once(&spec.root)
+        .chain(&spec.options.additional_roots)
+        .map(|root| root_segments(root)[parent_len..].join("/"))
+        .collect::<Vec<_>>();
+    if relative_roots.iter().any(|root| root.is_empty()) {
+        return Vec::new(); // one of the roots is the served directory itself
+    }
+    let mut filters = relative_roots
+        .iter()
+        .map(|root| format!("--filter=+ /{}/**", root))
+        .collect::<Vec<_>>();
+    filters.push("--filter=- **".to_string());
+    filters
+}
+
+fn root_segments(root: &str) -> Vec<&str> {
+    root.split('/').filter(|s| !s.is_empty()).collect()
+}
+
+/// The admin's cap on simultaneously running servers, if set.
+async fn max_running_servers() -> Option<u64> {
+    match DB.get_settings().await {
+        Ok(settings) => settings.max_running_servers,
+        Err(e) => {
+            tracing::error!("Failed to get settings: {}", e);
+            None
+        }
+    }
+}
+
+/// Number of servers that are running or starting.
+fn running_servers(server_states: &[ServerState<ServerSpec>]) -> usize {
+    server_states
+        .iter()
+        .filter(|s| matches!(s.status, ServerStatus::Starting | ServerStatus::Running))
+        .count()
+}
+
+/// Number of log lines attached to a server's error status.
+const ERROR_LOG_LINES: usize = 20;
+
+fn set_error_status(
+    server_states_tx: &tokio::sync::watch::Sender<Vec<ServerState<ServerSpec>>>,
+    id: &ServerId,
+    reason: ServerErrorReason,
+    exit_code: Option<i32>,
+    logs: &Mutex<LogBuffer>,
+) {
+    let last_logs = logs.lock().unwrap().last(ERROR_LOG_LINES);
+    let mut failed_spec = None;
+    server_states_tx.send_modify(|server_states| {
+        if let Some(s) = server_states.iter_mut().find(|s| s.spec.id == *id) {
+            s.status = ServerStatus::Error {
+                reason: reason.clone(),
+                exit_code,
+                last_logs,
+            };
+            s.process = None;
+            failed_spec = Some(s.spec.clone());
+        }
+    });
+    if let Some(spec) = failed_spec {
+        notifications::notify(Notification::ServerFailed { spec, reason });
+    }
+}
+
+/// Guesses why a server failed from its recent log output.
+fn classify_failure(logs: &Mutex<LogBuffer>) -> ServerErrorReason {
+    let logs = logs.lock().unwrap();
+    let is_bind_failure = logs.last(ERROR_LOG_LINES).iter().any(|line| {
+        let content = match &line.content {
+            LogLineContent::Event(content) | LogLineContent::ServerProcess(content) => content,
+        };
+        let content = content.to_lowercase();
+        content.contains("address already in use") || content.contains("bind:")
+    });
+    if is_bind_failure {
+        ServerErrorReason::BindFailure
+    } else {
+        ServerErrorReason::Crash
+    }
 }
 
 impl ServerManagerApi {
     /// Returns a receiver to listen for server state updates.
-    pub(crate) fn get_server_states(&self) -> tokio::sync::watch::Receiver<Vec<ServerState>> {
+    pub(crate) fn get_server_states(
+        &self,
+    ) -> tokio::sync::watch::Receiver<Vec<ServerState<ServerSpec>>> {
         self.server_states_rx.clone()
     }
 
-    /// Returns a receiver to listen for logs.
-    pub(crate) fn get_logs(&self, logs_id: &str) -> Option<Logs> {
+    /// Returns a receiver to listen for the logs of a server (across all of its runs).
+    pub(crate) fn get_logs(&self, server_id: &str) -> Option<Logs> {
         // todo: handle errors safely?
         let logs = self.logs.lock().unwrap();
-        logs.get(logs_id).cloned()
+        logs.get(server_id).cloned()
+    }
+
+    /// Notes that a server has been accessed (persisted by the maintenance task).
+    pub(crate) fn record_access(&self, id: &ServerId) {
+        let now = chrono::Utc::now();
+        self.accesses.lock().unwrap().insert(id.clone(), now);
+        ephemeral::record_server_access(id, now);
+    }
+
+    /// Fails with a `QuotaExceeded` error if no further server may be started (by the user with the given email),
+    /// or with an `Unavailable` error while draining.
+    pub(crate) async fn check_capacity(&self, email: &str) -> Result<()> {
+        drain::check_not_draining()?;
+        if let Some(cap) = usage::exceeded_egress_cap(email).await? {
+            return Err(ApiError::new(
+                ApiErrorCode::QuotaExceeded,
+                format!(
+                    "You have reached your monthly egress cap of {} GB. Servers can be started again next month.",
+                    cap.monthly_gb
+                ),
+            )
+            .into());
+        }
+        let Some(max_running_servers) = max_running_servers().await else {
+            return Ok(());
+        };
+        if running_servers(&self.server_states_rx.borrow()) as u64 >= max_running_servers {
+            let message = format!(
+                "This relay already runs the maximum of {} servers. Remove or wait for other servers first.",
+                max_running_servers
+            );
+            notifications::notify(Notification::QuotaExceeded {
+                email: email.to_string(),
+                message: message.clone(),
+            });
+            return Err(ApiError::new(ApiErrorCode::QuotaExceeded, message).into());
+        }
+        Ok(())
+    }
+
+    /// Returns the files currently being transferred by a running server.
+    pub(crate) async fn get_transfers(&self, id: &ServerId) -> Result<Vec<Transfer>> {
+        let (backend, process) = match self
+            .server_states_rx
+            .borrow()
+            .iter()
+            .find(|s| s.spec.id == *id)
+        {
+            Some(ServerState {
+                spec,
+                status: ServerStatus::Running,
+                process: Some(process),
+                ..
+            }) => (serve_backend::for_spec(spec), process.clone()),
+            _ => return Ok(Vec::new()),
+        };
+        Ok(backend.stats(&process).await?.transferring)
+    }
+
+    /// Waits until a (newly created) server has started, then sends a request to it through the relay's proxy.
+    /// FTP and SFTP servers aren't proxied, so for them, only a connection to the server is opened.
+    pub(crate) async fn test_server(&self, id: &ServerId) -> Result<ServerTestResult> {
+        let failed = |message: String| {
+            Ok(ServerTestResult {
+                success: false,
+                message,
+            })
+        };
+        let mut server_states_rx = self.server_states_rx.clone();
+        let started = tokio::time::timeout(
+            TEST_START_TIMEOUT,
+            server_states_rx.wait_for(|server_states| {
+                server_states
+                    .iter()
+                    .any(|s| s.spec.id == *id && !matches!(s.status, ServerStatus::Starting))
+            }),
+        )
+        .await;
+        let state = match started {
+            Ok(Ok(server_states)) => server_states.iter().find(|s| s.spec.id == *id).cloned(),
+            Ok(Err(_)) => None,
+            Err(_) => {
+                return failed(format!(
+                    "The server didn't start within {} seconds.",
+                    TEST_START_TIMEOUT.as_secs()
+                ))
+            }
+        };
+        let Some(state) = state else {
+            return failed("The server was removed.".to_string());
+        };
+        let process = match (&state.status, &state.process) {
+            (ServerStatus::Running, Some(process)) => process,
+            (ServerStatus::Error { reason, .. }, _) => {
+                return failed(format!("The server failed to start: {}", reason))
+            }
+            _ => return failed("The server isn't running.".to_string()),
+        };
+        if matches!(state.spec.server_type, ServerType::Ftp | ServerType::Sftp) {
+            let port = process.port.unwrap_or_default();
+            return match tokio::net::TcpStream::connect(("127.0.0.1", port)).await {
+                Ok(_) => Ok(ServerTestResult {
+                    success: true,
+                    message: format!("The server accepts connections on port {}.", port),
+                }),
+                Err(e) => failed(format!("Failed to connect to the server: {}", e)),
+            };
+        }
+        let url = format!("{}/s/{}/", super::local_url(), state.short_id);
+        match reqwest::Client::new().get(&url).send().await {
+            // the proxy responds with 502 if the server can't be reached
+            Ok(response) if response.status().is_server_error() => failed(format!(
+                "The server responded with HTTP {} through the relay.",
+                response.status()
+            )),
+            // 401 and 403 are expected for servers requiring a password (or S3 credentials)
+            Ok(response) => Ok(ServerTestResult {
+                success: true,
+                message: format!(
+                    "The server is reachable through the relay (HTTP {}).",
+                    response.status()
+                ),
+            }),
+            Err(e) => failed(format!(
+                "Failed to reach the server through the relay: {}",
+                e
+            )),
+        }
     }
 
     /// Add/remove the server spec via the manager (will start/stop it) and persist it to the database.
+    /// Waits until the manager has applied the update (e.g. the server has started) and returns the outcome.
     pub(crate) async fn update_server_spec(&self, update: ServerSpecUpdate) -> Result<()> {
+        let (reply_tx, reply_rx) = oneshot::channel();
         self.updates_tx
-            .send(update)
+            .send((update, reply_tx))
+            .await
+            .context("Failed to send server spec update")?;
+        reply_rx
             .await
-            .context("Failed to send server spec update")
+            .context("The server manager stopped before applying the update")?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::db::DbViaOfflineOrRemoteFile;
+    use crate::common::{ServerOptions, ServerType};
+
+    #[tokio::test]
+    async fn restoring_right_after_removing_keeps_the_server_running() {
+        mock::enable();
+        let db_dir = std::env::temp_dir().join(format!("filen-relay-test-{}", ServerId::new()));
+        std::fs::create_dir_all(&db_dir).unwrap();
+        DB.init(
+            DbViaOfflineOrRemoteFile::new_from_offline_location(db_dir.to_str())
+                .await
+                .unwrap(),
+        );
+        let mut manager = ServerManager {
+            server_states_tx: tokio::sync::watch::channel(Vec::new()).0,
+            logs: Default::default(),
+            stop_handles: HashMap::new(),
+        };
+        let spec = ServerSpec {
+            id: ServerId::new(),
+            name: "test".to_string(),
+            server_type: ServerType::Http,
+            root: "/".to_string(),
+            read_only: true,
+            password: None,
+            filen_email: "user@example.com".to_string(),
+            filen_auth_config: String::new(),
+            options: ServerOptions::default(),
+            collaborators: Vec::new(),
+            team_id: None,
+        };
+
+        manager
+            .apply_update(ServerSpecUpdate::Add(spec.clone()))
+            .await
+            .unwrap();
+        manager
+            .apply_update(ServerSpecUpdate::Remove(spec.id.clone()))
+            .await
+            .unwrap();
+        manager
+            .apply_update(ServerSpecUpdate::Restore(spec.id.clone()))
+            .await
+            .unwrap();
+
+        // give a stop that is still in flight the chance to (wrongly) remove the restored server
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        let server_states = manager.server_states_tx.borrow();
+        let states = server_states
+            .iter()
+            .filter(|s| s.spec.id == spec.id)
+            .collect::<Vec<_>>();
+        assert_eq!(states.len(), 1);
+        assert!(matches!(states[0].status, ServerStatus::Running));
+        assert!(manager.stop_handles.contains_key(&spec.id));
     }
 }
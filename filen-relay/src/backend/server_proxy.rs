@@ -0,0 +1,317 @@
+//! Proxying of requests to `/s/{id}/...` to the HTTP and WebDAV servers, which listen on a port on localhost or on a
+//! unix socket (on Linux, see `server_manager::socket_path`).
+//!
+//! Each server gets its own client, which keeps idle connections open, so that chatty clients (like WebDAV clients
+//! doing many small PROPFINDs) don't pay for a new connection per request. rclone serves plain HTTP/1.1 to the relay,
+//! so connections aren't upgraded to HTTP/2.
+//!
+//! `Accept-Encoding` is forwarded like any other header. For servers with the `compression` option, the relay also
+//! compresses text-like responses itself (see `compress`), since rclone doesn't.
+//!
+//! Requests to HTTP servers in website mode are mapped to the website's pages first (see `website`), and requests for
+//! folders as ZIPs are answered by the relay itself (see `folder_zip`). Drop boxes only get uploads (see `drop_box`).
+//! Before any of this, requests are checked against the server's access rules (see `access_rules`).
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+
+use async_compression::tokio::bufread::{BrotliEncoder, GzipEncoder};
+use dioxus::{
+    fullstack::extract::Request,
+    logger::tracing,
+    server::axum::{
+        self,
+        body::Body,
+        http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+        middleware::Next,
+    },
+};
+use tokio_stream::StreamExt as _;
+use tokio_util::io::{ReaderStream, StreamReader};
+
+use crate::{
+    backend::{
+        access_rules, dashboard, drop_box, exporter, folder_zip, forwarded, health, leader,
+        server_manager::SERVER_MANAGER, website,
+    },
+    common::{ServerProcess, ServerType},
+};
+
+/// Content types worth compressing (besides `text/*`); others, like images and videos, are compressed already.
+const COMPRESSIBLE_TYPES: &[&str] = &[
+    "application/json",
+    "application/xml",
+    "application/javascript",
+    "image/svg+xml",
+];
+
+/// How long idle connections to a server are kept open.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+/// Maximum number of idle connections kept open per server.
+const POOL_MAX_IDLE: usize = 32;
+
+/// Where a server listens.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Backend {
+    Port(u16),
+    Socket(String),
+}
+
+impl Backend {
+    fn of(process: &ServerProcess) -> Option<Self> {
+        match (&process.socket, process.port) {
+            (Some(socket), _) => Some(Backend::Socket(socket.clone())),
+            (None, Some(port)) => Some(Backend::Port(port)),
+            (None, None) => None,
+        }
+    }
+
+    fn base_url(&self) -> String {
+        match self {
+            Backend::Port(port) => format!("http://127.0.0.1:{}", port),
+            Backend::Socket(_) => "http://localhost".to_string(),
+        }
+    }
+}
+
+/// A client per server, since the socket is set on the client and connections are pooled per client.
+static CLIENTS: LazyLock<Mutex<HashMap<Backend, reqwest::Client>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn client(backend: &Backend) -> reqwest::Client {
+    let mut clients = CLIENTS.lock().unwrap();
+    if !clients.contains_key(backend) {
+        // a new server started, so drop the clients (and pooled connections) of those that stopped since
+        let running = SERVER_MANAGER
+            .get_server_states()
+            .borrow()
+            .iter()
+            .filter_map(|s| s.process.as_ref().and_then(Backend::of))
+            .collect::<Vec<_>>();
+        clients.retain(|backend, _| running.contains(backend));
+    }
+    clients
+        .entry(backend.clone())
+        .or_insert_with(|| {
+            let builder = reqwest::Client::builder()
+                .redirect(reqwest::redirect::Policy::none())
+                .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+                .pool_max_idle_per_host(POOL_MAX_IDLE)
+                .tcp_nodelay(true);
+            #[cfg(unix)]
+            let builder = match backend {
+                Backend::Socket(socket) => builder.unix_socket(socket.as_str()),
+                Backend::Port(_) => builder,
+            };
+            builder.build().expect("Failed to build HTTP client")
+        })
+        .clone()
+}
+
+/// Axum middleware forwarding requests to `/s/{id}/...` to the server, if it is running.
+pub(crate) async fn middleware_proxy_to_server(
+    request: Request,
+    next: Next,
+) -> axum::http::Response<Body> {
+    let Some(target) = request.uri().path().strip_prefix("/s/") else {
+        return next.run(request).await;
+    };
+    let (id, rest) = target.split_once('/').unwrap_or((target, ""));
+    let request_id = forwarded::request_id(&request).map(str::to_string);
+    let Some((spec, backend)) =
+        super::resolve_server(id).and_then(|(spec, process)| Some((spec, Backend::of(&process)?)))
+    else {
+        return forwarded::proxy_error(StatusCode::NOT_FOUND, request_id.as_deref());
+    };
+    if let Some(status) = access_rules::check(&spec, request.method(), rest, request.headers()) {
+        let mut response = forwarded::proxy_error(status, request_id.as_deref());
+        if status == StatusCode::METHOD_NOT_ALLOWED {
+            if let Ok(allow) = HeaderValue::from_str(&spec.options.allowed_methods.join(", ")) {
+                response.headers_mut().insert(header::ALLOW, allow);
+            }
+        }
+        return response;
+    }
+    let encoding = (spec.options.compression && request.method() != Method::HEAD)
+        .then(|| Encoding::accepted(request.headers()))
+        .flatten();
+    let base_url = backend.base_url();
+    let url = match request.uri().query() {
+        Some(query) => format!("{}/{}?{}", base_url, rest, query),
+        None => format!("{}/{}", base_url, rest),
+    };
+    let rest = rest.to_string();
+    exporter::record_request(&spec.id);
+    dashboard::record_request(&spec.id);
+    let result = if folder_zip::is_requested(&spec, request.method(), request.uri().query()) {
+        folder_zip::forward(&client(&backend), request, &spec, &base_url, &rest).await
+    } else if drop_box::is_drop_box(&spec) {
+        drop_box::forward(&client(&backend), request, &base_url, &rest).await
+    } else if spec.options.website && matches!(spec.server_type, ServerType::Http) {
+        website::forward(
+            &client(&backend),
+            request,
+            &base_url,
+            &rest,
+            &spec.options.blocked_paths,
+        )
+        .await
+    } else {
+        leader::forward(&client(&backend), request, url).await
+    };
+    match result {
+        Ok(response) if response.status().is_server_error() => response,
+        Ok(response) => {
+            health::record_successful_request(&spec.id);
+            match encoding {
+                Some(encoding) => compress(response, encoding),
+                None => response,
+            }
+        }
+        Err(e) => {
+            tracing::error!(
+                "Failed to forward request to the server at {}: {}",
+                base_url,
+                e
+            );
+            forwarded::proxy_error(StatusCode::BAD_GATEWAY, request_id.as_deref())
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    /// The encoding the relay can compress with that the client accepts (according to `Accept-Encoding`),
+    /// preferring Brotli.
+    fn accepted(headers: &HeaderMap) -> Option<Self> {
+        let accepted = headers
+            .get_all(header::ACCEPT_ENCODING)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|coding| {
+                let mut parts = coding.split(';').map(str::trim);
+                let name = parts.next()?.to_ascii_lowercase();
+                // "q=0" means not acceptable
+                let refused = parts.any(|param| {
+                    param
+                        .strip_prefix("q=")
+                        .and_then(|q| q.parse::<f32>().ok())
+                        .is_some_and(|q| q <= 0.0)
+                });
+                (!refused).then_some(name)
+            })
+            .collect::<Vec<_>>();
+        if accepted.iter().any(|name| name == "br") {
+            Some(Encoding::Brotli)
+        } else if accepted.iter().any(|name| name == "gzip") {
+            Some(Encoding::Gzip)
+        } else {
+            None
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+}
+
+/// Compresses the body of a successful response with a compressible content type, unless it's encoded already.
+/// Partial responses (to range requests) are left alone, since their ranges refer to the uncompressed content.
+fn compress(
+    response: axum::http::Response<Body>,
+    encoding: Encoding,
+) -> axum::http::Response<Body> {
+    let headers = response.headers();
+    let compressible = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| {
+            value
+                .split(';')
+                .next()
+                .unwrap_or("")
+                .trim()
+                .to_ascii_lowercase()
+        })
+        .is_some_and(|content_type| {
+            content_type.starts_with("text/") || COMPRESSIBLE_TYPES.contains(&content_type.as_str())
+        });
+    if !compressible
+        // 207 Multi-Status for WebDAV listings
+        || !matches!(response.status(), StatusCode::OK | StatusCode::MULTI_STATUS)
+        || headers.contains_key(header::CONTENT_ENCODING)
+        || headers.contains_key(header::CONTENT_RANGE)
+    {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let reader = StreamReader::new(
+        body.into_data_stream()
+            .map(|chunk| chunk.map_err(std::io::Error::other)),
+    );
+    let body = match encoding {
+        Encoding::Brotli => Body::from_stream(ReaderStream::new(BrotliEncoder::new(reader))),
+        Encoding::Gzip => Body::from_stream(ReaderStream::new(GzipEncoder::new(reader))),
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.remove(header::ACCEPT_RANGES);
+    // the compressed body isn't byte-identical to the one the ETag was given for
+    if let Some(etag) = parts.headers.remove(header::ETAG) {
+        if let Some(weak) = weaken_etag(&etag) {
+            parts.headers.insert(header::ETAG, weak);
+        }
+    }
+    parts.headers.insert(
+        header::CONTENT_ENCODING,
+        HeaderValue::from_static(encoding.name()),
+    );
+    parts
+        .headers
+        .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    axum::http::Response::from_parts(parts, body)
+}
+
+/// The weak version of an ETag (`W/"..."`), which only claims semantic equivalence. None if it isn't valid.
+fn weaken_etag(etag: &HeaderValue) -> Option<HeaderValue> {
+    let etag = etag.to_str().ok()?;
+    if etag.starts_with("W/") {
+        return HeaderValue::from_str(etag).ok();
+    }
+    HeaderValue::from_str(&format!("W/{}", etag)).ok()
+}
+
+/// Decodes percent-encoded characters of a URL path, e.g. "My%20Photos" to "My Photos".
+pub(crate) fn decode_path(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
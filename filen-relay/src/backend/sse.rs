@@ -0,0 +1,53 @@
+//! Server-Sent Events variants of the streaming API endpoints,
+//! for clients that don't speak the Dioxus streaming encoding (e.g. `curl` or monitoring tools).
+
+use dioxus::server::axum::{
+    self,
+    extract::{Path, Query},
+    http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use tokio_stream::{
+    wrappers::{BroadcastStream, WatchStream},
+    Stream, StreamExt as _,
+};
+
+use crate::backend::{auth, server_manager::SERVER_MANAGER};
+
+/// `GET /api/sse/servers`: emits a `servers` event with all visible server states on every change.
+pub(crate) async fn servers(
+    session: auth::Session,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let stream = WatchStream::new(SERVER_MANAGER.get_server_states()).map(move |server_states| {
+        Event::default()
+            .event("servers")
+            .json_data(session.visible_server_states(&server_states))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Query parameters of `logs`, see `api::get_logs`.
+#[derive(serde::Deserialize)]
+pub(crate) struct LogsQuery {
+    tail: Option<usize>,
+    cursor: Option<u64>,
+}
+
+/// `GET /api/sse/logs/{server_id}?tail=&cursor=`: emits a `log` event for every past and future log line of a server.
+pub(crate) async fn logs(
+    session: auth::Session,
+    Path(server_id): Path<String>,
+    Query(query): Query<LogsQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, StatusCode> {
+    let logs = SERVER_MANAGER
+        .get_logs(&server_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if !session.can_access(&logs.server_spec) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let (history, rx) = logs.subscribe(query.tail, query.cursor);
+    let stream = tokio_stream::iter(history)
+        .chain(BroadcastStream::new(rx).filter_map(Result::ok))
+        .map(|line| Event::default().event("log").json_data(line));
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
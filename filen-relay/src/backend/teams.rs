@@ -0,0 +1,33 @@
+//! In-memory copy of the teams, so that access checks don't need to query the database.
+
+use std::sync::Mutex;
+
+use anyhow::Result;
+
+use crate::{backend::db::DB, common::Team};
+
+static TEAMS: Mutex<Vec<Team>> = Mutex::new(Vec::new());
+
+/// Reloads the teams from the database. Call after every change to them.
+pub(crate) async fn reload() -> Result<()> {
+    let teams = DB.get_teams().await?;
+    *TEAMS.lock().unwrap() = teams;
+    Ok(())
+}
+
+pub(crate) fn get_teams() -> Vec<Team> {
+    TEAMS.lock().unwrap().clone()
+}
+
+pub(crate) fn find(id: &str) -> Option<Team> {
+    TEAMS.lock().unwrap().iter().find(|t| t.id == id).cloned()
+}
+
+/// Whether the user is the owner or a member of the team.
+pub(crate) fn is_member(team_id: &str, email: &str) -> bool {
+    TEAMS
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|t| t.id == team_id && t.is_member(email))
+}
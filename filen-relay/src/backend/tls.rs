@@ -0,0 +1,125 @@
+//! The certificate of FTP servers encrypting connections with TLS (explicit FTPS, see `ServerOptions::ftp_tls`),
+//! which clients connect to directly rather than through the relay. The admin can provide one with `--tls-cert` and
+//! `--tls-key` (e.g. kept up to date by certbot); otherwise a self-signed one is generated next to the rclone configs
+//! and renewed before it expires. rclone only reads the certificate on startup, so the servers using it are restarted
+//! when the certificate changed since they started (see `renew`).
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result};
+use chrono::Datelike as _;
+
+use crate::{
+    backend::{self, server_manager},
+    common::ServerId,
+};
+
+/// How long generated certificates are valid.
+const VALIDITY_DAYS: i64 = 90;
+/// How old a generated certificate gets before it is replaced.
+const RENEW_AFTER: Duration = Duration::from_secs(60 * 24 * 60 * 60);
+
+static CONFIGURED: OnceLock<Option<(PathBuf, PathBuf)>> = OnceLock::new();
+/// When the certificate passed to each server's rclone was modified.
+static LOADED: Mutex<Option<HashMap<ServerId, SystemTime>>> = Mutex::new(None);
+
+/// Sets the certificate and key (PEM files) provided by the admin, if any.
+pub(crate) fn init(cert: Option<&Path>, key: Option<&Path>) -> Result<()> {
+    let configured = match (cert, key) {
+        (Some(cert), Some(key)) => Some((cert.to_path_buf(), key.to_path_buf())),
+        (None, None) => None,
+        _ => anyhow::bail!("A TLS certificate requires a key and vice versa"),
+    };
+    CONFIGURED
+        .set(configured)
+        .map_err(|_| anyhow::anyhow!("TLS initialized twice"))
+}
+
+/// The paths of the certificate and key to pass to the rclone of `server`, generating a certificate if needed.
+pub(crate) fn certificate(server: &ServerId) -> Result<(PathBuf, PathBuf)> {
+    let (cert, key) = current()?;
+    let modified = modified(&cert)?;
+    LOADED
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(server.clone(), modified);
+    Ok((cert, key))
+}
+
+/// Renews the generated certificate if it's due. Returns the servers among `servers` that were started with an older
+/// certificate, i.e. that need to be restarted.
+pub(crate) fn renew(servers: Vec<ServerId>) -> Result<Vec<ServerId>> {
+    let (cert, _) = current()?;
+    let modified = modified(&cert)?;
+    let loaded = LOADED.lock().unwrap();
+    Ok(servers
+        .into_iter()
+        .filter(|server| {
+            loaded
+                .as_ref()
+                .and_then(|loaded| loaded.get(server))
+                .is_some_and(|loaded| *loaded != modified)
+        })
+        .collect())
+}
+
+fn current() -> Result<(PathBuf, PathBuf)> {
+    if let Some(configured) = CONFIGURED.get().cloned().flatten() {
+        return Ok(configured);
+    }
+    let dir = server_manager::rclone_dir()?.join("tls");
+    let (cert, key) = (dir.join("cert.pem"), dir.join("key.pem"));
+    let due = match modified(&cert) {
+        Ok(modified) => modified.elapsed().unwrap_or_default() >= RENEW_AFTER,
+        Err(_) => true,
+    };
+    if due || !key.exists() {
+        generate(&dir, &cert, &key)?;
+    }
+    Ok((cert, key))
+}
+
+/// Generates a self-signed certificate for the host of `--public-url` (or localhost).
+fn generate(dir: &Path, cert: &Path, key: &Path) -> Result<()> {
+    let host = backend::public_url()
+        .and_then(|url| {
+            let host = url.split("://").nth(1)?.split(['/', ':']).next()?;
+            (!host.is_empty()).then(|| host.to_string())
+        })
+        .unwrap_or("localhost".to_string());
+    let mut params = rcgen::CertificateParams::new(vec![host.clone()])
+        .context("Invalid host for TLS certificate")?;
+    let now = chrono::Utc::now();
+    let expires = now + chrono::Duration::days(VALIDITY_DAYS);
+    params.not_before = rcgen::date_time_ymd(now.year(), now.month() as u8, now.day() as u8);
+    params.not_after =
+        rcgen::date_time_ymd(expires.year(), expires.month() as u8, expires.day() as u8);
+    let key_pair = rcgen::KeyPair::generate().context("Failed to generate TLS key")?;
+    let certificate = params
+        .self_signed(&key_pair)
+        .context("Failed to generate TLS certificate")?;
+
+    std::fs::create_dir_all(dir).context("Failed to create TLS directory")?;
+    std::fs::write(key, key_pair.serialize_pem()).context("Failed to write TLS key")?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt as _;
+        std::fs::set_permissions(key, std::fs::Permissions::from_mode(0o600))
+            .context("Failed to restrict TLS key")?;
+    }
+    std::fs::write(cert, certificate.pem()).context("Failed to write TLS certificate")?;
+    dioxus::logger::tracing::info!("Generated a self-signed TLS certificate for {}", host);
+    Ok(())
+}
+
+fn modified(path: &Path) -> Result<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .with_context(|| format!("Failed to read TLS certificate {}", path.display()))
+}
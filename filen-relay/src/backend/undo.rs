@@ -0,0 +1,46 @@
+//! Recently removed servers, which the user who removed them can restore for `REMOVE_UNDO_WINDOW` (see
+//! `api::undo_remove_server`), while restoring archived servers is otherwise up to the admin. Kept in memory only,
+//! since the window is shorter than a restart.
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::Instant,
+};
+
+use crate::common::{ServerId, REMOVE_UNDO_WINDOW};
+
+/// The user who removed the server and when.
+static REMOVALS: LazyLock<Mutex<HashMap<ServerId, (String, Instant)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+pub(crate) fn record_removal(id: ServerId, filen_email: &str) {
+    let mut removals = REMOVALS.lock().unwrap();
+    removals.retain(|_, (_, removed_at)| removed_at.elapsed() < REMOVE_UNDO_WINDOW);
+    removals.insert(id, (filen_email.to_string(), Instant::now()));
+}
+
+/// Whether the user removed the server within the window, without taking the removal (see `take_removal`).
+pub(crate) fn has_removal(id: &ServerId, filen_email: &str) -> bool {
+    REMOVALS
+        .lock()
+        .unwrap()
+        .get(id)
+        .is_some_and(|(email, removed_at)| {
+            email == filen_email && removed_at.elapsed() < REMOVE_UNDO_WINDOW
+        })
+}
+
+/// Whether the user removed the server within the window, which can then no longer be undone.
+pub(crate) fn take_removal(id: &ServerId, filen_email: &str) -> bool {
+    let mut removals = REMOVALS.lock().unwrap();
+    match removals.get(id) {
+        Some((email, removed_at))
+            if email == filen_email && removed_at.elapsed() < REMOVE_UNDO_WINDOW =>
+        {
+            removals.remove(id);
+            true
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,96 @@
+//! Periodic check for newer Filen Relay releases on GitHub, surfaced to admins.
+
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use dioxus::logger::tracing;
+
+use crate::{
+    backend::scheduler,
+    common::{MaintenanceTask, UpdateInfo},
+};
+
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/JupiterPi/filen-relay/releases/latest";
+/// How often to check for a newer release.
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(6 * 60 * 60);
+/// Number of changelog lines shown in the update notice.
+const CHANGELOG_EXCERPT_LINES: usize = 10;
+
+static AVAILABLE_UPDATE: Mutex<Option<UpdateInfo>> = Mutex::new(None);
+
+/// The newest release, if it is newer than the running version.
+pub(crate) fn available_update() -> Option<UpdateInfo> {
+    AVAILABLE_UPDATE.lock().unwrap().clone()
+}
+
+/// Checks for a newer release every `CHECK_INTERVAL` (see `scheduler`). Runs forever.
+pub(crate) async fn run_update_check() {
+    scheduler::run_periodically(MaintenanceTask::UpdateCheck, CHECK_INTERVAL, || async {
+        let release = fetch_latest_release().await?;
+        let update = is_newer(&release.version, env!("CARGO_PKG_VERSION")).then_some(release);
+        let summary = match &update {
+            Some(update) => {
+                tracing::info!("Filen Relay {} is available", update.version);
+                format!("Filen Relay {} is available", update.version)
+            }
+            None => "Up to date".to_string(),
+        };
+        *AVAILABLE_UPDATE.lock().unwrap() = update;
+        anyhow::Ok(summary)
+    })
+    .await
+}
+
+async fn fetch_latest_release() -> Result<UpdateInfo> {
+    #[derive(serde::Deserialize)]
+    struct Release {
+        tag_name: String,
+        html_url: String,
+        #[serde(default)]
+        body: Option<String>,
+    }
+    let release = reqwest::Client::new()
+        .get(LATEST_RELEASE_URL)
+        // required by the GitHub API
+        .header(
+            "User-Agent",
+            concat!("filen-relay/", env!("CARGO_PKG_VERSION")),
+        )
+        .send()
+        .await
+        .context("Failed to query latest release")?
+        .error_for_status()
+        .context("Failed to query latest release")?
+        .json::<Release>()
+        .await
+        .context("Failed to parse latest release")?;
+    let changelog = release
+        .body
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .take(CHANGELOG_EXCERPT_LINES)
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(UpdateInfo {
+        version: release.tag_name.trim_start_matches('v').to_string(),
+        url: release.html_url,
+        changelog,
+    })
+}
+
+/// Whether `version` is newer than `current` (both like "1.2.3", pre-release suffixes are ignored).
+fn is_newer(version: &str, current: &str) -> bool {
+    fn parse(version: &str) -> Vec<u64> {
+        version
+            .split(['-', '+'])
+            .next()
+            .unwrap_or("")
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    }
+    parse(version) > parse(current)
+}
@@ -0,0 +1,264 @@
+//! Accounting of transferred bytes and running time per server, exported for the admin.
+
+use std::collections::HashMap;
+
+use chrono::Datelike as _;
+
+use dioxus::{
+    logger::tracing,
+    server::axum::{
+        extract::Query,
+        http::{header, StatusCode},
+        response::IntoResponse,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backend::{
+        auth, dashboard,
+        db::DB,
+        exporter, folder_zip,
+        notifications::{self, Notification},
+        serve_backend::{self, ServeBackend},
+        server_manager::{ServerSpecUpdate, SERVER_MANAGER},
+    },
+    common::{EgressCap, ServerId, ServerProcess, ServerSpec, ServerState, ServerStatus},
+};
+
+/// Transferred bytes and running time of a server (or all servers of a user) on one day (UTC).
+#[derive(Serialize)]
+pub(crate) struct UsageRecord {
+    /// Formatted like "2025-01-31".
+    pub day: String,
+    /// Unset when summed up per user.
+    pub server_id: Option<String>,
+    /// Unset when summed up per user, or if the server has been purged.
+    pub server_name: Option<String>,
+    /// Unset if the server has been purged.
+    pub filen_email: Option<String>,
+    pub bytes_transferred: u64,
+    pub running_hours: f64,
+}
+
+/// How often the usage of running servers is recorded.
+const ACCOUNTING_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+/// Periodically adds the bytes transferred by running servers, and the time they ran, to their usage. Runs forever.
+pub(crate) async fn run_usage_accounting(
+    server_states: tokio::sync::watch::Receiver<Vec<ServerState<ServerSpec>>>,
+) {
+    // servers count the bytes since they started, so remember the count per process
+    let mut last_bytes = HashMap::<ServerId, (ServerProcess, u64)>::new();
+    let mut interval = tokio::time::interval(ACCOUNTING_INTERVAL);
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let running = server_states
+            .borrow()
+            .iter()
+            .filter_map(|s| match (&s.status, &s.process) {
+                (ServerStatus::Running, Some(process)) => Some((
+                    s.spec.id.clone(),
+                    s.spec.filen_email.clone(),
+                    serve_backend::for_spec(&s.spec),
+                    process.clone(),
+                )),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let mut usage = Vec::new();
+        for (id, filen_email, backend, process) in running.iter().cloned() {
+            let bytes = match backend.stats(&process).await {
+                Ok(stats) => stats.bytes,
+                Err(e) => {
+                    tracing::warn!("Failed to get transferred bytes of server {}: {:#}", id, e);
+                    0
+                }
+            };
+            let previous = match last_bytes.get(&id) {
+                Some((previous_process, previous)) if *previous_process == process => *previous,
+                _ => 0,
+            };
+            // folder ZIPs are sent by the relay rather than the server
+            let transferred = bytes.saturating_sub(previous) + folder_zip::take_bytes(&id);
+            exporter::record_bytes(&id, transferred);
+            dashboard::record_bytes(&id, transferred);
+            usage.push((
+                id.clone(),
+                filen_email,
+                transferred,
+                ACCOUNTING_INTERVAL.as_secs(),
+            ));
+            last_bytes.insert(id, (process, bytes.max(previous)));
+        }
+        last_bytes.retain(|id, _| usage.iter().any(|(u, _, _, _)| u == id));
+        if usage.is_empty() {
+            continue;
+        }
+        if let Err(e) = DB
+            .record_usage(chrono::Utc::now().date_naive(), &usage)
+            .await
+        {
+            tracing::error!("Failed to record server usage: {}", e);
+        }
+        if let Err(e) = enforce_egress_caps(&running).await {
+            tracing::error!("Failed to enforce egress caps: {:#}", e);
+        }
+    }
+}
+
+/// Returns the egress caps of all users, with their usage in the current month.
+pub(crate) async fn get_egress_caps() -> anyhow::Result<Vec<EgressCap>> {
+    let month_start = chrono::Utc::now()
+        .date_naive()
+        .with_day(1)
+        .expect("Every month has a first day");
+    let used = DB.get_bytes_transferred_per_user_since(month_start).await?;
+    Ok(DB
+        .get_egress_caps()
+        .await?
+        .into_iter()
+        .map(|(filen_email, monthly_gb)| EgressCap {
+            used_bytes: used.get(&filen_email).copied().unwrap_or(0),
+            filen_email,
+            monthly_gb,
+        })
+        .collect())
+}
+
+/// Returns the cap of the user, if they have exceeded it this month.
+pub(crate) async fn exceeded_egress_cap(filen_email: &str) -> anyhow::Result<Option<EgressCap>> {
+    Ok(get_egress_caps()
+        .await?
+        .into_iter()
+        .find(|cap| cap.filen_email == filen_email && cap.is_exceeded()))
+}
+
+impl EgressCap {
+    pub(crate) fn is_exceeded(&self) -> bool {
+        self.used_bytes >= self.monthly_gb * 1024 * 1024 * 1024
+    }
+}
+
+/// Pauses the running servers of users who have exceeded their egress cap this month, and notifies them.
+async fn enforce_egress_caps(
+    running: &[(ServerId, String, &'static dyn ServeBackend, ServerProcess)],
+) -> anyhow::Result<()> {
+    for cap in get_egress_caps().await? {
+        if !cap.is_exceeded() {
+            continue;
+        }
+        let ids = running
+            .iter()
+            .filter(|(_, email, _, _)| *email == cap.filen_email)
+            .map(|(id, _, _, _)| id.clone())
+            .collect::<Vec<_>>();
+        if ids.is_empty() {
+            continue;
+        }
+        tracing::info!(
+            "Pausing {} servers of {}, who exceeded their monthly egress cap of {} GB",
+            ids.len(),
+            cap.filen_email,
+            cap.monthly_gb
+        );
+        for id in ids {
+            // the server is marked as paused even if stopping it failed
+            if let Err(e) = SERVER_MANAGER
+                .update_server_spec(ServerSpecUpdate::Pause(id))
+                .await
+            {
+                tracing::error!("Failed to pause server: {:#}", e);
+            }
+        }
+        notifications::notify(Notification::QuotaExceeded {
+            email: cap.filen_email.clone(),
+            message: format!(
+                "Monthly egress cap of {} GB reached, servers have been paused",
+                cap.monthly_gb
+            ),
+        });
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub(crate) struct UsageExportQuery {
+    #[serde(default)]
+    format: UsageExportFormat,
+    /// Sums up the usage of all servers of a user.
+    #[serde(default)]
+    by_user: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+enum UsageExportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+/// `GET /api/usage/export`: downloads the usage per day and server (or user, with `?by_user=true`) for the admin.
+/// Formatted as JSON, or as CSV with `?format=csv`.
+pub(crate) async fn export_usage(
+    session: auth::Session,
+    Query(query): Query<UsageExportQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    if !session.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let records = DB.get_usage(query.by_user).await.map_err(|e| {
+        tracing::error!("Failed to get usage for export: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(match query.format {
+        UsageExportFormat::Json => (
+            [
+                (header::CONTENT_TYPE, "application/json"),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"filen-relay-usage.json\"",
+                ),
+            ],
+            serde_json::to_string_pretty(&records)
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+        ),
+        UsageExportFormat::Csv => (
+            [
+                (header::CONTENT_TYPE, "text/csv"),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"filen-relay-usage.csv\"",
+                ),
+            ],
+            to_csv(&records),
+        ),
+    })
+}
+
+fn to_csv(records: &[UsageRecord]) -> String {
+    /// Quotes a field if it contains characters with a special meaning in CSV.
+    fn field(value: &str) -> String {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+    let mut csv =
+        String::from("day,server_id,server_name,filen_email,bytes_transferred,running_hours\n");
+    for record in records {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{:.2}\n",
+            record.day,
+            field(record.server_id.as_deref().unwrap_or("")),
+            field(record.server_name.as_deref().unwrap_or("")),
+            field(record.filen_email.as_deref().unwrap_or("")),
+            record.bytes_transferred,
+            record.running_hours,
+        ));
+    }
+    csv
+}
@@ -0,0 +1,100 @@
+//! Website mode of HTTP servers (see `ServerOptions::website`), which lets a folder host a static website: the proxy
+//! serves `index.html` for directories, `about.html` for the clean URL `/about` and the root's `404.html` for
+//! missing pages, by trying these paths on the server in turn. Paths blocked by the server's access rules are never
+//! tried, so that e.g. blocking "/secret.html" also blocks "/secret".
+
+use dioxus::{
+    fullstack::extract::Request,
+    server::axum::{
+        self,
+        body::Body,
+        http::{header, HeaderMap, Method, StatusCode},
+    },
+};
+
+use crate::backend::{access_rules, leader, server_proxy};
+
+/// Shown (with status 404) for paths that aren't found, if it exists.
+const NOT_FOUND_PAGE: &str = "404.html";
+
+/// Forwards the request for `path` (relative to the server's root) to the server at `base_url`, trying the paths
+/// the website may serve it at, except those matching `blocked_paths`.
+pub(crate) async fn forward(
+    client: &reqwest::Client,
+    request: Request,
+    base_url: &str,
+    path: &str,
+    blocked_paths: &[String],
+) -> reqwest::Result<axum::http::Response<Body>> {
+    let is_blocked =
+        |path: &str| access_rules::is_blocked(blocked_paths, &server_proxy::decode_path(path));
+    let method = request.method().clone();
+    if method != Method::GET && method != Method::HEAD {
+        return Ok(axum::http::Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header(header::ALLOW, "GET, HEAD")
+            .body(Body::empty())
+            .unwrap());
+    }
+    let query = request
+        .uri()
+        .query()
+        .map(|query| format!("?{}", query))
+        .unwrap_or_default();
+    let headers = request.headers().clone();
+    for candidate in candidates(path)
+        .into_iter()
+        .filter(|candidate| !is_blocked(candidate))
+    {
+        let url = format!("{}/{}{}", base_url, candidate, query);
+        let response = leader::forward(client, new_request(&method, headers.clone()), url).await?;
+        if response.status() != StatusCode::NOT_FOUND {
+            return Ok(response);
+        }
+    }
+    if is_blocked(NOT_FOUND_PAGE) {
+        return Ok(axum::http::Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap());
+    }
+    // the page of the root, without e.g. a range or conditional headers meant for the missing page
+    let mut headers = headers;
+    for name in [
+        header::RANGE,
+        header::IF_MATCH,
+        header::IF_NONE_MATCH,
+        header::IF_MODIFIED_SINCE,
+        header::IF_UNMODIFIED_SINCE,
+    ] {
+        headers.remove(name);
+    }
+    let url = format!("{}/{}", base_url, NOT_FOUND_PAGE);
+    let mut response = leader::forward(client, new_request(&method, headers), url).await?;
+    *response.status_mut() = StatusCode::NOT_FOUND;
+    Ok(response)
+}
+
+/// The paths to try for `path`, e.g. "docs/index.html" for "docs/", and "about.html", "about/index.html" and
+/// "about" (a file without extension) for "about".
+fn candidates(path: &str) -> Vec<String> {
+    if path.is_empty() || path.ends_with('/') {
+        return vec![format!("{}index.html", path)];
+    }
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    if file_name.contains('.') {
+        return vec![path.to_string()];
+    }
+    vec![
+        format!("{}.html", path),
+        format!("{}/index.html", path),
+        path.to_string(),
+    ]
+}
+
+fn new_request(method: &Method, headers: HeaderMap) -> Request {
+    let mut request = Request::new(Body::empty());
+    *request.method_mut() = method.clone();
+    *request.headers_mut() = headers;
+    request
+}
@@ -1,22 +1,192 @@
 use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator as _;
 use strum_macros::EnumIter;
 
-#[derive(Clone, Serialize, Deserialize)]
+/// The full spec of a server, including its credentials. It is only kept on the server; the browser gets a `ServerSpecPublic`.
+#[cfg(feature = "server")]
+#[derive(Clone)]
 pub(crate) struct ServerSpec {
     pub id: ServerId,
     pub name: String,
     pub server_type: ServerType,
     pub root: String,
     pub read_only: bool,
+    /// Stored obscured, see `backend::obscure`.
     pub password: Option<String>,
     pub filen_email: String,
-    pub filen_password: String,
-    pub filen_2fa_code: Option<String>,
+    /// The owner's serialized Filen auth config, used to start the server.
+    pub filen_auth_config: String,
+    pub options: ServerOptions,
+    /// Emails of other users who may view and manage this server (but not change its collaborators).
+    pub collaborators: Vec<String>,
+    /// The team owning this server, whose members may view and manage it.
+    pub team_id: Option<String>,
+}
+
+/// The parts of a `ServerSpec` sent to the browser, without the server's password and the owner's credentials.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct ServerSpecPublic {
+    pub id: ServerId,
+    pub name: String,
+    pub server_type: ServerType,
+    pub root: String,
+    pub read_only: bool,
+    /// Whether clients need a password to connect.
+    pub has_password: bool,
+    pub filen_email: String,
+    pub options: ServerOptions,
+    #[serde(default)]
+    pub collaborators: Vec<String>,
+    #[serde(default)]
+    pub team_id: Option<String>,
+}
+
+#[cfg(feature = "server")]
+impl From<&ServerSpec> for ServerSpecPublic {
+    fn from(spec: &ServerSpec) -> Self {
+        ServerSpecPublic {
+            id: spec.id.clone(),
+            name: spec.name.clone(),
+            server_type: spec.server_type.clone(),
+            root: spec.root.clone(),
+            read_only: spec.read_only,
+            has_password: spec.password.is_some(),
+            filen_email: spec.filen_email.clone(),
+            options: spec.options.clone(),
+            collaborators: spec.collaborators.clone(),
+            team_id: spec.team_id.clone(),
+        }
+    }
+}
+
+/// Additional, mostly protocol-specific options of a server.
+#[derive(Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub(crate) struct ServerOptions {
+    /// Applies the rclone flags needed by the Windows WebDAV redirector (WebDAV only).
+    pub windows_compatibility: bool,
+    /// Port range for passive FTP data connections, e.g. "30000-30009" (FTP only).
+    pub ftp_passive_ports: Option<String>,
+    /// Encrypts connections with TLS (explicit FTPS), using the relay's certificate (FTP only).
+    pub ftp_tls: bool,
+    /// The rclone VFS cache mode (rclone's default, i.e. no caching, if unset).
+    pub vfs_cache_mode: Option<VfsCacheMode>,
+    /// Absolute directory to keep the VFS cache in (a directory next to the rclone configs if unset).
+    pub cache_dir: Option<String>,
+    /// Additional rclone flags, e.g. "--dir-cache-time=1m". Only admins may set these (also via templates).
+    pub extra_flags: Vec<String>,
+    /// Further directories served side by side with the root, e.g. "/Documents" next to "/Photos".
+    pub additional_roots: Vec<String>,
+    /// Labels for grouping servers, e.g. to start or stop all servers of a project at once.
+    pub tags: Vec<String>,
+    /// Compresses text-like responses (e.g. HTML, JSON and WebDAV listings) for clients accepting gzip or Brotli,
+    /// to save egress (HTTP and WebDAV only).
+    pub compression: bool,
+    /// Serves the server in-process instead of with rclone, which uses less memory. Only read-only HTTP servers with
+    /// a single root are served this way (others ignore the option), and rclone's options don't apply to them.
+    pub native: bool,
+    /// rclone's bandwidth limit, e.g. "10M" or "10M:1M" for separate upload and download limits (unlimited if unset).
+    pub bwlimit: Option<String>,
+    /// The most verbose rclone log level to show in the server's logs (rclone's default, i.e. notice, if unset).
+    pub log_level: Option<LogLevel>,
+    /// Hosts a static website: serves `index.html` for directories, `page.html` at `/page` and the root's `404.html`
+    /// for missing pages (HTTP only).
+    pub website: bool,
+    /// Lets clients download folders as ZIP files with `?download=zip` (HTTP only).
+    pub zip_downloads: bool,
+    /// Only accepts uploads of new files, without listing or downloading files, e.g. to collect files from others
+    /// (read-write WebDAV only).
+    pub drop_box: bool,
+    /// HTTP methods the proxy forwards, e.g. "GET" and "HEAD" to only allow reading (all if empty, HTTP and WebDAV
+    /// only).
+    pub allowed_methods: Vec<String>,
+    /// Path patterns the proxy rejects requests to, e.g. "/.private/*", where `*` matches any characters (HTTP and
+    /// WebDAV only).
+    pub blocked_paths: Vec<String>,
+}
+
+impl ServerOptions {
+    /// Whether options only admins may set are used.
+    pub fn has_admin_only_options(&self) -> bool {
+        self.cache_dir.is_some() || !self.extra_flags.is_empty()
+    }
+
+    /// Whether `bwlimit` is a size like "10M" (bytes per second, with an optional B/K/M/G/T/P suffix) or "off",
+    /// optionally followed by ":" and another one for separate upload and download limits.
+    pub fn is_valid_bwlimit(&self) -> bool {
+        let Some(bwlimit) = &self.bwlimit else {
+            return true;
+        };
+        let is_valid_limit = |limit: &str| {
+            let number = limit.trim_end_matches(|c: char| "bBkKmMgGtTpP".contains(c));
+            limit == "off"
+                || (limit.len() - number.len() <= 1
+                    && number.parse::<f64>().is_ok_and(|n| n >= 0.0))
+        };
+        let mut limits = bwlimit.split(':');
+        limits.clone().count() <= 2 && limits.all(is_valid_limit)
+    }
+
+    /// Resets the options that only apply to other server types, e.g. after the type was changed.
+    pub fn clear_unsupported(&mut self, server_type: &ServerType) {
+        if !matches!(server_type, ServerType::Webdav) {
+            self.windows_compatibility = false;
+            self.drop_box = false;
+        }
+        if !matches!(server_type, ServerType::Ftp) {
+            self.ftp_passive_ports = None;
+            self.ftp_tls = false;
+        }
+        if !matches!(server_type, ServerType::Http | ServerType::Webdav) {
+            self.compression = false;
+        }
+        if !matches!(server_type, ServerType::Http) {
+            self.website = false;
+            self.zip_downloads = false;
+        }
+    }
+
+    /// Parses `ftp_passive_ports` into its first and last port.
+    pub fn ftp_passive_port_range(&self) -> Option<(u16, u16)> {
+        let (start, end) = self.ftp_passive_ports.as_deref()?.split_once('-')?;
+        let (start, end) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+        (1024 <= start && start <= end).then_some((start, end))
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, EnumIter)]
+pub(crate) enum VfsCacheMode {
+    Off,
+    Minimal,
+    Writes,
+    Full,
+}
+
+impl Display for VfsCacheMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VfsCacheMode::Off => write!(f, "off"),
+            VfsCacheMode::Minimal => write!(f, "minimal"),
+            VfsCacheMode::Writes => write!(f, "writes"),
+            VfsCacheMode::Full => write!(f, "full"),
+        }
+    }
+}
+
+impl From<&str> for VfsCacheMode {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "minimal" => VfsCacheMode::Minimal,
+            "writes" => VfsCacheMode::Writes,
+            "full" => VfsCacheMode::Full,
+            _ => VfsCacheMode::Off,
+        }
+    }
 }
 
-#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub(crate) struct ServerId(String);
 
 impl ServerId {
@@ -24,8 +194,9 @@ impl ServerId {
         ServerId(uuid::Uuid::new_v4().to_string())
     }
 
-    pub fn short(&self) -> &str {
-        self.0.split_once('-').unwrap().0
+    /// The first `len` hex digits of the ID, as used in server URLs.
+    pub fn prefix(&self, len: usize) -> String {
+        self.0.chars().filter(|c| *c != '-').take(len).collect()
     }
 }
 
@@ -52,7 +223,17 @@ impl rusqlite::ToSql for ServerId {
     }
 }
 
-#[derive(Clone, Serialize, Deserialize, EnumIter)]
+#[cfg(feature = "server")]
+impl redis::ToRedisArgs for ServerId {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        self.0.write_redis_args(out)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, EnumIter)]
 pub(crate) enum ServerType {
     Http,
     Webdav,
@@ -86,23 +267,282 @@ impl From<&str> for ServerType {
     }
 }
 
+/// Preset settings for common kinds of servers, curated by the admin and offered when creating a server.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct ServerTemplate {
+    pub id: String,
+    pub name: String,
+    pub server_type: ServerType,
+    pub read_only: bool,
+    pub options: ServerOptions,
+}
+
+/// A user's cap on the bytes transferred by their servers per calendar month (UTC), set by the admin.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct EgressCap {
+    pub filen_email: String,
+    pub monthly_gb: u64,
+    /// Transferred in the current month so far.
+    pub used_bytes: u64,
+}
+
+/// The time range of the charts in the dashboard.
+#[derive(Clone, Copy, Default, Serialize, Deserialize, PartialEq, EnumIter)]
+pub(crate) enum StatsRange {
+    #[default]
+    Day,
+    Week,
+}
+
+impl StatsRange {
+    /// The number of hours in the range, i.e. of points in a series.
+    pub(crate) fn hours(&self) -> usize {
+        match self {
+            StatsRange::Day => 24,
+            StatsRange::Week => 7 * 24,
+        }
+    }
+}
+
+impl Display for StatsRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatsRange::Day => write!(f, "Last 24 hours"),
+            StatsRange::Week => write!(f, "Last 7 days"),
+        }
+    }
+}
+
+/// The requests proxied to a server and the bytes it transferred per hour over a `StatsRange`, oldest first
+/// (ending with the current hour).
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct ServerStatsSeries {
+    pub server_id: ServerId,
+    pub name: String,
+    pub filen_email: String,
+    pub requests: Vec<u64>,
+    pub bytes: Vec<u64>,
+}
+
+/// A chat webhook receiving notifications about failed servers, exceeded quotas and logins.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct NotificationChannel {
+    pub id: String,
+    pub kind: NotificationChannelKind,
+    pub webhook_url: String,
+    /// The user receiving notifications about their own servers and quotas through this channel.
+    /// Unset for the admin's channels, which receive all notifications.
+    pub owner: Option<String>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, EnumIter)]
+pub(crate) enum NotificationChannelKind {
+    Discord,
+    Slack,
+}
+
+impl NotificationChannelKind {
+    /// Webhook URLs of this kind start with this, so that no other URLs can be requested by the relay.
+    pub(crate) fn webhook_url_prefixes(&self) -> &'static [&'static str] {
+        match self {
+            NotificationChannelKind::Discord => &[
+                "https://discord.com/api/webhooks/",
+                "https://discordapp.com/api/webhooks/",
+            ],
+            NotificationChannelKind::Slack => &["https://hooks.slack.com/"],
+        }
+    }
+}
+
+impl Display for NotificationChannelKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotificationChannelKind::Discord => write!(f, "Discord"),
+            NotificationChannelKind::Slack => write!(f, "Slack"),
+        }
+    }
+}
+
+impl From<&str> for NotificationChannelKind {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "slack" => NotificationChannelKind::Slack,
+            _ => NotificationChannelKind::Discord,
+        }
+    }
+}
+
+/// A group of users (e.g. a department) that can own servers together.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct Team {
+    pub id: String,
+    pub name: String,
+    /// The user managing the team's members, who is a member as well.
+    pub owner: String,
+    pub members: Vec<String>,
+    /// Maximum number of servers owned by the team (unlimited if unset).
+    pub max_servers: Option<u64>,
+}
+
+impl Team {
+    pub(crate) fn is_member(&self, email: &str) -> bool {
+        self.owner == email || self.members.iter().any(|m| m == email)
+    }
+}
+
+/// A read-only page showing whether selected servers are up, reachable without an account under `/status/{token}`
+/// (e.g. to embed it in a team wiki).
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct StatusPage {
+    /// The secret part of the page's URL.
+    pub token: String,
+    pub name: String,
+    pub owner: String,
+    pub server_ids: Vec<ServerId>,
+}
+
+/// What a status page shows, see `api::get_status_page_content`.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct StatusPageContent {
+    pub name: String,
+    pub servers: Vec<StatusPageServer>,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct StatusPageServer {
+    pub name: String,
+    pub server_type: ServerType,
+    pub online: bool,
+}
+
+/// The state of a server. The server manager keeps it with the full `ServerSpec`, the browser gets it with a `ServerSpecPublic`.
 #[derive(Clone, Serialize, Deserialize)]
-pub(crate) struct ServerState {
-    pub spec: ServerSpec,
-    pub logs_id: String,
+pub(crate) struct ServerState<Spec = ServerSpecPublic> {
+    pub spec: Spec,
+    /// The ID used in the server's URL (/s/<short id>).
+    pub short_id: String,
     pub status: ServerStatus,
+    /// The local server process, while running. Only sent to admins, e.g. to reach rclone directly when debugging a deployment.
+    pub process: Option<ServerProcess>,
+}
+
+#[cfg(feature = "server")]
+impl ServerState<ServerSpec> {
+    pub fn to_public(&self) -> ServerState {
+        ServerState {
+            spec: (&self.spec).into(),
+            short_id: self.short_id.clone(),
+            status: self.status.clone(),
+            process: self.process.clone(),
+        }
+    }
+}
+
+/// How long after removing a server the user can undo it (servers are only archived when removed).
+pub(crate) const REMOVE_UNDO_WINDOW: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Where a running server process can be reached locally.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct ServerProcess {
+    /// Unknown for the dummy servers of mock mode.
+    pub pid: Option<u32>,
+    /// Port the server listens on (on all interfaces), unless it listens on a unix socket.
+    pub port: Option<u16>,
+    /// Unix socket the server listens on instead of a port (HTTP and WebDAV servers on Linux).
+    pub socket: Option<String>,
+    /// Port of the rclone remote control API (only listening on localhost), if served by rclone.
+    pub rc_port: Option<u16>,
+    pub started: chrono::DateTime<chrono::Utc>,
+}
+
+impl ServerProcess {
+    /// Where the server listens, e.g. ":8080" or the path of its unix socket.
+    pub fn local_address(&self) -> String {
+        match (&self.socket, self.port) {
+            (Some(socket), _) => socket.clone(),
+            (None, Some(port)) => format!(":{}", port),
+            (None, None) => "-".to_string(),
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub(crate) enum ServerStatus {
     Starting,
-    Running { port: u16 },
-    Error,
+    Running,
+    Error {
+        reason: ServerErrorReason,
+        exit_code: Option<i32>,
+        /// The last few log lines before the failure, so that the cause can be seen at a glance.
+        last_logs: Vec<LogLine>,
+    },
+    /// Stopped (e.g. because it hasn't been accessed for a long time), keeping its spec; can be resumed.
+    Paused,
+}
+
+/// An action applied to all servers with a tag, see `api::run_group_action`.
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub(crate) enum GroupAction {
+    /// Resumes paused servers and restarts failed ones.
+    Start,
+    /// Pauses the servers.
+    Stop,
+}
+
+/// The outcome of a group action for one of the servers.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct GroupActionResult {
+    pub server_id: ServerId,
+    pub name: String,
+    /// Why the action failed for this server, if it did.
+    pub error: Option<String>,
+}
+
+/// A file currently being transferred by a server, as reported by rclone's `core/stats`.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct Transfer {
+    pub name: String,
+    /// Bytes transferred so far.
+    pub bytes: u64,
+    /// Total size in bytes (negative if unknown).
+    pub size: i64,
+    /// Current speed in bytes per second.
+    pub speed: f64,
+    pub percentage: u8,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub(crate) enum ServerErrorReason {
+    AuthFailure,
+    BindFailure,
+    Crash,
+    /// Not started because the relay already runs the maximum number of servers.
+    CapacityReached,
+}
+
+impl Display for ServerErrorReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServerErrorReason::AuthFailure => write!(f, "Authentication failed"),
+            ServerErrorReason::BindFailure => write!(f, "Failed to bind port"),
+            ServerErrorReason::Crash => write!(f, "Server crashed"),
+            ServerErrorReason::CapacityReached => {
+                write!(f, "Too many servers running on this relay")
+            }
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct LogLine {
+    /// Position in the server's log history (across runs), to resume from with the `cursor` of `get_logs`.
+    #[serde(default)]
+    pub index: u64,
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Counts the starts of a server, so that the logs of different runs can be told apart.
+    pub run: u32,
+    #[serde(default)]
+    pub level: LogLevel,
     pub content: LogLineContent,
 }
 
@@ -111,3 +551,799 @@ pub(crate) enum LogLineContent {
     Event(String),
     ServerProcess(String),
 }
+
+/// Severity of a log line, ordered from least to most severe.
+#[derive(
+    Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, EnumIter,
+)]
+pub(crate) enum LogLevel {
+    Debug,
+    #[default]
+    Info,
+    Notice,
+    Error,
+}
+
+impl LogLevel {
+    /// Parses the level marker of an rclone log line (like `2025/01/01 12:00:00 ERROR : file.txt: ...`),
+    /// defaulting to `Info` for lines without one. rclone's more severe levels count as `Error`, `WARNING` as `Notice`.
+    pub(crate) fn from_rclone_output(line: &str) -> Self {
+        // the marker follows the date and time, if any
+        line.split_whitespace()
+            .take(3)
+            .find_map(|word| match word.trim_end_matches(':') {
+                "DEBUG" => Some(LogLevel::Debug),
+                "INFO" => Some(LogLevel::Info),
+                "NOTICE" | "WARNING" => Some(LogLevel::Notice),
+                "ERROR" | "CRITICAL" | "ALERT" | "EMERGENCY" => Some(LogLevel::Error),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl Display for LogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogLevel::Debug => write!(f, "Debug"),
+            LogLevel::Info => write!(f, "Info"),
+            LogLevel::Notice => write!(f, "Notice"),
+            LogLevel::Error => write!(f, "Error"),
+        }
+    }
+}
+
+impl From<&str> for LogLevel {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "debug" => LogLevel::Debug,
+            "notice" => LogLevel::Notice,
+            "error" => LogLevel::Error,
+            _ => LogLevel::Info,
+        }
+    }
+}
+
+/// A relay-wide event in the admin's activity feed, see `api::get_activity`.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct ActivityEvent {
+    /// Position in the activity history, to resume from with the `cursor` of `get_activity`.
+    pub index: u64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub kind: ActivityKind,
+    pub message: String,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub(crate) enum ActivityKind {
+    ServerStarted,
+    ServerStopped,
+    ServerFailed,
+    UserLoggedIn,
+    QuotaExceeded,
+}
+
+/// An action of the admin, recorded in the audit log, see `backend::audit`.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct AuditEntry {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Email of the admin who took the action.
+    pub actor: String,
+    pub action: AuditAction,
+    /// What the action applied to, e.g. the email of an added user.
+    pub details: String,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, EnumIter)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AuditAction {
+    AllowedUsersChanged,
+    SettingsChanged,
+    AnnouncementChanged,
+    TemplatesChanged,
+    TeamsChanged,
+    EgressCapChanged,
+    ServerRestored,
+    ServerPurged,
+    CachesPurged,
+    AdminAuthConfigRotated,
+    DbConflictOverwritten,
+    ConfigReloaded,
+    DrainStarted,
+    SessionsRevoked,
+}
+
+impl AuditAction {
+    /// Identifies the action in the database and in query strings.
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::AllowedUsersChanged => "allowed_users_changed",
+            AuditAction::SettingsChanged => "settings_changed",
+            AuditAction::AnnouncementChanged => "announcement_changed",
+            AuditAction::TemplatesChanged => "templates_changed",
+            AuditAction::TeamsChanged => "teams_changed",
+            AuditAction::EgressCapChanged => "egress_cap_changed",
+            AuditAction::ServerRestored => "server_restored",
+            AuditAction::ServerPurged => "server_purged",
+            AuditAction::CachesPurged => "caches_purged",
+            AuditAction::AdminAuthConfigRotated => "admin_auth_config_rotated",
+            AuditAction::DbConflictOverwritten => "db_conflict_overwritten",
+            AuditAction::ConfigReloaded => "config_reloaded",
+            AuditAction::DrainStarted => "drain_started",
+            AuditAction::SessionsRevoked => "sessions_revoked",
+        }
+    }
+}
+
+impl Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditAction::AllowedUsersChanged => write!(f, "Allowed users changed"),
+            AuditAction::SettingsChanged => write!(f, "Settings changed"),
+            AuditAction::AnnouncementChanged => write!(f, "Announcement changed"),
+            AuditAction::TemplatesChanged => write!(f, "Templates changed"),
+            AuditAction::TeamsChanged => write!(f, "Teams changed"),
+            AuditAction::EgressCapChanged => write!(f, "Egress cap changed"),
+            AuditAction::ServerRestored => write!(f, "Server restored"),
+            AuditAction::ServerPurged => write!(f, "Server purged"),
+            AuditAction::CachesPurged => write!(f, "Caches purged"),
+            AuditAction::AdminAuthConfigRotated => write!(f, "Admin auth config rotated"),
+            AuditAction::DbConflictOverwritten => write!(f, "Database conflict overwritten"),
+            AuditAction::ConfigReloaded => write!(f, "Config reloaded"),
+            AuditAction::DrainStarted => write!(f, "Drain started"),
+            AuditAction::SessionsRevoked => write!(f, "Sessions revoked"),
+        }
+    }
+}
+
+impl From<&str> for AuditAction {
+    fn from(s: &str) -> Self {
+        AuditAction::iter()
+            .find(|action| action.as_str() == s)
+            .unwrap_or(AuditAction::SettingsChanged)
+    }
+}
+
+/// Narrows down the audit log, see `api::get_audit_log`. Unset fields match all entries.
+#[derive(Clone, Default, Serialize, Deserialize, PartialEq)]
+pub(crate) struct AuditFilter {
+    pub actor: Option<String>,
+    pub action: Option<AuditAction>,
+    /// First day to include (UTC).
+    pub from: Option<chrono::NaiveDate>,
+    /// Last day to include (UTC).
+    pub to: Option<chrono::NaiveDate>,
+}
+
+/// Operational settings of the relay, managed by the admin.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct Settings {
+    pub registration_mode: RegistrationMode,
+    /// Disk quota shared by the VFS caches of all servers.
+    pub cache_quota_gb: Option<u64>,
+    /// Servers that haven't been accessed for this many days are paused.
+    pub auto_pause_days: Option<u64>,
+    /// Maximum number of servers running at the same time on this instance.
+    pub max_running_servers: Option<u64>,
+    /// Maintenance tasks the admin turned off (all run by default).
+    pub disabled_maintenance_tasks: Vec<MaintenanceTask>,
+    pub server_defaults: ServerDefaults,
+    /// Users logging in for the first time enter a code sent to their Filen email (requires SMTP).
+    pub verify_email_on_first_login: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            registration_mode: RegistrationMode::AllowedUsers,
+            cache_quota_gb: None,
+            auto_pause_days: None,
+            max_running_servers: None,
+            disabled_maintenance_tasks: Vec::new(),
+            server_defaults: ServerDefaults::default(),
+            verify_email_on_first_login: false,
+        }
+    }
+}
+
+/// Options new servers get unless their creator sets them, managed by the admin.
+#[derive(Clone, Default, Serialize, Deserialize, PartialEq)]
+pub(crate) struct ServerDefaults {
+    /// Preselected when creating a server.
+    pub read_only: bool,
+    pub vfs_cache_mode: Option<VfsCacheMode>,
+    pub bwlimit: Option<String>,
+    pub log_level: Option<LogLevel>,
+}
+
+impl ServerDefaults {
+    /// Sets the options of a new server that weren't set to the defaults.
+    pub fn apply(&self, options: &mut ServerOptions) {
+        options.vfs_cache_mode = options.vfs_cache_mode.or(self.vfs_cache_mode);
+        options.bwlimit = options.bwlimit.take().or_else(|| self.bwlimit.clone());
+        options.log_level = options.log_level.or(self.log_level);
+    }
+}
+
+/// A periodic background task of the relay, see `backend::scheduler`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, EnumIter)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum MaintenanceTask {
+    /// Persists server accesses and pauses servers unused for `auto_pause_days`.
+    IdleStop,
+    /// Uploads database changes whose backup failed.
+    Backup,
+    /// Deletes log segments on disk of servers that no longer exist.
+    LogPruning,
+    /// Deletes VFS caches of servers that were archived or purged.
+    CacheEviction,
+    UpdateCheck,
+    /// Renews the self-signed TLS certificate and restarts FTPS servers when the certificate changed.
+    CertificateRenewal,
+}
+
+impl MaintenanceTask {
+    /// Identifies the task in the settings.
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            MaintenanceTask::IdleStop => "idle_stop",
+            MaintenanceTask::Backup => "backup",
+            MaintenanceTask::LogPruning => "log_pruning",
+            MaintenanceTask::CacheEviction => "cache_eviction",
+            MaintenanceTask::UpdateCheck => "update_check",
+            MaintenanceTask::CertificateRenewal => "certificate_renewal",
+        }
+    }
+
+    /// Parses `as_str`, ignoring unknown tasks (e.g. ones removed since).
+    pub(crate) fn parse(s: &str) -> Option<Self> {
+        MaintenanceTask::iter().find(|task| task.as_str() == s)
+    }
+}
+
+impl Display for MaintenanceTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MaintenanceTask::IdleStop => write!(f, "Pause unused servers"),
+            MaintenanceTask::Backup => write!(f, "Retry database backups"),
+            MaintenanceTask::LogPruning => write!(f, "Prune logs"),
+            MaintenanceTask::CacheEviction => write!(f, "Evict stale VFS caches"),
+            MaintenanceTask::UpdateCheck => write!(f, "Check for updates"),
+            MaintenanceTask::CertificateRenewal => write!(f, "Renew FTPS certificate"),
+        }
+    }
+}
+
+/// The schedule and latest run of a maintenance task, as shown to the admin.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct MaintenanceTaskStatus {
+    pub task: MaintenanceTask,
+    pub enabled: bool,
+    pub interval_seconds: u64,
+    pub next_run: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_run: Option<MaintenanceTaskRun>,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct MaintenanceTaskRun {
+    pub started: chrono::DateTime<chrono::Utc>,
+    pub duration_ms: u64,
+    /// A summary of what was done, or the error.
+    pub result: Result<String, String>,
+}
+
+/// A newer Filen Relay release, as shown to the admin.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct UpdateInfo {
+    pub version: String,
+    /// The release page.
+    pub url: String,
+    /// The first lines of the release notes.
+    pub changelog: String,
+}
+
+/// Disk usage of the VFS caches, as shown to the admin.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct CacheUsage {
+    pub total_bytes: u64,
+    pub quota_bytes: Option<u64>,
+    /// Usage per server (name and bytes), for servers with a VFS cache.
+    pub servers: Vec<(String, u64)>,
+}
+
+/// Resource usage of the relay's host (or container), as shown to the admin.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct SystemMetrics {
+    pub sampled_at: chrono::DateTime<chrono::Utc>,
+    /// Averaged across all CPUs (of the host, also inside a container).
+    pub cpu_usage_percent: f32,
+    pub cpu_count: usize,
+    /// Of the container, if it has a memory limit, otherwise of the host.
+    pub memory_used_bytes: u64,
+    pub memory_total_bytes: u64,
+    /// Size of `rclone_configs` (the rclone binary, configs and VFS caches).
+    pub rclone_configs_bytes: u64,
+    /// Of the file system containing `rclone_configs`.
+    pub disk_available_bytes: u64,
+    pub disk_total_bytes: u64,
+    /// Ports used by running servers.
+    pub open_ports: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, EnumIter)]
+pub(crate) enum RegistrationMode {
+    /// Any Filen user may log in.
+    Open,
+    /// Only allowed users may log in (or anyone, if no allowed users are configured).
+    AllowedUsers,
+    /// Only the admin may log in.
+    AdminOnly,
+}
+
+impl Display for RegistrationMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistrationMode::Open => write!(f, "Open"),
+            RegistrationMode::AllowedUsers => write!(f, "Allowed Users"),
+            RegistrationMode::AdminOnly => write!(f, "Admin Only"),
+        }
+    }
+}
+
+impl From<&str> for RegistrationMode {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "open" => RegistrationMode::Open,
+            "admin only" => RegistrationMode::AdminOnly,
+            _ => RegistrationMode::AllowedUsers,
+        }
+    }
+}
+
+/// A newly created server, see `api::add_server`.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct AddedServer {
+    pub state: ServerState,
+    /// Where clients connect to the server (unless it's an FTP or SFTP server, which isn't proxied).
+    /// Relative (/s/<short id>/) if the relay doesn't know its public URL.
+    pub url: Option<String>,
+}
+
+/// The outcome of testing a newly created server, see `api::test_server`.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct ServerTestResult {
+    pub success: bool,
+    pub message: String,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub(crate) enum ServerSpecField {
+    Name,
+    ServerType,
+    Root,
+    Password,
+    FtpPassivePorts,
+    CacheDir,
+    ExtraFlags,
+    Bwlimit,
+    AccessRules,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) enum ServerSpecValidationError {
+    NameEmpty,
+    NameTaken,
+    RootInvalid,
+    /// The root directory doesn't exist in the user's Filen drive.
+    RootNotFound,
+    /// Website mode is set for a server that isn't an HTTP server.
+    WebsiteUnsupported,
+    /// The drop box is set for a server that isn't a WebDAV server.
+    DropBoxUnsupported,
+    PasswordTooWeak,
+    FtpPassivePortsInvalid,
+    CacheDirInvalid,
+    ExtraFlagsInvalid,
+    BwlimitInvalid,
+    AllowedMethodsInvalid,
+    BlockedPathsInvalid,
+}
+
+impl ServerSpecValidationError {
+    /// The form field this error should be displayed next to.
+    pub fn field(&self) -> ServerSpecField {
+        match self {
+            ServerSpecValidationError::NameEmpty | ServerSpecValidationError::NameTaken => {
+                ServerSpecField::Name
+            }
+            ServerSpecValidationError::RootInvalid | ServerSpecValidationError::RootNotFound => {
+                ServerSpecField::Root
+            }
+            ServerSpecValidationError::WebsiteUnsupported
+            | ServerSpecValidationError::DropBoxUnsupported => ServerSpecField::ServerType,
+            ServerSpecValidationError::PasswordTooWeak => ServerSpecField::Password,
+            ServerSpecValidationError::FtpPassivePortsInvalid => ServerSpecField::FtpPassivePorts,
+            ServerSpecValidationError::CacheDirInvalid => ServerSpecField::CacheDir,
+            ServerSpecValidationError::ExtraFlagsInvalid => ServerSpecField::ExtraFlags,
+            ServerSpecValidationError::BwlimitInvalid => ServerSpecField::Bwlimit,
+            ServerSpecValidationError::AllowedMethodsInvalid
+            | ServerSpecValidationError::BlockedPathsInvalid => ServerSpecField::AccessRules,
+        }
+    }
+}
+
+impl Display for ServerSpecValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServerSpecValidationError::NameEmpty => write!(f, "Name cannot be empty"),
+            ServerSpecValidationError::NameTaken => {
+                write!(f, "You already have a server with this name")
+            }
+            ServerSpecValidationError::RootInvalid => write!(
+                f,
+                "Root path must be absolute and must not contain empty, \".\" or \"..\" segments"
+            ),
+            ServerSpecValidationError::RootNotFound => {
+                write!(f, "Root path does not exist in your Filen drive")
+            }
+            ServerSpecValidationError::WebsiteUnsupported => {
+                write!(f, "Website mode is only available for HTTP servers")
+            }
+            ServerSpecValidationError::DropBoxUnsupported => {
+                write!(f, "Drop boxes are only available for WebDAV servers")
+            }
+            ServerSpecValidationError::PasswordTooWeak => write!(
+                f,
+                "Password must be at least {} characters long for writable servers",
+                MIN_PASSWORD_LENGTH
+            ),
+            ServerSpecValidationError::FtpPassivePortsInvalid => write!(
+                f,
+                "Passive ports must be a range like \"30000-30009\" of ports from 1024"
+            ),
+            ServerSpecValidationError::CacheDirInvalid => {
+                write!(f, "Cache directory must be an absolute path")
+            }
+            ServerSpecValidationError::ExtraFlagsInvalid => write!(
+                f,
+                "Extra flags must look like \"--flag=value\" and must not set the address or remote control options"
+            ),
+            ServerSpecValidationError::BwlimitInvalid => write!(
+                f,
+                "Bandwidth limit must be a rate like \"10M\", or \"10M:1M\" for separate upload and download limits"
+            ),
+            ServerSpecValidationError::AllowedMethodsInvalid => write!(
+                f,
+                "Allowed methods must be HTTP methods in upper case, like \"GET\""
+            ),
+            ServerSpecValidationError::BlockedPathsInvalid => write!(
+                f,
+                "Blocked paths must start with \"/\", like \"/.private/*\""
+            ),
+        }
+    }
+}
+
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Validates the user-provided parts of a server spec.
+/// `existing_names` are the names of the other servers owned by the same user.
+#[cfg(feature = "server")]
+pub(crate) fn validate_server_spec(
+    name: &str,
+    server_type: &ServerType,
+    root: &str,
+    read_only: bool,
+    password: Option<&str>,
+    options: &ServerOptions,
+    existing_names: &[String],
+) -> Vec<ServerSpecValidationError> {
+    let mut errors = Vec::new();
+    let name = name.trim();
+    if name.is_empty() {
+        errors.push(ServerSpecValidationError::NameEmpty);
+    } else if existing_names.iter().any(|n| n.trim() == name) {
+        errors.push(ServerSpecValidationError::NameTaken);
+    }
+    if !is_valid_root(root) || !options.additional_roots.iter().all(|r| is_valid_root(r)) {
+        errors.push(ServerSpecValidationError::RootInvalid);
+    }
+    if options.website && !matches!(server_type, ServerType::Http) {
+        errors.push(ServerSpecValidationError::WebsiteUnsupported);
+    }
+    if options.drop_box && !matches!(server_type, ServerType::Webdav) {
+        errors.push(ServerSpecValidationError::DropBoxUnsupported);
+    }
+    if let Some(password) = password {
+        if !read_only && password.chars().count() < MIN_PASSWORD_LENGTH {
+            errors.push(ServerSpecValidationError::PasswordTooWeak);
+        }
+    }
+    if options.ftp_passive_ports.is_some() && options.ftp_passive_port_range().is_none() {
+        errors.push(ServerSpecValidationError::FtpPassivePortsInvalid);
+    }
+    if let Some(cache_dir) = &options.cache_dir {
+        if !std::path::Path::new(cache_dir).is_absolute() {
+            errors.push(ServerSpecValidationError::CacheDirInvalid);
+        }
+    }
+    if !options
+        .extra_flags
+        .iter()
+        .all(|flag| is_valid_extra_flag(flag))
+    {
+        errors.push(ServerSpecValidationError::ExtraFlagsInvalid);
+    }
+    if !options.is_valid_bwlimit() {
+        errors.push(ServerSpecValidationError::BwlimitInvalid);
+    }
+    if !options
+        .allowed_methods
+        .iter()
+        .all(|method| !method.is_empty() && method.chars().all(|c| c.is_ascii_uppercase()))
+    {
+        errors.push(ServerSpecValidationError::AllowedMethodsInvalid);
+    }
+    if !options
+        .blocked_paths
+        .iter()
+        .all(|pattern| pattern.starts_with('/'))
+    {
+        errors.push(ServerSpecValidationError::BlockedPathsInvalid);
+    }
+    errors
+}
+
+/// Extra flags must not interfere with the flags the relay sets itself.
+#[cfg(feature = "server")]
+fn is_valid_extra_flag(flag: &str) -> bool {
+    let name = flag.split('=').next().unwrap_or(flag);
+    name.starts_with("--")
+        && name.len() > 2
+        && !flag.chars().any(char::is_whitespace)
+        && !name.starts_with("--rc")
+        && name != "--addr"
+}
+
+#[cfg(feature = "server")]
+fn is_valid_root(root: &str) -> bool {
+    if root == "/" {
+        return true;
+    }
+    let Some(path) = root.strip_prefix('/') else {
+        return false;
+    };
+    !root.chars().any(char::is_control)
+        && path
+            .trim_end_matches('/')
+            .split('/')
+            .all(|segment| !segment.is_empty() && segment != "." && segment != "..")
+}
+
+/// A server spec as exported for migration to another relay instance.
+/// Owner and Filen credentials are not included, they are taken from the importing user.
+#[cfg(feature = "server")]
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct ServerSpecExport {
+    pub name: String,
+    pub server_type: ServerType,
+    pub root: String,
+    pub read_only: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub options: ServerOptions,
+}
+
+/// The result of importing server specs.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct ServerSpecImportResult {
+    pub imported: Vec<String>,
+    pub skipped: Vec<(String, Vec<ServerSpecValidationError>)>,
+}
+
+/// An allowed user along with their activity, so that the admin can spot dormant accounts.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct AllowedUser {
+    pub email: String,
+    pub last_login: Option<chrono::DateTime<chrono::Utc>>,
+    /// Number of (not archived) servers owned by the user.
+    pub servers: usize,
+    pub active_sessions: usize,
+}
+
+/// A session of the user, i.e. a device they are logged in on, which they can log out individually.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct UserSession {
+    /// Derived from the session token, which isn't revealed.
+    pub id: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created: chrono::DateTime<chrono::Utc>,
+    /// The session making the request.
+    pub current: bool,
+}
+
+/// Whether the relay is draining before being stopped (e.g. for a redeploy), see `api::start_drain`.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct DrainStatus {
+    /// Set while logins and new servers are refused.
+    pub draining: bool,
+    /// Whether the database has been backed up since draining started, so that the relay can be stopped.
+    pub safe_to_stop: bool,
+    /// Why backing up the database failed, if it did.
+    pub error: Option<String>,
+}
+
+/// The result (or, in a dry run, the preview) of importing allowed users.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct AllowedUsersImportResult {
+    pub added: Vec<String>,
+    /// Already allowed, or listed more than once.
+    pub duplicates: Vec<String>,
+    /// Entries that aren't email addresses.
+    pub invalid: Vec<String>,
+}
+
+/// The error type of all API endpoints, so that the frontend can branch on `code`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub(crate) struct ApiError {
+    pub code: ApiErrorCode,
+    pub message: String,
+    pub details: Option<ApiErrorDetails>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub(crate) enum ApiErrorCode {
+    /// Not logged in (or the session expired).
+    Unauthorized,
+    /// Logged in, but not allowed to do this (e.g. not the admin).
+    Forbidden,
+    NotFound,
+    InvalidInput,
+    TwoFactorRequired,
+    /// Logging in for the first time, so the code sent to the user's email is required, see
+    /// `ApiErrorDetails::EmailVerification`.
+    EmailVerificationRequired,
+    InvalidCredentials,
+    QuotaExceeded,
+    /// The relay is draining before being stopped, see `DrainStatus`.
+    Unavailable,
+    Internal,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub(crate) enum ApiErrorDetails {
+    Validation(Vec<ServerSpecValidationError>),
+    /// The server was created, but didn't start (`reason` is unset if it didn't start in time).
+    ServerStartFailed {
+        server_id: ServerId,
+        reason: Option<ServerErrorReason>,
+    },
+    /// The login to complete by entering the code sent to the user's email.
+    EmailVerification {
+        verification_id: String,
+    },
+}
+
+impl ApiError {
+    pub fn new(code: ApiErrorCode, message: impl Into<String>) -> Self {
+        ApiError {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: ApiErrorDetails) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn unauthorized() -> Self {
+        Self::new(ApiErrorCode::Unauthorized, "Not logged in")
+    }
+
+    pub fn forbidden() -> Self {
+        Self::new(ApiErrorCode::Forbidden, "Not allowed")
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ApiErrorCode::NotFound, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ApiErrorCode::Internal, message)
+    }
+
+    pub fn validation(errors: Vec<ServerSpecValidationError>) -> Self {
+        Self::new(ApiErrorCode::InvalidInput, "Invalid server spec")
+            .with_details(ApiErrorDetails::Validation(errors))
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<dioxus::prelude::ServerFnError> for ApiError {
+    fn from(e: dioxus::prelude::ServerFnError) -> Self {
+        ApiError::internal(e.to_string())
+    }
+}
+
+impl dioxus::fullstack::AsStatusCode for ApiError {
+    fn as_status_code(&self) -> dioxus::prelude::StatusCode {
+        use dioxus::prelude::StatusCode;
+        match self.code {
+            ApiErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiErrorCode::Forbidden => StatusCode::FORBIDDEN,
+            ApiErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ApiErrorCode::InvalidInput
+            | ApiErrorCode::TwoFactorRequired
+            | ApiErrorCode::EmailVerificationRequired
+            | ApiErrorCode::InvalidCredentials => StatusCode::BAD_REQUEST,
+            ApiErrorCode::QuotaExceeded => StatusCode::TOO_MANY_REQUESTS,
+            ApiErrorCode::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ApiErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Keeps the code of an `ApiError` that was wrapped into an `anyhow::Error` (e.g. with added context).
+#[cfg(feature = "server")]
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast_ref::<ApiError>() {
+            Some(api_error) => ApiError {
+                message: format!("{:#}", e),
+                ..api_error.clone()
+            },
+            None => ApiError::internal(format!("{:#}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clear_unsupported_keeps_only_options_of_the_type() {
+        let all = ServerOptions {
+            windows_compatibility: true,
+            ftp_passive_ports: Some("30000-30009".to_string()),
+            ftp_tls: true,
+            website: true,
+            zip_downloads: true,
+            drop_box: true,
+            compression: true,
+            ..Default::default()
+        };
+
+        let mut http = all.clone();
+        http.clear_unsupported(&ServerType::Http);
+        assert!(http.compression && http.website && http.zip_downloads);
+        assert!(!http.windows_compatibility && !http.drop_box && !http.ftp_tls);
+        assert_eq!(http.ftp_passive_ports, None);
+
+        let mut webdav = all.clone();
+        webdav.clear_unsupported(&ServerType::Webdav);
+        assert!(webdav.windows_compatibility && webdav.drop_box && webdav.compression);
+        assert!(!webdav.website && !webdav.zip_downloads);
+
+        let mut ftp = all.clone();
+        ftp.clear_unsupported(&ServerType::Ftp);
+        assert!(ftp.ftp_tls && ftp.ftp_passive_ports.is_some());
+        assert!(!ftp.website && !ftp.zip_downloads && !ftp.windows_compatibility);
+        assert!(!ftp.compression);
+
+        let mut sftp = all.clone();
+        sftp.clear_unsupported(&ServerType::Sftp);
+        assert!(!sftp.compression && !sftp.ftp_tls);
+    }
+}
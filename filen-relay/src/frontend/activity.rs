@@ -0,0 +1,87 @@
+use chrono::Local;
+use dioxus::{
+    logger::tracing::{self},
+    prelude::*,
+};
+
+use crate::{
+    common::{ActivityEvent, ActivityKind, ApiErrorCode},
+    frontend::servers::{RECONNECT_MAX_DELAY, RECONNECT_MIN_DELAY},
+};
+
+/// Live feed of relay-wide events for the admin, newest first.
+#[component]
+pub(crate) fn ActivityFeed() -> Element {
+    let mut events = use_signal(Vec::<ActivityEvent>::new);
+    use_future(move || async move {
+        let mut reconnect_delay = RECONNECT_MIN_DELAY;
+        loop {
+            // resume after the last received event
+            let cursor = events.read().last().map(|event| event.index);
+            match crate::api::get_activity(cursor).await {
+                Ok(mut activity_stream) => loop {
+                    match activity_stream.next().await {
+                        Some(Ok(event)) => {
+                            events.write().push(event);
+                            reconnect_delay = RECONNECT_MIN_DELAY;
+                        }
+                        Some(Err(err)) => {
+                            tracing::error!("Error receiving activity: {}", err);
+                            break;
+                        }
+                        None => {
+                            tracing::info!("Activity stream ended");
+                            break;
+                        }
+                    }
+                },
+                Err(err)
+                    if matches!(
+                        err.code,
+                        ApiErrorCode::Unauthorized | ApiErrorCode::Forbidden
+                    ) =>
+                {
+                    tracing::error!("Failed to fetch activity: {}", err);
+                    return;
+                }
+                Err(err) => {
+                    tracing::error!("Failed to fetch activity: {}", err);
+                }
+            }
+            tracing::info!("Reconnecting to activity in {:?}", reconnect_delay);
+            crate::util::sleep(reconnect_delay).await;
+            reconnect_delay = (reconnect_delay * 2).min(RECONNECT_MAX_DELAY);
+        }
+    });
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-4 rounded-lg",
+            h2 { class: "font-bold text-lg", "Activity" }
+            if events.read().is_empty() {
+                div { class: "text-gray-500", "No activity yet." }
+            }
+            for (event , timestamp , color) in events.read()
+                .iter()
+                .rev()
+                .map(|event| (
+                    event.clone(),
+                    event.timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+                    kind_color(event.kind),
+                ))
+            {
+                div { class: "font-mono text-sm",
+                    span { class: "text-gray-500 mr-2", "[{timestamp}] " }
+                    span { class: "{color}", "{event.message}" }
+                }
+            }
+        }
+    }
+}
+
+fn kind_color(kind: ActivityKind) -> &'static str {
+    match kind {
+        ActivityKind::ServerStarted => "text-green-600",
+        ActivityKind::ServerStopped => "text-gray-600",
+        ActivityKind::ServerFailed | ActivityKind::QuotaExceeded => "text-red-500",
+        ActivityKind::UserLoggedIn => "text-blue-500",
+    }
+}
@@ -0,0 +1,74 @@
+use dioxus::{
+    logger::tracing::{self},
+    prelude::*,
+};
+
+use crate::frontend::toasts::show_error;
+
+/// The current announcement, shared so that the banner updates as soon as the admin changes it.
+static ANNOUNCEMENT: GlobalSignal<Option<String>> = Signal::global(|| None);
+
+/// Shows the announcement set by the admin to all logged-in users.
+#[component]
+pub(crate) fn AnnouncementBanner() -> Element {
+    use_effect(|| {
+        spawn(async move {
+            match crate::api::get_announcement().await {
+                Ok(announcement) => *ANNOUNCEMENT.write() = announcement,
+                Err(err) => tracing::error!("Failed to fetch announcement: {}", err),
+            }
+        });
+    });
+
+    rsx! {
+        if let Some(announcement) = ANNOUNCEMENT() {
+            div { class: "border border-blue-500 p-2 rounded-lg mb-4 whitespace-pre-wrap",
+                "{announcement}"
+            }
+        }
+    }
+}
+
+#[component]
+pub(crate) fn ManageAnnouncement() -> Element {
+    let mut message = use_signal(|| ANNOUNCEMENT().unwrap_or_default());
+    let mut saving = use_signal(|| false);
+
+    rsx! {
+        form {
+            class: "flex flex-col gap-2 border p-4 rounded-lg max-w-120",
+            onsubmit: move |e| async move {
+                e.prevent_default();
+                saving.set(true);
+                match crate::api::set_announcement(message()).await {
+                    Ok(_) => {
+                        tracing::info!("Announcement saved successfully");
+                        let message = message().trim().to_string();
+                        *ANNOUNCEMENT.write() = (!message.is_empty()).then_some(message);
+                    }
+                    Err(err) => {
+                        show_error("Failed to save announcement", &err);
+                    }
+                }
+                saving.set(false);
+            },
+            h2 { class: "font-bold text-lg", "Announcement" }
+            p { class: "text-sm text-gray-500",
+                "Shown as a banner to all logged-in users. Leave empty to remove it."
+            }
+            textarea {
+                class: "_input",
+                rows: "3",
+                placeholder: "e.g. Maintenance tonight from 22:00",
+                value: "{message}",
+                oninput: move |e| message.set(e.value()),
+            }
+            button {
+                class: "_button",
+                r#type: "submit",
+                disabled: *saving.read(),
+                "Save Announcement"
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use dioxus::{
+    logger::tracing::{self},
+    prelude::*,
+};
+
+use crate::{common::ServerSpecPublic, frontend::toasts::show_error};
+
+#[component]
+pub(crate) fn ArchivedServers() -> Element {
+    let mut archived_servers = use_signal(|| None::<Vec<ServerSpecPublic>>);
+    let mut loading = use_signal(|| false);
+
+    let fetch_archived_servers = move || {
+        spawn(async move {
+            loading.set(true);
+            match crate::api::get_archived_servers().await {
+                Ok(servers) => {
+                    archived_servers.set(Some(servers));
+                }
+                Err(err) => {
+                    show_error("Failed to fetch archived servers", &err);
+                }
+            }
+            loading.set(false);
+        });
+    };
+    use_effect(move || {
+        fetch_archived_servers();
+    });
+
+    rsx! {
+        div { class: "flex flex-col gap-4 border p-4 rounded-lg",
+            h2 { class: "font-bold text-lg", "Archived Servers" }
+            if *loading.read() {
+                div { class: "text-gray-500", "Loading..." }
+            } else {
+                match archived_servers() {
+                    Some(servers) if !servers.is_empty() => rsx! {
+                        div { class: "flex flex-col gap-2",
+                            for server in servers {
+                                div { class: "flex items-center gap-2 p-2 border rounded",
+                                    span { class: "flex-1",
+                                        "{server.name} "
+                                        span { class: "font-mono text-gray-500", "#{server.id}" }
+                                        span { class: "text-gray-500", " ({server.server_type}, {server.filen_email})" }
+                                    }
+                                    button {
+                                        class: "_button px-2 py-1 text-sm",
+                                        onclick: {
+                                            let id = server.id.clone();
+                                            move |_| {
+                                                let id = id.clone();
+                                                async move {
+                                                    match crate::api::restore_server(id).await {
+                                                        Ok(_) => {
+                                                            tracing::info!("Server restored successfully");
+                                                            fetch_archived_servers();
+                                                        }
+                                                        Err(err) => {
+                                                            show_error("Failed to restore server", &err);
+                                                        }
+                                                    }
+                                                }
+                                            }
+                                        },
+                                        "Restore"
+                                    }
+                                    button {
+                                        class: "_button px-2 py-1 text-sm bg-red-500 hover:bg-red-600",
+                                        onclick: {
+                                            let id = server.id.clone();
+                                            move |_| {
+                                                let id = id.clone();
+                                                async move {
+                                                    match crate::api::purge_server(id).await {
+                                                        Ok(_) => {
+                                                            tracing::info!("Server purged successfully");
+                                                            fetch_archived_servers();
+                                                        }
+                                                        Err(err) => {
+                                                            show_error("Failed to purge server", &err);
+                                                        }
+                                                    }
+                                                }
+                                            }
+                                        },
+                                        "Purge"
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    Some(_) => rsx! {
+                        div { class: "text-gray-500", "No archived servers." }
+                    },
+                    None => rsx! {
+                        div { class: "text-gray-500", "Failed to load archived servers." }
+                    },
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use chrono::{Local, NaiveDate};
+use dioxus::prelude::*;
+use strum::IntoEnumIterator as _;
+
+use crate::{
+    common::{AuditAction, AuditFilter},
+    frontend::toasts::show_error,
+};
+
+/// The admin's audit log, filtered by actor, action and date range, newest first.
+#[component]
+pub(crate) fn AuditLog() -> Element {
+    let mut filter = use_signal(AuditFilter::default);
+    let entries = use_resource(move || async move {
+        crate::api::get_audit_log(filter())
+            .await
+            .inspect_err(|err| show_error("Failed to fetch audit log", err))
+            .ok()
+    });
+    let actor = filter.read().actor.clone().unwrap_or_default();
+    let export_url = format!("/api/audit/export{}", export_query(&filter.read()));
+
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-4 rounded-lg",
+            h2 { class: "font-bold text-lg", "Audit Log" }
+            p { class: "text-sm text-gray-500",
+                "Changes made by the admin, kept for a year. Only the newest 1000 matching entries are shown; the export contains all of them."
+            }
+            div { class: "flex flex-wrap gap-2 items-center",
+                input {
+                    class: "_input",
+                    r#type: "email",
+                    placeholder: "Actor email",
+                    value: "{actor}",
+                    onchange: move |e| {
+                        let actor = e.value().trim().to_string();
+                        filter.write().actor = (!actor.is_empty()).then_some(actor);
+                    },
+                }
+                select {
+                    class: "_input",
+                    onchange: move |e| {
+                        filter.write().action = match e.value().as_str() {
+                            "" => None,
+                            action => Some(AuditAction::from(action)),
+                        };
+                    },
+                    option { value: "", selected: filter.read().action.is_none(), "All actions" }
+                    for action in AuditAction::iter() {
+                        option {
+                            value: action.as_str(),
+                            selected: filter.read().action == Some(action),
+                            "{action}"
+                        }
+                    }
+                }
+                label { class: "flex items-center gap-2",
+                    "From"
+                    input {
+                        class: "_input",
+                        r#type: "date",
+                        onchange: move |e| filter.write().from = e.value().parse::<NaiveDate>().ok(),
+                    }
+                }
+                label { class: "flex items-center gap-2",
+                    "To"
+                    input {
+                        class: "_input",
+                        r#type: "date",
+                        onchange: move |e| filter.write().to = e.value().parse::<NaiveDate>().ok(),
+                    }
+                }
+                a {
+                    class: "_button",
+                    href: "{export_url}",
+                    download: "filen-relay-audit-log.csv",
+                    "Export CSV"
+                }
+            }
+            match entries() {
+                Some(Some(entries)) if entries.is_empty() => rsx! {
+                    div { class: "text-gray-500", "No matching entries." }
+                },
+                Some(Some(entries)) => rsx! {
+                    table { class: "text-sm",
+                        thead {
+                            tr { class: "text-left",
+                                th { class: "pr-4", "Time" }
+                                th { class: "pr-4", "Actor" }
+                                th { class: "pr-4", "Action" }
+                                th { "Details" }
+                            }
+                        }
+                        tbody {
+                            for (entry , timestamp) in entries
+                                .into_iter()
+                                .map(|entry| {
+                                    let timestamp = entry
+                                        .timestamp
+                                        .with_timezone(&Local)
+                                        .format("%Y-%m-%d %H:%M:%S")
+                                        .to_string();
+                                    (entry, timestamp)
+                                })
+                            {
+                                tr {
+                                    td { class: "pr-4 font-mono text-gray-500 whitespace-nowrap",
+                                        "{timestamp}"
+                                    }
+                                    td { class: "pr-4", "{entry.actor}" }
+                                    td { class: "pr-4 whitespace-nowrap", "{entry.action}" }
+                                    td { "{entry.details}" }
+                                }
+                            }
+                        }
+                    }
+                },
+                Some(None) => rsx! {
+                    div { class: "text-red-500", "Failed to load the audit log." }
+                },
+                None => rsx! {
+                    div { class: "text-gray-500", "Loading..." }
+                },
+            }
+        }
+    }
+}
+
+/// The query string for `/api/audit/export` with the set fields of `filter`.
+fn export_query(filter: &AuditFilter) -> String {
+    let params = [
+        filter
+            .actor
+            .as_deref()
+            .map(|actor| format!("actor={}", encode(actor))),
+        filter
+            .action
+            .map(|action| format!("action={}", action.as_str())),
+        filter.from.map(|from| format!("from={}", from)),
+        filter.to.map(|to| format!("to={}", to)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+    if params.is_empty() {
+        String::new()
+    } else {
+        format!("?{}", params.join("&"))
+    }
+}
+
+/// Percent-encodes a query parameter value.
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -0,0 +1,275 @@
+use dioxus::prelude::*;
+
+use crate::{
+    common::{ServerId, ServerState, ServerStatus, ServerType},
+    frontend::{pwa, toasts::show_error, Route, AUTH},
+};
+
+/// How many matching commands are listed at most.
+const MAX_RESULTS: usize = 10;
+
+/// Opens the palette on Ctrl+K (or Cmd+K), also while typing in an input.
+const SHORTCUT_LISTENER: &str = r#"
+    document.addEventListener("keydown", (e) => {
+        if ((e.ctrlKey || e.metaKey) && e.key.toLowerCase() === "k") {
+            e.preventDefault();
+            dioxus.send(true);
+        }
+    });
+"#;
+
+/// A server the palette can act on.
+#[derive(Clone, PartialEq)]
+struct IndexedServer {
+    id: ServerId,
+    short_id: String,
+    name: String,
+    server_type: ServerType,
+    tags: Vec<String>,
+    paused: bool,
+}
+
+/// The user's servers, updated from the states stream (see `index_servers`).
+static SERVER_INDEX: GlobalSignal<Vec<IndexedServer>> = Signal::global(Vec::new);
+
+/// Updates the servers the palette searches, e.g. on each snapshot of the states stream.
+pub(crate) fn index_servers(servers: &[ServerState]) {
+    *SERVER_INDEX.write() = servers
+        .iter()
+        .map(|server| IndexedServer {
+            id: server.spec.id.clone(),
+            short_id: server.short_id.clone(),
+            name: server.spec.name.clone(),
+            server_type: server.spec.server_type.clone(),
+            tags: server.spec.options.tags.clone(),
+            paused: matches!(server.status, ServerStatus::Paused),
+        })
+        .collect();
+}
+
+/// Forgets the indexed servers, e.g. on logout.
+pub(crate) fn clear_index() {
+    SERVER_INDEX.write().clear();
+}
+
+#[derive(Clone, PartialEq)]
+enum Command {
+    JumpToServer(IndexedServer),
+    OpenLogs(IndexedServer),
+    RestartServer(IndexedServer),
+    CreateServer,
+    OpenPage(Route, &'static str),
+}
+
+impl Command {
+    fn label(&self) -> String {
+        match self {
+            Command::JumpToServer(server) => format!("Jump to {}", server.name),
+            Command::OpenLogs(server) => format!("Open logs of {}", server.name),
+            Command::RestartServer(server) => format!("Restart {}", server.name),
+            Command::CreateServer => "Create server".to_string(),
+            Command::OpenPage(_, title) => format!("Go to {}", title),
+        }
+    }
+
+    /// What the query is matched against besides the label.
+    fn keywords(&self) -> String {
+        match self {
+            Command::JumpToServer(server)
+            | Command::OpenLogs(server)
+            | Command::RestartServer(server) => format!(
+                "#{} {} {}",
+                server.short_id,
+                server.server_type,
+                server.tags.join(" ")
+            ),
+            Command::CreateServer => "new add".to_string(),
+            Command::OpenPage(_, _) => "page open".to_string(),
+        }
+    }
+
+    /// Whether every word of the query appears in the label or keywords, ignoring case.
+    fn matches(&self, query: &str) -> bool {
+        let haystack = format!("{} {}", self.label(), self.keywords()).to_lowercase();
+        query
+            .to_lowercase()
+            .split_whitespace()
+            .all(|word| haystack.contains(word))
+    }
+}
+
+fn commands(is_admin: bool) -> Vec<Command> {
+    let mut commands = vec![Command::CreateServer];
+    for server in SERVER_INDEX.read().iter() {
+        commands.push(Command::JumpToServer(server.clone()));
+        commands.push(Command::OpenLogs(server.clone()));
+        if !server.paused {
+            commands.push(Command::RestartServer(server.clone()));
+        }
+    }
+    commands.extend([
+        Command::OpenPage(Route::DashboardPage {}, "Dashboard"),
+        Command::OpenPage(Route::TeamsPage {}, "Teams"),
+        Command::OpenPage(Route::NotificationsPage {}, "Notifications"),
+        Command::OpenPage(Route::DevicesPage {}, "Devices"),
+    ]);
+    if is_admin {
+        commands.extend([
+            Command::OpenPage(Route::ManageAllowedUsersPage {}, "Manage Allowed Users"),
+            Command::OpenPage(Route::SettingsPage {}, "Settings"),
+            Command::OpenPage(Route::ArchivedServersPage {}, "Archived Servers"),
+            Command::OpenPage(Route::ActivityPage {}, "Activity"),
+            Command::OpenPage(Route::AuditPage {}, "Audit Log"),
+        ]);
+    }
+    commands
+}
+
+fn run(command: Command) {
+    let navigator = navigator();
+    match command {
+        Command::JumpToServer(server) => {
+            navigator.push(Route::Home {});
+            scroll_to(&format!("server-{}", server.short_id));
+        }
+        Command::OpenLogs(server) => {
+            navigator.push(Route::LogsPage {
+                server_id: server.id.to_string(),
+            });
+        }
+        Command::RestartServer(server) => {
+            spawn(async move {
+                if let Err(err) = crate::api::restart_server(server.id).await {
+                    show_error("Failed to restart server", &err);
+                }
+            });
+        }
+        Command::CreateServer => {
+            navigator.push(Route::Home {});
+            scroll_to("create-server");
+        }
+        Command::OpenPage(route, _) => {
+            navigator.push(route);
+        }
+    }
+}
+
+/// Scrolls to the element once it's rendered, e.g. after navigating to the page containing it.
+fn scroll_to(element_id: &str) {
+    let eval = document::eval(
+        r#"
+            const id = await dioxus.recv();
+            const scroll = (tries) => {
+                const element = document.getElementById(id);
+                if (element) {
+                    element.scrollIntoView({ behavior: "smooth", block: "center" });
+                } else if (tries > 0) {
+                    setTimeout(() => scroll(tries - 1), 100);
+                }
+            };
+            scroll(20);
+        "#,
+    );
+    let _ = eval.send(element_id);
+}
+
+/// Searches the user's servers and pages to jump to them, open their logs or restart them, opened with Ctrl+K.
+#[component]
+pub(crate) fn CommandPalette() -> Element {
+    let mut open = use_signal(|| false);
+    let mut query = use_signal(String::new);
+    let mut selected = use_signal(|| 0usize);
+    use_future(move || async move {
+        let mut shortcut = document::eval(SHORTCUT_LISTENER);
+        while shortcut.recv::<bool>().await.is_ok() {
+            open.toggle();
+        }
+    });
+    // outside of the servers list, the states stream isn't received, so start with the last known states
+    use_effect(move || {
+        if open() && SERVER_INDEX.peek().is_empty() {
+            spawn(async move {
+                if let Some(cached) = pwa::load_cached_servers().await {
+                    index_servers(&cached);
+                }
+            });
+        }
+    });
+    let mut close = move || {
+        open.set(false);
+        query.set(String::new());
+        selected.set(0);
+    };
+
+    let is_admin = AUTH.read().as_ref().is_some_and(|auth| auth.is_admin);
+    let results = commands(is_admin)
+        .into_iter()
+        .filter(|command| command.matches(&query.read()))
+        .take(MAX_RESULTS)
+        .collect::<Vec<_>>();
+    let selected_index = selected().min(results.len().saturating_sub(1));
+
+    rsx! {
+        button {
+            class: "cursor-pointer text-gray-500 hover:underline",
+            onclick: move |_| open.set(true),
+            "Search (Ctrl+K)"
+        }
+        if open() {
+            div {
+                class: "fixed inset-0 bg-black/50 flex justify-center items-start pt-24 z-40",
+                onclick: move |_| close(),
+                div {
+                    class: "flex flex-col gap-2 border bg-gray-900 p-4 rounded-lg w-120 max-w-full",
+                    onclick: move |e| e.stop_propagation(),
+                    input {
+                        class: "_input w-full",
+                        r#type: "text",
+                        placeholder: "Search servers and pages...",
+                        autofocus: true,
+                        value: "{query}",
+                        oninput: move |e| {
+                            query.set(e.value());
+                            selected.set(0);
+                        },
+                        onkeydown: {
+                            let results = results.clone();
+                            move |e: KeyboardEvent| match e.key() {
+                                Key::ArrowDown => {
+                                    e.prevent_default();
+                                    selected.set((selected_index + 1).min(results.len().saturating_sub(1)));
+                                }
+                                Key::ArrowUp => {
+                                    e.prevent_default();
+                                    selected.set(selected_index.saturating_sub(1));
+                                }
+                                Key::Enter => {
+                                    if let Some(command) = results.get(selected_index) {
+                                        run(command.clone());
+                                        close();
+                                    }
+                                }
+                                Key::Escape => close(),
+                                _ => {}
+                            }
+                        },
+                    }
+                    if results.is_empty() {
+                        div { class: "text-gray-500", "No matches." }
+                    }
+                    for (i, command) in results.into_iter().enumerate() {
+                        div {
+                            class: if i == selected_index { "cursor-pointer px-2 py-1 rounded bg-blue-600" } else { "cursor-pointer px-2 py-1 rounded" },
+                            onmouseenter: move |_| selected.set(i),
+                            onclick: move |_| {
+                                run(command.clone());
+                                close();
+                            },
+                            "{command.label()}"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
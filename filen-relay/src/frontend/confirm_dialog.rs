@@ -0,0 +1,36 @@
+use dioxus::prelude::*;
+
+/// Asks the user to confirm a destructive action, e.g. removing a server. Shown on top of the page until either button
+/// (or the backdrop) is clicked.
+#[component]
+pub(crate) fn ConfirmDialog(
+    message: String,
+    confirm_label: String,
+    on_confirm: EventHandler<()>,
+    on_cancel: EventHandler<()>,
+) -> Element {
+    rsx! {
+        div {
+            class: "fixed inset-0 bg-black/50 flex justify-center items-start pt-24 z-40",
+            onclick: move |_| on_cancel.call(()),
+            div {
+                class: "flex flex-col gap-4 border bg-gray-900 p-4 rounded-lg w-100 max-w-full",
+                onclick: move |e| e.stop_propagation(),
+                p { "{message}" }
+                div { class: "flex gap-2 justify-end",
+                    button {
+                        class: "_button",
+                        autofocus: true,
+                        onclick: move |_| on_cancel.call(()),
+                        "Cancel"
+                    }
+                    button {
+                        class: "_button bg-red-500 hover:bg-red-600",
+                        onclick: move |_| on_confirm.call(()),
+                        "{confirm_label}"
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use dioxus::{
+    logger::tracing::{self},
+    prelude::*,
+};
+use strum::IntoEnumIterator as _;
+
+use crate::{
+    common::{ServerStatsSeries, StatsRange},
+    frontend::AUTH,
+    util::format_bytes,
+};
+
+/// Requests and transferred bytes of the user's servers (or, for the admin, of all servers) over time.
+#[component]
+pub(crate) fn Dashboard() -> Element {
+    let mut range = use_signal(StatsRange::default);
+    let mut all = use_signal(|| false);
+    let series = use_resource(move || async move {
+        crate::api::get_dashboard(range(), all())
+            .await
+            .inspect_err(|err| tracing::error!("Failed to fetch dashboard: {}", err))
+            .unwrap_or_default()
+    });
+    let is_admin = AUTH.read().as_ref().is_some_and(|auth| auth.is_admin);
+
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-4 rounded-lg",
+            div { class: "flex gap-2 items-center",
+                h2 { class: "font-bold text-lg flex-1", "Dashboard" }
+                if is_admin {
+                    label { class: "flex items-center gap-2",
+                        "All servers"
+                        input {
+                            r#type: "checkbox",
+                            checked: all(),
+                            onchange: move |e| all.set(e.value() == "true"),
+                        }
+                    }
+                }
+                for option in StatsRange::iter() {
+                    button {
+                        class: if option == range() { "_button bg-blue-600" } else { "_button" },
+                        onclick: move |_| range.set(option),
+                        "{option}"
+                    }
+                }
+            }
+            p { class: "text-sm text-gray-500",
+                "Requests and transferred bytes per hour. The current hour is updated every few minutes."
+            }
+            match series() {
+                Some(series) if series.is_empty() => rsx! {
+                    div { class: "text-gray-500", "No servers." }
+                },
+                Some(series) => rsx! {
+                    for server in series {
+                        ServerStatsRow { key: "{server.server_id}", server, show_owner: all() }
+                    }
+                },
+                None => rsx! {
+                    div { class: "text-gray-500", "Loading..." }
+                },
+            }
+        }
+    }
+}
+
+#[component]
+fn ServerStatsRow(server: ServerStatsSeries, show_owner: bool) -> Element {
+    let requests = server.requests.iter().sum::<u64>();
+    let bytes = server.bytes.iter().sum::<u64>();
+    rsx! {
+        div { class: "flex gap-4 items-center",
+            div { class: "w-48 min-w-0",
+                p { class: "truncate", "{server.name}" }
+                if show_owner {
+                    p { class: "text-sm text-gray-500 truncate", "{server.filen_email}" }
+                }
+            }
+            div { class: "flex flex-col",
+                Sparkline { values: server.requests.clone(), color: "#3b82f6" }
+                span { class: "text-sm text-gray-500", "{requests} requests" }
+            }
+            div { class: "flex flex-col",
+                Sparkline { values: server.bytes.clone(), color: "#22c55e" }
+                span { class: "text-sm text-gray-500", "{format_bytes(bytes)}" }
+            }
+        }
+    }
+}
+
+const SPARKLINE_WIDTH: f64 = 200.0;
+const SPARKLINE_HEIGHT: f64 = 32.0;
+
+/// A small line chart of the values, scaled to the largest one.
+#[component]
+fn Sparkline(values: Vec<u64>, color: &'static str) -> Element {
+    let max = values.iter().copied().max().unwrap_or(0).max(1) as f64;
+    let step = SPARKLINE_WIDTH / (values.len().max(2) - 1) as f64;
+    let points = values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let x = i as f64 * step;
+            // keep the line inside the box
+            let y = 1.0 + (SPARKLINE_HEIGHT - 2.0) * (1.0 - *value as f64 / max);
+            format!("{:.1},{:.1}", x, y)
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    rsx! {
+        svg {
+            width: "{SPARKLINE_WIDTH}",
+            height: "{SPARKLINE_HEIGHT}",
+            view_box: "0 0 {SPARKLINE_WIDTH} {SPARKLINE_HEIGHT}",
+            polyline {
+                points: "{points}",
+                fill: "none",
+                stroke: "{color}",
+                stroke_width: "1.5",
+            }
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use chrono::Local;
+use dioxus::{
+    logger::tracing::{self},
+    prelude::*,
+};
+
+use crate::frontend::{toasts::show_error, AUTH};
+
+/// The devices (sessions) the user is logged in on, each of which they can log out.
+#[component]
+pub(crate) fn Devices() -> Element {
+    let mut sessions = use_resource(|| async {
+        crate::api::get_sessions()
+            .await
+            .inspect_err(|err| tracing::error!("Failed to fetch sessions: {}", err))
+            .unwrap_or_default()
+    });
+
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-4 rounded-lg max-w-160",
+            h2 { class: "font-bold text-lg", "Your Devices" }
+            p { class: "text-sm text-gray-500",
+                "You are logged in on these devices. Log out those you don't recognize or no longer use."
+            }
+            for session in sessions().unwrap_or_default() {
+                div { class: "flex gap-2 items-center",
+                    div { class: "flex-1 min-w-0",
+                        p { class: "truncate",
+                            {session.user_agent.clone().unwrap_or_else(|| "Unknown device".to_string())}
+                            if session.current {
+                                span { class: "text-green-500 ml-2", "(this device)" }
+                            }
+                        }
+                        p { class: "text-sm text-gray-500",
+                            "Logged in "
+                            {session.created.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()}
+                            if let Some(ip) = &session.ip {
+                                " from {ip}"
+                            }
+                        }
+                    }
+                    button {
+                        class: "_button bg-red-500 hover:bg-red-600",
+                        onclick: {
+                            let id = session.id.clone();
+                            let current = session.current;
+                            move |_| {
+                                let id = id.clone();
+                                async move {
+                                    match crate::api::remove_session(id).await {
+                                        Ok(()) if current => *AUTH.write() = None,
+                                        Ok(()) => sessions.restart(),
+                                        Err(err) => show_error("Failed to log out device", &err),
+                                    }
+                                }
+                            }
+                        },
+                        "Log Out"
+                    }
+                }
+            }
+            button {
+                class: "_button bg-red-500 hover:bg-red-600",
+                onclick: move |_| async move {
+                    match crate::api::logout_all(None).await {
+                        Ok(_) => *AUTH.write() = None,
+                        Err(err) => show_error("Failed to log out all devices", &err),
+                    }
+                },
+                "Log Out All Devices"
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use dioxus::{
+    logger::tracing::{self},
+    prelude::*,
+};
+
+use crate::{common::ServerSpecImportResult, frontend::toasts::show_error};
+
+#[component]
+pub(crate) fn ImportExportServers() -> Element {
+    let mut include_credentials = use_signal(|| false);
+    let mut import_json = use_signal(|| "".to_string());
+    let mut import_result = use_signal(|| None::<ServerSpecImportResult>);
+    let mut importing = use_signal(|| false);
+
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-4 rounded-lg max-w-120",
+            h2 { class: "font-bold text-lg", "Export / Import Servers" }
+            div { class: "flex items-center gap-2",
+                a {
+                    class: "_button flex-1",
+                    href: "/api/servers/export?include_credentials={include_credentials}",
+                    download: "filen-relay-servers.json",
+                    "Export Servers"
+                }
+                label { class: "flex items-center gap-2",
+                    input {
+                        r#type: "checkbox",
+                        checked: *include_credentials.read(),
+                        onchange: move |e| include_credentials.set(e.value() == "true"),
+                    }
+                    "Include passwords"
+                }
+            }
+            form {
+                class: "flex flex-col gap-2",
+                onsubmit: move |e| async move {
+                    e.prevent_default();
+                    importing.set(true);
+                    match crate::api::import_servers(import_json.read().clone()).await {
+                        Ok(result) => {
+                            tracing::info!("Imported {} servers", result.imported.len());
+                            import_json.set("".to_string());
+                            import_result.set(Some(result));
+                        }
+                        Err(err) => {
+                            show_error("Failed to import servers", &err);
+                        }
+                    }
+                    importing.set(false);
+                },
+                textarea {
+                    class: "_input h-32 font-mono text-sm",
+                    placeholder: "Paste exported servers JSON here",
+                    value: "{import_json}",
+                    oninput: move |e| import_json.set(e.value().clone()),
+                }
+                button {
+                    class: "_button",
+                    r#type: "submit",
+                    disabled: import_json.read().is_empty() || *importing.read(),
+                    "Import Servers"
+                }
+            }
+            if let Some(result) = import_result() {
+                div { class: "text-sm",
+                    p { class: "text-green-500", "Imported {result.imported.len()} servers." }
+                    for (name , errors) in result.skipped {
+                        p { class: "text-red-500",
+                            "Skipped \"{name}\": "
+                            {errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(", ")}
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,14 +1,23 @@
+use chrono::Local;
 use dioxus::{core::Element, hooks::use_signal, prelude::component};
 use dioxus::{
     logger::tracing::{self},
     prelude::*,
 };
 
+use crate::{
+    common::{AllowedUser, AllowedUsersImportResult},
+    frontend::{confirm_dialog::ConfirmDialog, toasts::show_error},
+};
+
 #[component]
 pub(crate) fn ManageAllowedUsers() -> Element {
-    let mut allowed_users = use_signal(|| None::<Vec<String>>);
+    let mut allowed_users = use_signal(|| None::<Vec<AllowedUser>>);
     let mut loading = use_signal(|| false);
     let mut new_user_email = use_signal(|| "".to_string());
+    let mut import_list = use_signal(|| "".to_string());
+    let mut import_result = use_signal(|| None::<(AllowedUsersImportResult, bool)>);
+    let mut confirm_clear = use_signal(|| false);
 
     let fetch_users = move || {
         spawn(async move {
@@ -18,7 +27,7 @@ pub(crate) fn ManageAllowedUsers() -> Element {
                     allowed_users.set(Some(users));
                 }
                 Err(err) => {
-                    tracing::error!("Failed to fetch allowed users: {}", err);
+                    show_error("Failed to fetch allowed users", &err);
                 }
             }
             loading.set(false);
@@ -47,7 +56,7 @@ pub(crate) fn ManageAllowedUsers() -> Element {
                             fetch_users();
                         }
                         Err(err) => {
-                            tracing::error!("Failed to add user: {}", err);
+                            show_error("Failed to add user", &err);
                         }
                     }
                 },
@@ -73,19 +82,50 @@ pub(crate) fn ManageAllowedUsers() -> Element {
                         div { class: "flex flex-col gap-2",
                             for user in users.iter().cloned() {
                                 div { class: "flex items-center gap-2 p-2 border rounded",
-                                    span { class: "flex-1", "{user}" }
+                                    div { class: "flex-1",
+                                        p { "{user.email}" }
+                                        p { class: "text-sm text-gray-500",
+                                            match user.last_login {
+                                                Some(last_login) => rsx! {
+                                                    "Last login "
+                                                    {last_login.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()}
+                                                },
+                                                None => rsx! { "Never logged in" },
+                                            }
+                                            ", {user.servers} servers, {user.active_sessions} active sessions"
+                                        }
+                                    }
+                                    if user.active_sessions > 0 {
+                                        button {
+                                            class: "_button px-2 py-1 text-sm",
+                                            title: "Log out all sessions of this user",
+                                            onclick: {
+                                                let email = user.email.clone();
+                                                move |_| {
+                                                    let email = email.clone();
+                                                    async move {
+                                                        match crate::api::logout_all(Some(email)).await {
+                                                            Ok(_) => fetch_users(),
+                                                            Err(err) => show_error("Failed to log out user", &err),
+                                                        }
+                                                    }
+                                                }
+                                            },
+                                            "Log Out"
+                                        }
+                                    }
                                     button {
                                         class: "_button px-2 py-1 text-sm bg-red-500 hover:bg-red-600",
                                         onclick: move |_| {
-                                            let user = user.clone();
+                                            let email = user.email.clone();
                                             async move {
-                                                match crate::api::remove_allowed_user(user.clone()).await {
+                                                match crate::api::remove_allowed_user(email).await {
                                                     Ok(_) => {
                                                         tracing::info!("User removed successfully");
                                                         fetch_users();
                                                     }
                                                     Err(err) => {
-                                                        tracing::error!("Failed to remove user: {}", err);
+                                                        show_error("Failed to remove user", &err);
                                                     }
                                                 }
                                             }
@@ -96,19 +136,28 @@ pub(crate) fn ManageAllowedUsers() -> Element {
                             }
                             button {
                                 class: "_button mt-2 bg-red-500 hover:bg-red-600",
-                                onclick: move |_| async move {
-                                    match crate::api::clear_allowed_users().await {
-                                        Ok(_) => {
-                                            tracing::info!("All users cleared successfully");
-                                            fetch_users();
-                                        }
-                                        Err(err) => {
-                                            tracing::error!("Failed to clear users: {}", err);
-                                        }
-                                    }
-                                },
+                                onclick: move |_| confirm_clear.set(true),
                                 "Clear All"
                             }
+                            if confirm_clear() {
+                                ConfirmDialog {
+                                    message: "Remove all allowed users? Without allowed users, anyone can access the relay and create servers.",
+                                    confirm_label: "Clear All",
+                                    on_confirm: move |_| async move {
+                                        confirm_clear.set(false);
+                                        match crate::api::clear_allowed_users().await {
+                                            Ok(_) => {
+                                                tracing::info!("All users cleared successfully");
+                                                fetch_users();
+                                            }
+                                            Err(err) => {
+                                                show_error("Failed to clear users", &err);
+                                            }
+                                        }
+                                    },
+                                    on_cancel: move |_| confirm_clear.set(false),
+                                }
+                            }
                         }
                     },
                     Some(_) => rsx! {
@@ -121,6 +170,75 @@ pub(crate) fn ManageAllowedUsers() -> Element {
                     },
                 }
             }
+            div { class: "flex flex-col gap-2 border-t pt-2",
+                h3 { class: "font-bold", "Import / Export" }
+                textarea {
+                    class: "_input h-32 font-mono text-sm",
+                    placeholder: "One email per line, or a CSV file's email column",
+                    value: "{import_list}",
+                    oninput: move |e| {
+                        import_list.set(e.value().clone());
+                        import_result.set(None);
+                    },
+                }
+                div { class: "flex gap-2",
+                    for dry_run in [true, false] {
+                        button {
+                            class: "_button flex-1",
+                            disabled: import_list.read().trim().is_empty(),
+                            onclick: move |_| async move {
+                                match crate::api::import_allowed_users(import_list(), dry_run).await {
+                                    Ok(result) => {
+                                        if !dry_run {
+                                            import_list.set("".to_string());
+                                            fetch_users();
+                                        }
+                                        import_result.set(Some((result, dry_run)));
+                                    }
+                                    Err(err) => {
+                                        show_error("Failed to import users", &err);
+                                    }
+                                }
+                            },
+                            if dry_run {
+                                "Preview Import"
+                            } else {
+                                "Import Users"
+                            }
+                        }
+                    }
+                    a {
+                        class: "_button flex-1",
+                        href: "/api/allowedUsers/export",
+                        download: "filen-relay-allowed-users.csv",
+                        "Export CSV"
+                    }
+                }
+                if let Some((result, dry_run)) = import_result() {
+                    div { class: "text-sm",
+                        p { class: "text-green-500",
+                            if dry_run {
+                                "Would add {result.added.len()} users: "
+                                {result.added.join(", ")}
+                            } else {
+                                "Added {result.added.len()} users."
+                            }
+                        }
+                        if !result.duplicates.is_empty() {
+                            p { class: "text-gray-500",
+                                "Skipped duplicates: "
+                                {result.duplicates.join(", ")}
+                            }
+                        }
+                        if !result.invalid.is_empty() {
+                            p { class: "text-red-500",
+                                "Skipped invalid entries: "
+                                {result.invalid.join(", ")}
+                            }
+                        }
+                    }
+                }
+            }
         }
     }
 }
@@ -1,5 +1,22 @@
+mod activity;
+mod announcement;
+mod archived_servers;
+mod audit;
+mod command_palette;
+mod confirm_dialog;
+mod dashboard;
+mod devices;
+mod import_export;
 mod manage_allowed_users;
+mod notifications;
+mod path_picker;
+mod pwa;
 mod servers;
+mod settings;
+mod status_pages;
+mod teams;
+mod templates;
+mod toasts;
 use std::ops::Deref;
 
 use dioxus::{
@@ -7,9 +24,28 @@ use dioxus::{
     prelude::*,
 };
 
+use crate::common::{ApiError, ApiErrorCode, ApiErrorDetails};
 use crate::frontend::{
+    activity::ActivityFeed,
+    announcement::{AnnouncementBanner, ManageAnnouncement},
+    archived_servers::ArchivedServers,
+    audit::AuditLog,
+    command_palette::CommandPalette,
+    dashboard::Dashboard,
+    devices::Devices,
+    import_export::ImportExportServers,
     manage_allowed_users::ManageAllowedUsers,
-    servers::{CreateServerForm, Logs, Servers},
+    notifications::ManageNotificationChannels,
+    servers::{CreateServerForm, Logs, Servers, Transfers},
+    settings::{
+        Backups, CacheUsageView, DbConflictBanner, Drain, EgressCaps, MaintenanceTasks,
+        ManageSettings, ReloadConfig, RotateAdminAuthConfig, SystemMetricsView, UpdateNotice,
+        UsageExport,
+    },
+    status_pages::StatusPageContentView,
+    teams::Teams,
+    templates::ManageServerTemplates,
+    toasts::{show_error, Toasts},
 };
 
 struct Authentication {
@@ -38,10 +74,29 @@ pub(crate) enum Route {
     #[layout(Navbar)]
     #[route("/")]
     Home {},
-    #[route("/logs/:logs_id")]
-    LogsPage { logs_id: String },
+    #[route("/logs/:server_id")]
+    LogsPage { server_id: String },
     #[route("/manage-allowed-users")]
     ManageAllowedUsersPage {},
+    #[route("/settings")]
+    SettingsPage {},
+    #[route("/archived-servers")]
+    ArchivedServersPage {},
+    #[route("/teams")]
+    TeamsPage {},
+    #[route("/notifications")]
+    NotificationsPage {},
+    #[route("/devices")]
+    DevicesPage {},
+    #[route("/dashboard")]
+    DashboardPage {},
+    #[route("/activity")]
+    ActivityPage {},
+    #[route("/audit")]
+    AuditPage {},
+    #[end_layout]
+    #[route("/status/:token")]
+    PublicStatusPage { token: String },
 }
 
 #[component]
@@ -57,6 +112,7 @@ fn Navbar() -> Element {
             Link { to: Route::Home {}, class: "font-bold", "Filen Relay" }
             div { class: "flex-1" }
             if let Some(auth) = AUTH.read().deref() {
+                CommandPalette {}
                 span {
                     "{auth.email}"
                     if auth.is_admin {
@@ -76,10 +132,12 @@ fn Navbar() -> Element {
                             match crate::api::logout().await {
                                 Ok(_) => {
                                     tracing::info!("Logged out successfully");
+                                    pwa::clear_cache();
+                                    command_palette::clear_index();
                                     *AUTH.write() = None;
                                 }
                                 Err(err) => {
-                                    tracing::error!("Logout failed: {}", err);
+                                    show_error("Logout failed", &err);
                                 }
                             }
                         });
@@ -90,21 +148,161 @@ fn Navbar() -> Element {
         }
         div { class: "p-4",
             if AUTH.read().is_some() {
+                AnnouncementBanner {}
                 Outlet::<Route> {}
             } else {
                 Login {}
             }
         }
+        Footer {}
+        Toasts {}
+    }
+}
+
+#[component]
+fn Footer() -> Element {
+    let mut version_info = use_signal(|| None::<crate::api::VersionInfo>);
+    use_effect(move || {
+        spawn(async move {
+            match crate::api::get_version().await {
+                Ok(info) => version_info.set(Some(info)),
+                Err(err) => tracing::error!("Failed to fetch version info: {}", err),
+            }
+        });
+    });
+
+    rsx! {
+        div { class: "flex gap-4 border-t-1 border-gray-400 p-4 text-sm text-gray-500",
+            if let Some(info) = version_info() {
+                span { "Filen Relay v{info.version}" }
+                if let Some(git_commit) = info.git_commit {
+                    span { class: "font-mono", "{git_commit}" }
+                }
+                if let Some(build_date) = info.build_date {
+                    span { "built {build_date}" }
+                }
+                if let Some(rclone_version) = info.rclone_version {
+                    span { "{rclone_version}" }
+                }
+            }
+        }
     }
 }
 
 #[component]
 fn Login() -> Element {
+    let mut with_auth_config = use_signal(|| false);
+    // set once the relay asks for the code sent to the user's email
+    let mut verification_id = use_signal(|| None::<String>);
+    let tab_class = |active: bool| {
+        if active {
+            "px-2 py-1 border-b-2 font-bold"
+        } else {
+            "px-2 py-1 border-b-2 border-transparent text-gray-500"
+        }
+    };
+    if let Some(id) = verification_id() {
+        return rsx! {
+            div { class: "w-full flex flex-col items-center gap-4",
+                EmailVerification {
+                    verification_id: id,
+                    on_cancel: move |_| verification_id.set(None),
+                }
+            }
+        };
+    }
+    let on_verification_required = move |id| verification_id.set(Some(id));
+    rsx! {
+        div { class: "w-full flex flex-col items-center gap-4",
+            div { class: "flex gap-2",
+                button {
+                    class: tab_class(!with_auth_config()),
+                    onclick: move |_| with_auth_config.set(false),
+                    "Email & Password"
+                }
+                button {
+                    class: tab_class(with_auth_config()),
+                    onclick: move |_| with_auth_config.set(true),
+                    "Auth Config"
+                }
+            }
+            if with_auth_config() {
+                AuthConfigLogin { on_verification_required }
+            } else {
+                PasswordLogin { on_verification_required }
+            }
+        }
+    }
+}
+
+/// Logs in with an auth config exported via the Filen CLI, e.g. for accounts with 2FA.
+#[component]
+fn AuthConfigLogin(on_verification_required: EventHandler<String>) -> Element {
+    let mut auth_config = use_signal(String::new);
+    let mut loading = use_signal(|| false);
+    let mut error = use_signal(|| None::<ApiError>);
+
+    rsx! {
+        form {
+            class: "flex flex-col gap-2 max-w-120",
+            onsubmit: move |e| async move {
+                e.prevent_default();
+                loading.set(true);
+                error.set(None);
+                match crate::api::login_with_auth_config(auth_config()).await {
+                    Ok(_response) => {
+                        tracing::info!("Logged in successfully");
+                        fetch_authentication().await;
+                        auth_config.set(String::new());
+                    }
+                    Err(err) => {
+                        tracing::error!("Login failed: {}", err);
+                        if let Some(ApiErrorDetails::EmailVerification { verification_id }) = &err.details {
+                            on_verification_required.call(verification_id.clone());
+                        }
+                        error.set(Some(err));
+                    }
+                }
+                loading.set(false);
+            },
+            p { class: "text-sm text-gray-500",
+                "Paste an auth config exported via the Filen CLI (filen export-auth-config). No password or 2FA code is stored."
+            }
+            textarea {
+                class: "_input font-mono",
+                rows: "4",
+                value: "{auth_config}",
+                oninput: move |e| auth_config.set(e.value()),
+            }
+            button {
+                class: "_button",
+                disabled: *loading.read() || auth_config.read().trim().is_empty(),
+                r#type: "submit",
+                "Login"
+            }
+            if let Some(err) = error() {
+                div { class: "text-red-500",
+                    match err.code {
+                        ApiErrorCode::InvalidCredentials => rsx! { "The auth config is invalid or has expired." },
+                        ApiErrorCode::Forbidden => rsx! { "You are not allowed to use this relay." },
+                        _ => rsx! { "Login failed: {err}" },
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn PasswordLogin(on_verification_required: EventHandler<String>) -> Element {
     let mut email = use_signal(|| "".to_string());
     let mut password = use_signal(|| "".to_string());
     let mut two_factor_code = use_signal(|| None::<String>);
+    // only asked for once Filen requires it
+    let mut show_two_factor = use_signal(|| false);
 
     let mut loading = use_signal(|| false);
+    let mut error = use_signal(|| None::<ApiError>);
 
     let mut saved_credentials_pending = use_signal(|| true);
     let mut save_credentials = use_signal(|| false);
@@ -121,6 +319,7 @@ fn Login() -> Element {
             }
             if let Some(Ok(code_val)) = wasm_cookies::get("filen_two_factor_code") {
                 two_factor_code.set(Some(code_val));
+                show_two_factor.set(true);
                 save_credentials.set(true);
             }
         }
@@ -129,6 +328,7 @@ fn Login() -> Element {
 
     let login = move || async move {
         loading.set(true);
+        error.set(None);
         match crate::api::login(email.cloned(), password.cloned(), two_factor_code.cloned()).await {
             Ok(_response) => {
                 tracing::info!("Logged in successfully");
@@ -152,9 +352,19 @@ fn Login() -> Element {
                 email.set("".to_string());
                 password.set("".to_string());
                 two_factor_code.set(None);
+                show_two_factor.set(false);
             }
             Err(err) => {
                 tracing::error!("Login failed: {}", err);
+                if err.code == ApiErrorCode::TwoFactorRequired {
+                    // a code that was sent anyway has been rejected, so ask for a fresh one
+                    show_two_factor.set(true);
+                    two_factor_code.set(None);
+                }
+                if let Some(ApiErrorDetails::EmailVerification { verification_id }) = &err.details {
+                    on_verification_required.call(verification_id.clone());
+                }
+                error.set(Some(err));
             }
         };
         loading.set(false);
@@ -189,20 +399,25 @@ fn Login() -> Element {
                         oninput: move |e| password.set(e.value().clone()),
                     }
                 }
-                div {
-                    label { "2FA Code (optional):" }
-                    input {
-                        class: "_input w-full",
-                        r#type: "text",
-                        value: format!("{}", two_factor_code().as_deref().unwrap_or("")),
-                        oninput: move |e| {
-                            let val = e.value().clone();
-                            if val.is_empty() {
-                                two_factor_code.set(None);
-                            } else {
-                                two_factor_code.set(Some(val));
-                            }
-                        },
+                if show_two_factor() {
+                    div {
+                        label { "2FA Code:" }
+                        input {
+                            class: "_input w-full",
+                            r#type: "text",
+                            inputmode: "numeric",
+                            autocomplete: "one-time-code",
+                            autofocus: true,
+                            value: format!("{}", two_factor_code().as_deref().unwrap_or("")),
+                            oninput: move |e| {
+                                let val = e.value().clone();
+                                if val.is_empty() {
+                                    two_factor_code.set(None);
+                                } else {
+                                    two_factor_code.set(Some(val));
+                                }
+                            },
+                        }
                     }
                 }
                 div {
@@ -222,6 +437,74 @@ fn Login() -> Element {
                     r#type: "submit",
                     "Login"
                 }
+                if let Some(err) = error() {
+                    div { class: "text-red-500",
+                        match err.code {
+                            ApiErrorCode::TwoFactorRequired => rsx! { "This account uses two-factor authentication. Please enter your 2FA code." },
+                            ApiErrorCode::InvalidCredentials => rsx! { "Email or password is wrong." },
+                            ApiErrorCode::Forbidden => rsx! { "You are not allowed to use this relay." },
+                            _ => rsx! { "Login failed: {err}" },
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Asks a user logging in for the first time for the code sent to their email, if the admin requires it.
+#[component]
+fn EmailVerification(verification_id: String, on_cancel: EventHandler<()>) -> Element {
+    let mut code = use_signal(String::new);
+    let mut loading = use_signal(|| false);
+    let mut error = use_signal(|| None::<ApiError>);
+
+    rsx! {
+        form {
+            class: "flex flex-col gap-2 max-w-120",
+            onsubmit: move |e| {
+                let verification_id = verification_id.clone();
+                async move {
+                    e.prevent_default();
+                    loading.set(true);
+                    error.set(None);
+                    match crate::api::verify_login_email(verification_id, code()).await {
+                        Ok(_response) => {
+                            tracing::info!("Logged in successfully");
+                            fetch_authentication().await;
+                        }
+                        Err(err) => {
+                            tracing::error!("Email verification failed: {}", err);
+                            error.set(Some(err));
+                        }
+                    }
+                    loading.set(false);
+                }
+            },
+            p { "As this is your first login, please enter the code we sent to your Filen email." }
+            input {
+                class: "_input w-full",
+                r#type: "text",
+                inputmode: "numeric",
+                autocomplete: "one-time-code",
+                autofocus: true,
+                value: "{code}",
+                oninput: move |e| code.set(e.value()),
+            }
+            button {
+                class: "_button",
+                disabled: *loading.read() || code.read().trim().is_empty(),
+                r#type: "submit",
+                "Verify"
+            }
+            button {
+                class: "text-sm text-gray-500 hover:underline",
+                r#type: "button",
+                onclick: move |_| on_cancel.call(()),
+                "Back to Login"
+            }
+            if let Some(err) = error() {
+                div { class: "text-red-500", "{err}" }
             }
         }
     }
@@ -229,9 +512,13 @@ fn Login() -> Element {
 
 #[component]
 pub(crate) fn App() -> Element {
+    use_effect(pwa::register_service_worker);
     rsx! {
         document::Title { "Filen Relay" }
         document::Link { rel: "icon", href: "https://filen.io/favicon.ico" }
+        document::Link { rel: "manifest", href: "/manifest.webmanifest" }
+        document::Link { rel: "apple-touch-icon", href: "/icon.svg" }
+        document::Meta { name: "theme-color", content: "#1e293b" }
         document::Link { rel: "stylesheet", href: asset!("/assets/tailwind.css") }
         Router::<Route> {}
     }
@@ -243,19 +530,35 @@ fn Home() -> Element {
     let auth = auth.as_ref().unwrap();
     rsx! {
         div { class: "flex flex-col gap-4",
+            if auth.is_admin {
+                DbConflictBanner {}
+                UpdateNotice {}
+            }
             Servers {}
             CreateServerForm {}
+            ImportExportServers {}
+            Link { to: Route::TeamsPage {}, class: "_button", "Teams" }
+            Link { to: Route::NotificationsPage {}, class: "_button", "Notifications" }
+            Link { to: Route::DevicesPage {}, class: "_button", "Devices" }
+            Link { to: Route::DashboardPage {}, class: "_button", "Dashboard" }
             if auth.is_admin {
                 Link { to: Route::ManageAllowedUsersPage {}, class: "_button", "Manage Allowed Users" }
+                Link { to: Route::SettingsPage {}, class: "_button", "Settings" }
+                Link { to: Route::ArchivedServersPage {}, class: "_button", "Archived Servers" }
+                Link { to: Route::ActivityPage {}, class: "_button", "Activity" }
+                Link { to: Route::AuditPage {}, class: "_button", "Audit Log" }
             }
         }
     }
 }
 
 #[component]
-fn LogsPage(logs_id: String) -> Element {
+fn LogsPage(server_id: String) -> Element {
     rsx! {
-        Logs { logs_id }
+        div { class: "flex flex-col gap-4",
+            Transfers { server_id: server_id.clone() }
+            Logs { server_id }
+        }
     }
 }
 
@@ -265,3 +568,85 @@ fn ManageAllowedUsersPage() -> Element {
         ManageAllowedUsers {}
     }
 }
+
+#[component]
+fn SettingsPage() -> Element {
+    rsx! {
+        div { class: "flex flex-col gap-4",
+            ManageSettings {}
+            ManageAnnouncement {}
+            ManageServerTemplates {}
+            SystemMetricsView {}
+            CacheUsageView {}
+            UsageExport {}
+            EgressCaps {}
+            Backups {}
+            MaintenanceTasks {}
+            RotateAdminAuthConfig {}
+            ReloadConfig {}
+            Drain {}
+        }
+    }
+}
+
+#[component]
+fn ArchivedServersPage() -> Element {
+    rsx! {
+        ArchivedServers {}
+    }
+}
+
+#[component]
+fn TeamsPage() -> Element {
+    rsx! {
+        Teams {}
+    }
+}
+
+#[component]
+fn NotificationsPage() -> Element {
+    rsx! {
+        ManageNotificationChannels {}
+    }
+}
+
+#[component]
+fn DevicesPage() -> Element {
+    rsx! {
+        Devices {}
+    }
+}
+
+#[component]
+fn DashboardPage() -> Element {
+    rsx! {
+        Dashboard {}
+    }
+}
+
+#[component]
+fn ActivityPage() -> Element {
+    rsx! {
+        ActivityFeed {}
+    }
+}
+
+#[component]
+fn AuditPage() -> Element {
+    let is_admin = AUTH.read().as_ref().is_some_and(|auth| auth.is_admin);
+    rsx! {
+        if is_admin {
+            AuditLog {}
+        } else {
+            div { class: "text-gray-500", "Only the admin can see the audit log." }
+        }
+    }
+}
+
+/// A status page, shown without login or navigation (e.g. when embedded in a team wiki).
+#[component]
+fn PublicStatusPage(token: String) -> Element {
+    rsx! {
+        StatusPageContentView { token }
+    }
+}
@@ -0,0 +1,117 @@
+use dioxus::{
+    logger::tracing::{self},
+    prelude::*,
+};
+use strum::IntoEnumIterator as _;
+
+use crate::{
+    common::NotificationChannelKind,
+    frontend::{toasts::show_error, AUTH},
+};
+
+/// Lists the user's Discord and Slack notification channels and lets them add and remove channels.
+#[component]
+pub(crate) fn ManageNotificationChannels() -> Element {
+    let mut channels = use_resource(|| async {
+        crate::api::get_notification_channels()
+            .await
+            .inspect_err(|err| tracing::error!("Failed to fetch notification channels: {}", err))
+            .ok()
+    });
+    let is_admin = AUTH.read().as_ref().is_some_and(|auth| auth.is_admin);
+
+    let mut kind = use_signal(|| NotificationChannelKind::Discord);
+    let mut webhook_url = use_signal(String::new);
+    let mut error = use_signal(|| None::<String>);
+    let placeholder = format!("{}...", kind.read().webhook_url_prefixes()[0]);
+
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-4 rounded-lg max-w-160",
+            h2 { class: "font-bold text-lg", "Notifications" }
+            p { class: "text-sm text-gray-500",
+                if is_admin {
+                    "These webhooks are notified when a server fails, a quota is exceeded or a user logs in."
+                } else {
+                    "These webhooks are notified when one of your servers fails or you exceed a quota."
+                }
+            }
+            match channels() {
+                Some(Some(list)) if !list.is_empty() => rsx! {
+                    for channel in list {
+                        div { class: "flex gap-2 items-center",
+                            span { class: "flex-1 truncate",
+                                "{channel.kind}: {channel.webhook_url}"
+                            }
+                            button {
+                                class: "_button",
+                                onclick: move |_| {
+                                    let id = channel.id.clone();
+                                    async move {
+                                        match crate::api::remove_notification_channel(id).await {
+                                            Ok(()) => channels.restart(),
+                                            Err(err) => show_error("Failed to remove notification channel", &err),
+                                        }
+                                    }
+                                },
+                                "Remove"
+                            }
+                        }
+                    }
+                },
+                Some(Some(_)) => rsx! {
+                    div { class: "text-gray-500", "No notification channels yet." }
+                },
+                Some(None) => rsx! {
+                    div { class: "text-gray-500", "Failed to load notification channels." }
+                },
+                None => rsx! {
+                    div { class: "text-gray-500", "Loading notification channels..." }
+                },
+            }
+            form {
+                class: "flex flex-col gap-2 border-t pt-2",
+                onsubmit: move |e| async move {
+                    e.prevent_default();
+                    match crate::api::add_notification_channel(kind(), webhook_url()).await {
+                        Ok(()) => {
+                            webhook_url.set(String::new());
+                            error.set(None);
+                            channels.restart();
+                        }
+                        Err(err) => {
+                            tracing::error!("Failed to add notification channel: {}", err);
+                            error.set(Some(err.message));
+                        }
+                    }
+                },
+                select {
+                    class: "_input w-full",
+                    onchange: move |e| kind.set(NotificationChannelKind::from(e.value().as_str())),
+                    for kind_ in NotificationChannelKind::iter() {
+                        option {
+                            value: kind_.to_string(),
+                            selected: *kind.read() == kind_,
+                            "{kind_} webhook"
+                        }
+                    }
+                }
+                input {
+                    class: "_input",
+                    r#type: "url",
+                    placeholder: "{placeholder}",
+                    value: "{webhook_url}",
+                    oninput: move |e| webhook_url.set(e.value()),
+                }
+                if let Some(error) = error() {
+                    p { class: "text-red-500 text-sm", "{error}" }
+                }
+                button {
+                    class: "_button",
+                    r#type: "submit",
+                    disabled: webhook_url.read().trim().is_empty(),
+                    "Add Channel"
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use dioxus::{
+    logger::tracing::{self},
+    prelude::*,
+};
+
+/// Lets the user pick a directory in their Filen drive by browsing it, or type its path (e.g. of a new folder to be
+/// created with the server).
+#[component]
+pub(crate) fn DrivePathPicker(mut path: Signal<String>) -> Element {
+    let mut browsing = use_signal(|| false);
+    let mut current_path = use_signal(|| "/".to_string());
+    let mut directories = use_signal(|| None::<Vec<String>>);
+
+    let mut navigate = move |new_path: String| {
+        current_path.set(new_path.clone());
+        directories.set(None);
+        spawn(async move {
+            match crate::api::list_drive_directories(new_path).await {
+                Ok(dirs) => directories.set(Some(dirs)),
+                Err(err) => {
+                    tracing::error!("Failed to list directories: {}", err);
+                    directories.set(Some(Vec::new()));
+                }
+            }
+        });
+    };
+
+    rsx! {
+        div { class: "flex gap-2 mt-1",
+            input {
+                class: "_input font-mono w-full",
+                r#type: "text",
+                placeholder: "/",
+                value: "{path}",
+                oninput: move |e| path.set(e.value()),
+            }
+            button {
+                class: "_button",
+                r#type: "button",
+                onclick: move |_| {
+                    if !*browsing.read() {
+                        navigate(path());
+                    }
+                    browsing.toggle();
+                },
+                if *browsing.read() { "Close" } else { "Browse" }
+            }
+        }
+        if *browsing.read() {
+            div { class: "flex flex-col gap-1 border rounded-lg p-2 mt-1 max-h-64 overflow-y-auto",
+                div { class: "font-mono text-sm text-gray-500", "{current_path}" }
+                if current_path() != "/" {
+                    a {
+                        class: "cursor-pointer hover:underline",
+                        onclick: move |_| navigate(parent_path(&current_path())),
+                        ".."
+                    }
+                }
+                match directories() {
+                    None => rsx! {
+                        div { class: "text-gray-500", "Loading..." }
+                    },
+                    Some(dirs) if dirs.is_empty() => rsx! {
+                        div { class: "text-gray-500", "No subdirectories." }
+                    },
+                    Some(dirs) => rsx! {
+                        for dir in dirs {
+                            a {
+                                class: "cursor-pointer hover:underline",
+                                onclick: move |_| navigate(join_path(&current_path(), &dir)),
+                                "📁 {dir}"
+                            }
+                        }
+                    },
+                }
+                button {
+                    class: "_button mt-1",
+                    r#type: "button",
+                    onclick: move |_| {
+                        path.set(current_path());
+                        browsing.set(false);
+                    },
+                    "Select this folder"
+                }
+            }
+        }
+    }
+}
+
+fn join_path(parent: &str, name: &str) -> String {
+    format!("{}/{}", parent.trim_end_matches('/'), name)
+}
+
+fn parent_path(path: &str) -> String {
+    match path.trim_end_matches('/').rsplit_once('/') {
+        Some((parent, _)) if !parent.is_empty() => parent.to_string(),
+        _ => "/".to_string(),
+    }
+}
@@ -0,0 +1,42 @@
+use dioxus::prelude::*;
+
+use crate::common::ServerState;
+
+/// Key of the last known server states in the browser's local storage.
+const SERVERS_CACHE_KEY: &str = "filen-relay-servers";
+
+/// Registers the service worker caching the app shell (see `assets/pwa/sw.js`), so that the app can be installed and
+/// loads offline.
+pub(crate) fn register_service_worker() {
+    document::eval(
+        r#"if ("serviceWorker" in navigator) { navigator.serviceWorker.register("/sw.js"); }"#,
+    );
+}
+
+/// The server states last received, shown while the states stream (re)connects.
+pub(crate) async fn load_cached_servers() -> Option<Vec<ServerState>> {
+    document::eval(&format!(
+        r#"return JSON.parse(localStorage.getItem("{}"));"#,
+        SERVERS_CACHE_KEY
+    ))
+    .join::<Option<Vec<ServerState>>>()
+    .await
+    .ok()
+    .flatten()
+}
+
+pub(crate) fn cache_servers(servers: &[ServerState]) {
+    let eval = document::eval(&format!(
+        r#"localStorage.setItem("{}", JSON.stringify(await dioxus.recv()));"#,
+        SERVERS_CACHE_KEY
+    ));
+    let _ = eval.send(servers);
+}
+
+/// Forgets the cached server states, e.g. on logout.
+pub(crate) fn clear_cache() {
+    document::eval(&format!(
+        r#"localStorage.removeItem("{}");"#,
+        SERVERS_CACHE_KEY
+    ));
+}
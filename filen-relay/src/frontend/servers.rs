@@ -1,3 +1,5 @@
+use std::{collections::HashSet, rc::Rc, time::Duration};
+
 use chrono::Local;
 use dioxus::{
     logger::tracing::{self},
@@ -6,56 +8,165 @@ use dioxus::{
 use strum::IntoEnumIterator as _;
 
 use crate::{
-    common::{LogLine, LogLineContent, ServerState, ServerStatus, ServerType},
-    frontend::Route,
+    common::{
+        ApiError, ApiErrorCode, ApiErrorDetails, GroupAction, GroupActionResult, LogLevel, LogLine,
+        LogLineContent, ServerId, ServerOptions, ServerSpecField, ServerSpecValidationError,
+        ServerState, ServerStatus, ServerTestResult, ServerType, Transfer, VfsCacheMode,
+        REMOVE_UNDO_WINDOW,
+    },
+    frontend::{
+        command_palette, confirm_dialog::ConfirmDialog, path_picker::DrivePathPicker, pwa,
+        status_pages::StatusPages, toasts::show_error, Route, AUTH,
+    },
+    util::format_bytes,
 };
 
+const TRANSFERS_POLL_INTERVAL: Duration = Duration::from_secs(2);
+pub(super) const RECONNECT_MIN_DELAY: Duration = Duration::from_secs(1);
+pub(super) const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
 #[component]
 pub(crate) fn Servers() -> Element {
     let mut servers = use_signal(|| None::<Vec<ServerState>>);
+    // whether the states stream is connected, otherwise the last known states are shown
+    let mut connected = use_signal(|| false);
     use_future(move || async move {
-        match crate::api::get_servers().await {
-            Ok(mut servers_stream) => loop {
-                match servers_stream.next().await {
-                    Some(Ok(new_servers)) => {
-                        servers.set(Some(new_servers));
-                    }
-                    Some(Err(err)) => {
-                        tracing::error!("Error receiving server states: {}", err);
-                        break;
-                    }
-                    None => {
-                        tracing::info!("Server states stream ended");
-                        break;
+        if let Some(cached) = pwa::load_cached_servers().await {
+            servers.set(Some(cached));
+        }
+        let mut reconnect_delay = RECONNECT_MIN_DELAY;
+        loop {
+            match crate::api::get_servers().await {
+                Ok(mut servers_stream) => loop {
+                    match servers_stream.next().await {
+                        Some(Ok(new_servers)) => {
+                            // the stream always starts with a snapshot of all server states
+                            pwa::cache_servers(&new_servers);
+                            command_palette::index_servers(&new_servers);
+                            servers.set(Some(new_servers));
+                            connected.set(true);
+                            reconnect_delay = RECONNECT_MIN_DELAY;
+                        }
+                        Some(Err(err)) => {
+                            tracing::error!("Error receiving server states: {}", err);
+                            break;
+                        }
+                        None => {
+                            tracing::info!("Server states stream ended");
+                            break;
+                        }
                     }
+                },
+                Err(err) if err.code == ApiErrorCode::Unauthorized => {
+                    tracing::error!("Failed to fetch servers: {}", err);
+                    return;
+                }
+                Err(err) => {
+                    tracing::error!("Failed to fetch servers: {}", err);
                 }
-            },
-            Err(err) => {
-                tracing::error!("Failed to fetch servers: {}", err);
             }
+            connected.set(false);
+            tracing::info!("Reconnecting to server states in {:?}", reconnect_delay);
+            crate::util::sleep(reconnect_delay).await;
+            reconnect_delay = (reconnect_delay * 2).min(RECONNECT_MAX_DELAY);
         }
     });
+    let teams = use_resource(|| async {
+        crate::api::get_teams()
+            .await
+            .inspect_err(|err| tracing::error!("Failed to fetch teams: {}", err))
+            .unwrap_or_default()
+    });
+    let team_name = move |team_id: &str| {
+        teams
+            .read()
+            .iter()
+            .flatten()
+            .find(|t| t.team.id == team_id)
+            .map(|t| t.team.name.clone())
+    };
+    // servers with a resume, restart or remove request in flight (which returns once it's done)
+    let mut pending = use_signal(HashSet::<ServerId>::new);
+    // the server to confirm removing, and the last removed one, which can be restored for `REMOVE_UNDO_WINDOW`
+    let mut confirm_remove = use_signal(|| None::<ServerState>);
+    let mut removed = use_signal(|| None::<(ServerId, String)>);
+    let remove = move |server: ServerState| async move {
+        let id = server.spec.id.clone();
+        pending.write().insert(id.clone());
+        let result = crate::api::remove_server(id.clone()).await;
+        pending.write().remove(&id);
+        match result {
+            Ok(_) => {
+                tracing::info!("Server removed successfully");
+                removed.set(Some((id.clone(), server.spec.name)));
+                crate::util::sleep(REMOVE_UNDO_WINDOW).await;
+                // unless another server was removed in the meantime
+                if removed
+                    .peek()
+                    .as_ref()
+                    .is_some_and(|(removed_id, _)| *removed_id == id)
+                {
+                    removed.set(None);
+                }
+            }
+            Err(err) => {
+                show_error("Failed to remove server", &err);
+            }
+        }
+    };
+    let base_url = use_base_url();
+    let base_url = base_url().unwrap_or_default();
     let servers = &*servers;
 
-    match servers() {
+    let list = match servers() {
         Some(servers) if !servers.is_empty() => {
+            let paused_count = servers
+                .iter()
+                .filter(|s| matches!(s.status, ServerStatus::Paused))
+                .count();
             rsx! {
+                if !connected() {
+                    div { class: "border border-yellow-500 p-2 rounded-lg",
+                        "Connecting to the relay... Showing the last known server states."
+                    }
+                }
+                if paused_count > 0 {
+                    div { class: "border border-yellow-500 p-2 rounded-lg",
+                        "{paused_count} server(s) are paused, e.g. because they haven't been used for a long time. Resume them to use them again."
+                    }
+                }
+                GroupActions { servers: servers.clone() }
+                StatusPages { servers: servers.clone() }
                 div { class: "flex flex-wrap gap-4",
                     for server in servers {
-                        div { class: "border p-4 inline-flex flex-col w-64 rounded-lg",
+                        div {
+                            id: "server-{server.short_id}",
+                            class: "border p-4 inline-flex flex-col w-64 rounded-lg",
                             h2 { class: "font-bold text-lg", "{server.spec.name}" }
                             p {
                                 "ID: "
-                                span { class: "font-mono", "#{server.spec.id.short()}" }
+                                span { class: "font-mono", "#{server.short_id}" }
                             }
                             p { "Type: {server.spec.server_type}" }
                             p { "Root: {server.spec.root}" }
+                            if !server.spec.options.additional_roots.is_empty() {
+                                p {
+                                    "Also serving: "
+                                    {server.spec.options.additional_roots.join(", ")}
+                                }
+                            }
+                            if !server.spec.options.tags.is_empty() {
+                                p {
+                                    "Tags: "
+                                    {server.spec.options.tags.join(", ")}
+                                }
+                            }
                             if server.spec.read_only {
                                 p { "Mode: Read-Only" }
                             } else {
                                 p { "Mode: Read-Write" }
                             }
-                            if server.spec.password.is_some() {
+                            if server.spec.has_password {
                                 p { "Password protection" }
                             } else {
                                 p { "No password protection" }
@@ -70,39 +181,162 @@ pub(crate) fn Servers() -> Element {
                                         "Connect: "
                                         a {
                                             class: "font-mono text-blue-400",
-                                            href: "/s/{server.spec.id.short()}/",
+                                            href: "{base_url}/s/{server.short_id}/",
                                             target: "_blank",
-                                            "/s/{server.spec.id.short()}/"
+                                            "{base_url}/s/{server.short_id}/"
+                                        }
+                                    }
+                                    if matches!(server.spec.server_type, ServerType::Webdav)
+                                        && server.spec.options.windows_compatibility
+                                    {
+                                        WindowsConnectInstructions { short_id: server.short_id.clone() }
+                                    }
+                                    // only sent to admins
+                                    if let Some(process) = server.process.clone() {
+                                        p { class: "text-xs text-gray-400 font-mono",
+                                            "Local: "
+                                            {process.local_address()}
+                                            if let Some(rc_port) = process.rc_port {
+                                                ", rc 127.0.0.1:{rc_port}"
+                                            }
+                                            if let Some(pid) = process.pid {
+                                                ", PID {pid}"
+                                            }
+                                        }
+                                    }
+                                },
+                                ServerStatus::Paused => rsx! {
+                                    p { class: "text-yellow-500", "Status: Paused" }
+                                    button {
+                                        class: "_button mt-2",
+                                        disabled: pending.read().contains(&server.spec.id),
+                                        onclick: {
+                                            let id = server.spec.id.clone();
+                                            move |_| {
+                                                let id = id.clone();
+                                                async move {
+                                                    pending.write().insert(id.clone());
+                                                    if let Err(err) = crate::api::resume_server(id.clone()).await {
+                                                        show_error("Failed to resume server", &err);
+                                                    }
+                                                    pending.write().remove(&id);
+                                                }
+                                            }
+                                        },
+                                        if pending.read().contains(&server.spec.id) {
+                                            "Resuming..."
+                                        } else {
+                                            "Resume"
                                         }
                                     }
                                 },
-                                ServerStatus::Error => rsx! {
-                                    p { class: "text-red-500", "Status: Error" }
+                                ServerStatus::Error { reason, exit_code, last_logs } => rsx! {
+                                    p { class: "text-red-500", "Status: Error ({reason})" }
+                                    if let Some(exit_code) = exit_code {
+                                        p { "Exit code: {exit_code}" }
+                                    }
+                                    if !last_logs.is_empty() {
+                                        details {
+                                            summary { class: "cursor-pointer", "Last log lines" }
+                                            div { class: "flex flex-col text-xs font-mono text-gray-400 max-h-48 overflow-y-auto",
+                                                for line in last_logs {
+                                                    match line.content {
+                                                        LogLineContent::Event(content) | LogLineContent::ServerProcess(content) => rsx! {
+                                                            span { "{content}" }
+                                                        },
+                                                    }
+                                                }
+                                            }
+                                        }
+                                    }
                                 },
                             }
+                            if let Some(team_name) = server.spec.team_id.as_deref().and_then(team_name) {
+                                p { "Team: {team_name}" }
+                            }
+                            if AUTH.read().as_ref().is_some_and(|auth| auth.email != server.spec.filen_email) {
+                                p { class: "text-gray-500", "Shared by {server.spec.filen_email}" }
+                            }
+                            if AUTH
+                                .read()
+                                .as_ref()
+                                .is_some_and(|auth| auth.is_admin || auth.email == server.spec.filen_email)
+                            {
+                                Collaborators {
+                                    server_id: server.spec.id.clone(),
+                                    collaborators: server.spec.collaborators.clone(),
+                                }
+                            }
+                            label { class: "flex items-center gap-2 mt-2",
+                                "Log Level:"
+                                select {
+                                    class: "_input",
+                                    disabled: pending.read().contains(&server.spec.id),
+                                    onchange: {
+                                        let id = server.spec.id.clone();
+                                        move |e: Event<FormData>| {
+                                            let id = id.clone();
+                                            let value = e.value();
+                                            let level = if value.is_empty() {
+                                                None
+                                            } else {
+                                                Some(LogLevel::from(value.as_str()))
+                                            };
+                                            async move {
+                                                if let Err(err) = crate::api::set_server_log_level(id, level).await {
+                                                    show_error("Failed to change log level", &err);
+                                                }
+                                            }
+                                        }
+                                    },
+                                    option { value: "", selected: server.spec.options.log_level.is_none(), "Default" }
+                                    for level in LogLevel::iter() {
+                                        option {
+                                            value: level.to_string(),
+                                            selected: server.spec.options.log_level == Some(level),
+                                            "{level}"
+                                        }
+                                    }
+                                }
+                            }
                             Link {
                                 to: Route::LogsPage {
-                                    logs_id: server.logs_id.clone(),
+                                    server_id: server.spec.id.to_string(),
                                 },
                                 class: "flex _button mt-2",
                                 "View Logs"
                             }
-                            button {
-                                class: "_button mt-2",
-                                onclick: move |_| {
-                                    let server = server.clone();
-                                    async move {
-                                        match crate::api::remove_server(server.spec.id.clone()).await {
-                                            Ok(_) => {
-                                                tracing::info!("Server removed successfully");
+                            if !matches!(server.status, ServerStatus::Paused) {
+                                button {
+                                    class: "_button mt-2",
+                                    disabled: pending.read().contains(&server.spec.id),
+                                    onclick: {
+                                        let id = server.spec.id.clone();
+                                        move |_| {
+                                            let id = id.clone();
+                                            async move {
+                                                pending.write().insert(id.clone());
+                                                if let Err(err) = crate::api::restart_server(id.clone()).await {
+                                                    show_error("Failed to restart server", &err);
+                                                }
+                                                pending.write().remove(&id);
                                             }
-                                            Err(err) => {
-                                                tracing::error!("Failed to remove server: {}", err);
-                                            }
-                                        };
-                                    }
-                                },
-                                "Remove Server"
+                                        }
+                                    },
+                                    "Restart Server"
+                                }
+                            }
+                            if AUTH
+                                .read()
+                                .as_ref()
+                                .is_some_and(|auth| auth.is_admin || auth.email == server.spec.filen_email)
+                            {
+                                button {
+                                    class: "_button mt-2",
+                                    disabled: pending.read().contains(&server.spec.id),
+                                    onclick: move |_| confirm_remove.set(Some(server.clone())),
+                                    "Remove Server"
+                                }
                             }
                         }
                     }
@@ -117,6 +351,204 @@ pub(crate) fn Servers() -> Element {
         None => rsx! {
             div { class: "text-gray-500", "Loading servers..." }
         },
+    };
+
+    rsx! {
+        if let Some(server) = confirm_remove() {
+            ConfirmDialog {
+                message: "Remove the server \"{server.spec.name}\"? You can undo this for {REMOVE_UNDO_WINDOW.as_secs()} seconds, after that only the admin can restore it.",
+                confirm_label: "Remove Server",
+                on_confirm: move |_| {
+                    confirm_remove.set(None);
+                    spawn(remove(server.clone()));
+                },
+                on_cancel: move |_| confirm_remove.set(None),
+            }
+        }
+        if let Some((id, name)) = removed() {
+            div { class: "flex gap-2 items-center border border-yellow-500 p-2 rounded-lg",
+                span { class: "flex-1", "Removed \"{name}\"." }
+                button {
+                    class: "_button",
+                    onclick: move |_| {
+                        let id = id.clone();
+                        async move {
+                            removed.set(None);
+                            if let Err(err) = crate::api::undo_remove_server(id).await {
+                                show_error("Failed to undo removing server", &err);
+                            }
+                        }
+                    },
+                    "Undo"
+                }
+            }
+        }
+        {list}
+    }
+}
+
+/// Starts or stops all servers with a tag at once, listing the outcome per server.
+#[component]
+fn GroupActions(servers: Vec<ServerState>) -> Element {
+    let mut results = use_signal(Vec::<GroupActionResult>::new);
+    let mut error = use_signal(|| None::<String>);
+    let mut tags = servers
+        .iter()
+        .flat_map(|s| s.spec.options.tags.clone())
+        .collect::<Vec<_>>();
+    tags.sort();
+    tags.dedup();
+    if tags.is_empty() {
+        return rsx! {};
+    }
+    let run = move |tag: String, action: GroupAction| async move {
+        results.set(Vec::new());
+        error.set(None);
+        match crate::api::run_group_action(tag, action).await {
+            Ok(mut results_stream) => {
+                while let Some(result) = results_stream.next().await {
+                    match result {
+                        Ok(result) => results.write().push(result),
+                        Err(err) => {
+                            tracing::error!("Error receiving group action results: {}", err);
+                            break;
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                tracing::error!("Failed to run group action: {}", err);
+                error.set(Some(err.message));
+            }
+        }
+    };
+
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-4 rounded-lg max-w-120",
+            h2 { class: "font-bold text-lg", "Tags" }
+            for tag in tags {
+                div { class: "flex gap-2 items-center",
+                    span { class: "flex-1 truncate", "{tag}" }
+                    button {
+                        class: "_button",
+                        onclick: {
+                            let tag = tag.clone();
+                            move |_| run(tag.clone(), GroupAction::Start)
+                        },
+                        "Start All"
+                    }
+                    button {
+                        class: "_button",
+                        onclick: move |_| run(tag.clone(), GroupAction::Stop),
+                        "Stop All"
+                    }
+                }
+            }
+            if let Some(error) = error() {
+                p { class: "text-red-500 text-sm", "{error}" }
+            }
+            for result in results() {
+                match result.error {
+                    None => rsx! {
+                        p { class: "text-sm text-green-500", "{result.name}: done" }
+                    },
+                    Some(error) => rsx! {
+                        p { class: "text-sm text-red-500", "{result.name}: {error}" }
+                    },
+                }
+            }
+        }
+    }
+}
+
+fn parse_tags(tags: &str) -> Vec<String> {
+    let mut tags = tags
+        .split(',')
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect::<Vec<_>>();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Lets the owner share a server with other users, who can then view its logs and restart it (but not remove it).
+#[component]
+fn Collaborators(server_id: ServerId, collaborators: Vec<String>) -> Element {
+    let mut emails = use_signal(|| collaborators.join(", "));
+    let mut error = use_signal(|| None::<String>);
+
+    rsx! {
+        details { class: "mt-2",
+            summary { class: "cursor-pointer",
+                if collaborators.is_empty() {
+                    "Not shared"
+                } else {
+                    "Shared with {collaborators.len()} user(s)"
+                }
+            }
+            form {
+                class: "flex flex-col gap-2 mt-2",
+                onsubmit: move |e| {
+                    let server_id = server_id.clone();
+                    async move {
+                        e.prevent_default();
+                        let collaborators = emails()
+                            .split(',')
+                            .map(|email| email.trim().to_string())
+                            .filter(|email| !email.is_empty())
+                            .collect::<Vec<_>>();
+                        match crate::api::set_server_collaborators(server_id, collaborators).await {
+                            Ok(()) => error.set(None),
+                            Err(err) => {
+                                tracing::error!("Failed to share server: {}", err);
+                                error.set(Some(err.message));
+                            }
+                        }
+                    }
+                },
+                input {
+                    class: "_input",
+                    r#type: "text",
+                    placeholder: "colleague@example.com, ...",
+                    value: "{emails}",
+                    oninput: move |e| emails.set(e.value()),
+                }
+                if let Some(error) = error() {
+                    p { class: "text-red-500 text-sm", "{error}" }
+                }
+                button { class: "_button", r#type: "submit", "Save" }
+            }
+        }
+    }
+}
+
+/// The URL under which users reach the relay: the configured public URL, or else the browser's origin.
+pub(super) fn use_base_url() -> Resource<String> {
+    use_resource(|| async {
+        match crate::api::get_public_url().await {
+            Ok(Some(public_url)) => public_url,
+            _ => document::eval("return window.location.origin;")
+                .join::<String>()
+                .await
+                .unwrap_or_default(),
+        }
+    })
+}
+
+/// Shows how to map a WebDAV server as a network drive on Windows.
+#[component]
+fn WindowsConnectInstructions(short_id: String) -> Element {
+    let base_url = use_base_url();
+    let base_url = base_url().unwrap_or_default();
+    rsx! {
+        details { class: "text-sm",
+            summary { class: "cursor-pointer", "Connect on Windows" }
+            p { "Run in a command prompt:" }
+            code { class: "block font-mono text-xs break-all bg-gray-900 p-1 rounded",
+                "net use * {base_url}/s/{short_id}/ /persistent:yes"
+            }
+        }
     }
 }
 
@@ -128,12 +560,83 @@ pub(crate) fn CreateServerForm() -> Element {
     let mut read_only = use_signal(|| false);
     let mut password = use_signal(|| None::<String>);
     let password_str = password.read().as_deref().unwrap_or("").to_string();
+    let mut options = use_signal(ServerOptions::default);
+    let mut tags = use_signal(String::new);
+    let mut errors = use_signal(Vec::<ServerSpecValidationError>::new);
+    let mut create_root = use_signal(|| false);
+    let mut template_id = use_signal(|| None::<String>);
+    let templates = use_resource(|| async {
+        crate::api::get_server_templates()
+            .await
+            .inspect_err(|err| tracing::error!("Failed to fetch server templates: {}", err))
+            .unwrap_or_default()
+    });
+    let defaults = use_resource(|| async {
+        crate::api::get_server_defaults()
+            .await
+            .inspect_err(|err| tracing::error!("Failed to fetch server defaults: {}", err))
+            .unwrap_or_default()
+    });
+    use_effect(move || {
+        if let Some(defaults) = defaults() {
+            read_only.set(defaults.read_only);
+        }
+    });
+    let mut team_id = use_signal(|| None::<String>);
+    let teams = use_resource(|| async {
+        crate::api::get_teams()
+            .await
+            .inspect_err(|err| tracing::error!("Failed to fetch teams: {}", err))
+            .unwrap_or_default()
+    });
+    let mut step = use_signal(|| CreateStep::Basics);
+    let mut test_result = use_signal(|| None::<Result<ServerTestResult, String>>);
+    let (step_title, submit_label) = match step() {
+        CreateStep::Basics => ("Step 1 of 3: What to serve", "Next"),
+        CreateStep::Options => ("Step 2 of 3: How to serve it", "Create Server"),
+        CreateStep::Test => ("Step 3 of 3: Connection test", "Done"),
+    };
+    let root_not_found = errors
+        .read()
+        .contains(&ServerSpecValidationError::RootNotFound);
+    let field_errors = move |field: ServerSpecField| {
+        errors
+            .read()
+            .iter()
+            .filter(|e| e.field() == field)
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+    };
 
     rsx! {
         form {
+            id: "create-server",
             class: "flex flex-col gap-2 border p-4 rounded-lg max-w-80",
             onsubmit: move |e| async move {
                 e.prevent_default();
+                match step() {
+                    CreateStep::Basics => {
+                        step.set(CreateStep::Options);
+                        return;
+                    }
+                    CreateStep::Test => {
+                        name.set("".to_string());
+                        server_type.set(ServerType::Http);
+                        root.set("/".to_string());
+                        read_only.set(defaults().is_some_and(|defaults| defaults.read_only));
+                        password.set(None);
+                        options.set(ServerOptions::default());
+                        tags.set(String::new());
+                        create_root.set(false);
+                        template_id.set(None);
+                        team_id.set(None);
+                        errors.set(Vec::new());
+                        test_result.set(None);
+                        step.set(CreateStep::Basics);
+                        return;
+                    }
+                    CreateStep::Options => {}
+                }
                 let name_ = name.read().clone();
                 if name_.is_empty() {
                     tracing::error!("Server name cannot be empty");
@@ -143,142 +646,779 @@ pub(crate) fn CreateServerForm() -> Element {
                 let root_ = root.read().clone();
                 let read_only_ = *read_only.read();
                 let password_ = password.read().clone();
+                let mut options_ = options.read().clone();
+                options_.tags = parse_tags(&tags());
+                let create_root_ = *create_root.read();
+                let template_id_ = template_id.read().clone();
+                let team_id_ = team_id.read().clone();
                 match crate::api::add_server(
                         name_.to_string(),
                         server_type_.clone(),
                         root_,
                         read_only_,
                         password_,
+                        options_,
+                        create_root_,
+                        template_id_,
+                        team_id_,
+                        true,
                     )
                     .await
                 {
-                    Ok(_) => {
+                    Ok(added) => {
                         tracing::info!("Server created successfully");
-                        name.set("".to_string());
-                        server_type.set(ServerType::Http);
-                        root.set("/".to_string());
-                        read_only.set(false);
-                        password.set(None);
+                        errors.set(Vec::new());
+                        step.set(CreateStep::Test);
+                        test_result.set(Some(crate::api::test_server(added.state.spec.id).await.map_err(|err| err.to_string())));
+                    }
+                    // the server was created, so it isn't submitted again
+                    Err(ApiError { details: Some(ApiErrorDetails::ServerStartFailed { .. }), message, .. }) => {
+                        errors.set(Vec::new());
+                        step.set(CreateStep::Test);
+                        test_result.set(Some(Ok(ServerTestResult { success: false, message })));
+                    }
+                    Err(ApiError { details: Some(ApiErrorDetails::Validation(validation_errors)), .. }) => {
+                        if validation_errors
+                            .iter()
+                            .any(|e| matches!(e.field(), ServerSpecField::Name | ServerSpecField::ServerType | ServerSpecField::Root))
+                        {
+                            step.set(CreateStep::Basics);
+                        }
+                        errors.set(validation_errors);
                     }
                     Err(err) => {
-                        tracing::error!("Failed to create server: {}", err);
+                        show_error("Failed to create server", &err);
                     }
                 };
             },
+            p { class: "text-sm text-gray-500", "{step_title}" }
             div { class: "flex flex-col gap-2",
-                div {
-                    label { "Server Name:" }
-                    input {
-                        class: "mt-1 _input",
-                        r#type: "text",
-                        placeholder: "My Server",
-                        value: "{name}",
-                        oninput: move |e| name.set(e.value().clone()),
+                if step() == CreateStep::Basics {
+                    if let Some(templates) = templates().filter(|t| !t.is_empty()) {
+                        div {
+                            label { "Start from Template:" }
+                            select {
+                                class: "mt-1 _input w-full",
+                                onchange: move |e| {
+                                    let id = e.value();
+                                    match templates.iter().find(|t| t.id == id) {
+                                        Some(template) => {
+                                            server_type.set(template.server_type.clone());
+                                            read_only.set(template.read_only);
+                                            options.set(template.options.clone());
+                                            tags.set(template.options.tags.join(", "));
+                                            template_id.set(Some(template.id.clone()));
+                                        }
+                                        None => template_id.set(None),
+                                    }
+                                },
+                                option { value: "", selected: template_id.read().is_none(), "None" }
+                                for template in templates.iter() {
+                                    option {
+                                        value: "{template.id}",
+                                        selected: template_id.read().as_ref() == Some(&template.id),
+                                        "{template.name}"
+                                    }
+                                }
+                            }
+                        }
                     }
-                }
-                div {
-                    label { "Server Type:" }
-                    select {
-                        class: "mt-1 _input w-full",
-                        onchange: move |e| {
-                            server_type.set(ServerType::from(e.value().as_str()));
-                        },
-                        for server_type in ServerType::iter() {
-                            option { value: server_type.to_string(), "{server_type.to_string()}" }
+                    if let Some(teams) = teams().filter(|t| !t.is_empty()) {
+                        div {
+                            label { "Owner:" }
+                            select {
+                                class: "mt-1 _input w-full",
+                                onchange: move |e| {
+                                    let id = e.value();
+                                    team_id.set((!id.is_empty()).then_some(id));
+                                },
+                                option { value: "", selected: team_id.read().is_none(), "Me" }
+                                for overview in teams.iter() {
+                                    option {
+                                        value: "{overview.team.id}",
+                                        selected: team_id.read().as_ref() == Some(&overview.team.id),
+                                        "Team {overview.team.name}"
+                                    }
+                                }
+                            }
                         }
                     }
-                }
-                div {
-                    label { "Root Path:" }
-                    input {
-                        class: "mt-1 _input",
-                        r#type: "text",
-                        placeholder: "/",
-                        value: "{root}",
-                        oninput: move |e| root.set(e.value().clone()),
+                    div {
+                        label { "Server Name:" }
+                        input {
+                            class: "mt-1 _input",
+                            r#type: "text",
+                            placeholder: "My Server",
+                            value: "{name}",
+                            oninput: move |e| name.set(e.value().clone()),
+                        }
+                        for error in field_errors(ServerSpecField::Name) {
+                            p { class: "text-red-500 text-sm", "{error}" }
+                        }
+                    }
+                    div {
+                        label { "Server Type:" }
+                        select {
+                            class: "mt-1 _input w-full",
+                            onchange: move |e| {
+                                let type_ = ServerType::from(e.value().as_str());
+                                options.write().clear_unsupported(&type_);
+                                server_type.set(type_);
+                            },
+                            for type_ in ServerType::iter() {
+                                option {
+                                    value: type_.to_string(),
+                                    selected: *server_type.read() == type_,
+                                    "{type_.to_string()}"
+                                }
+                            }
+                        }
+                        for error in field_errors(ServerSpecField::ServerType) {
+                            p { class: "text-red-500 text-sm", "{error}" }
+                        }
+                    }
+                    div {
+                        label { "Root Path:" }
+                        DrivePathPicker { path: root }
+                        for error in field_errors(ServerSpecField::Root) {
+                            p { class: "text-red-500 text-sm", "{error}" }
+                        }
+                        if root_not_found {
+                            label { class: "flex items-center gap-2 text-sm",
+                                input {
+                                    r#type: "checkbox",
+                                    checked: *create_root.read(),
+                                    onchange: move |e| create_root.set(e.value() == "true"),
+                                }
+                                "Create missing folders"
+                            }
+                        }
+                    }
+                    div {
+                        label { "Additional Root Paths (optional, one per line):" }
+                        textarea {
+                            class: "mt-1 _input w-full",
+                            rows: "2",
+                            placeholder: "/Documents",
+                            value: options.read().additional_roots.join("\n"),
+                            oninput: move |e| {
+                                options.write().additional_roots = e
+                                    .value()
+                                    .lines()
+                                    .map(|line| line.trim().to_string())
+                                    .filter(|line| !line.is_empty())
+                                    .collect();
+                            },
+                        }
+                        p { class: "text-sm text-gray-500",
+                            "Served side by side with the root path, e.g. /Photos and /Documents in one server."
+                        }
                     }
                 }
-                div {
-                    label { class: "flex items-center gap-2",
-                        "Read-Only"
+                if step() == CreateStep::Options {
+                    div {
+                        label { "Tags (optional, comma-separated):" }
                         input {
-                            r#type: "checkbox",
-                            checked: *read_only.read(),
-                            onchange: move |e| read_only.set(e.value() == "true"),
+                            class: "mt-1 _input w-full",
+                            r#type: "text",
+                            placeholder: "project-x, backups",
+                            value: "{tags}",
+                            oninput: move |e| tags.set(e.value()),
                         }
                     }
+                    div {
+                        label { class: "flex items-center gap-2",
+                            "Read-Only"
+                            input {
+                                r#type: "checkbox",
+                                checked: *read_only.read(),
+                                onchange: move |e| read_only.set(e.value() == "true"),
+                            }
+                        }
 
+                    }
+                    if matches!(*server_type.read(), ServerType::Webdav) {
+                        div {
+                            label { class: "flex items-center gap-2",
+                                "Windows compatibility"
+                                input {
+                                    r#type: "checkbox",
+                                    checked: options.read().windows_compatibility,
+                                    onchange: move |e| options.write().windows_compatibility = e.value() == "true",
+                                }
+                            }
+                        }
+                    }
+                    if matches!(*server_type.read(), ServerType::Webdav) && !*read_only.read() {
+                        div {
+                            label { class: "flex items-center gap-2",
+                                "Drop box (upload only)"
+                                input {
+                                    r#type: "checkbox",
+                                    checked: options.read().drop_box,
+                                    onchange: move |e| options.write().drop_box = e.value() == "true",
+                                }
+                            }
+                            p { class: "text-sm text-gray-500",
+                                "Others can upload new files (e.g. on the upload page at the server's URL), but can't list, download or replace files."
+                            }
+                        }
+                    }
+                    if matches!(*server_type.read(), ServerType::Http | ServerType::Webdav) {
+                        div {
+                            label { class: "flex items-center gap-2",
+                                "Compress text responses (saves egress)"
+                                input {
+                                    r#type: "checkbox",
+                                    checked: options.read().compression,
+                                    onchange: move |e| options.write().compression = e.value() == "true",
+                                }
+                            }
+                        }
+                    }
+                    if matches!(*server_type.read(), ServerType::Http) {
+                        div {
+                            label { class: "flex items-center gap-2",
+                                "Website mode"
+                                input {
+                                    r#type: "checkbox",
+                                    checked: options.read().website,
+                                    onchange: move |e| options.write().website = e.value() == "true",
+                                }
+                            }
+                            p { class: "text-sm text-gray-500",
+                                "Hosts a static website: serves index.html for folders, about.html at /about and 404.html for missing pages."
+                            }
+                        }
+                        div {
+                            label { class: "flex items-center gap-2",
+                                "Allow downloading folders as ZIP"
+                                input {
+                                    r#type: "checkbox",
+                                    checked: options.read().zip_downloads,
+                                    onchange: move |e| options.write().zip_downloads = e.value() == "true",
+                                }
+                            }
+                            p { class: "text-sm text-gray-500",
+                                "Add ?download=zip to the URL of a folder to download it as a ZIP file."
+                            }
+                        }
+                    }
+                    if matches!(*server_type.read(), ServerType::Http) && *read_only.read() {
+                        div {
+                            label { class: "flex items-center gap-2",
+                                "Serve without rclone (uses less memory)"
+                                input {
+                                    r#type: "checkbox",
+                                    checked: options.read().native,
+                                    onchange: move |e| options.write().native = e.value() == "true",
+                                }
+                            }
+                            p { class: "text-sm text-gray-500",
+                                "Streams files directly from your drive. Cache, bandwidth limit and extra flags don't apply."
+                            }
+                        }
+                    }
+                    if matches!(*server_type.read(), ServerType::Ftp) {
+                        div {
+                            label { "Passive Ports (optional):" }
+                            input {
+                                class: "mt-1 _input",
+                                r#type: "text",
+                                placeholder: "30000-30009",
+                                value: options.read().ftp_passive_ports.clone().unwrap_or_default(),
+                                oninput: move |e| {
+                                    let value = e.value();
+                                    options.write().ftp_passive_ports = if value.is_empty() { None } else { Some(value) };
+                                },
+                            }
+                            for error in field_errors(ServerSpecField::FtpPassivePorts) {
+                                p { class: "text-red-500 text-sm", "{error}" }
+                            }
+                        }
+                        div {
+                            label { class: "flex items-center gap-2",
+                                "Encrypt connections (FTPS)"
+                                input {
+                                    r#type: "checkbox",
+                                    checked: options.read().ftp_tls,
+                                    onchange: move |e| options.write().ftp_tls = e.value() == "true",
+                                }
+                            }
+                        }
+                    }
+                    div {
+                        label { "VFS Cache Mode:" }
+                        select {
+                            class: "mt-1 _input w-full",
+                            onchange: move |e| {
+                                let value = e.value();
+                                options.write().vfs_cache_mode = if value.is_empty() {
+                                    None
+                                } else {
+                                    Some(VfsCacheMode::from(value.as_str()))
+                                };
+                            },
+                            option { value: "", selected: options.read().vfs_cache_mode.is_none(), "Default" }
+                            for mode in VfsCacheMode::iter() {
+                                option {
+                                    value: mode.to_string(),
+                                    selected: options.read().vfs_cache_mode == Some(mode),
+                                    "{mode}"
+                                }
+                            }
+                        }
+                    }
+                    div {
+                        label { "Bandwidth Limit (optional):" }
+                        input {
+                            class: "mt-1 _input",
+                            r#type: "text",
+                            placeholder: defaults().and_then(|defaults| defaults.bwlimit).unwrap_or("10M or 10M:1M".to_string()),
+                            value: options.read().bwlimit.clone().unwrap_or_default(),
+                            oninput: move |e| {
+                                let value = e.value().trim().to_string();
+                                options.write().bwlimit = if value.is_empty() { None } else { Some(value) };
+                            },
+                        }
+                        for error in field_errors(ServerSpecField::Bwlimit) {
+                            p { class: "text-red-500 text-sm", "{error}" }
+                        }
+                    }
+                    if matches!(*server_type.read(), ServerType::Http | ServerType::Webdav) {
+                        div {
+                            label { "Allowed Methods (optional, comma-separated):" }
+                            input {
+                                class: "mt-1 _input",
+                                r#type: "text",
+                                placeholder: "GET, HEAD",
+                                value: options.read().allowed_methods.join(", "),
+                                onchange: move |e| {
+                                    options.write().allowed_methods = e
+                                        .value()
+                                        .split(',')
+                                        .map(|method| method.trim().to_uppercase())
+                                        .filter(|method| !method.is_empty())
+                                        .collect();
+                                },
+                            }
+                        }
+                        div {
+                            label { "Blocked Paths (optional, one per line):" }
+                            textarea {
+                                class: "mt-1 _input w-full font-mono",
+                                rows: "2",
+                                placeholder: "/.private/*",
+                                value: options.read().blocked_paths.join("\n"),
+                                oninput: move |e| {
+                                    options.write().blocked_paths = e
+                                        .value()
+                                        .lines()
+                                        .map(|line| line.trim().to_string())
+                                        .filter(|line| !line.is_empty())
+                                        .collect();
+                                },
+                            }
+                            p { class: "text-sm text-gray-500",
+                                "Requests to matching paths are rejected; * matches anything. Listings still show their names."
+                            }
+                            for error in field_errors(ServerSpecField::AccessRules) {
+                                p { class: "text-red-500 text-sm", "{error}" }
+                            }
+                        }
+                    }
+                    div {
+                        label { "Log Level:" }
+                        select {
+                            class: "mt-1 _input w-full",
+                            onchange: move |e| {
+                                let value = e.value();
+                                options.write().log_level = if value.is_empty() {
+                                    None
+                                } else {
+                                    Some(LogLevel::from(value.as_str()))
+                                };
+                            },
+                            option { value: "", selected: options.read().log_level.is_none(), "Default" }
+                            for level in LogLevel::iter() {
+                                option {
+                                    value: level.to_string(),
+                                    selected: options.read().log_level == Some(level),
+                                    "{level}"
+                                }
+                            }
+                        }
+                    }
+                    if AUTH.read().as_ref().is_some_and(|auth| auth.is_admin) {
+                        div {
+                            label { "Cache Directory (optional):" }
+                            input {
+                                class: "mt-1 _input",
+                                r#type: "text",
+                                placeholder: "/var/cache/filen-relay",
+                                value: options.read().cache_dir.clone().unwrap_or_default(),
+                                oninput: move |e| {
+                                    let value = e.value();
+                                    options.write().cache_dir = if value.is_empty() { None } else { Some(value) };
+                                },
+                            }
+                            for error in field_errors(ServerSpecField::CacheDir) {
+                                p { class: "text-red-500 text-sm", "{error}" }
+                            }
+                        }
+                        if template_id.read().is_none() {
+                            ExtraFlagsInput { options }
+                            for error in field_errors(ServerSpecField::ExtraFlags) {
+                                p { class: "text-red-500 text-sm", "{error}" }
+                            }
+                        }
+                    }
+                    div {
+                        label { "Password:" }
+                        input {
+                            class: "mt-1 _input",
+                            r#type: "password",
+                            placeholder: "Password",
+                            value: "{password_str}",
+                            oninput: move |e| password.set(Some(e.value().clone())),
+                        }
+                        for error in field_errors(ServerSpecField::Password) {
+                            p { class: "text-red-500 text-sm", "{error}" }
+                        }
+                    }
+                }
+            }
+            if step() == CreateStep::Test {
+                match test_result() {
+                    None => rsx! {
+                        div { class: "text-gray-500", "Starting the server and testing the connection..." }
+                    },
+                    Some(Ok(result)) if result.success => rsx! {
+                        div { class: "text-green-500", "{result.message}" }
+                    },
+                    Some(Ok(result)) => rsx! {
+                        div { class: "text-red-500", "{result.message}" }
+                        p { class: "text-sm text-gray-500", "Check the server's logs for details." }
+                    },
+                    Some(Err(err)) => rsx! {
+                        div { class: "text-red-500", "Failed to test the server: {err}" }
+                    },
                 }
-                div {
-                    label { "Password:" }
-                    input {
-                        class: "mt-1 _input",
-                        r#type: "password",
-                        placeholder: "Password",
-                        value: "{password_str}",
-                        oninput: move |e| password.set(Some(e.value().clone())),
+            }
+            div { class: "flex gap-2",
+                if step() == CreateStep::Options {
+                    button {
+                        class: "_button flex-1",
+                        r#type: "button",
+                        onclick: move |_| step.set(CreateStep::Basics),
+                        "Back"
                     }
                 }
+                button {
+                    class: "_button flex-1",
+                    r#type: "submit",
+                    disabled: name.read().is_empty() || (step() == CreateStep::Test && test_result().is_none()),
+                    "{submit_label}"
+                }
             }
-            button {
-                class: "_button",
-                r#type: "submit",
-                disabled: name.read().is_empty(),
-                "Create Server"
+        }
+    }
+}
+
+/// The steps of `CreateServerForm`: what to serve, how to serve it, and a connection test of the created server.
+#[derive(Clone, Copy, PartialEq)]
+enum CreateStep {
+    Basics,
+    Options,
+    Test,
+}
+
+/// Edits `options.extra_flags`, one flag per line.
+#[component]
+pub(crate) fn ExtraFlagsInput(options: Signal<ServerOptions>) -> Element {
+    let mut options = options;
+    rsx! {
+        div {
+            label { "Extra rclone Flags (one per line):" }
+            textarea {
+                class: "mt-1 _input w-full font-mono",
+                rows: "2",
+                placeholder: "--dir-cache-time=1m",
+                value: options.read().extra_flags.join("\n"),
+                oninput: move |e| {
+                    options.write().extra_flags = e
+                        .value()
+                        .lines()
+                        .map(|line| line.trim().to_string())
+                        .filter(|line| !line.is_empty())
+                        .collect();
+                },
             }
         }
     }
 }
 
 #[component]
-pub(crate) fn Logs(logs_id: String) -> Element {
-    let mut logs = use_signal(Vec::<LogLine>::new);
+pub(crate) fn Transfers(server_id: String) -> Element {
+    let mut transfers = use_signal(Vec::<Transfer>::new);
     use_future(move || {
-        let logs_id = logs_id.clone();
+        let server_id = server_id.clone();
         async move {
-            match crate::api::get_logs(logs_id.clone()).await {
-                Ok(mut logs_stream) => loop {
-                    match logs_stream.next().await {
-                        Some(Ok(new_log)) => {
-                            logs.write().push(new_log);
+            loop {
+                match crate::api::get_transfers(server_id.clone()).await {
+                    Ok(fetched) => transfers.set(fetched),
+                    Err(err) if matches!(err.code, ApiErrorCode::NotFound) => {
+                        transfers.set(Vec::new());
+                    }
+                    Err(err) => {
+                        tracing::error!("Failed to fetch transfers: {}", err);
+                    }
+                }
+                crate::util::sleep(TRANSFERS_POLL_INTERVAL).await;
+            }
+        }
+    });
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-4 rounded-lg",
+            h2 { class: "font-bold text-lg", "Active Transfers" }
+            if transfers.read().is_empty() {
+                div { class: "text-gray-500", "No active transfers." }
+            }
+            for transfer in transfers.read().iter() {
+                div { class: "flex flex-col gap-1",
+                    div { class: "flex gap-2",
+                        span { class: "flex-1 font-mono truncate", "{transfer.name}" }
+                        span { class: "text-gray-500",
+                            "{format_bytes(transfer.bytes)}"
+                            if transfer.size >= 0 {
+                                " / {format_bytes(transfer.size as u64)}"
+                            }
+                            " ({format_bytes(transfer.speed as u64)}/s)"
                         }
-                        Some(Err(err)) => {
-                            tracing::error!("Error receiving logs: {}", err);
-                            break;
+                    }
+                    div { class: "h-1 bg-gray-200 rounded",
+                        div {
+                            class: "h-1 bg-blue-500 rounded",
+                            style: "width: {transfer.percentage}%",
                         }
-                        None => {
-                            tracing::info!("Logs stream ended");
-                            break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Lines of history loaded when opening the logs (older ones are left out).
+const LOG_HISTORY_LINES: usize = 5000;
+/// Height of a row in the log viewer, which only renders the rows scrolled into view.
+const LOG_ROW_HEIGHT: f64 = 20.0;
+/// Rows rendered above and below the visible ones, so that fast scrolling doesn't show gaps.
+const LOG_OVERSCAN_ROWS: usize = 30;
+
+/// A row of the log viewer: the start of a run or a line (by its index).
+#[derive(Clone, Copy)]
+enum LogRow {
+    Run(u32),
+    Line(usize),
+}
+
+/// The logs of a server, streamed live, with a text filter. Follows the newest line unless paused (or scrolled up).
+#[component]
+pub(crate) fn Logs(server_id: String) -> Element {
+    let download_url = format!("/api/logs/{}/download", server_id);
+    let mut logs = use_signal(Vec::<LogLine>::new);
+    use_future(move || {
+        let server_id = server_id.clone();
+        async move {
+            let mut reconnect_delay = RECONNECT_MIN_DELAY;
+            loop {
+                // load the recent history at first, then resume after the last received line
+                let cursor = logs.read().last().map(|line| line.index);
+                let tail = cursor.is_none().then_some(LOG_HISTORY_LINES);
+                match crate::api::get_logs(server_id.clone(), tail, cursor).await {
+                    Ok(mut logs_stream) => loop {
+                        match logs_stream.next().await {
+                            Some(Ok(new_log)) => {
+                                logs.write().push(new_log);
+                                reconnect_delay = RECONNECT_MIN_DELAY;
+                            }
+                            Some(Err(err)) => {
+                                tracing::error!("Error receiving logs: {}", err);
+                                break;
+                            }
+                            None => {
+                                tracing::info!("Logs stream ended");
+                                break;
+                            }
                         }
+                    },
+                    Err(err)
+                        if matches!(
+                            err.code,
+                            ApiErrorCode::Unauthorized
+                                | ApiErrorCode::Forbidden
+                                | ApiErrorCode::NotFound
+                        ) =>
+                    {
+                        tracing::error!("Failed to fetch logs: {}", err);
+                        return;
+                    }
+                    Err(err) => {
+                        tracing::error!("Failed to fetch logs: {}", err);
                     }
-                },
-                Err(err) => {
-                    tracing::error!("Failed to fetch logs: {}", err);
                 }
+                tracing::info!("Reconnecting to logs in {:?}", reconnect_delay);
+                crate::util::sleep(reconnect_delay).await;
+                reconnect_delay = (reconnect_delay * 2).min(RECONNECT_MAX_DELAY);
             }
         }
     });
+    let mut filter = use_signal(String::new);
+    let mut min_level = use_signal(|| LogLevel::Debug);
+    // whether the view keeps scrolling to the newest line
+    let mut follow = use_signal(|| true);
+    let mut scroll_top = use_signal(|| 0.0);
+    let mut viewport_height = use_signal(|| 600.0);
+    let mut end = use_signal(|| None::<Rc<MountedData>>);
+    use_effect(move || {
+        let _ = logs.read().len();
+        if let (true, Some(end)) = (follow(), end()) {
+            spawn(async move {
+                let _ = end.scroll_to(ScrollBehavior::Instant).await;
+            });
+        }
+    });
+
+    let logs_ = logs.read();
+    // older lines were left out if the first one isn't the first of the server's history
+    let truncated = logs_.first().is_some_and(|line| line.index > 0);
+    let filter_ = filter.read().to_lowercase();
+    let min_level_ = min_level();
+    let mut rows = Vec::new();
+    for (i, log) in logs_.iter().enumerate() {
+        let (LogLineContent::ServerProcess(content) | LogLineContent::Event(content)) =
+            &log.content;
+        if log.level < min_level_
+            || (!filter_.is_empty() && !content.to_lowercase().contains(&filter_))
+        {
+            continue;
+        }
+        if rows.last().is_none_or(|row| match row {
+            LogRow::Run(run) => *run != log.run,
+            LogRow::Line(j) => logs_[*j].run != log.run,
+        }) {
+            rows.push(LogRow::Run(log.run));
+        }
+        rows.push(LogRow::Line(i));
+    }
+    let matching_lines = rows
+        .iter()
+        .filter(|row| matches!(row, LogRow::Line(_)))
+        .count();
+    // only the rows scrolled into view (and some around them) are rendered
+    let first = ((scroll_top() / LOG_ROW_HEIGHT) as usize)
+        .saturating_sub(LOG_OVERSCAN_ROWS)
+        .min(rows.len());
+    let last = (((scroll_top() + viewport_height()) / LOG_ROW_HEIGHT) as usize + LOG_OVERSCAN_ROWS)
+        .min(rows.len())
+        .max(first);
+    let padding_top = first as f64 * LOG_ROW_HEIGHT;
+    let padding_bottom = (rows.len() - last) as f64 * LOG_ROW_HEIGHT;
+
     rsx! {
-        div { class: "flex flex-col gap-1 p-2 rounded-lg overflow-y-auto font-mono text-gray-200",
-            for (log , timestamp) in logs.read()
-                .iter()
-                .map(|log| (
-                    log,
-                    log.clone().timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
-                ))
-            {
-                div {
-                    span { class: "text-gray-500 mr-2", "[{timestamp}] " }
-                    match &log.content {
-                        LogLineContent::ServerProcess(content) => rsx! {
-                            span { "{content}" }
-                        },
-                        LogLineContent::Event(content) => rsx! {
-                            span { class: "text-blue-400", "{content}" }
+        div { class: "flex flex-col gap-2",
+            div { class: "flex gap-2 items-center",
+                input {
+                    class: "_input flex-1",
+                    placeholder: "Filter logs...",
+                    value: "{filter}",
+                    oninput: move |e| filter.set(e.value()),
+                }
+                select {
+                    class: "_input",
+                    onchange: move |e| min_level.set(LogLevel::from(e.value().as_str())),
+                    for level in LogLevel::iter() {
+                        option {
+                            value: level.to_string(),
+                            selected: level == min_level(),
+                            "{level} and above"
+                        }
+                    }
+                }
+                button {
+                    class: "_button",
+                    onclick: move |_| follow.toggle(),
+                    if follow() {
+                        "Pause Auto-Scroll"
+                    } else {
+                        "Resume Auto-Scroll"
+                    }
+                }
+                a { class: "_button", href: "{download_url}", "Download" }
+            }
+            if truncated {
+                p { class: "text-sm text-gray-500", "Only the last {LOG_HISTORY_LINES} lines of earlier logs are shown." }
+            }
+            if !filter_.is_empty() || min_level_ > LogLevel::Debug {
+                p { class: "text-sm text-gray-500", "{matching_lines} of {logs_.len()} lines match." }
+            }
+            div {
+                class: "p-2 rounded-lg overflow-auto font-mono text-gray-200 h-[70vh]",
+                onscroll: move |e| {
+                    let data = e.data();
+                    scroll_top.set(data.scroll_top());
+                    viewport_height.set(data.client_height() as f64);
+                    // scrolling up pauses following, scrolling back to the end resumes it
+                    let at_end = data.scroll_top() + data.client_height() as f64
+                        >= data.scroll_height() as f64 - LOG_ROW_HEIGHT;
+                    if follow() != at_end {
+                        follow.set(at_end);
+                    }
+                },
+                div { style: "height: {padding_top}px" }
+                for row in rows[first..last].iter().copied() {
+                    match row {
+                        LogRow::Run(run) => rsx! {
+                            div {
+                                class: "text-gray-500 border-b border-gray-600 whitespace-pre",
+                                style: "height: {LOG_ROW_HEIGHT}px",
+                                "Run #{run}"
+                            }
                         },
+                        LogRow::Line(i) => {
+                            let log = &logs_[i];
+                            let timestamp = log.timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S");
+                            rsx! {
+                                div { class: "whitespace-pre", style: "height: {LOG_ROW_HEIGHT}px",
+                                    span { class: "text-gray-500 mr-2", "[{timestamp}] " }
+                                    match &log.content {
+                                        LogLineContent::ServerProcess(content) => rsx! {
+                                            span { class: level_color(log.level), "{content}" }
+                                        },
+                                        LogLineContent::Event(content) if log.level == LogLevel::Error => rsx! {
+                                            span { class: "text-red-400", "{content}" }
+                                        },
+                                        LogLineContent::Event(content) => rsx! {
+                                            span { class: "text-blue-400", "{content}" }
+                                        },
+                                    }
+                                }
+                            }
+                        }
                     }
                 }
+                div { style: "height: {padding_bottom}px" }
+                div { onmounted: move |e| end.set(Some(e.data())) }
             }
         }
     }
 }
+
+fn level_color(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::Debug => "text-gray-500",
+        LogLevel::Info => "",
+        LogLevel::Notice => "text-yellow-400",
+        LogLevel::Error => "text-red-400",
+    }
+}
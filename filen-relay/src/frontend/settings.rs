@@ -0,0 +1,852 @@
+use dioxus::{
+    logger::tracing::{self},
+    prelude::*,
+};
+use strum::IntoEnumIterator as _;
+
+use crate::{
+    common::{
+        CacheUsage, LogLevel, MaintenanceTask, MaintenanceTaskStatus, RegistrationMode, Settings,
+        SystemMetrics, VfsCacheMode,
+    },
+    frontend::{toasts::show_error, Route},
+    util::format_bytes,
+};
+
+#[component]
+pub(crate) fn ManageSettings() -> Element {
+    let mut settings = use_signal(|| None::<Settings>);
+    let mut saving = use_signal(|| false);
+
+    use_effect(move || {
+        spawn(async move {
+            match crate::api::get_settings().await {
+                Ok(fetched) => {
+                    settings.set(Some(fetched));
+                }
+                Err(err) => {
+                    tracing::error!("Failed to fetch settings: {}", err);
+                }
+            }
+        });
+    });
+
+    let Some(current) = settings() else {
+        return rsx! {
+            div { class: "text-gray-500", "Loading settings..." }
+        };
+    };
+    let auto_pause_days = current
+        .auto_pause_days
+        .map(|v| v.to_string())
+        .unwrap_or_default();
+    let max_running_servers = current
+        .max_running_servers
+        .map(|v| v.to_string())
+        .unwrap_or_default();
+    let cache_quota_gb = current
+        .cache_quota_gb
+        .map(|v| v.to_string())
+        .unwrap_or_default();
+    let default_bwlimit = current.server_defaults.bwlimit.clone().unwrap_or_default();
+
+    rsx! {
+        form {
+            class: "flex flex-col gap-2 border p-4 rounded-lg max-w-120",
+            onsubmit: move |e| async move {
+                e.prevent_default();
+                let Some(new_settings) = settings() else {
+                    return;
+                };
+                saving.set(true);
+                match crate::api::update_settings(new_settings).await {
+                    Ok(_) => {
+                        tracing::info!("Settings saved successfully");
+                    }
+                    Err(err) => {
+                        show_error("Failed to save settings", &err);
+                    }
+                }
+                saving.set(false);
+            },
+            h2 { class: "font-bold text-lg", "Settings" }
+            div {
+                label { "Cache Quota (GB):" }
+                input {
+                    class: "mt-1 _input",
+                    r#type: "number",
+                    min: "0",
+                    placeholder: "Unlimited",
+                    value: "{cache_quota_gb}",
+                    oninput: move |e| {
+                        if let Some(settings) = settings.write().as_mut() {
+                            settings.cache_quota_gb = e.value().parse().ok();
+                        }
+                    },
+                }
+            }
+            div {
+                label { "Pause Unused Servers After (days):" }
+                input {
+                    class: "mt-1 _input",
+                    r#type: "number",
+                    min: "1",
+                    placeholder: "Never",
+                    value: "{auto_pause_days}",
+                    oninput: move |e| {
+                        if let Some(settings) = settings.write().as_mut() {
+                            settings.auto_pause_days = e.value().parse().ok();
+                        }
+                    },
+                }
+            }
+            div {
+                label { "Max. Running Servers:" }
+                input {
+                    class: "mt-1 _input",
+                    r#type: "number",
+                    min: "1",
+                    placeholder: "Unlimited",
+                    value: "{max_running_servers}",
+                    oninput: move |e| {
+                        if let Some(settings) = settings.write().as_mut() {
+                            settings.max_running_servers = e.value().parse().ok();
+                        }
+                    },
+                }
+            }
+            div {
+                label { "Registration Mode:" }
+                select {
+                    class: "mt-1 _input w-full",
+                    onchange: move |e| {
+                        if let Some(settings) = settings.write().as_mut() {
+                            settings.registration_mode = RegistrationMode::from(e.value().as_str());
+                        }
+                    },
+                    for mode in RegistrationMode::iter() {
+                        option {
+                            value: mode.to_string(),
+                            selected: mode == current.registration_mode,
+                            "{mode}"
+                        }
+                    }
+                }
+            }
+            label { class: "flex items-center gap-2",
+                "Verify Email on First Login"
+                input {
+                    r#type: "checkbox",
+                    checked: current.verify_email_on_first_login,
+                    onchange: move |e| {
+                        if let Some(settings) = settings.write().as_mut() {
+                            settings.verify_email_on_first_login = e.value() == "true";
+                        }
+                    },
+                }
+            }
+            p { class: "text-sm text-gray-500",
+                "Users logging in for the first time enter a code sent to their Filen email, so that a typo in the allowed users doesn't let in someone else. Requires an SMTP server."
+            }
+            h3 { class: "font-bold", "Defaults for New Servers" }
+            label { class: "flex items-center gap-2",
+                "Read-Only"
+                input {
+                    r#type: "checkbox",
+                    checked: current.server_defaults.read_only,
+                    onchange: move |e| {
+                        if let Some(settings) = settings.write().as_mut() {
+                            settings.server_defaults.read_only = e.value() == "true";
+                        }
+                    },
+                }
+            }
+            div {
+                label { "VFS Cache Mode:" }
+                select {
+                    class: "mt-1 _input w-full",
+                    onchange: move |e| {
+                        if let Some(settings) = settings.write().as_mut() {
+                            let value = e.value();
+                            settings.server_defaults.vfs_cache_mode = if value.is_empty() {
+                                None
+                            } else {
+                                Some(VfsCacheMode::from(value.as_str()))
+                            };
+                        }
+                    },
+                    option {
+                        value: "",
+                        selected: current.server_defaults.vfs_cache_mode.is_none(),
+                        "rclone's default"
+                    }
+                    for mode in VfsCacheMode::iter() {
+                        option {
+                            value: mode.to_string(),
+                            selected: current.server_defaults.vfs_cache_mode == Some(mode),
+                            "{mode}"
+                        }
+                    }
+                }
+            }
+            div {
+                label { "Bandwidth Limit:" }
+                input {
+                    class: "mt-1 _input",
+                    r#type: "text",
+                    placeholder: "Unlimited, e.g. 10M or 10M:1M",
+                    value: "{default_bwlimit}",
+                    oninput: move |e| {
+                        if let Some(settings) = settings.write().as_mut() {
+                            let value = e.value().trim().to_string();
+                            settings.server_defaults.bwlimit = if value.is_empty() { None } else { Some(value) };
+                        }
+                    },
+                }
+            }
+            div {
+                label { "Log Level:" }
+                select {
+                    class: "mt-1 _input w-full",
+                    onchange: move |e| {
+                        if let Some(settings) = settings.write().as_mut() {
+                            let value = e.value();
+                            settings.server_defaults.log_level = if value.is_empty() {
+                                None
+                            } else {
+                                Some(LogLevel::from(value.as_str()))
+                            };
+                        }
+                    },
+                    option {
+                        value: "",
+                        selected: current.server_defaults.log_level.is_none(),
+                        "rclone's default"
+                    }
+                    for level in LogLevel::iter() {
+                        option {
+                            value: level.to_string(),
+                            selected: current.server_defaults.log_level == Some(level),
+                            "{level}"
+                        }
+                    }
+                }
+            }
+            div {
+                label { "Maintenance Tasks:" }
+                for task in MaintenanceTask::iter() {
+                    label { class: "flex items-center gap-2",
+                        input {
+                            r#type: "checkbox",
+                            checked: !current.disabled_maintenance_tasks.contains(&task),
+                            onchange: move |e| {
+                                if let Some(settings) = settings.write().as_mut() {
+                                    settings.disabled_maintenance_tasks.retain(|t| *t != task);
+                                    if e.value() != "true" {
+                                        settings.disabled_maintenance_tasks.push(task);
+                                    }
+                                }
+                            },
+                        }
+                        "{task}"
+                    }
+                }
+            }
+            button {
+                class: "_button",
+                r#type: "submit",
+                disabled: *saving.read(),
+                "Save Settings"
+            }
+        }
+    }
+}
+
+/// How often the system metrics are refreshed (they are sampled every 10 seconds).
+const METRICS_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// CPU, memory and disk usage of the relay's host, so that the admin knows when it needs more resources.
+#[component]
+pub(crate) fn SystemMetricsView() -> Element {
+    let mut metrics = use_signal(|| None::<SystemMetrics>);
+    use_future(move || async move {
+        loop {
+            match crate::api::get_system_metrics().await {
+                Ok(fetched) => metrics.set(fetched),
+                Err(err) => {
+                    tracing::error!("Failed to fetch system metrics: {}", err);
+                }
+            }
+            crate::util::sleep(METRICS_POLL_INTERVAL).await;
+        }
+    });
+
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-4 rounded-lg max-w-120",
+            h2 { class: "font-bold text-lg", "System" }
+            match metrics() {
+                Some(metrics) => {
+                    let cpu = format!("{:.0}%", metrics.cpu_usage_percent);
+                    let disk_used_bytes = metrics.disk_total_bytes.saturating_sub(metrics.disk_available_bytes);
+                    rsx! {
+                        p { "CPU: {cpu} of {metrics.cpu_count} cores" }
+                        p {
+                            "Memory: {format_bytes(metrics.memory_used_bytes)} of {format_bytes(metrics.memory_total_bytes)}"
+                        }
+                        p {
+                            "Disk: {format_bytes(disk_used_bytes)} of {format_bytes(metrics.disk_total_bytes)}"
+                        }
+                        p { class: "text-sm text-gray-500",
+                            "rclone_configs: {format_bytes(metrics.rclone_configs_bytes)}"
+                        }
+                        p { "Open ports: {metrics.open_ports}" }
+                    }
+                }
+                None => rsx! {
+                    div { class: "text-gray-500", "Waiting for the first sample..." }
+                },
+            }
+        }
+    }
+}
+
+/// The periodic maintenance tasks of the relay, with when they run next and how their last run went.
+#[component]
+pub(crate) fn MaintenanceTasks() -> Element {
+    let tasks = use_resource(|| async {
+        crate::api::get_maintenance_tasks()
+            .await
+            .inspect_err(|err| tracing::error!("Failed to fetch maintenance tasks: {}", err))
+            .ok()
+    });
+
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-4 rounded-lg max-w-120",
+            h2 { class: "font-bold text-lg", "Maintenance" }
+            match tasks() {
+                Some(Some(tasks)) => rsx! {
+                    for status in tasks {
+                        MaintenanceTaskView { status }
+                    }
+                },
+                Some(None) => rsx! {
+                    div { class: "text-gray-500", "Failed to load maintenance tasks." }
+                },
+                None => rsx! {
+                    div { class: "text-gray-500", "Loading maintenance tasks..." }
+                },
+            }
+        }
+    }
+}
+
+#[component]
+fn MaintenanceTaskView(status: MaintenanceTaskStatus) -> Element {
+    let format_time = |time: chrono::DateTime<chrono::Utc>| {
+        time.with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    };
+    let interval = format_interval(status.interval_seconds);
+    let next_run = match status.next_run {
+        Some(next_run) => format!("next run {}", format_time(next_run)),
+        None if status.enabled => "not scheduled".to_string(),
+        None => "turned off".to_string(),
+    };
+    rsx! {
+        div {
+            p {
+                span { class: "font-bold", "{status.task}" }
+                span { class: "text-sm text-gray-500", " every {interval}, {next_run}" }
+            }
+            match status.last_run {
+                Some(run) => {
+                    let started = format_time(run.started);
+                    match run.result {
+                        Ok(summary) => rsx! {
+                            p { class: "text-sm", "{started}: {summary} ({run.duration_ms} ms)" }
+                        },
+                        Err(error) => rsx! {
+                            p { class: "text-sm text-red-500", "{started}: {error}" }
+                        },
+                    }
+                }
+                None => rsx! {
+                    p { class: "text-sm text-gray-500", "Not run yet." }
+                },
+            }
+        }
+    }
+}
+
+/// Formats an interval for display, e.g. "6 h".
+fn format_interval(seconds: u64) -> String {
+    match seconds {
+        s if s >= 60 * 60 && s % (60 * 60) == 0 => format!("{} h", s / (60 * 60)),
+        s if s >= 60 && s % 60 == 0 => format!("{} min", s / 60),
+        s => format!("{} s", s),
+    }
+}
+
+#[component]
+pub(crate) fn CacheUsageView() -> Element {
+    let mut usage = use_signal(|| None::<CacheUsage>);
+    let mut purging = use_signal(|| false);
+
+    let fetch_usage = move || {
+        spawn(async move {
+            match crate::api::get_cache_usage().await {
+                Ok(fetched) => {
+                    usage.set(Some(fetched));
+                }
+                Err(err) => {
+                    tracing::error!("Failed to fetch cache usage: {}", err);
+                }
+            }
+        });
+    };
+    use_effect(move || {
+        fetch_usage();
+    });
+
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-4 rounded-lg max-w-120",
+            h2 { class: "font-bold text-lg", "VFS Caches" }
+            match usage() {
+                Some(usage) => rsx! {
+                    p {
+                        "Total: {format_bytes(usage.total_bytes)}"
+                        if let Some(quota_bytes) = usage.quota_bytes {
+                            " of {format_bytes(quota_bytes)}"
+                        }
+                    }
+                    for (name , bytes) in usage.servers {
+                        p { class: "text-sm text-gray-500", "{name}: {format_bytes(bytes)}" }
+                    }
+                },
+                None => rsx! {
+                    div { class: "text-gray-500", "Loading cache usage..." }
+                },
+            }
+            button {
+                class: "_button bg-red-500 hover:bg-red-600",
+                disabled: *purging.read(),
+                onclick: move |_| async move {
+                    purging.set(true);
+                    match crate::api::purge_caches().await {
+                        Ok(_) => {
+                            tracing::info!("Caches purged successfully");
+                        }
+                        Err(err) => {
+                            show_error("Failed to purge caches", &err);
+                        }
+                    }
+                    purging.set(false);
+                    fetch_usage();
+                },
+                "Purge Caches (restarts servers)"
+            }
+        }
+    }
+}
+
+/// Downloads of the transferred bytes and running hours, e.g. for chargeback.
+#[component]
+pub(crate) fn UsageExport() -> Element {
+    let mut by_user = use_signal(|| false);
+
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-4 rounded-lg max-w-120",
+            h2 { class: "font-bold text-lg", "Usage" }
+            p { class: "text-sm text-gray-500",
+                "Transferred bytes and running hours per day, recorded every 10 minutes."
+            }
+            label { class: "flex items-center gap-2",
+                input {
+                    r#type: "checkbox",
+                    checked: *by_user.read(),
+                    onchange: move |e| by_user.set(e.value() == "true"),
+                }
+                "Sum up per user"
+            }
+            div { class: "flex gap-2",
+                a {
+                    class: "_button flex-1",
+                    href: "/api/usage/export?format=csv&by_user={by_user}",
+                    download: "filen-relay-usage.csv",
+                    "Export CSV"
+                }
+                a {
+                    class: "_button flex-1",
+                    href: "/api/usage/export?format=json&by_user={by_user}",
+                    download: "filen-relay-usage.json",
+                    "Export JSON"
+                }
+            }
+        }
+    }
+}
+
+/// Lists the users' monthly egress caps and lets the admin set and remove them.
+#[component]
+pub(crate) fn EgressCaps() -> Element {
+    let mut caps = use_resource(|| async {
+        crate::api::get_egress_caps()
+            .await
+            .inspect_err(|err| tracing::error!("Failed to fetch egress caps: {}", err))
+            .ok()
+    });
+    let mut email = use_signal(String::new);
+    let mut monthly_gb = use_signal(String::new);
+    let mut error = use_signal(|| None::<String>);
+
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-4 rounded-lg max-w-120",
+            h2 { class: "font-bold text-lg", "Egress Caps" }
+            p { class: "text-sm text-gray-500",
+                "When a user's servers have transferred this much in a month, they are paused until the next month."
+            }
+            match caps() {
+                Some(Some(list)) => rsx! {
+                    for cap in list {
+                        div { class: "flex gap-2 items-center",
+                            span { class: "flex-1",
+                                "{cap.filen_email}: {format_bytes(cap.used_bytes)} / {cap.monthly_gb} GB"
+                            }
+                            button {
+                                class: "_button",
+                                onclick: move |_| {
+                                    let email = cap.filen_email.clone();
+                                    async move {
+                                        match crate::api::set_egress_cap(email, None).await {
+                                            Ok(()) => caps.restart(),
+                                            Err(err) => show_error("Failed to remove egress cap", &err),
+                                        }
+                                    }
+                                },
+                                "Remove"
+                            }
+                        }
+                    }
+                },
+                Some(None) => rsx! {
+                    div { class: "text-gray-500", "Failed to load egress caps." }
+                },
+                None => rsx! {
+                    div { class: "text-gray-500", "Loading egress caps..." }
+                },
+            }
+            form {
+                class: "flex gap-2 border-t pt-2",
+                onsubmit: move |e| async move {
+                    e.prevent_default();
+                    let Ok(gb) = monthly_gb().trim().parse::<u64>() else {
+                        error.set(Some("Cap must be a number".to_string()));
+                        return;
+                    };
+                    match crate::api::set_egress_cap(email(), Some(gb)).await {
+                        Ok(()) => {
+                            email.set(String::new());
+                            monthly_gb.set(String::new());
+                            error.set(None);
+                            caps.restart();
+                        }
+                        Err(err) => {
+                            tracing::error!("Failed to set egress cap: {}", err);
+                            error.set(Some(err.message));
+                        }
+                    }
+                },
+                input {
+                    class: "_input flex-1",
+                    r#type: "email",
+                    placeholder: "user@example.com",
+                    value: "{email}",
+                    oninput: move |e| email.set(e.value()),
+                }
+                input {
+                    class: "_input w-24",
+                    r#type: "number",
+                    min: "0",
+                    placeholder: "GB",
+                    value: "{monthly_gb}",
+                    oninput: move |e| monthly_gb.set(e.value()),
+                }
+                button {
+                    class: "_button",
+                    r#type: "submit",
+                    disabled: email.read().trim().is_empty(),
+                    "Set Cap"
+                }
+            }
+            if let Some(error) = error() {
+                p { class: "text-red-500 text-sm", "{error}" }
+            }
+        }
+    }
+}
+
+#[component]
+pub(crate) fn Backups() -> Element {
+    let backups = use_resource(|| async {
+        crate::api::get_backups()
+            .await
+            .inspect_err(|err| tracing::error!("Failed to fetch backups: {}", err))
+            .ok()
+    });
+
+    let mut conflict = use_resource(|| async {
+        crate::api::get_db_conflict()
+            .await
+            .inspect_err(|err| tracing::error!("Failed to fetch database conflict: {}", err))
+            .ok()
+            .flatten()
+    });
+
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-4 rounded-lg max-w-120",
+            h2 { class: "font-bold text-lg", "Database Backups" }
+            if let Some(Some(other_backup)) = conflict() {
+                div { class: "flex flex-col gap-2 border border-red-500 p-2 rounded-lg",
+                    p {
+                        "The database backups were changed elsewhere (backup "
+                        span { class: "font-mono", "{other_backup}" }
+                        "), e.g. by another relay instance. Changes made here are not being backed up."
+                    }
+                    p { class: "text-sm text-gray-500",
+                        "Restart the relay to use the other database, or overwrite it with this instance's database."
+                    }
+                    button {
+                        class: "_button bg-red-500 hover:bg-red-600",
+                        onclick: move |_| async move {
+                            match crate::api::overwrite_db_conflict().await {
+                                Ok(_) => {
+                                    tracing::info!("Database conflict resolved");
+                                    conflict.restart();
+                                }
+                                Err(err) => {
+                                    show_error("Failed to resolve database conflict", &err);
+                                }
+                            }
+                        },
+                        "Overwrite"
+                    }
+                }
+            }
+            match backups() {
+                Some(Some(backups)) if !backups.is_empty() => rsx! {
+                    p { class: "text-sm text-gray-500",
+                        "To restore a backup, restart the relay with "
+                        code { "--restore-backup <name>" }
+                        "."
+                    }
+                    for backup in backups {
+                        p { class: "font-mono text-sm", "{backup}" }
+                    }
+                },
+                Some(Some(_)) => rsx! {
+                    div { class: "text-gray-500", "No backups (yet)." }
+                },
+                Some(None) => rsx! {
+                    div { class: "text-gray-500", "Failed to load backups." }
+                },
+                None => rsx! {
+                    div { class: "text-gray-500", "Loading backups..." }
+                },
+            }
+        }
+    }
+}
+
+/// Lets the admin supply a new auth config for syncing the database, e.g. after their session expired.
+#[component]
+pub(crate) fn RotateAdminAuthConfig() -> Element {
+    let mut auth_config = use_signal(String::new);
+    let mut saving = use_signal(|| false);
+    let mut result = use_signal(|| None::<Result<(), String>>);
+
+    rsx! {
+        form {
+            class: "flex flex-col gap-2 border p-4 rounded-lg max-w-120",
+            onsubmit: move |e| async move {
+                e.prevent_default();
+                saving.set(true);
+                match crate::api::rotate_admin_auth_config(auth_config()).await {
+                    Ok(_) => {
+                        tracing::info!("Admin auth config rotated successfully");
+                        auth_config.set(String::new());
+                        result.set(Some(Ok(())));
+                    }
+                    Err(err) => {
+                        tracing::error!("Failed to rotate admin auth config: {}", err);
+                        result.set(Some(Err(err.to_string())));
+                    }
+                }
+                saving.set(false);
+            },
+            h2 { class: "font-bold text-lg", "Admin Credentials" }
+            p { class: "text-sm text-gray-500",
+                "If the database can't be synced to your Filen drive anymore (e.g. because the session expired), paste a new auth config exported via the Filen CLI."
+            }
+            textarea {
+                class: "_input font-mono",
+                rows: "3",
+                value: "{auth_config}",
+                oninput: move |e| auth_config.set(e.value()),
+            }
+            button {
+                class: "_button",
+                r#type: "submit",
+                disabled: *saving.read() || auth_config().trim().is_empty(),
+                "Update Credentials"
+            }
+            match result() {
+                Some(Ok(())) => rsx! {
+                    div { class: "text-green-500", "Credentials updated." }
+                },
+                Some(Err(err)) => rsx! {
+                    div { class: "text-red-500", "{err}" }
+                },
+                None => rsx! {},
+            }
+        }
+    }
+}
+
+/// Reloads the config file without restarting the relay or its servers.
+#[component]
+pub(crate) fn ReloadConfig() -> Element {
+    let mut reloading = use_signal(|| false);
+    let mut result = use_signal(|| None::<Result<(), String>>);
+
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-4 rounded-lg max-w-120",
+            h2 { class: "font-bold text-lg", "Config" }
+            p { class: "text-sm text-gray-500",
+                "Applies changes to the public URL and trusted proxies in the config file (like sending SIGHUP) without restarting servers. Other options require a restart."
+            }
+            button {
+                class: "_button",
+                disabled: *reloading.read(),
+                onclick: move |_| async move {
+                    reloading.set(true);
+                    match crate::api::reload_config().await {
+                        Ok(()) => result.set(Some(Ok(()))),
+                        Err(err) => {
+                            tracing::error!("Failed to reload config: {}", err);
+                            result.set(Some(Err(err.to_string())));
+                        }
+                    }
+                    reloading.set(false);
+                },
+                "Reload Config"
+            }
+            match result() {
+                Some(Ok(())) => rsx! {
+                    div { class: "text-green-500", "Config reloaded." }
+                },
+                Some(Err(err)) => rsx! {
+                    div { class: "text-red-500", "{err}" }
+                },
+                None => rsx! {},
+            }
+        }
+    }
+}
+
+/// Lets the admin drain the relay before a redeploy and shows when it can be stopped.
+#[component]
+pub(crate) fn Drain() -> Element {
+    let mut status = use_resource(|| async {
+        crate::api::get_drain_status()
+            .await
+            .inspect_err(|err| tracing::error!("Failed to fetch drain status: {}", err))
+            .ok()
+    });
+    let mut draining = use_signal(|| false);
+
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-4 rounded-lg max-w-120",
+            h2 { class: "font-bold text-lg", "Drain" }
+            p { class: "text-sm text-gray-500",
+                "Before a redeploy, stop accepting logins and new servers while existing servers keep running, and back up the database. The relay also drains when it receives SIGTERM."
+            }
+            match status() {
+                Some(Some(current)) if current.safe_to_stop => rsx! {
+                    div { class: "text-green-500", "Draining. The database is backed up, the relay can be stopped safely." }
+                },
+                Some(Some(current)) if current.draining => rsx! {
+                    div { class: "text-red-500",
+                        "Draining, but backing up the database failed: "
+                        {current.error.unwrap_or_default()}
+                    }
+                },
+                Some(Some(_)) => rsx! {},
+                Some(None) => rsx! {
+                    div { class: "text-gray-500", "Failed to load drain status." }
+                },
+                None => rsx! {
+                    div { class: "text-gray-500", "Loading drain status..." }
+                },
+            }
+            button {
+                class: "_button bg-red-500 hover:bg-red-600",
+                disabled: *draining.read(),
+                onclick: move |_| async move {
+                    draining.set(true);
+                    if let Err(err) = crate::api::start_drain().await {
+                        show_error("Failed to start draining", &err);
+                    }
+                    status.restart();
+                    draining.set(false);
+                },
+                if status().flatten().is_some_and(|s| s.draining) {
+                    "Retry Backup"
+                } else {
+                    "Start Draining"
+                }
+            }
+        }
+    }
+}
+
+/// Tells the admin about a newer Filen Relay release.
+#[component]
+pub(crate) fn UpdateNotice() -> Element {
+    let update = use_resource(|| async { crate::api::get_update().await.ok().flatten() });
+    let current_version = env!("CARGO_PKG_VERSION");
+
+    rsx! {
+        if let Some(Some(update)) = update() {
+            div { class: "flex flex-col gap-1 border border-blue-500 p-2 rounded-lg",
+                p {
+                    "Filen Relay v{update.version} is available (running v{current_version}). "
+                    a {
+                        class: "underline",
+                        href: "{update.url}",
+                        target: "_blank",
+                        "Release notes"
+                    }
+                }
+                if !update.changelog.is_empty() {
+                    p { class: "text-sm text-gray-500 whitespace-pre-wrap", "{update.changelog}" }
+                }
+            }
+        }
+    }
+}
+
+/// Warns the admin about a database conflict, linking to the settings page to resolve it.
+#[component]
+pub(crate) fn DbConflictBanner() -> Element {
+    let conflict = use_resource(|| async { crate::api::get_db_conflict().await.ok().flatten() });
+
+    rsx! {
+        if let Some(Some(_)) = conflict() {
+            div { class: "border border-red-500 p-2 rounded-lg",
+                "The database in your Filen drive was changed elsewhere, so changes are not being saved to it. "
+                Link { to: Route::SettingsPage {}, class: "underline", "Resolve in Settings" }
+            }
+        }
+    }
+}
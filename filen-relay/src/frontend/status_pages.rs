@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+
+use dioxus::{
+    logger::tracing::{self},
+    prelude::*,
+};
+
+use crate::{
+    common::{ServerId, ServerState},
+    frontend::{servers::use_base_url, toasts::show_error, Route},
+};
+
+/// How often a status page refreshes the state of its servers.
+const STATUS_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// The user's status pages, which show whether selected servers are up to people without an account.
+#[component]
+pub(crate) fn StatusPages(servers: Vec<ServerState>) -> Element {
+    let mut pages = use_resource(|| async {
+        crate::api::get_status_pages()
+            .await
+            .inspect_err(|err| tracing::error!("Failed to fetch status pages: {}", err))
+            .unwrap_or_default()
+    });
+    let mut name = use_signal(String::new);
+    let mut selected = use_signal(HashSet::<ServerId>::new);
+    let mut adding = use_signal(|| false);
+    let base_url = use_base_url();
+    let base_url = base_url().unwrap_or_default();
+    let server_name = |id: &ServerId| {
+        servers
+            .iter()
+            .find(|s| s.spec.id == *id)
+            .map(|s| s.spec.name.clone())
+            .unwrap_or_else(|| "(removed)".to_string())
+    };
+
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-4 rounded-lg max-w-120",
+            h2 { class: "font-bold text-lg", "Status Pages" }
+            p { class: "text-sm text-gray-500",
+                "Anyone with the link sees whether these servers are online, e.g. in a team wiki."
+            }
+            for page in pages().unwrap_or_default() {
+                div { class: "flex gap-2 items-center",
+                    div { class: "flex-1 min-w-0",
+                        Link {
+                            class: "hover:underline",
+                            to: Route::PublicStatusPage {
+                                token: page.token.clone(),
+                            },
+                            "{page.name}"
+                        }
+                        p { class: "text-sm text-gray-500 truncate",
+                            {page.server_ids.iter().map(server_name).collect::<Vec<_>>().join(", ")}
+                        }
+                        p { class: "text-sm font-mono truncate", "{base_url}/status/{page.token}" }
+                    }
+                    button {
+                        class: "_button bg-red-500 hover:bg-red-600",
+                        onclick: {
+                            let token = page.token.clone();
+                            move |_| {
+                                let token = token.clone();
+                                async move {
+                                    match crate::api::remove_status_page(token).await {
+                                        Ok(_) => pages.restart(),
+                                        Err(err) => show_error("Failed to remove status page", &err),
+                                    }
+                                }
+                            }
+                        },
+                        "Remove"
+                    }
+                }
+            }
+            form {
+                class: "flex flex-col gap-2",
+                onsubmit: move |e| async move {
+                    e.prevent_default();
+                    adding.set(true);
+                    let server_ids = selected().into_iter().collect::<Vec<_>>();
+                    match crate::api::add_status_page(name(), server_ids).await {
+                        Ok(_) => {
+                            name.set(String::new());
+                            selected.set(HashSet::new());
+                            pages.restart();
+                        }
+                        Err(err) => show_error("Failed to add status page", &err),
+                    }
+                    adding.set(false);
+                },
+                input {
+                    class: "_input",
+                    r#type: "text",
+                    placeholder: "Name, e.g. Project X Shares",
+                    value: "{name}",
+                    oninput: move |e| name.set(e.value()),
+                }
+                for server in servers.iter() {
+                    label { class: "flex items-center gap-2",
+                        input {
+                            r#type: "checkbox",
+                            checked: selected.read().contains(&server.spec.id),
+                            onchange: {
+                                let id = server.spec.id.clone();
+                                move |e: Event<FormData>| {
+                                    if e.value() == "true" {
+                                        selected.write().insert(id.clone());
+                                    } else {
+                                        selected.write().remove(&id);
+                                    }
+                                }
+                            },
+                        }
+                        "{server.spec.name}"
+                    }
+                }
+                button {
+                    class: "_button",
+                    r#type: "submit",
+                    disabled: adding() || name.read().trim().is_empty() || selected.read().is_empty(),
+                    "Add Status Page"
+                }
+            }
+        }
+    }
+}
+
+/// A status page as seen by anyone with its link, refreshed periodically.
+#[component]
+pub(crate) fn StatusPageContentView(token: String) -> Element {
+    let mut content = use_signal(|| None::<Result<crate::common::StatusPageContent, String>>);
+    use_future(move || {
+        let token = token.clone();
+        async move {
+            loop {
+                match crate::api::get_status_page_content(token.clone()).await {
+                    Ok(fetched) => content.set(Some(Ok(fetched))),
+                    Err(err) => {
+                        tracing::error!("Failed to fetch status page: {}", err);
+                        content.set(Some(Err(err.message)));
+                    }
+                }
+                crate::util::sleep(STATUS_POLL_INTERVAL).await;
+            }
+        }
+    });
+
+    rsx! {
+        div { class: "flex flex-col gap-2 p-4 max-w-120",
+            match content() {
+                Some(Ok(content)) => rsx! {
+                    h1 { class: "font-bold text-lg", "{content.name}" }
+                    for server in content.servers {
+                        div { class: "flex gap-2 items-center border p-2 rounded-lg",
+                            span { class: "flex-1", "{server.name}" }
+                            span { class: "text-sm text-gray-500", "{server.server_type}" }
+                            if server.online {
+                                span { class: "text-green-500", "Online" }
+                            } else {
+                                span { class: "text-red-500", "Offline" }
+                            }
+                        }
+                    }
+                },
+                Some(Err(error)) => rsx! {
+                    div { class: "text-red-500", "{error}" }
+                },
+                None => rsx! {
+                    div { class: "text-gray-500", "Loading..." }
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use dioxus::{
+    logger::tracing::{self},
+    prelude::*,
+};
+
+use crate::{api::TeamOverview, frontend::AUTH};
+
+/// Lists the user's teams (all teams for the admin), letting team owners manage members and the admin manage teams.
+#[component]
+pub(crate) fn Teams() -> Element {
+    let mut teams = use_resource(|| async {
+        crate::api::get_teams()
+            .await
+            .inspect_err(|err| tracing::error!("Failed to fetch teams: {}", err))
+            .ok()
+    });
+    let is_admin = AUTH.read().as_ref().is_some_and(|auth| auth.is_admin);
+
+    let mut name = use_signal(String::new);
+    let mut owner = use_signal(String::new);
+    let mut error = use_signal(|| None::<String>);
+
+    rsx! {
+        div { class: "flex flex-col gap-4 border p-4 rounded-lg max-w-160",
+            h2 { class: "font-bold text-lg", "Teams" }
+            p { class: "text-sm text-gray-500",
+                "All members of a team can view and manage the servers owned by the team."
+            }
+            match teams() {
+                Some(Some(list)) if !list.is_empty() => rsx! {
+                    for overview in list {
+                        TeamCard {
+                            key: "{overview.team.id}",
+                            overview,
+                            on_change: move |_| teams.restart(),
+                        }
+                    }
+                },
+                Some(Some(_)) => rsx! {
+                    div { class: "text-gray-500", "You are not a member of any team." }
+                },
+                Some(None) => rsx! {
+                    div { class: "text-gray-500", "Failed to load teams." }
+                },
+                None => rsx! {
+                    div { class: "text-gray-500", "Loading teams..." }
+                },
+            }
+            if is_admin {
+                form {
+                    class: "flex flex-col gap-2 border-t pt-2",
+                    onsubmit: move |e| async move {
+                        e.prevent_default();
+                        match crate::api::add_team(name(), owner()).await {
+                            Ok(()) => {
+                                name.set(String::new());
+                                owner.set(String::new());
+                                error.set(None);
+                                teams.restart();
+                            }
+                            Err(err) => {
+                                tracing::error!("Failed to add team: {}", err);
+                                error.set(Some(err.message));
+                            }
+                        }
+                    },
+                    input {
+                        class: "_input",
+                        r#type: "text",
+                        placeholder: "Team name",
+                        value: "{name}",
+                        oninput: move |e| name.set(e.value()),
+                    }
+                    input {
+                        class: "_input",
+                        r#type: "email",
+                        placeholder: "owner@example.com",
+                        value: "{owner}",
+                        oninput: move |e| owner.set(e.value()),
+                    }
+                    if let Some(error) = error() {
+                        p { class: "text-red-500 text-sm", "{error}" }
+                    }
+                    button {
+                        class: "_button",
+                        r#type: "submit",
+                        disabled: name.read().trim().is_empty() || owner.read().trim().is_empty(),
+                        "Add Team"
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn TeamCard(overview: TeamOverview, on_change: EventHandler) -> Element {
+    let team = overview.team.clone();
+    let (is_admin, is_owner) = AUTH
+        .read()
+        .as_ref()
+        .map(|auth| (auth.is_admin, auth.email == team.owner))
+        .unwrap_or_default();
+    let member_list = team.members.join(", ");
+    let mut members = use_signal(|| member_list.clone());
+    let mut max_servers =
+        use_signal(|| team.max_servers.map(|v| v.to_string()).unwrap_or_default());
+    let mut error = use_signal(|| None::<String>);
+
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-2 rounded",
+            h3 { class: "font-bold", "{team.name}" }
+            p { "Owner: {team.owner}" }
+            match team.max_servers {
+                Some(max) => rsx! {
+                    p { "Servers: {overview.servers} / {max}" }
+                },
+                None => rsx! {
+                    p { "Servers: {overview.servers}" }
+                },
+            }
+            if is_admin || is_owner {
+                form {
+                    class: "flex gap-2 items-center",
+                    onsubmit: {
+                        let id = team.id.clone();
+                        move |e: FormEvent| {
+                            let id = id.clone();
+                            async move {
+                                e.prevent_default();
+                                let members = members()
+                                    .split(',')
+                                    .map(|email| email.trim().to_string())
+                                    .filter(|email| !email.is_empty())
+                                    .collect::<Vec<_>>();
+                                match crate::api::set_team_members(id, members).await {
+                                    Ok(()) => {
+                                        error.set(None);
+                                        on_change.call(());
+                                    }
+                                    Err(err) => {
+                                        tracing::error!("Failed to update team members: {}", err);
+                                        error.set(Some(err.message));
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    input {
+                        class: "_input flex-1",
+                        r#type: "text",
+                        placeholder: "member@example.com, ...",
+                        value: "{members}",
+                        oninput: move |e| members.set(e.value()),
+                    }
+                    button { class: "_button", r#type: "submit", "Save Members" }
+                }
+            } else {
+                p { "Members: {member_list}" }
+            }
+            if is_admin {
+                form {
+                    class: "flex gap-2 items-center",
+                    onsubmit: {
+                        let id = team.id.clone();
+                        move |e: FormEvent| {
+                            let id = id.clone();
+                            async move {
+                                e.prevent_default();
+                                let max_servers = match max_servers().trim() {
+                                    "" => None,
+                                    value => match value.parse() {
+                                        Ok(value) => Some(value),
+                                        Err(_) => {
+                                            error.set(Some("Server limit must be a number".to_string()));
+                                            return;
+                                        }
+                                    },
+                                };
+                                match crate::api::set_team_max_servers(id, max_servers).await {
+                                    Ok(()) => {
+                                        error.set(None);
+                                        on_change.call(());
+                                    }
+                                    Err(err) => {
+                                        tracing::error!("Failed to update team server limit: {}", err);
+                                        error.set(Some(err.message));
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    input {
+                        class: "_input flex-1",
+                        r#type: "number",
+                        min: "0",
+                        placeholder: "Unlimited servers",
+                        value: "{max_servers}",
+                        oninput: move |e| max_servers.set(e.value()),
+                    }
+                    button { class: "_button", r#type: "submit", "Save Limit" }
+                }
+                button {
+                    class: "_button bg-red-500 hover:bg-red-600",
+                    onclick: {
+                        let id = team.id.clone();
+                        move |_| {
+                            let id = id.clone();
+                            async move {
+                                match crate::api::remove_team(id).await {
+                                    Ok(()) => on_change.call(()),
+                                    Err(err) => {
+                                        tracing::error!("Failed to remove team: {}", err);
+                                        error.set(Some(err.message));
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "Remove Team"
+                }
+            }
+            if let Some(error) = error() {
+                p { class: "text-red-500 text-sm", "{error}" }
+            }
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use dioxus::{
+    logger::tracing::{self},
+    prelude::*,
+};
+use strum::IntoEnumIterator as _;
+
+use crate::{
+    common::{
+        ApiError, ApiErrorDetails, ServerOptions, ServerSpecValidationError, ServerType,
+        VfsCacheMode,
+    },
+    frontend::{servers::ExtraFlagsInput, toasts::show_error},
+};
+
+/// Lists the server templates and lets the admin add and remove them.
+#[component]
+pub(crate) fn ManageServerTemplates() -> Element {
+    let mut templates = use_resource(|| async {
+        crate::api::get_server_templates()
+            .await
+            .inspect_err(|err| tracing::error!("Failed to fetch server templates: {}", err))
+            .ok()
+    });
+
+    let mut name = use_signal(String::new);
+    let mut server_type = use_signal(|| ServerType::Http);
+    let mut read_only = use_signal(|| false);
+    let mut options = use_signal(ServerOptions::default);
+    let mut errors = use_signal(Vec::<ServerSpecValidationError>::new);
+
+    rsx! {
+        div { class: "flex flex-col gap-2 border p-4 rounded-lg max-w-120",
+            h2 { class: "font-bold text-lg", "Server Templates" }
+            p { class: "text-sm text-gray-500",
+                "Offered as \"Start from Template\" when creating a server."
+            }
+            match templates() {
+                Some(Some(list)) => rsx! {
+                    for template in list {
+                        div { class: "flex gap-2 items-center",
+                            span { class: "flex-1",
+                                "{template.name} ({template.server_type}"
+                                if template.read_only {
+                                    ", read-only"
+                                }
+                                ")"
+                            }
+                            button {
+                                class: "_button",
+                                onclick: move |_| {
+                                    let id = template.id.clone();
+                                    async move {
+                                        match crate::api::remove_server_template(id).await {
+                                            Ok(_) => templates.restart(),
+                                            Err(err) => show_error("Failed to remove server template", &err),
+                                        }
+                                    }
+                                },
+                                "Remove"
+                            }
+                        }
+                    }
+                },
+                Some(None) => rsx! {
+                    div { class: "text-gray-500", "Failed to load server templates." }
+                },
+                None => rsx! {
+                    div { class: "text-gray-500", "Loading server templates..." }
+                },
+            }
+            form {
+                class: "flex flex-col gap-2 border-t pt-2",
+                onsubmit: move |e| async move {
+                    e.prevent_default();
+                    match crate::api::add_server_template(
+                            name(),
+                            server_type(),
+                            read_only(),
+                            options(),
+                        )
+                        .await
+                    {
+                        Ok(()) => {
+                            name.set(String::new());
+                            server_type.set(ServerType::Http);
+                            read_only.set(false);
+                            options.set(ServerOptions::default());
+                            errors.set(Vec::new());
+                            templates.restart();
+                        }
+                        Err(ApiError { details: Some(ApiErrorDetails::Validation(validation_errors)), .. }) => {
+                            errors.set(validation_errors);
+                        }
+                        Err(err) => {
+                            show_error("Failed to add server template", &err);
+                        }
+                    }
+                },
+                input {
+                    class: "_input",
+                    r#type: "text",
+                    placeholder: "Windows WebDAV share",
+                    value: "{name}",
+                    oninput: move |e| name.set(e.value()),
+                }
+                select {
+                    class: "_input w-full",
+                    onchange: move |e| server_type.set(ServerType::from(e.value().as_str())),
+                    for type_ in ServerType::iter() {
+                        option {
+                            value: type_.to_string(),
+                            selected: *server_type.read() == type_,
+                            "{type_.to_string()}"
+                        }
+                    }
+                }
+                label { class: "flex items-center gap-2",
+                    "Read-Only"
+                    input {
+                        r#type: "checkbox",
+                        checked: *read_only.read(),
+                        onchange: move |e| read_only.set(e.value() == "true"),
+                    }
+                }
+                if matches!(*server_type.read(), ServerType::Webdav) {
+                    label { class: "flex items-center gap-2",
+                        "Windows compatibility"
+                        input {
+                            r#type: "checkbox",
+                            checked: options.read().windows_compatibility,
+                            onchange: move |e| options.write().windows_compatibility = e.value() == "true",
+                        }
+                    }
+                    label { class: "flex items-center gap-2",
+                        "Drop box (read-write only)"
+                        input {
+                            r#type: "checkbox",
+                            checked: options.read().drop_box,
+                            onchange: move |e| options.write().drop_box = e.value() == "true",
+                        }
+                    }
+                }
+                if matches!(*server_type.read(), ServerType::Http | ServerType::Webdav) {
+                    label { class: "flex items-center gap-2",
+                        "Compress text responses"
+                        input {
+                            r#type: "checkbox",
+                            checked: options.read().compression,
+                            onchange: move |e| options.write().compression = e.value() == "true",
+                        }
+                    }
+                }
+                if matches!(*server_type.read(), ServerType::Http) {
+                    label { class: "flex items-center gap-2",
+                        "Website mode"
+                        input {
+                            r#type: "checkbox",
+                            checked: options.read().website,
+                            onchange: move |e| options.write().website = e.value() == "true",
+                        }
+                    }
+                    label { class: "flex items-center gap-2",
+                        "Allow downloading folders as ZIP"
+                        input {
+                            r#type: "checkbox",
+                            checked: options.read().zip_downloads,
+                            onchange: move |e| options.write().zip_downloads = e.value() == "true",
+                        }
+                    }
+                    label { class: "flex items-center gap-2",
+                        "Serve without rclone (read-only only)"
+                        input {
+                            r#type: "checkbox",
+                            checked: options.read().native,
+                            onchange: move |e| options.write().native = e.value() == "true",
+                        }
+                    }
+                }
+                select {
+                    class: "_input w-full",
+                    onchange: move |e| {
+                        let value = e.value();
+                        options.write().vfs_cache_mode = if value.is_empty() {
+                            None
+                        } else {
+                            Some(VfsCacheMode::from(value.as_str()))
+                        };
+                    },
+                    option { value: "", selected: options.read().vfs_cache_mode.is_none(), "Default VFS cache mode" }
+                    for mode in VfsCacheMode::iter() {
+                        option {
+                            value: mode.to_string(),
+                            selected: options.read().vfs_cache_mode == Some(mode),
+                            "VFS cache mode: {mode}"
+                        }
+                    }
+                }
+                ExtraFlagsInput { options }
+                for error in errors() {
+                    p { class: "text-red-500 text-sm", "{error}" }
+                }
+                button {
+                    class: "_button",
+                    r#type: "submit",
+                    disabled: name.read().trim().is_empty(),
+                    "Add Template"
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use dioxus::{
+    logger::tracing::{self},
+    prelude::*,
+};
+
+use crate::common::{ApiError, ApiErrorCode};
+
+/// How long a toast is shown unless dismissed.
+const TOAST_DURATION: Duration = Duration::from_secs(8);
+
+#[derive(Clone, PartialEq)]
+struct Toast {
+    id: u64,
+    message: String,
+}
+
+static TOASTS: GlobalSignal<Vec<Toast>> = Signal::global(Vec::new);
+static NEXT_TOAST_ID: GlobalSignal<u64> = Signal::global(|| 0);
+
+/// Logs a failed API call and shows it as a toast, e.g. `show_error("Failed to remove server", &err)`.
+pub(crate) fn show_error(context: &str, err: &ApiError) {
+    tracing::error!("{}: {}", context, err);
+    let hint = match err.code {
+        ApiErrorCode::Unauthorized => "Your session has expired, please log in again.".to_string(),
+        ApiErrorCode::Forbidden => "You are not allowed to do this.".to_string(),
+        ApiErrorCode::Unavailable => {
+            format!("{} Please try again once the relay is back.", err.message)
+        }
+        _ => err.message.clone(),
+    };
+    let id = *NEXT_TOAST_ID.read();
+    *NEXT_TOAST_ID.write() += 1;
+    TOASTS.write().push(Toast {
+        id,
+        message: format!("{}: {}", context, hint),
+    });
+    spawn_forever(async move {
+        crate::util::sleep(TOAST_DURATION).await;
+        TOASTS.write().retain(|toast| toast.id != id);
+    });
+}
+
+/// Shows the current toasts in the bottom right corner.
+#[component]
+pub(crate) fn Toasts() -> Element {
+    rsx! {
+        div { class: "fixed bottom-4 right-4 flex flex-col gap-2 max-w-100 z-50",
+            for toast in TOASTS.read().iter().cloned() {
+                div {
+                    key: "{toast.id}",
+                    class: "flex gap-2 items-start border border-red-500 bg-gray-900 text-red-400 p-3 rounded-lg shadow-lg",
+                    span { class: "flex-1", "{toast.message}" }
+                    button {
+                        class: "cursor-pointer text-gray-400 hover:text-gray-200",
+                        onclick: move |_| TOASTS.write().retain(|t| t.id != toast.id),
+                        "✕"
+                    }
+                }
+            }
+        }
+    }
+}
@@ -27,6 +27,26 @@ impl<T> Deref for UnwrapOnceLock<T> {
     }
 }
 
+/// Sleeps for the given duration, both in the browser and on the server.
+pub async fn sleep(duration: std::time::Duration) {
+    #[cfg(target_arch = "wasm32")]
+    gloo_timers::future::sleep(duration).await;
+    #[cfg(all(not(target_arch = "wasm32"), feature = "server"))]
+    tokio::time::sleep(duration).await;
+}
+
+/// Formats a number of bytes for display, e.g. "1.5 GB".
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
 #[cfg(feature = "server")]
 pub struct IncrementalVec<T> {
     vec: Vec<T>,
@@ -50,4 +70,10 @@ impl<T: Clone> IncrementalVec<T> {
     pub fn get(&self) -> (&Vec<T>, broadcast::Receiver<T>) {
         (&self.vec, self.tx.subscribe())
     }
+
+    /// Drops all but the last `n` items.
+    pub fn keep_last(&mut self, n: usize) {
+        let excess = self.vec.len().saturating_sub(n);
+        self.vec.drain(..excess);
+    }
 }